@@ -24,6 +24,27 @@ pub struct SolanaConfig {
     pub program_keypair_path: Option<String>,
 }
 
+impl SolanaConfig {
+    /// JSON RPC endpoint of the configured cluster
+    pub fn rpc_url(&self) -> &'static str {
+        self.endpoints().0
+    }
+
+    /// Websocket endpoint of the configured cluster
+    pub fn websocket_url(&self) -> &'static str {
+        self.endpoints().1
+    }
+
+    fn endpoints(&self) -> (&'static str, &'static str) {
+        match self.cluster.as_str() {
+            "mainnet-beta" => ("https://api.mainnet-beta.solana.com", "wss://api.mainnet-beta.solana.com"),
+            "testnet" => ("https://api.testnet.solana.com", "wss://api.testnet.solana.com"),
+            "devnet" => ("https://api.devnet.solana.com", "wss://api.devnet.solana.com"),
+            _ => ("http://localhost:8899", "ws://localhost:8900"), // Assume local validator
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct BuildConfig {
     pub target: String,
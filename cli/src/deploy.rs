@@ -1,6 +1,8 @@
 use anyhow::{Result, Context};
 use std::{path::{Path, PathBuf}, thread::sleep, time::Duration, process::Command};
 use solana_sdk::{
+    bpf_loader_upgradeable::{self, UpgradeableLoaderState},
+    hash::{hash, Hash},
     signature::{read_keypair_file, Keypair},
     signer::Signer,
     pubkey::Pubkey,
@@ -50,12 +52,7 @@ impl Deployer {
         let keypair = read_keypair_file(expanded_path.as_ref())
             .map_err(|e| anyhow::anyhow!("Failed to read keypair at {}: {}", expanded_path, e))?;
 
-        let cluster_url = match config.solana.cluster.as_str() {
-            "mainnet-beta" => "https://api.mainnet-beta.solana.com",
-            "testnet" => "https://api.testnet.solana.com",
-            "devnet" => "https://api.devnet.solana.com",
-            _ => "http://localhost:8899", // Assume local validator
-        };
+        let cluster_url = config.solana.rpc_url();
 
         Ok(Deployer {
             config,
@@ -575,7 +572,59 @@ pub fn update_project(path: &Path, artifact: &BuildArtifact, hot_reload: bool) -
     Ok(())
 }
 
-pub fn check_status(path: &Path) -> Result<()> {
+/// Hashes of a local build and the program deployed on chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Verification {
+    pub local: Hash,
+    pub deployed: Hash,
+}
+
+impl Verification {
+    /// Compare the artifact's binary with deployed program bytes. Trailing
+    /// zeros are ignored, since the loader pads program data out to the
+    /// account's full size.
+    pub fn new(artifact: &BuildArtifact, deployed: &[u8]) -> Result<Self> {
+        let local = std::fs::read(&artifact.program_binary)
+            .context("Failed to read program binary")?;
+        Ok(Verification {
+            local: hash(trim_padding(&local)),
+            deployed: hash(trim_padding(deployed)),
+        })
+    }
+
+    pub fn matches(&self) -> bool {
+        self.local == self.deployed
+    }
+}
+
+fn trim_padding(bytes: &[u8]) -> &[u8] {
+    let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |last| last + 1);
+    &bytes[..len]
+}
+
+/// Program bytes as deployed, read from the program data account for
+/// upgradeable programs
+fn deployed_program(rpc_client: &RpcClient, program_id: &Pubkey) -> Result<Vec<u8>> {
+    let account = rpc_client.get_account(program_id)?;
+    if account.owner != bpf_loader_upgradeable::id() {
+        return Ok(account.data);
+    }
+
+    let (programdata_address, _) = Pubkey::find_program_address(
+        &[program_id.as_ref()],
+        &bpf_loader_upgradeable::id(),
+    );
+    let programdata = rpc_client.get_account(&programdata_address)
+        .context("Failed to fetch program data account")?;
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    programdata.data.get(metadata_len..)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| anyhow::anyhow!("Program data account is too short"))
+}
+
+/// Print the deployment status, and with `artifact` whether the deployed
+/// program matches it
+pub fn check_status(path: &Path, artifact: Option<&BuildArtifact>) -> Result<()> {
     let config = Config::load(path)?;
     
     if let Some(program_id) = config.solana.program_id {
@@ -583,14 +632,7 @@ pub fn check_status(path: &Path) -> Result<()> {
         println!("  Program ID: {}", program_id);
         println!("  Cluster: {}", config.solana.cluster);
         
-        let rpc_client = RpcClient::new(
-            match config.solana.cluster.as_str() {
-                "mainnet-beta" => "https://api.mainnet-beta.solana.com",
-                "testnet" => "https://api.testnet.solana.com",
-                "devnet" => "https://api.devnet.solana.com",
-                _ => "http://localhost:8899",
-            }
-        );
+        let rpc_client = RpcClient::new(config.solana.rpc_url());
 
         let program_id = Pubkey::try_from(program_id.as_str())
             .map_err(|_| anyhow::anyhow!("Invalid program ID in config"))?;
@@ -599,9 +641,63 @@ pub fn check_status(path: &Path) -> Result<()> {
             Ok(_) => println!("  Status: {}", "Active".green()),
             Err(_) => println!("  Status: {}", "Inactive".red()),
         }
+
+        if let Some(artifact) = artifact {
+            let deployed = deployed_program(&rpc_client, &program_id)
+                .context("Failed to fetch the deployed program")?;
+            let verification = Verification::new(artifact, &deployed)?;
+            if verification.matches() {
+                println!("  Bytecode: {} ({})", "verified".green(), verification.local);
+            } else {
+                println!("  Bytecode: {}", "MISMATCH".red());
+                println!("    Local:    {}", verification.local);
+                println!("    Deployed: {}", verification.deployed);
+            }
+        }
     } else {
         println!("{} No program deployed yet", "!".yellow());
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::build::BuildMetadata;
+
+    fn artifact(dir: &Path, binary: &[u8]) -> BuildArtifact {
+        let program_binary = dir.join("program.so");
+        std::fs::write(&program_binary, binary).unwrap();
+        BuildArtifact {
+            program_binary,
+            metadata: BuildMetadata {
+                timestamp: chrono::Utc::now(),
+                optimization_level: "release".to_string(),
+                target: "solana".to_string(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_verification() {
+        let dir = tempfile::tempdir().unwrap();
+        let program = b"\x7fELF program bytes";
+        // The loader leaves zero padding after the program
+        let mut deployed = program.to_vec();
+        deployed.resize(program.len() * 2, 0);
+
+        let matching = Verification::new(&artifact(dir.path(), program), &deployed).unwrap();
+        assert!(matching.matches());
+        assert_eq!(matching.local, hash(program));
+
+        // A partial upgrade leaves different bytes on chain
+        let mismatching = Verification::new(&artifact(dir.path(), b"\x7fELF newer program"), &deployed).unwrap();
+        assert!(!mismatching.matches());
+        assert_eq!(mismatching.deployed, hash(program));
+        assert_ne!(mismatching.local, mismatching.deployed);
+
+        let missing = BuildArtifact { program_binary: dir.path().join("missing.so"), ..artifact(dir.path(), program) };
+        assert!(Verification::new(&missing, &deployed).is_err());
+    }
+}
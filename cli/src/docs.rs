@@ -0,0 +1,84 @@
+//! Reference documentation generated from the VM itself
+//!
+//! The opcode table is built from `OpCode`'s own byte values, gas costs,
+//! categories and stack effects, so regenerating it after a VM change keeps
+//! the documentation in step with the implementation.
+
+use clap::ValueEnum;
+use lessvm_solana::vm::OpCode;
+use serde_json::json;
+
+/// Output format for generated documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DocFormat {
+    Markdown,
+    Json,
+}
+
+/// Table of every opcode
+pub fn opcode_table(format: DocFormat) -> String {
+    match format {
+        DocFormat::Markdown => opcode_markdown(),
+        DocFormat::Json => opcode_json(),
+    }
+}
+
+fn opcode_markdown() -> String {
+    let mut out = String::from("| Byte | Mnemonic | Category | Stack effect | Gas | Description |\n");
+    out.push_str("|------|----------|----------|--------------|-----|-------------|\n");
+    for op in OpCode::all() {
+        out.push_str(&format!(
+            "| `{:#04X}` | `{:?}` | {:?} | `{}` | {} | {} |\n",
+            op as u8,
+            op,
+            op.category(),
+            // Pipes inside a cell would end it early
+            op.stack_effect().replace('|', "\\|"),
+            op.gas_cost(),
+            op.description(),
+        ));
+    }
+    out
+}
+
+fn opcode_json() -> String {
+    let rows: Vec<serde_json::Value> = OpCode::all()
+        .map(|op| json!({
+            "byte": op as u8,
+            "mnemonic": format!("{:?}", op),
+            "category": format!("{:?}", op.category()),
+            "stack_effect": op.stack_effect(),
+            "gas": op.gas_cost(),
+            "description": op.description(),
+        }))
+        .collect();
+    serde_json::to_string_pretty(&rows).expect("opcode table serializes")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_table_has_every_opcode() {
+        let opcodes: Vec<OpCode> = (0..=u8::MAX).filter_map(OpCode::from_byte).collect();
+
+        let markdown = opcode_table(DocFormat::Markdown);
+        let rows: Vec<&str> = markdown.lines().skip(2).collect();
+        assert_eq!(rows.len(), opcodes.len());
+        for (row, op) in rows.iter().zip(&opcodes) {
+            assert!(row.starts_with(&format!("| `{:#04X}` | `{:?}` |", *op as u8, op)), "{}", row);
+            assert_eq!(row.replace("\\|", "").matches('|').count(), 7, "{}", row);
+        }
+        assert!(markdown.contains("| `0x18` | `And` | Bitwise | `( a b -- a&b )` | 3 |"));
+        assert!(markdown.contains("`( a b -- a\\|b )`"));
+
+        let json: Vec<serde_json::Value> = serde_json::from_str(&opcode_table(DocFormat::Json)).unwrap();
+        assert_eq!(json.len(), opcodes.len());
+        for (row, op) in json.iter().zip(&opcodes) {
+            assert_eq!(row["byte"], *op as u8);
+            assert_eq!(row["gas"], op.gas_cost());
+            assert!(!row["description"].as_str().unwrap().is_empty());
+        }
+    }
+}
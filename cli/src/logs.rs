@@ -0,0 +1,125 @@
+use anyhow::{Result, Context};
+use std::{path::Path, str::FromStr};
+use futures_util::StreamExt;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_client::{
+    nonblocking::pubsub_client::PubsubClient,
+    rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient},
+    rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter},
+};
+use solana_transaction_status::UiTransactionEncoding;
+use colored::*;
+
+use crate::config::Config;
+
+/// Default number of transactions shown by `lessvm logs`
+pub const DEFAULT_LOG_LIMIT: usize = 20;
+
+/// Read the deployed program id from the project config
+fn deployed_program(config: &Config) -> Result<Pubkey> {
+    let program_id = config.solana.program_id
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("No program deployed yet. Run `lessvm deploy` first."))?;
+
+    Pubkey::try_from(program_id.as_str())
+        .map_err(|_| anyhow::anyhow!("Invalid program ID in config"))
+}
+
+fn print_transaction_logs(signature: &str, failed: bool, logs: &[String]) {
+    let status = if failed { "failed".red() } else { "ok".green() };
+    println!("{} {} ({})", "→".blue(), signature.yellow(), status);
+    for line in logs {
+        println!("  {}", line);
+    }
+}
+
+/// Print the logs of the last `limit` transactions that mention the program
+pub fn fetch_logs(path: &Path, limit: usize) -> Result<()> {
+    let config = Config::load(path)?;
+    let program_id = deployed_program(&config)?;
+    let rpc_client = RpcClient::new(config.solana.rpc_url());
+
+    println!("{} Fetching recent logs for {} on {}", "→".blue(), program_id, config.solana.cluster);
+
+    let signatures = rpc_client
+        .get_signatures_for_address_with_config(
+            &program_id,
+            GetConfirmedSignaturesForAddress2Config {
+                limit: Some(limit),
+                ..Default::default()
+            },
+        )
+        .context("Failed to fetch program signatures")?;
+
+    if signatures.is_empty() {
+        println!("{} No transactions found", "!".yellow());
+        return Ok(());
+    }
+
+    // Signatures come back newest first; print oldest first like a log
+    for entry in signatures.iter().rev() {
+        let signature = Signature::from_str(&entry.signature)
+            .with_context(|| format!("Invalid signature {}", entry.signature))?;
+        let transaction = rpc_client
+            .get_transaction_with_config(
+                &signature,
+                RpcTransactionConfig {
+                    encoding: Some(UiTransactionEncoding::Json),
+                    commitment: Some(CommitmentConfig::confirmed()),
+                    max_supported_transaction_version: Some(0),
+                },
+            )
+            .with_context(|| format!("Failed to fetch transaction {}", entry.signature))?;
+
+        let logs: Vec<String> = transaction.transaction.meta
+            .and_then(|meta| Option::from(meta.log_messages))
+            .unwrap_or_default();
+        print_transaction_logs(&entry.signature, entry.err.is_some(), &logs);
+    }
+
+    Ok(())
+}
+
+/// Stream log lines for the program until Ctrl+C
+pub async fn follow_logs(path: &Path) -> Result<()> {
+    let config = Config::load(path)?;
+    let program_id = deployed_program(&config)?;
+    let ws_url = config.solana.websocket_url();
+
+    let pubsub_client = PubsubClient::new(ws_url)
+        .await
+        .with_context(|| format!("Failed to connect to {}", ws_url))?;
+    let (mut stream, unsubscribe) = pubsub_client
+        .logs_subscribe(
+            RpcTransactionLogsFilter::Mentions(vec![program_id.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::confirmed()),
+            },
+        )
+        .await
+        .context("Failed to subscribe to program logs")?;
+
+    println!("{} Following logs for {} on {} (Ctrl+C to stop)", "→".blue(), program_id, config.solana.cluster);
+
+    loop {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            response = stream.next() => match response {
+                Some(response) => {
+                    let logs = response.value;
+                    print_transaction_logs(&logs.signature, logs.err.is_some(), &logs.logs);
+                }
+                None => {
+                    println!("{} Log subscription closed by the server", "!".yellow());
+                    break;
+                }
+            },
+        }
+    }
+
+    drop(stream);
+    unsubscribe().await;
+    pubsub_client.shutdown().await.ok();
+
+    Ok(())
+}
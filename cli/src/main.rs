@@ -8,10 +8,19 @@ mod config;
 mod project;
 mod build;
 mod deploy;
+mod logs;
+mod transpile;
+mod docs;
+mod test_runner;
 
 use crate::project::create_new_project;
 use crate::build::build_project;
 use crate::deploy::{deploy_project, update_project, check_status};
+use crate::logs::{fetch_logs, follow_logs, DEFAULT_LOG_LIMIT};
+use crate::transpile::{transpile, Dialect};
+use crate::docs::{opcode_table, DocFormat};
+use crate::test_runner::run_tests;
+use lessvm_solana::vm::container;
 
 #[derive(Parser)]
 #[command(name = "lessvm")]
@@ -57,6 +66,9 @@ enum Commands {
         /// Path to project directory
         #[arg(short, long)]
         path: Option<String>,
+        /// Check the deployed bytecode matches a fresh local build
+        #[arg(long)]
+        verify: bool,
     },
     /// Update deployed application
     Update {
@@ -75,6 +87,40 @@ enum Commands {
         /// Follow log output
         #[arg(short, long)]
         follow: bool,
+        /// Number of recent transactions to show when not following
+        #[arg(short = 'n', long, default_value_t = DEFAULT_LOG_LIMIT)]
+        limit: usize,
+    },
+    /// Convert bytecode between the ideless and lessvm-solana dialects
+    Transpile {
+        /// Dialect of the input bytecode
+        #[arg(long, value_enum)]
+        from: Dialect,
+        /// Dialect to convert to
+        #[arg(long, value_enum)]
+        to: Dialect,
+        /// Input bytecode file
+        input: PathBuf,
+        /// Output bytecode file
+        output: PathBuf,
+    },
+    /// Generate reference documentation
+    Docs {
+        /// Emit the opcode table
+        #[arg(long)]
+        opcodes: bool,
+        /// Output format
+        #[arg(long, value_enum, default_value = "markdown")]
+        format: DocFormat,
+        /// Write to a file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Run the project's *.test.json bytecode tests
+    Test {
+        /// Path to project directory
+        #[arg(short, long)]
+        path: Option<String>,
     },
 }
 
@@ -121,10 +167,16 @@ async fn main() -> Result<()> {
             // Then deploy
             deploy_project(&path, &artifact)?;
         }
-        Commands::Status { path } => {
+        Commands::Status { path, verify } => {
             let path = path.map(PathBuf::from)
                 .unwrap_or_else(|| std::env::current_dir().unwrap());
-            check_status(&path)?;
+            let artifact = if verify {
+                println!("{} Building project to verify against", "→".blue());
+                Some(build_project(&path).await?)
+            } else {
+                None
+            };
+            check_status(&path, artifact.as_ref())?;
         }
         Commands::Update { path, hot_reload } => {
             let path = path.map(PathBuf::from)
@@ -137,16 +189,46 @@ async fn main() -> Result<()> {
             // Then update
             update_project(&path, &artifact, hot_reload)?;
         }
-        Commands::Logs { path: _, follow } => {
-            // Path will be used when log viewing is implemented
+        Commands::Logs { path, follow, limit } => {
+            let path = path.map(PathBuf::from)
+                .unwrap_or_else(|| std::env::current_dir().unwrap());
             if follow {
-                println!("{} Following program logs (Ctrl+C to stop)", "→".blue());
-                // TODO: Implement log following
-                println!("Log following not yet implemented");
+                follow_logs(&path).await?;
             } else {
-                println!("{} Fetching recent logs", "→".blue());
-                // TODO: Implement log fetching
-                println!("Log fetching not yet implemented");
+                fetch_logs(&path, limit)?;
+            }
+        }
+        Commands::Transpile { from, to, input, output } => {
+            let file = std::fs::read(&input)?;
+            // Solana programs may be wrapped in a versioned container
+            let code = match from {
+                Dialect::Solana => container::split(&file)
+                    .map_err(|e| anyhow::anyhow!("{}: {}", input.display(), e))?.1,
+                Dialect::Ideless => file.as_slice(),
+            };
+            let converted = transpile(code, from, to)?;
+            std::fs::write(&output, &converted)?;
+            println!("{} Transpiled {} ({} bytes) to {} ({} bytes)",
+                     "✓".green(), input.display(), code.len(), output.display(), converted.len());
+        }
+        Commands::Docs { opcodes, format, output } => {
+            if !opcodes {
+                anyhow::bail!("Nothing to generate, pass --opcodes");
+            }
+            let table = opcode_table(format);
+            match output {
+                Some(output) => {
+                    std::fs::write(&output, &table)?;
+                    println!("{} Wrote opcode table to {}", "✓".green(), output.display());
+                }
+                None => print!("{}", table),
+            }
+        }
+        Commands::Test { path } => {
+            let project_path = path.map(PathBuf::from).unwrap_or_else(|| std::env::current_dir().unwrap());
+            let failed = run_tests(&project_path).await?;
+            if failed > 0 {
+                anyhow::bail!("{} test(s) failed", failed);
             }
         }
     }
@@ -2,6 +2,9 @@ pub mod config;
 pub mod project;
 pub mod build;
 pub mod deploy;
+pub mod logs;
+pub mod transpile;
+pub mod docs;
 
 // Re-export commonly used types
 pub use config::Config;
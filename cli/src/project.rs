@@ -13,9 +13,20 @@ fn main() {
 }
 "#;
 
+/// Example for `lessvm test`, see `test_runner` for the format
+const EXAMPLE_TEST: &str = r#"
+{
+  "name": "adds two numbers",
+  "bytecode": "01 02 01 03 10 FF",
+  "stack": [],
+  "expect": { "stack": [5], "gas": 8 }
+}
+"#;
+
 const TEMPLATE_FILES: &[(&str, &str)] = &[
     ("src/main.less", BASIC_TEMPLATE),
     ("tests/main_test.less", "// Add your tests here"),
+    ("tests/add.test.json", EXAMPLE_TEST),
     ("README.md", "# LessVM Project\n\nCreated with lessvm-cli"),
 ];
 
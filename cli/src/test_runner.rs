@@ -0,0 +1,253 @@
+//! Bytecode tests run by `lessvm test`
+//!
+//! A test is a `*.test.json` file anywhere in the project outside `build/`:
+//!
+//! ```json
+//! {
+//!   "name": "adds two numbers",
+//!   "bytecode": "01 02 01 03 10 FF",
+//!   "stack": [],
+//!   "expect": { "stack": [5], "gas": 8 }
+//! }
+//! ```
+//!
+//! | Field          | Meaning                                                    |
+//! |----------------|------------------------------------------------------------|
+//! | `name`         | Shown in the report, defaults to the file's path           |
+//! | `bytecode`     | Hex program to run; without it the project's build is used |
+//! | `stack`        | Values pushed before the program starts, bottom first      |
+//! | `expect.stack` | The whole stack when the program stops, bottom first       |
+//! | `expect.gas`   | Gas the run uses                                           |
+//!
+//! Every field is optional. A test without expectations only checks that
+//! the program runs without an error.
+
+use anyhow::{anyhow, bail, Context, Result};
+use colored::*;
+use lessvm_solana::vm::{container, Value, VM};
+use serde::Deserialize;
+use solana_program::pubkey::Pubkey;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::build::build_project;
+
+/// Suffix of test files
+pub const TEST_SUFFIX: &str = ".test.json";
+
+/// Directories never searched for tests
+const SKIPPED_DIRS: [&str; 2] = ["build", "target"];
+
+/// One `*.test.json` file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TestCase {
+    pub name: Option<String>,
+    pub bytecode: Option<String>,
+    #[serde(default)]
+    pub stack: Vec<u64>,
+    #[serde(default)]
+    pub expect: Expectation,
+}
+
+/// What a test checks once the program stops
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Expectation {
+    pub stack: Option<Vec<u64>>,
+    pub gas: Option<u64>,
+}
+
+impl TestCase {
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&json).with_context(|| format!("Invalid test file {}", path.display()))
+    }
+
+    /// Run the test's bytecode, or `program` if it has none, returning a
+    /// line for each expectation that wasn't met
+    pub fn run(&self, program: Option<&[u8]>) -> Result<Vec<String>> {
+        let bytecode = match &self.bytecode {
+            Some(hex) => parse_hex(hex)?,
+            None => program
+                .ok_or_else(|| anyhow!("No bytecode in the test and no project program to run"))?
+                .to_vec(),
+        };
+        let (meta, code) = container::split(&bytecode).map_err(|e| anyhow!("Invalid program: {}", e))?;
+
+        let program_id = Pubkey::new_unique();
+        let mut vm = VM::new(&program_id, &[], &[]);
+        if let Some(entrypoint) = meta.and_then(|meta| meta.entrypoint) {
+            vm.set_pc(entrypoint as usize);
+        }
+        for &value in &self.stack {
+            vm.stack_mut().push(Value(value)).map_err(|e| anyhow!("Invalid input stack: {}", e))?;
+        }
+        if let Err(e) = vm.execute(code) {
+            return Ok(vec![format!("execution failed: {}", e)]);
+        }
+
+        let mut mismatches = Vec::new();
+        if let Some(expected) = &self.expect.stack {
+            let stack = vm.stack();
+            let actual: Vec<u64> = (0..stack.depth())
+                .filter_map(|i| stack.get_value(i))
+                .map(|value| value.0)
+                .collect();
+            if &actual != expected {
+                mismatches.push(format!("stack: expected {:?}, got {:?}", expected, actual));
+            }
+        }
+        if let Some(expected) = self.expect.gas {
+            if vm.gas_used() != expected {
+                mismatches.push(format!("gas: expected {}, got {}", expected, vm.gas_used()));
+            }
+        }
+        Ok(mismatches)
+    }
+}
+
+/// Bytes of a hex string, ignoring whitespace
+fn parse_hex(hex: &str) -> Result<Vec<u8>> {
+    let digits: Vec<u8> = hex.bytes().filter(|b| !b.is_ascii_whitespace()).collect();
+    if digits.len() % 2 == 1 {
+        bail!("Bytecode has an odd number of hex digits");
+    }
+    digits
+        .chunks(2)
+        .map(|pair| {
+            let pair = std::str::from_utf8(pair).ok().filter(|p| p.bytes().all(|b| b.is_ascii_hexdigit()));
+            pair.and_then(|p| u8::from_str_radix(p, 16).ok())
+                .ok_or_else(|| anyhow!("Bytecode is not valid hex"))
+        })
+        .collect()
+}
+
+/// Test files under `root`, in path order
+pub fn discover(root: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    let mut dirs = vec![root.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(&dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let path = entry?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+            if path.is_dir() {
+                if !name.starts_with('.') && !SKIPPED_DIRS.contains(&name) {
+                    dirs.push(path);
+                }
+            } else if name.ends_with(TEST_SUFFIX) {
+                files.push(path);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Run every test file, returning each test's name and its mismatches
+pub fn run_files(root: &Path, files: &[PathBuf], program: Option<&[u8]>) -> Vec<(String, Result<Vec<String>>)> {
+    files
+        .iter()
+        .map(|file| {
+            let relative = file.strip_prefix(root).unwrap_or(file).display().to_string();
+            match TestCase::load(file) {
+                Ok(case) => (case.name.clone().unwrap_or(relative), case.run(program)),
+                Err(e) => (relative, Err(e)),
+            }
+        })
+        .collect()
+}
+
+/// Run the project's tests, building it first if any test uses the
+/// project's program. Returns the number of failed tests.
+pub async fn run_tests(path: &Path) -> Result<usize> {
+    let files = discover(path)?;
+    if files.is_empty() {
+        println!("{} No *{} files found", "!".yellow(), TEST_SUFFIX);
+        return Ok(0);
+    }
+
+    let needs_program = files
+        .iter()
+        .any(|file| TestCase::load(file).is_ok_and(|case| case.bytecode.is_none()));
+    let program = if needs_program {
+        let artifact = build_project(path).await?;
+        Some(fs::read(&artifact.program_binary).context("Failed to read the built program")?)
+    } else {
+        None
+    };
+
+    println!("{} Running {} tests", "→".blue(), files.len());
+    let mut failed = 0;
+    for (name, result) in run_files(path, &files, program.as_deref()) {
+        match result {
+            Ok(mismatches) if mismatches.is_empty() => println!("{} {}", "✓".green(), name),
+            Ok(mismatches) => {
+                failed += 1;
+                println!("{} {}", "✗".red(), name);
+                for mismatch in mismatches {
+                    println!("    {}", mismatch);
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                println!("{} {}: {:#}", "✗".red(), name, e);
+            }
+        }
+    }
+    println!("\n{} passed, {} failed", files.len() - failed, failed);
+    Ok(failed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lessvm_solana::vm::OpCode;
+
+    #[test]
+    fn test_run_test_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let code = [OpCode::Push1 as u8, 2, OpCode::Add as u8, OpCode::Halt as u8];
+        let hex: String = code.iter().map(|b| format!("{:02X} ", b)).collect();
+        let gas = {
+            let program_id = Pubkey::new_unique();
+            let mut vm = VM::new(&program_id, &[], &[]);
+            vm.stack_mut().push(Value(3)).unwrap();
+            vm.execute(&code).unwrap();
+            vm.gas_used()
+        };
+
+        fs::create_dir_all(dir.path().join("tests")).unwrap();
+        fs::create_dir_all(dir.path().join("build")).unwrap();
+        fs::write(dir.path().join("tests/add.test.json"), format!(
+            r#"{{"name": "adds", "bytecode": "{}", "stack": [3], "expect": {{"stack": [5], "gas": {}}}}}"#,
+            hex, gas,
+        )).unwrap();
+        fs::write(dir.path().join("tests/wrong.test.json"), format!(
+            r#"{{"bytecode": "{}", "stack": [3], "expect": {{"stack": [6], "gas": {}}}}}"#,
+            hex, gas + 1,
+        )).unwrap();
+        // Build output is never treated as a test
+        fs::write(dir.path().join("build/stale.test.json"), "{}").unwrap();
+
+        let files = discover(dir.path()).unwrap();
+        assert_eq!(files.len(), 2);
+        let results = run_files(dir.path(), &files, None);
+
+        assert_eq!(results[0].0, "adds");
+        assert!(results[0].1.as_ref().unwrap().is_empty());
+
+        assert_eq!(results[1].0, Path::new("tests").join("wrong.test.json").display().to_string());
+        assert_eq!(results[1].1.as_ref().unwrap(), &vec![
+            "stack: expected [6], got [5]".to_string(),
+            format!("gas: expected {}, got {}", gas + 1, gas),
+        ]);
+
+        // Tests without bytecode need the project's program
+        assert!(TestCase::default().run(None).is_err());
+        assert!(TestCase::default().run(Some(&[OpCode::Halt as u8])).unwrap().is_empty());
+        assert!(parse_hex("0").is_err());
+        assert!(parse_hex("zz").is_err());
+    }
+}
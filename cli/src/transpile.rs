@@ -0,0 +1,391 @@
+//! Conversion between ideless and lessvm-solana bytecode
+//!
+//! The two VMs share most basic instructions but assign them different opcode
+//! bytes and operand widths. ideless has a 32-bit stack, PUSH1/PUSH2/PUSH4 and
+//! jumps that mostly take their destination as an operand. lessvm-solana has a
+//! 64-bit stack, Push1/Push8 and jumps that pop their destination. Programs
+//! are decoded into a common instruction list, jump targets are remapped to
+//! the new layout, and instructions without an equivalent are rejected.
+//!
+//! Arithmetic is translated as-is. A result that overflows 32 bits, or a
+//! division by zero, wraps or yields 0 on ideless but fails on-chain.
+
+use anyhow::{anyhow, bail, Result};
+use clap::ValueEnum;
+use lessvm_solana::vm::OpCode;
+use std::collections::HashMap;
+
+/// A bytecode dialect
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Dialect {
+    /// The ideless emulator
+    Ideless,
+    /// The on-chain lessvm-solana VM
+    Solana,
+}
+
+/// ideless opcode bytes
+mod ideless {
+    pub const PUSH1: u8 = 0x01;
+    pub const PUSH2: u8 = 0x02;
+    pub const PUSH4: u8 = 0x03;
+    pub const POP: u8 = 0x04;
+    pub const DUP: u8 = 0x05;
+    pub const SWAP: u8 = 0x06;
+    pub const ADD: u8 = 0x10;
+    pub const SUB: u8 = 0x11;
+    pub const MUL: u8 = 0x12;
+    pub const DIV: u8 = 0x13;
+    pub const AND: u8 = 0x20;
+    pub const OR: u8 = 0x21;
+    pub const XOR: u8 = 0x22;
+    pub const NOT: u8 = 0x23;
+    pub const LT: u8 = 0x24;
+    pub const GT: u8 = 0x25;
+    pub const EQ: u8 = 0x26;
+    pub const ISZERO: u8 = 0x27;
+    pub const JUMP: u8 = 0x30;
+    pub const JUMPIF: u8 = 0x31;
+    pub const JUMP2: u8 = 0x34;
+    pub const JUMPIF2: u8 = 0x35;
+    pub const LOAD: u8 = 0x40;
+    pub const STORE: u8 = 0x41;
+    pub const HALT: u8 = 0xFF;
+}
+
+/// An instruction both dialects can express
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Op {
+    Push(u64),
+    Pop,
+    Dup(u8),
+    Swap(u8),
+    Add,
+    Sub,
+    Mul,
+    Div,
+    And,
+    Or,
+    Xor,
+    /// Bitwise NOT of a 32-bit word, or of a 64-bit one if `wide`
+    Not { wide: bool },
+    Lt,
+    Gt,
+    Eq,
+    IsZero,
+    /// Jump to an offset in the source program
+    Jump(usize),
+    /// Pop a condition and jump to an offset in the source program if nonzero
+    JumpIf(usize),
+    /// Push the byte at a constant address
+    Load8(u64),
+    /// Pop a value and store its low byte at a constant address
+    Store8(u64),
+    Halt,
+}
+
+/// Convert `code` from one dialect to the other
+pub fn transpile(code: &[u8], from: Dialect, to: Dialect) -> Result<Vec<u8>> {
+    if from == to {
+        return Ok(code.to_vec());
+    }
+
+    let ops = match from {
+        Dialect::Ideless => decode_ideless(code)?,
+        Dialect::Solana => decode_solana(code)?,
+    };
+
+    // Lay out the program once to find where each instruction moves to. Jump
+    // operands have a fixed width, so the targets don't change any sizes.
+    let mut offsets = HashMap::new();
+    let mut len = 0;
+    for &(offset, op) in &ops {
+        offsets.insert(offset, len);
+        len += encode(op, to, |_| Ok(0))
+            .map_err(|e| anyhow!("offset {:#06x}: {}", offset, e))?
+            .len();
+    }
+
+    let resolve = |target: usize| {
+        offsets.get(&target).copied()
+            .ok_or_else(|| anyhow!("jump target {:#06x} is not an instruction", target))
+    };
+    let mut output = Vec::with_capacity(len);
+    for &(offset, op) in &ops {
+        let bytes = encode(op, to, resolve)
+            .map_err(|e| anyhow!("offset {:#06x}: {}", offset, e))?;
+        output.extend_from_slice(&bytes);
+    }
+    Ok(output)
+}
+
+/// Operand bytes of the instruction at `pc`
+fn operand(code: &[u8], pc: usize, len: usize) -> Result<&[u8]> {
+    code.get(pc + 1..pc + 1 + len)
+        .ok_or_else(|| anyhow!("offset {:#06x}: truncated operand", pc))
+}
+
+/// Replace a preceding constant push with the instruction that consumes it,
+/// since the constant is a jump target or address that has to be remapped
+fn fold_push(ops: &mut Vec<(usize, Op)>, pc: usize, name: &str, make: fn(u64) -> Op) -> Result<()> {
+    if let Some((_, op)) = ops.last_mut() {
+        if let Op::Push(value) = *op {
+            *op = make(value);
+            return Ok(());
+        }
+    }
+    bail!("offset {:#06x}: {} needs a constant operand pushed just before it", pc, name)
+}
+
+fn decode_ideless(code: &[u8]) -> Result<Vec<(usize, Op)>> {
+    use ideless::*;
+
+    let mut ops = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = code[pc];
+        let (op, size) = match opcode {
+            PUSH1 => (Op::Push(operand(code, pc, 1)?[0] as u64), 2),
+            PUSH2 => {
+                let bytes = operand(code, pc, 2)?;
+                (Op::Push(u16::from_be_bytes([bytes[0], bytes[1]]) as u64), 3)
+            }
+            PUSH4 => {
+                let bytes = operand(code, pc, 4)?;
+                (Op::Push(u32::from_be_bytes(bytes.try_into().unwrap()) as u64), 5)
+            }
+            POP => (Op::Pop, 1),
+            DUP => (Op::Dup(operand(code, pc, 1)?[0]), 2),
+            SWAP => (Op::Swap(operand(code, pc, 1)?[0]), 2),
+            ADD => (Op::Add, 1),
+            SUB => (Op::Sub, 1),
+            MUL => (Op::Mul, 1),
+            DIV => (Op::Div, 1),
+            AND => (Op::And, 1),
+            OR => (Op::Or, 1),
+            XOR => (Op::Xor, 1),
+            NOT => (Op::Not { wide: false }, 1),
+            LT => (Op::Lt, 1),
+            GT => (Op::Gt, 1),
+            EQ => (Op::Eq, 1),
+            ISZERO => (Op::IsZero, 1),
+            JUMP => {
+                fold_push(&mut ops, pc, "JUMP", |target| Op::Jump(target as usize))?;
+                pc += 1;
+                continue;
+            }
+            JUMPIF => (Op::JumpIf(operand(code, pc, 1)?[0] as usize), 2),
+            JUMP2 | JUMPIF2 => {
+                let bytes = operand(code, pc, 2)?;
+                let target = u16::from_le_bytes([bytes[0], bytes[1]]) as usize;
+                let op = if opcode == JUMP2 { Op::Jump(target) } else { Op::JumpIf(target) };
+                (op, 3)
+            }
+            LOAD => (Op::Load8(operand(code, pc, 1)?[0] as u64), 2),
+            STORE => (Op::Store8(operand(code, pc, 1)?[0] as u64), 2),
+            HALT => (Op::Halt, 1),
+            _ => bail!("offset {:#06x}: opcode {:#04x} has no lessvm-solana equivalent", pc, opcode),
+        };
+        ops.push((pc, op));
+        pc += size;
+    }
+    Ok(ops)
+}
+
+fn decode_solana(code: &[u8]) -> Result<Vec<(usize, Op)>> {
+    let mut ops = Vec::new();
+    let mut pc = 0;
+    while pc < code.len() {
+        let opcode = OpCode::from_byte(code[pc])
+            .ok_or_else(|| anyhow!("offset {:#06x}: unknown opcode {:#04x}", pc, code[pc]))?;
+        let op = match opcode {
+            OpCode::Push1 => Op::Push(operand(code, pc, 1)?[0] as u64),
+            OpCode::Push8 => Op::Push(u64::from_le_bytes(operand(code, pc, 8)?.try_into().unwrap())),
+            OpCode::Pop => Op::Pop,
+            OpCode::Dup => Op::Dup(operand(code, pc, 1)?[0]),
+            OpCode::Swap => Op::Swap(operand(code, pc, 1)?[0]),
+            OpCode::Add => Op::Add,
+            OpCode::Sub => Op::Sub,
+            OpCode::Mul => Op::Mul,
+            OpCode::Div => Op::Div,
+            OpCode::And => Op::And,
+            OpCode::Or => Op::Or,
+            OpCode::Xor => Op::Xor,
+            OpCode::Not => Op::Not { wide: true },
+            OpCode::Lt => Op::Lt,
+            OpCode::Gt => Op::Gt,
+            OpCode::Eq => Op::Eq,
+            OpCode::IsZero => Op::IsZero,
+            OpCode::Jump | OpCode::JumpI | OpCode::Mload8 | OpCode::Mstore8 => {
+                let (name, make): (_, fn(u64) -> Op) = match opcode {
+                    OpCode::Jump => ("Jump", |target| Op::Jump(target as usize)),
+                    OpCode::JumpI => ("JumpI", |target| Op::JumpIf(target as usize)),
+                    OpCode::Mload8 => ("Mload8", Op::Load8),
+                    _ => ("Mstore8", Op::Store8),
+                };
+                fold_push(&mut ops, pc, name, make)?;
+                pc += 1;
+                continue;
+            }
+            OpCode::Halt => Op::Halt,
+            _ => bail!("offset {:#06x}: {:?} has no ideless equivalent", pc, opcode),
+        };
+        ops.push((pc, op));
+        pc += 1 + opcode.operand_size();
+    }
+    Ok(ops)
+}
+
+/// Encode one instruction, mapping source jump targets through `resolve`
+fn encode(op: Op, to: Dialect, resolve: impl Fn(usize) -> Result<usize>) -> Result<Vec<u8>> {
+    match to {
+        Dialect::Ideless => encode_ideless(op, resolve),
+        Dialect::Solana => encode_solana(op, resolve),
+    }
+}
+
+fn encode_ideless(op: Op, resolve: impl Fn(usize) -> Result<usize>) -> Result<Vec<u8>> {
+    use ideless::*;
+
+    let byte_address = |address: u64| {
+        u8::try_from(address).map_err(|_| anyhow!("address {:#x} doesn't fit in a byte", address))
+    };
+    let jump = |opcode: u8, target: usize| -> Result<Vec<u8>> {
+        let target = u16::try_from(resolve(target)?)
+            .map_err(|_| anyhow!("jump target beyond 0xFFFF"))?;
+        let [lo, hi] = target.to_le_bytes();
+        Ok(vec![opcode, lo, hi])
+    };
+
+    Ok(match op {
+        Op::Push(value) => match value {
+            0..=0xFF => vec![PUSH1, value as u8],
+            0x100..=0xFFFF => [&[PUSH2][..], &(value as u16).to_be_bytes()].concat(),
+            0x1_0000..=0xFFFF_FFFF => [&[PUSH4][..], &(value as u32).to_be_bytes()].concat(),
+            _ => bail!("{:#x} doesn't fit in a 32-bit word", value),
+        },
+        Op::Pop => vec![POP],
+        Op::Dup(n) => vec![DUP, n],
+        Op::Swap(n) => vec![SWAP, n],
+        Op::Add => vec![ADD],
+        Op::Sub => vec![SUB],
+        Op::Mul => vec![MUL],
+        Op::Div => vec![DIV],
+        Op::And => vec![AND],
+        Op::Or => vec![OR],
+        Op::Xor => vec![XOR],
+        Op::Not { wide: false } => vec![NOT],
+        Op::Not { wide: true } => bail!("64-bit Not has no ideless equivalent"),
+        Op::Lt => vec![LT],
+        Op::Gt => vec![GT],
+        Op::Eq => vec![EQ],
+        Op::IsZero => vec![ISZERO],
+        Op::Jump(target) => jump(JUMP2, target)?,
+        Op::JumpIf(target) => jump(JUMPIF2, target)?,
+        Op::Load8(address) => vec![LOAD, byte_address(address)?],
+        Op::Store8(address) => vec![STORE, byte_address(address)?],
+        Op::Halt => vec![HALT],
+    })
+}
+
+fn encode_solana(op: Op, resolve: impl Fn(usize) -> Result<usize>) -> Result<Vec<u8>> {
+    let push = |value: u64| match u8::try_from(value) {
+        Ok(byte) => vec![OpCode::Push1 as u8, byte],
+        Err(_) => [&[OpCode::Push8 as u8][..], &value.to_le_bytes()].concat(),
+    };
+    // Jump targets always use Push8, so their size doesn't depend on the layout
+    let jump = |opcode: OpCode, target: usize| -> Result<Vec<u8>> {
+        let target = resolve(target)? as u64;
+        Ok([&[OpCode::Push8 as u8][..], &target.to_le_bytes(), &[opcode as u8]].concat())
+    };
+
+    Ok(match op {
+        Op::Push(value) => push(value),
+        Op::Pop => vec![OpCode::Pop as u8],
+        Op::Dup(n) => vec![OpCode::Dup as u8, n],
+        Op::Swap(n) => vec![OpCode::Swap as u8, n],
+        Op::Add => vec![OpCode::Add as u8],
+        Op::Sub => vec![OpCode::Sub as u8],
+        Op::Mul => vec![OpCode::Mul as u8],
+        Op::Div => vec![OpCode::Div as u8],
+        Op::And => vec![OpCode::And as u8],
+        Op::Or => vec![OpCode::Or as u8],
+        Op::Xor => vec![OpCode::Xor as u8],
+        // Mask the 64-bit result back down to the 32 bits ideless works with
+        Op::Not { wide: false } => {
+            [&[OpCode::Not as u8][..], &push(0xFFFF_FFFF), &[OpCode::And as u8]].concat()
+        }
+        Op::Not { wide: true } => vec![OpCode::Not as u8],
+        Op::Lt => vec![OpCode::Lt as u8],
+        Op::Gt => vec![OpCode::Gt as u8],
+        Op::Eq => vec![OpCode::Eq as u8],
+        Op::IsZero => vec![OpCode::IsZero as u8],
+        Op::Jump(target) => jump(OpCode::Jump, target)?,
+        Op::JumpIf(target) => jump(OpCode::JumpI, target)?,
+        Op::Load8(address) => [push(address), vec![OpCode::Mload8 as u8]].concat(),
+        Op::Store8(address) => [push(address), vec![OpCode::Mstore8 as u8]].concat(),
+        Op::Halt => vec![OpCode::Halt as u8],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lessvm_solana::vm::VM;
+    use solana_program::pubkey::Pubkey;
+
+    /// Run solana bytecode and return the top of the stack
+    fn run_solana(code: &[u8]) -> u64 {
+        let program_id = Pubkey::new_unique();
+        let mut vm = VM::new(&program_id, &[], &[]);
+        vm.execute(code).unwrap();
+        vm.stack().peek().unwrap().0
+    }
+
+    #[test]
+    fn test_transpile_arithmetic() {
+        // Sum 5 + 4 + 3 + 2 + 1 in a loop, then compute sum * 300 / 7. On
+        // ideless this halts with 642 on the stack.
+        let program = [
+            0x01, 0, 0x01, 5,   // 0x00: PUSH1 0 (sum), PUSH1 5 (i)
+            0x05, 0, 0x06, 2,   // 0x04: DUP 0, SWAP 2
+            0x10, 0x06, 1,      // 0x08: ADD, SWAP 1
+            0x01, 1, 0x11,      // 0x0B: PUSH1 1, SUB
+            0x05, 0, 0x31, 0x04, // 0x0E: DUP 0, JUMPIF 0x04
+            0x04,               // 0x12: POP
+            0x02, 0x01, 0x2C,   // 0x13: PUSH2 300
+            0x12, 0x01, 7,      // 0x16: MUL, PUSH1 7
+            0x13, 0xFF,         // 0x19: DIV, HALT
+        ];
+
+        let solana = transpile(&program, Dialect::Ideless, Dialect::Solana).unwrap();
+        assert_eq!(run_solana(&solana), 642);
+
+        // Converting back and forth again reaches the same solana program
+        let ideless = transpile(&solana, Dialect::Solana, Dialect::Ideless).unwrap();
+        assert_eq!(transpile(&ideless, Dialect::Ideless, Dialect::Solana).unwrap(), solana);
+
+        // NOT keeps ideless's 32-bit width
+        let not = transpile(&[0x01, 0x0F, 0x23, 0xFF], Dialect::Ideless, Dialect::Solana).unwrap();
+        assert_eq!(run_solana(&not), 0xFFFF_FFF0);
+    }
+
+    #[test]
+    fn test_transpile_rejects_untranslatable() {
+        // CALL has no on-chain equivalent
+        let err = transpile(&[0x01, 0x03, 0x32, 0xFF], Dialect::Ideless, Dialect::Solana).unwrap_err();
+        assert!(err.to_string().contains("0x0002"));
+
+        // A jump into the middle of an instruction
+        assert!(transpile(&[0x31, 0x01, 0xFF], Dialect::Ideless, Dialect::Solana).is_err());
+
+        // A computed jump target can't be remapped
+        let computed = [OpCode::Push1 as u8, 1, OpCode::Dup as u8, 0, OpCode::Jump as u8];
+        assert!(transpile(&computed, Dialect::Solana, Dialect::Ideless).is_err());
+
+        // 64-bit values don't fit on the ideless stack
+        let mut wide = vec![OpCode::Push8 as u8];
+        wide.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(transpile(&wide, Dialect::Solana, Dialect::Ideless).is_err());
+    }
+}
@@ -1,5 +1,300 @@
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 
+use serde_json::json;
+
+use crate::lessvm::DEFAULT_MAX_MEMORY;
+
+/// Mnemonic for `opcode`, or `UNKNOWN(0xNN)` if it isn't a LessVM opcode
+pub fn opcode_name(opcode: u8) -> String {
+    match opcode {
+        0x01 => "PUSH1".to_string(),
+        0x02 => "PUSH2".to_string(),
+        0x03 => "PUSH4".to_string(),
+        0x04 => "POP".to_string(),
+        0x05 => "DUP".to_string(),
+        0x06 => "SWAP".to_string(),
+        0x10 => "ADD".to_string(),
+        0x11 => "SUB".to_string(),
+        0x12 => "MUL".to_string(),
+        0x13 => "DIV".to_string(),
+        0x14 => "MOD".to_string(),
+        0x20 => "AND".to_string(),
+        0x21 => "OR".to_string(),
+        0x22 => "XOR".to_string(),
+        0x23 => "NOT".to_string(),
+        0x24 => "LT".to_string(),
+        0x25 => "GT".to_string(),
+        0x26 => "EQ".to_string(),
+        0x27 => "ISZERO".to_string(),
+        0x30 => "JUMP".to_string(),
+        0x31 => "JUMPIF".to_string(),
+        0x32 => "CALL".to_string(),
+        0x33 => "RETURN".to_string(),
+        0x34 => "JUMP2".to_string(),
+        0x35 => "JUMPIF2".to_string(),
+        0x36 => "JUMPC".to_string(),
+        0x37 => "JUMPZ".to_string(),
+        0x38 => "JUMPO".to_string(),
+        0x40 => "LOAD".to_string(),
+        0x41 => "STORE".to_string(),
+        0x44 => "LOADW".to_string(),
+        0x45 => "STOREW".to_string(),
+        0x50 => "SOLTRANSFER".to_string(),
+        0x51 => "TOKENTRANSFER".to_string(),
+        0x52 => "SYSCALL".to_string(),
+        0xFF => "HALT".to_string(),
+        0x60 => "VLOAD".to_string(),
+        0x61 => "VSTORE".to_string(),
+        0x62 => "VADD".to_string(),
+        0x63 => "VSUB".to_string(),
+        0x64 => "VDOT".to_string(),
+        0x65 => "VMUL".to_string(),
+        0x66 => "VBROADCAST".to_string(),
+        0x70 => "PUSHF".to_string(),
+        0x71 => "LOADF".to_string(),
+        0x72 => "STOREF".to_string(),
+        0x73 => "ADDF".to_string(),
+        0x74 => "SUBF".to_string(),
+        0x75 => "MULF".to_string(),
+        0x76 => "DIVF".to_string(),
+        0x80 => "MATMUL".to_string(),
+        0x81 => "MATVEC".to_string(),
+        0x82 => "MATTRANS".to_string(),
+        0x90 => "CRYPTOHASH".to_string(),
+        0xA0 => "CPLXADD".to_string(),
+        0xA1 => "CPLXMUL".to_string(),
+        0xB0 => "STRPUSH".to_string(),
+        0xB1 => "STRPOP".to_string(),
+        0xB2 => "STRCLEAR".to_string(),
+        0xB3 => "STRLEN".to_string(),
+        0xB8 => "ITOA".to_string(),
+        0xB9 => "ATOI".to_string(),
+        0xC0 => "JSONPARSE".to_string(),
+        0xD0 => "MAPINIT".to_string(),
+        0xD1 => "MAPSET".to_string(),
+        0xD2 => "MAPGET".to_string(),
+        0xE0 => "RAND".to_string(),
+        _ => format!("UNKNOWN(0x{:02X})", opcode),
+    }
+}
+
+/// One-line description of what `opcode` does
+pub fn opcode_description(opcode: u8) -> String {
+    match opcode {
+        0x01 => "Push 1-byte value onto stack".to_string(),
+        0x02 => "Push 2-byte value onto stack".to_string(),
+        0x03 => "Push 4-byte value onto stack".to_string(),
+        0x04 => "Remove top item from stack".to_string(),
+        0x05 => "Duplicate nth stack item".to_string(),
+        0x06 => "Swap nth stack item with top".to_string(),
+        0x10 => "Addition".to_string(),
+        0x11 => "Subtraction".to_string(),
+        0x12 => "Multiplication".to_string(),
+        0x13 => "Division".to_string(),
+        0x14 => "Remainder".to_string(),
+        0x20 => "Bitwise AND".to_string(),
+        0x21 => "Bitwise OR".to_string(),
+        0x22 => "Bitwise XOR".to_string(),
+        0x23 => "Bitwise NOT".to_string(),
+        0x24 => "Unsigned less than".to_string(),
+        0x25 => "Unsigned greater than".to_string(),
+        0x26 => "Equality".to_string(),
+        0x27 => "Test for zero".to_string(),
+        0x30 => "Unconditional jump".to_string(),
+        0x31 => "Conditional jump".to_string(),
+        0x32 => "Function call".to_string(),
+        0x33 => "Return from function".to_string(),
+        0x34 => "Unconditional jump to 2-byte address".to_string(),
+        0x35 => "Conditional jump to 2-byte address".to_string(),
+        0x36 => "Jump if the carry flag is set".to_string(),
+        0x37 => "Jump if the zero flag is set".to_string(),
+        0x38 => "Jump if the overflow flag is set".to_string(),
+        0x40 => "Load from memory".to_string(),
+        0x41 => "Store to memory".to_string(),
+        0x44 => "Load 32-bit word from 2-byte address".to_string(),
+        0x45 => "Store 32-bit word to 2-byte address".to_string(),
+        0x50 => "Transfer SOL".to_string(),
+        0x51 => "Transfer tokens".to_string(),
+        0x52 => "System call".to_string(),
+        0xFF => "Halt execution".to_string(),
+        0x60 => "Load 4 values into vector register".to_string(),
+        0x61 => "Store vector register to memory".to_string(),
+        0x62 => "Vector addition".to_string(),
+        0x63 => "Vector subtraction".to_string(),
+        0x64 => "Vector dot product".to_string(),
+        0x65 => "Vector element-wise multiplication".to_string(),
+        0x66 => "Copy a value into every lane of a vector register".to_string(),
+        0x70 => "Push 64-bit floating point value onto stack".to_string(),
+        0x71 => "Load floating point value from register to stack".to_string(),
+        0x72 => "Store floating point value from stack to register".to_string(),
+        0x73 => "Floating point addition".to_string(),
+        0x74 => "Floating point subtraction".to_string(),
+        0x75 => "Floating point multiplication".to_string(),
+        0x76 => "Floating point division".to_string(),
+        0x80 => "Matrix multiplication".to_string(),
+        0x81 => "Multiply a vector register by a matrix register".to_string(),
+        0x82 => "Transpose a matrix register in place".to_string(),
+        0x90 => "Compute SHA-256 hash".to_string(),
+        0xA0 => "Complex number addition".to_string(),
+        0xA1 => "Complex number multiplication".to_string(),
+        0xB0 => "Push byte to string buffer".to_string(),
+        0xB1 => "Pop byte from string buffer".to_string(),
+        0xB2 => "Clear string buffer".to_string(),
+        0xB3 => "Get string length in bytes".to_string(),
+        0xB8 => "Append integer as decimal string".to_string(),
+        0xB9 => "Parse string buffer as decimal integer".to_string(),
+        0xC0 => "Parse JSON in string buffer, push value at path (address popped from stack)".to_string(),
+        0xD0 => "Initialize key-value map".to_string(),
+        0xD1 => "Set key-value pair in map".to_string(),
+        0xD2 => "Get value from map by key".to_string(),
+        0xE0 => "Generate cryptographically secure random number".to_string(),
+        _ => "Unknown opcode".to_string(),
+    }
+}
+
+/// Stack effect of `opcode` as `( before -- after )`, top of stack rightmost.
+/// Opcodes that only touch registers or buffers show `( -- )`.
+pub fn opcode_stack_effect(opcode: u8) -> &'static str {
+    match opcode {
+        0x01..=0x03 => "( -- n )",
+        0x04 => "( a -- )",
+        0x05 => "( xn .. x0 -- xn .. x0 xn )",
+        0x06 => "( xn .. x0 -- x0 .. xn )",
+        0x10 => "( a b -- a+b )",
+        0x11 => "( a b -- a-b )",
+        0x12 => "( a b -- a*b )",
+        0x13 => "( a b -- a/b )",
+        0x14 => "( a b -- a%b )",
+        0x20 => "( a b -- a&b )",
+        0x21 => "( a b -- a|b )",
+        0x22 => "( a b -- a^b )",
+        0x23 => "( a -- !a )",
+        0x24 => "( a b -- a<b )",
+        0x25 => "( a b -- a>b )",
+        0x26 => "( a b -- a==b )",
+        0x27 => "( a -- a==0 )",
+        0x30 => "( dest -- )",
+        0x31 | 0x35 => "( cond -- )",
+        0x32 => "( dest -- ret )",
+        0x33 => "( ret -- )",
+        0x34 | 0x36..=0x38 => "( -- )",
+        0x40 => "( -- value )",
+        0x41 => "( value -- )",
+        0x44 => "( -- word )",
+        0x45 => "( word -- )",
+        0x50 => "( to amount -- )",
+        0x51 => "( token to amount -- )",
+        0x52 => "( [arg] id -- [result] )",
+        0x60 | 0x61 => "( addr -- )",
+        0x62 | 0x63 | 0x65 => "( -- )",
+        0x64 => "( -- dot )",
+        0x66 => "( value -- )",
+        0x70 | 0x71 => "( -- lo hi )",
+        0x72 => "( lo hi -- )",
+        0x73..=0x76 | 0x80..=0x82 | 0xA0 | 0xA1 => "( -- )",
+        0x90 => "( src -- dest )",
+        0xB0 | 0xB2 => "( -- )",
+        0xB1 => "( -- byte )",
+        0xB3 => "( -- len )",
+        0xB8 => "( n -- )",
+        0xB9 => "( -- n err )",
+        0xC0 => "( path -- value )",
+        0xD0 => "( -- )",
+        0xD1 => "( value key -- )",
+        0xD2 => "( key -- value )",
+        0xE0 => "( -- n )",
+        0xFF => "( -- )",
+        _ => "?",
+    }
+}
+
+/// Number of values `opcode` pops and pushes, taken from its stack effect,
+/// or `None` if that depends on operands or runtime values
+pub fn opcode_stack_counts(opcode: u8) -> Option<(usize, usize)> {
+    let effect = opcode_stack_effect(opcode);
+    let inner = effect.strip_prefix('(')?.strip_suffix(')')?;
+    if inner.contains('[') || inner.contains("..") {
+        return None;
+    }
+    let (inputs, outputs) = inner.split_once("--")?;
+    Some((inputs.split_whitespace().count(), outputs.split_whitespace().count()))
+}
+
+/// Number of operand bytes that follow `opcode` in the bytecode stream
+pub fn opcode_param_count(opcode: u8) -> usize {
+    match opcode {
+        0x01 => 1, // PUSH1
+        0x02 => 2, // PUSH2
+        0x03 => 4, // PUSH4
+        0x04 => 0, // POP
+        0x05 => 1, // DUP
+        0x06 => 1, // SWAP
+        0x10 => 0, // ADD
+        0x11 => 0, // SUB
+        0x12 => 0, // MUL
+        0x13 => 0, // DIV
+        0x14 => 0, // MOD
+        0x20 => 0, // AND
+        0x21 => 0, // OR
+        0x22 => 0, // XOR
+        0x23 => 0, // NOT
+        0x24 => 0, // LT
+        0x25 => 0, // GT
+        0x26 => 0, // EQ
+        0x27 => 0, // ISZERO
+        0x30 => 0, // JUMP
+        0x31 => 1, // JUMPIF
+        0x32 => 0, // CALL
+        0x33 => 0, // RETURN
+        0x34 => 2, // JUMP2 (little-endian destination)
+        0x35 => 2, // JUMPIF2 (little-endian destination)
+        0x36 => 1, // JUMPC
+        0x37 => 1, // JUMPZ
+        0x38 => 1, // JUMPO
+        0x40 => 1, // LOAD
+        0x41 => 1, // STORE
+        0x44 => 2, // LOADW
+        0x45 => 2, // STOREW
+        0x50 => 0, // SOLTRANSFER
+        0x51 => 0, // TOKENTRANSFER
+        0x52 => 0, // SYSCALL
+        0x60 => 1, // VLOAD
+        0x61 => 1, // VSTORE
+        0x62 => 1, // VADD
+        0x63 => 1, // VSUB
+        0x64 => 1, // VDOT
+        0x65 => 1, // VMUL
+        0x66 => 1, // VBROADCAST
+        0x70 => 8, // PUSHF (8 bytes)
+        0x71 => 1, // LOADF
+        0x72 => 1, // STOREF
+        0x73 => 1, // ADDF
+        0x74 => 1, // SUBF
+        0x75 => 1, // MULF
+        0x76 => 1, // DIVF
+        0x80 => 1, // MATMUL
+        0x81 => 2, // MATVEC (matrix, then destination and source vectors)
+        0x82 => 1, // MATTRANS
+        0x90 => 2, // CRYPTOHASH (destination address + length, source from stack)
+        0xA0 => 1, // CPLXADD
+        0xA1 => 1, // CPLXMUL
+        0xB0 => 1, // STRPUSH
+        0xB1 => 0, // STRPOP
+        0xB2 => 0, // STRCLEAR
+        0xB3 => 0, // STRLEN
+        0xB8 => 0, // ITOA
+        0xB9 => 0, // ATOI
+        0xC0 => 1, // JSONPARSE
+        0xD0 => 0, // MAPINIT
+        0xD1 => 0, // MAPSET
+        0xD2 => 0, // MAPGET
+        0xE0 => 0, // RAND
+        0xFF => 0, // HALT
+        _ => 0,    // UNKNOWN
+    }
+}
+
 /// Instruction representation with metadata
 #[derive(Debug, Clone)]
 pub struct Instruction {
@@ -12,21 +307,206 @@ pub struct Instruction {
     pub issues: Vec<String>,
 }
 
+/// Disassembled program as structured data, for tools that want the
+/// instructions rather than the text the `Disassembler` prints
+#[derive(Debug, Clone)]
+pub struct Program {
+    /// Instructions in offset order
+    pub instructions: Vec<Instruction>,
+    
+    /// Byte ranges taken to be data rather than code
+    pub data_regions: Vec<DataRegion>,
+}
+
+impl Program {
+    /// Disassemble `bytecode`
+    pub fn from_bytecode(bytecode: &[u8]) -> Self {
+        let mut disasm = Disassembler::new(bytecode.to_vec());
+        disasm.run();
+        Self::from_disassembly(&disasm)
+    }
+    
+    /// Collect the instructions of a disassembler that has been run
+    fn from_disassembly(disasm: &Disassembler) -> Self {
+        let instructions = disasm.disassembled.iter()
+            .filter(|line| is_code_line(line))
+            .map(|line| {
+                let opcode = line.raw_bytes[0];
+                Instruction {
+                    opcode,
+                    name: opcode_name(opcode),
+                    description: opcode_description(opcode),
+                    params: line.raw_bytes[1..].to_vec(),
+                    offset: line.offset,
+                    size: line.raw_bytes.len(),
+                    issues: line.issues.clone(),
+                }
+            })
+            .collect();
+        
+        Program {
+            instructions,
+            data_regions: disasm.data_regions.clone(),
+        }
+    }
+    
+    /// The program as `{instructions: [{offset, opcode, name, params, size, issues}], data_regions: [{start, end, kind}]}`
+    pub fn to_json(&self) -> serde_json::Value {
+        let instructions: Vec<serde_json::Value> = self.instructions.iter()
+            .map(|instruction| json!({
+                "offset": instruction.offset,
+                "opcode": instruction.opcode,
+                "name": instruction.name,
+                "params": instruction.params,
+                "size": instruction.size,
+                "issues": instruction.issues,
+            }))
+            .collect();
+        let data_regions: Vec<serde_json::Value> = self.data_regions.iter()
+            .map(|region| json!({
+                "start": region.start,
+                "end": region.end,
+                "kind": format!("{:?}", region.kind),
+            }))
+            .collect();
+        json!({
+            "instructions": instructions,
+            "data_regions": data_regions,
+        })
+    }
+}
+
 /// Disassembled line with metadata
 #[derive(Debug, Clone)]
 pub struct DisassembledLine {
     pub offset: usize,
     pub raw_bytes: Vec<u8>,
     pub instruction: Option<String>,
+    
+    /// Operand decoded as one value, e.g. `0x0000DEAD` for a PUSH4 or `3`
+    /// for a DUP, if the instruction has one. Also part of `instruction`.
+    pub operand: Option<String>,
     pub description: Option<String>,
     pub issues: Vec<String>,
 }
 
+/// Shortest run of printable bytes treated as a string in unreachable code
+const MIN_ASCII_RUN: usize = 4;
+
+/// Shortest run of zero bytes treated as padding in unreachable code
+const MIN_ZERO_RUN: usize = 4;
+
+/// Longer minimums used when reachability is unknown, since the run may be code
+const MIN_ASCII_RUN_UNKNOWN_FLOW: usize = 16;
+const MIN_ZERO_RUN_UNKNOWN_FLOW: usize = 32;
+
+/// Bytes shown per disassembled data line
+const DATA_LINE_BYTES: usize = 8;
+
+/// Why a byte range looks like data rather than code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataKind {
+    /// Run of printable ASCII characters
+    Ascii,
+    
+    /// Run of zero bytes
+    Zeros,
+    
+    /// Bytes that no control flow path reaches
+    Unreachable,
+}
+
+/// Byte range the disassembler believes is data, `start..end`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataRegion {
+    pub start: usize,
+    pub end: usize,
+    pub kind: DataKind,
+}
+
+/// How control passes along a control-flow graph edge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Execution continues with the next instruction
+    Fallthrough,
+    
+    /// A JUMP, or a taken JUMPIF
+    Jump,
+    
+    /// A CALL into a function
+    Call,
+}
+
+/// Run of instructions that is only entered at its first instruction and
+/// only left after its last
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Byte offset of the first instruction
+    pub start: usize,
+    
+    /// Indices into `Disassembler::disassembled`
+    pub lines: Vec<usize>,
+}
+
+/// Control-flow graph of a disassembled program
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ControlFlowGraph {
+    pub blocks: Vec<BasicBlock>,
+    
+    /// Edges as (from block, to block, kind)
+    pub edges: Vec<(usize, usize, EdgeKind)>,
+}
+
+impl ControlFlowGraph {
+    /// Render the graph in Graphviz DOT format, labelling each block with
+    /// its instructions
+    pub fn to_dot(&self, disasm: &Disassembler) -> String {
+        let escape = |text: &str| text.replace('\\', "\\\\").replace('"', "\\\"");
+        
+        let mut dot = String::from("digraph cfg {\n    node [shape=box, fontname=\"monospace\"];\n");
+        for (i, block) in self.blocks.iter().enumerate() {
+            let mut label = String::new();
+            for &idx in &block.lines {
+                let line = &disasm.disassembled[idx];
+                let text = line.instruction.as_deref().unwrap_or("???");
+                label.push_str(&format!("{:04X}: {}\\l", line.offset, escape(text)));
+            }
+            dot.push_str(&format!("    b{} [label=\"{}\"];\n", i, label));
+        }
+        for &(from, to, kind) in &self.edges {
+            let attrs = match kind {
+                EdgeKind::Fallthrough => "",
+                EdgeKind::Jump => " [label=\"jump\"]",
+                EdgeKind::Call => " [label=\"call\", style=dashed]",
+            };
+            dot.push_str(&format!("    b{} -> b{}{};\n", from, to, attrs));
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+fn is_printable(byte: u8) -> bool {
+    byte.is_ascii_graphic() || byte == b' '
+}
+
+/// Whether a disassembled line is an instruction rather than data
+fn is_code_line(line: &DisassembledLine) -> bool {
+    line.instruction.as_deref().is_some_and(|text| !text.starts_with('.'))
+        && !line.raw_bytes.is_empty()
+}
+
 /// LessVM Disassembler
 pub struct Disassembler {
     pub bytecode: Vec<u8>,
     pub disassembled: Vec<DisassembledLine>,
     pub has_issues: bool,
+    
+    /// Probable data regions found by `run`, in offset order
+    pub data_regions: Vec<DataRegion>,
+    
+    /// Labels given to static jump and call targets by `run`, keyed by offset
+    pub labels: HashMap<usize, String>,
 }
 
 impl Disassembler {
@@ -36,188 +516,217 @@ impl Disassembler {
             bytecode,
             disassembled: Vec::new(),
             has_issues: false,
+            data_regions: Vec::new(),
+            labels: HashMap::new(),
         }
     }
     
     /// Run the disassembler
     pub fn run(&mut self) {
+        self.data_regions = self.detect_data_regions();
+        
         let mut offset = 0;
+        let mut regions = self.data_regions.clone().into_iter().peekable();
         
         while offset < self.bytecode.len() {
+            if let Some(region) = regions.next_if(|r| r.start == offset) {
+                self.push_data_lines(region.start, region.end, Some(region.kind));
+                offset = region.end;
+                continue;
+            }
+            
             let (line, next_offset) = self.disassemble_instruction(offset);
+            
+            // Don't let an instruction swallow the start of a data region
+            let limit = regions.peek().map_or(usize::MAX, |r| r.start);
+            if next_offset > limit {
+                self.push_data_lines(offset, limit, None);
+                offset = limit;
+                continue;
+            }
             self.disassembled.push(line);
             
             // Move to next instruction
             offset = next_offset;
         }
+        
+        self.label_targets();
+    }
+    
+    /// Bytes reached by following control flow from offset 0, or `None` if
+    /// a computed jump target can't be worked out statically.
+    ///
+    /// JUMP and CALL take their target from the stack, so they are only
+    /// followed when the previous instruction pushed a constant.
+    fn reachable_bytes(&self) -> Option<Vec<bool>> {
+        let code = &self.bytecode;
+        let mut reachable = vec![false; code.len()];
+        let mut visited = vec![false; code.len()];
+        let mut work = vec![0];
+        
+        while let Some(mut offset) = work.pop() {
+            let mut pushed = None;
+            
+            while offset < code.len() && !visited[offset] {
+                visited[offset] = true;
+                let opcode = code[offset];
+                if opcode_name(opcode).starts_with("UNKNOWN") {
+                    // Execution would fault here
+                    break;
+                }
+                
+                let size = 1 + opcode_param_count(opcode);
+                let end = (offset + size).min(code.len());
+                reachable[offset..end].fill(true);
+                let operands = &code[offset + 1..end];
+                let wide = |bytes: &[u8]| {
+                    bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize)
+                };
+                
+                match opcode {
+                    0x30 | 0x32 => {
+                        // JUMP never falls through; CALL returns to the next instruction
+                        work.push(pushed?);
+                        if opcode == 0x30 {
+                            break;
+                        }
+                    }
+                    0x31 | 0x36..=0x38 => work.push(wide(operands)),
+                    0x34 | 0x35 => {
+                        let dest = operands.iter().rev().copied().collect::<Vec<_>>();
+                        work.push(wide(&dest));
+                        if opcode == 0x34 {
+                            break;
+                        }
+                    }
+                    0x33 | 0xFF => break,
+                    _ => {}
+                }
+                
+                pushed = match opcode {
+                    0x01..=0x03 => Some(wide(operands)),
+                    _ => None,
+                };
+                offset += size;
+            }
+        }
+        
+        Some(reachable)
+    }
+    
+    /// Find byte ranges that are probably data rather than code: unreachable
+    /// bytes, split into printable strings and zero padding where possible.
+    /// Without reliable reachability only long strings and zero runs count.
+    fn detect_data_regions(&self) -> Vec<DataRegion> {
+        let code = &self.bytecode;
+        let reachable = self.reachable_bytes();
+        let (min_ascii, min_zeros) = match reachable {
+            Some(_) => (MIN_ASCII_RUN, MIN_ZERO_RUN),
+            None => (MIN_ASCII_RUN_UNKNOWN_FLOW, MIN_ZERO_RUN_UNKNOWN_FLOW),
+        };
+        let candidate = |i: usize| reachable.as_ref().is_none_or(|r| !r[i]);
+        
+        let mut regions = Vec::new();
+        let mut offset = 0;
+        while offset < code.len() {
+            if !candidate(offset) {
+                offset += 1;
+                continue;
+            }
+            
+            let run_start = offset;
+            while offset < code.len() && candidate(offset) {
+                offset += 1;
+            }
+            
+            // Carve strings and padding out of the run; what's left is only
+            // data if we know nothing reaches it
+            let mut leftover = run_start;
+            let mut i = run_start;
+            while i < offset {
+                let kind = if is_printable(code[i]) { DataKind::Ascii } else { DataKind::Zeros };
+                let matches = |b: u8| match kind {
+                    DataKind::Ascii => is_printable(b),
+                    _ => b == 0,
+                };
+                let min_len = if kind == DataKind::Ascii { min_ascii } else { min_zeros };
+                
+                let mut j = i;
+                while j < offset && matches(code[j]) {
+                    j += 1;
+                }
+                if j - i >= min_len {
+                    if reachable.is_some() && leftover < i {
+                        regions.push(DataRegion { start: leftover, end: i, kind: DataKind::Unreachable });
+                    }
+                    regions.push(DataRegion { start: i, end: j, kind });
+                    leftover = j;
+                }
+                i = j.max(i + 1);
+            }
+            if reachable.is_some() && leftover < offset {
+                regions.push(DataRegion { start: leftover, end: offset, kind: DataKind::Unreachable });
+            }
+        }
+        
+        regions
+    }
+    
+    /// Add disassembly lines for data bytes in `start..end`
+    fn push_data_lines(&mut self, start: usize, end: usize, kind: Option<DataKind>) {
+        let description = match kind {
+            Some(DataKind::Ascii) => "Probable data: printable string",
+            Some(DataKind::Zeros) => "Probable data: zero padding",
+            Some(DataKind::Unreachable) => "Probable data: never reached by control flow",
+            None => "Bytes before a probable data region",
+        };
+        
+        if kind == Some(DataKind::Zeros) {
+            self.disassembled.push(DisassembledLine {
+                offset: start,
+                raw_bytes: Vec::new(),
+                instruction: Some(format!(".zero {}", end - start)),
+                operand: None,
+                description: Some(description.to_string()),
+                issues: Vec::new(),
+            });
+            return;
+        }
+        
+        for chunk_start in (start..end).step_by(DATA_LINE_BYTES) {
+            let bytes = &self.bytecode[chunk_start..(chunk_start + DATA_LINE_BYTES).min(end)];
+            let instruction = if kind == Some(DataKind::Ascii) {
+                let text: String = bytes.iter().map(|&b| b as char).collect();
+                format!(".ascii {:?}", text)
+            } else {
+                let values: Vec<String> = bytes.iter().map(|b| format!("0x{:02X}", b)).collect();
+                format!(".byte {}", values.join(", "))
+            };
+            
+            self.disassembled.push(DisassembledLine {
+                offset: chunk_start,
+                raw_bytes: bytes.to_vec(),
+                instruction: Some(instruction),
+                operand: None,
+                description: Some(description.to_string()),
+                issues: Vec::new(),
+            });
+        }
     }
     
     /// Get opcode name
     fn get_opcode_name(&self, opcode: u8) -> String {
-        match opcode {
-            0x01 => "PUSH1".to_string(),
-            0x02 => "PUSH2".to_string(),
-            0x03 => "PUSH4".to_string(),
-            0x04 => "POP".to_string(),
-            0x05 => "DUP".to_string(),
-            0x06 => "SWAP".to_string(),
-            0x10 => "ADD".to_string(),
-            0x11 => "SUB".to_string(),
-            0x12 => "MUL".to_string(),
-            0x13 => "DIV".to_string(),
-            0x20 => "AND".to_string(),
-            0x21 => "OR".to_string(),
-            0x22 => "XOR".to_string(),
-            0x23 => "NOT".to_string(),
-            0x30 => "JUMP".to_string(),
-            0x31 => "JUMPIF".to_string(),
-            0x32 => "CALL".to_string(),
-            0x33 => "RETURN".to_string(),
-            0x40 => "LOAD".to_string(),
-            0x41 => "STORE".to_string(),
-            0x50 => "SOLTRANSFER".to_string(),
-            0x51 => "TOKENTRANSFER".to_string(),
-            0x52 => "SYSCALL".to_string(),
-            0xFF => "HALT".to_string(),
-            0x60 => "VLOAD".to_string(),
-            0x61 => "VSTORE".to_string(),
-            0x62 => "VADD".to_string(),
-            0x63 => "VSUB".to_string(),
-            0x64 => "VDOT".to_string(),
-            0x70 => "PUSHF".to_string(),
-            0x71 => "LOADF".to_string(),
-            0x72 => "STOREF".to_string(),
-            0x73 => "ADDF".to_string(),
-            0x74 => "SUBF".to_string(),
-            0x75 => "MULF".to_string(),
-            0x76 => "DIVF".to_string(),
-            0x80 => "MATMUL".to_string(),
-            0x90 => "CRYPTOHASH".to_string(),
-            0xA0 => "CPLXADD".to_string(),
-            0xA1 => "CPLXMUL".to_string(),
-            0xB0 => "STRPUSH".to_string(),
-            0xB1 => "STRPOP".to_string(),
-            0xB2 => "STRCLEAR".to_string(),
-            0xB3 => "STRLEN".to_string(),
-            0xC0 => "JSONPARSE".to_string(),
-            0xD0 => "MAPINIT".to_string(),
-            0xD1 => "MAPSET".to_string(),
-            0xD2 => "MAPGET".to_string(),
-            0xE0 => "RAND".to_string(),
-            _ => format!("UNKNOWN(0x{:02X})", opcode),
-        }
+        opcode_name(opcode)
     }
     
     /// Get opcode description
     fn get_opcode_description(&self, opcode: u8) -> String {
-        match opcode {
-            0x01 => "Push 1-byte value onto stack".to_string(),
-            0x02 => "Push 2-byte value onto stack".to_string(),
-            0x03 => "Push 4-byte value onto stack".to_string(),
-            0x04 => "Remove top item from stack".to_string(),
-            0x05 => "Duplicate nth stack item".to_string(),
-            0x06 => "Swap nth stack item with top".to_string(),
-            0x10 => "Addition".to_string(),
-            0x11 => "Subtraction".to_string(),
-            0x12 => "Multiplication".to_string(),
-            0x13 => "Division".to_string(),
-            0x20 => "Bitwise AND".to_string(),
-            0x21 => "Bitwise OR".to_string(),
-            0x22 => "Bitwise XOR".to_string(),
-            0x23 => "Bitwise NOT".to_string(),
-            0x30 => "Unconditional jump".to_string(),
-            0x31 => "Conditional jump".to_string(),
-            0x32 => "Function call".to_string(),
-            0x33 => "Return from function".to_string(),
-            0x40 => "Load from memory".to_string(),
-            0x41 => "Store to memory".to_string(),
-            0x50 => "Transfer SOL".to_string(),
-            0x51 => "Transfer tokens".to_string(),
-            0x52 => "System call".to_string(),
-            0xFF => "Halt execution".to_string(),
-            0x60 => "Load 4 values into vector register".to_string(),
-            0x61 => "Store vector register to memory".to_string(),
-            0x62 => "Vector addition".to_string(),
-            0x63 => "Vector subtraction".to_string(),
-            0x64 => "Vector dot product".to_string(),
-            0x70 => "Push 64-bit floating point value onto stack".to_string(),
-            0x71 => "Load floating point value from register to stack".to_string(),
-            0x72 => "Store floating point value from stack to register".to_string(),
-            0x73 => "Floating point addition".to_string(),
-            0x74 => "Floating point subtraction".to_string(),
-            0x75 => "Floating point multiplication".to_string(),
-            0x76 => "Floating point division".to_string(),
-            0x80 => "Matrix multiplication".to_string(),
-            0x90 => "Compute SHA-256 hash".to_string(),
-            0xA0 => "Complex number addition".to_string(),
-            0xA1 => "Complex number multiplication".to_string(),
-            0xB0 => "Push character to string buffer".to_string(),
-            0xB1 => "Pop character from string buffer".to_string(),
-            0xB2 => "Clear string buffer".to_string(),
-            0xB3 => "Get string length".to_string(),
-            0xC0 => "Parse JSON from string buffer".to_string(),
-            0xD0 => "Initialize key-value map".to_string(),
-            0xD1 => "Set key-value pair in map".to_string(),
-            0xD2 => "Get value from map by key".to_string(),
-            0xE0 => "Generate cryptographically secure random number".to_string(),
-            _ => "Unknown opcode".to_string(),
-        }
+        opcode_description(opcode)
     }
     
     /// Get number of parameters for opcode
     fn get_opcode_param_count(&self, opcode: u8) -> usize {
-        match opcode {
-            0x01 => 1, // PUSH1
-            0x02 => 2, // PUSH2
-            0x03 => 4, // PUSH4
-            0x04 => 0, // POP
-            0x05 => 1, // DUP
-            0x06 => 1, // SWAP
-            0x10 => 0, // ADD
-            0x11 => 0, // SUB
-            0x12 => 0, // MUL
-            0x13 => 0, // DIV
-            0x20 => 0, // AND
-            0x21 => 0, // OR
-            0x22 => 0, // XOR
-            0x23 => 0, // NOT
-            0x30 => 0, // JUMP
-            0x31 => 1, // JUMPIF
-            0x32 => 0, // CALL
-            0x33 => 0, // RETURN
-            0x40 => 1, // LOAD
-            0x41 => 1, // STORE
-            0x50 => 0, // SOLTRANSFER
-            0x51 => 0, // TOKENTRANSFER
-            0x52 => 0, // SYSCALL
-            0x60 => 1, // VLOAD
-            0x61 => 1, // VSTORE
-            0x62 => 1, // VADD
-            0x63 => 1, // VSUB
-            0x64 => 1, // VDOT
-            0x70 => 8, // PUSHF (8 bytes)
-            0x71 => 1, // LOADF
-            0x72 => 1, // STOREF
-            0x73 => 1, // ADDF
-            0x74 => 1, // SUBF
-            0x75 => 1, // MULF
-            0x76 => 1, // DIVF
-            0x80 => 1, // MATMUL
-            0x90 => 2, // CRYPTOHASH (destination address + length)
-            0xA0 => 1, // CPLXADD
-            0xA1 => 1, // CPLXMUL
-            0xB0 => 1, // STRPUSH
-            0xB1 => 0, // STRPOP
-            0xB2 => 0, // STRCLEAR
-            0xB3 => 0, // STRLEN
-            0xC0 => 1, // JSONPARSE
-            0xD0 => 0, // MAPINIT
-            0xD1 => 0, // MAPSET
-            0xD2 => 0, // MAPGET
-            0xE0 => 0, // RAND
-            0xFF => 0, // HALT
-            _ => 0,    // UNKNOWN
-        }
+        opcode_param_count(opcode)
     }
     
     /// Disassemble a single instruction
@@ -226,6 +735,7 @@ impl Disassembler {
             offset,
             raw_bytes: Vec::new(),
             instruction: None,
+            operand: None,
             description: None,
             issues: Vec::new(),
         };
@@ -267,9 +777,15 @@ impl Disassembler {
             param_str.push_str(&format!("0x{:02X}", param));
         }
         
-        // Build instruction string
+        // Build instruction string, with the operand as one value where it
+        // has one and as raw bytes otherwise
+        if params.len() == param_count {
+            line.operand = decode_operand(opcode, &params);
+        }
         let mut instr_str = opcode_name.clone();
-        if !param_str.is_empty() {
+        if let Some(operand) = &line.operand {
+            instr_str.push_str(&format!(" {}", operand));
+        } else if !param_str.is_empty() {
             instr_str.push_str(&format!(" {}", param_str));
         }
         
@@ -319,6 +835,155 @@ impl Disassembler {
         analysis
     }
     
+    /// Issues found by `run`, as an array of `{offset, issue}` objects
+    pub fn issues_json(&self) -> serde_json::Value {
+        let issues: Vec<serde_json::Value> = self.disassembled.iter()
+            .flat_map(|line| line.issues.iter().map(|issue| json!({
+                "offset": line.offset,
+                "issue": issue,
+            })))
+            .collect();
+        json!(issues)
+    }
+    
+    /// Compute unit analysis as `{total_cu, instructions: [{offset, instruction, cu}]}`
+    pub fn cu_analysis_json(&self) -> serde_json::Value {
+        let instructions: Vec<serde_json::Value> = self.get_detailed_cu_analysis().into_iter()
+            .map(|(offset, instruction, cu)| json!({
+                "offset": offset,
+                "instruction": instruction,
+                "cu": cu,
+            }))
+            .collect();
+        json!({
+            "total_cu": self.analyze_compute_units(),
+            "instructions": instructions,
+        })
+    }
+    
+    /// The disassembly as assembler source, with each label defined on the
+    /// line before its target
+    pub fn to_source(&self) -> String {
+        let mut source = String::new();
+        for line in &self.disassembled {
+            if let Some(label) = self.labels.get(&line.offset) {
+                source.push_str(&format!("{}:\n", label));
+            }
+            source.push_str(line.instruction.as_deref().unwrap_or(""));
+            source.push('\n');
+        }
+        source
+    }
+    
+    /// Static destination of each control transfer, keyed by line index.
+    /// JUMP and CALL only have one when a constant was pushed just before.
+    fn jump_targets(&self) -> HashMap<usize, usize> {
+        let wide = |bytes: &[u8]| bytes.iter().fold(0usize, |acc, &b| (acc << 8) | b as usize);
+        
+        let mut targets = HashMap::new();
+        for (i, line) in self.disassembled.iter().enumerate() {
+            if !is_code_line(line) {
+                continue;
+            }
+            let operands = &line.raw_bytes[1..];
+            let target = match line.raw_bytes[0] {
+                0x30 | 0x32 => i.checked_sub(1)
+                    .map(|p| &self.disassembled[p])
+                    .filter(|prev| is_code_line(prev) && matches!(prev.raw_bytes[0], 0x01..=0x03))
+                    .map(|prev| wide(&prev.raw_bytes[1..])),
+                0x31 | 0x36..=0x38 => Some(wide(operands)),
+                0x34 | 0x35 if operands.len() == 2 => {
+                    Some(u16::from_le_bytes([operands[0], operands[1]]) as usize)
+                }
+                _ => None,
+            };
+            if let Some(target) = target {
+                targets.insert(i, target);
+            }
+        }
+        targets
+    }
+    
+    /// Name each static jump and call target and show the name in place of
+    /// the numeric operand. For JUMP and CALL that is the PUSH before them.
+    /// Targets that aren't the start of an instruction keep their number.
+    fn label_targets(&mut self) {
+        self.labels.clear();
+        let starts: HashSet<usize> = self.disassembled.iter()
+            .filter(|line| is_code_line(line))
+            .map(|line| line.offset)
+            .collect();
+        
+        for (i, target) in self.jump_targets() {
+            if !starts.contains(&target) {
+                continue;
+            }
+            let label = self.labels.entry(target)
+                .or_insert_with(|| format!("L_{:04X}", target))
+                .clone();
+            let operand_line = match self.disassembled[i].raw_bytes[0] {
+                0x30 | 0x32 => i - 1,
+                _ => i,
+            };
+            let line = &mut self.disassembled[operand_line];
+            line.instruction = Some(format!("{} {}", opcode_name(line.raw_bytes[0]), label));
+            line.operand = Some(label);
+        }
+    }
+    
+    /// Split the disassembled program into basic blocks and connect them.
+    ///
+    /// Blocks start at the program entry, at static jump targets and after
+    /// any jump, CALL, RETURN or HALT. Data lines are left out. JUMP and CALL
+    /// destinations are only known when a constant was pushed just before.
+    pub fn build_cfg(&self) -> ControlFlowGraph {
+        let targets = self.jump_targets();
+        let leaders: HashSet<usize> = targets.values().copied().collect();
+        
+        // Cut the listing into blocks
+        let mut cfg = ControlFlowGraph::default();
+        let mut current: Option<BasicBlock> = None;
+        for (i, line) in self.disassembled.iter().enumerate() {
+            if !is_code_line(line) {
+                cfg.blocks.extend(current.take());
+                continue;
+            }
+            if leaders.contains(&line.offset) {
+                cfg.blocks.extend(current.take());
+            }
+            current.get_or_insert_with(|| BasicBlock { start: line.offset, lines: Vec::new() })
+                .lines.push(i);
+            if matches!(line.raw_bytes[0], 0x30..=0x38 | 0xFF) {
+                cfg.blocks.extend(current.take());
+            }
+        }
+        cfg.blocks.extend(current);
+        
+        // Connect each block to where control can go next
+        let block_at = |offset: usize| cfg.blocks.iter().position(|b| b.start == offset);
+        let mut edges = Vec::new();
+        for (b, block) in cfg.blocks.iter().enumerate() {
+            let last = *block.lines.last().unwrap();
+            let line = &self.disassembled[last];
+            let opcode = line.raw_bytes[0];
+            
+            if let Some(to) = targets.get(&last).and_then(|&t| block_at(t)) {
+                let kind = if opcode == 0x32 { EdgeKind::Call } else { EdgeKind::Jump };
+                edges.push((b, to, kind));
+            }
+            // Everything but unconditional jumps, RETURN and HALT can fall
+            // through; CALL comes back to the next instruction
+            if !matches!(opcode, 0x30 | 0x33 | 0x34 | 0xFF) {
+                let next = line.offset + line.raw_bytes.len();
+                if let Some(to) = block_at(next) {
+                    edges.push((b, to, EdgeKind::Fallthrough));
+                }
+            }
+        }
+        cfg.edges = edges;
+        cfg
+    }
+    
     /// Calculate compute units for an opcode
     fn compute_units_for_opcode(&self, opcode: u8) -> u64 {
         match opcode {
@@ -329,17 +994,21 @@ impl Disassembler {
             
             // Basic arithmetic - medium cost
             0x10 | 0x11 => 10,        // ADD, SUB
-            0x12 | 0x13 => 20,        // MUL, DIV
+            0x12..=0x14 => 20,        // MUL, DIV, MOD
             
             // Bitwise operations - medium cost
             0x20 | 0x21 | 0x22 | 0x23 => 10, // AND, OR, XOR, NOT
+            0x24..=0x27 => 10,         // LT, GT, EQ, ISZERO
             
             // Control flow - higher cost
             0x30 | 0x31 => 15,        // JUMP, JUMPIF
+            0x34 | 0x35 => 15,        // JUMP2, JUMPIF2
+            0x36..=0x38 => 15,        // JUMPC, JUMPZ, JUMPO
             0x32 | 0x33 => 25,        // CALL, RETURN
             
             // Memory operations - high cost
             0x40 | 0x41 => 30,        // LOAD, STORE
+            0x44 | 0x45 => 40,        // LOADW, STOREW
             
             // Solana operations - very high cost
             0x50 | 0x51 | 0x52 => 100, // SOLTRANSFER, TOKENTRANSFER, SYSCALL
@@ -348,6 +1017,8 @@ impl Disassembler {
             0x60 | 0x61 => 20,         // VLOAD, VSTORE
             0x62 | 0x63 => 15,         // VADD, VSUB
             0x64 => 25,                // VDOT
+            0x65 => 20,                // VMUL
+            0x66 => 10,                // VBROADCAST
             
             // Floating point operations
             0x70 => 5,                 // PUSHF
@@ -358,11 +1029,14 @@ impl Disassembler {
             
             // Advanced operations
             0x80 => 40,                // MATMUL
+            0x81 => 20,                // MATVEC
+            0x82 => 10,                // MATTRANS
             0x90 => 100,               // CRYPTOHASH
             0xA0 => 8,                 // CPLXADD
             0xA1 => 15,                // CPLXMUL
             0xB0 | 0xB1 => 2,          // STRPUSH, STRPOP
             0xB2 | 0xB3 => 1,          // STRCLEAR, STRLEN
+            0xB8 | 0xB9 => 5,          // ITOA, ATOI
             0xC0 => 25,                // JSONPARSE
             0xD0 => 10,                // MAPINIT
             0xD1 | 0xD2 => 5,          // MAPSET, MAPGET
@@ -383,6 +1057,10 @@ impl fmt::Display for Disassembler {
         writeln!(f, "-------|------------------|--------------------------------------------------")?;
         
         for line in &self.disassembled {
+            if let Some(label) = self.labels.get(&line.offset) {
+                writeln!(f, "       |                  | {}:", label)?;
+            }
+            
             // Format raw bytes
             let mut bytes_str = String::new();
             for byte in &line.raw_bytes {
@@ -410,6 +1088,454 @@ impl fmt::Display for Disassembler {
     }
 }
 
+/// Problem found while assembling source text
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    /// 1-based line number
+    pub line: usize,
+    
+    /// 1-based column of the offending token
+    pub column: usize,
+    
+    pub message: String,
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Opcode for a mnemonic such as `PUSH1` or `add`, ignoring case
+pub fn opcode_from_name(name: &str) -> Option<u8> {
+    let name = name.to_ascii_uppercase();
+    if name.starts_with("UNKNOWN") {
+        return None;
+    }
+    (0..=255u8).find(|&opcode| opcode_name(opcode) == name)
+}
+
+/// Encoding of opcodes whose multi-byte operand can be written as one value
+#[derive(Debug, Clone, Copy)]
+enum WideOperand {
+    BigEndian,
+    LittleEndian,
+    Float,
+}
+
+fn wide_operand(opcode: u8) -> Option<WideOperand> {
+    match opcode {
+        0x02 | 0x03 => Some(WideOperand::BigEndian),    // PUSH2, PUSH4
+        0x44 | 0x45 => Some(WideOperand::BigEndian),    // LOADW, STOREW
+        0x34 | 0x35 => Some(WideOperand::LittleEndian), // JUMP2, JUMPIF2
+        0x70 => Some(WideOperand::Float),               // PUSHF
+        _ => None,
+    }
+}
+
+/// Operand of a complete instruction as the assembler would accept it:
+/// wide values as a single hex number, floats in decimal and DUP and SWAP
+/// depths in decimal. `None` for instructions whose operands are separate
+/// bytes, and for floats that can't be written back exactly.
+fn decode_operand(opcode: u8, params: &[u8]) -> Option<String> {
+    let wide = |bytes: &mut dyn Iterator<Item = &u8>| bytes.fold(0u64, |acc, &b| (acc << 8) | b as u64);
+    match (opcode, wide_operand(opcode)) {
+        (0x05 | 0x06, _) => Some(params[0].to_string()),
+        (_, Some(WideOperand::BigEndian)) => {
+            Some(format!("0x{:0width$X}", wide(&mut params.iter()), width = params.len() * 2))
+        }
+        (_, Some(WideOperand::LittleEndian)) => {
+            Some(format!("0x{:0width$X}", wide(&mut params.iter().rev()), width = params.len() * 2))
+        }
+        (_, Some(WideOperand::Float)) => {
+            let value = f64::from_le_bytes(params.try_into().ok()?);
+            value.is_finite().then(|| format!("{:?}", value))
+        }
+        _ => None,
+    }
+}
+
+/// Instruction waiting for its operands to be encoded once labels are known
+struct PendingInstruction {
+    line: usize,
+    column: usize,
+    
+    /// Opcode, or `None` for a `.byte`, `.ascii` or `.zero` data directive
+    opcode: Option<u8>,
+    
+    operands: Vec<(usize, String)>,
+    
+    /// Bytes already known when the line was parsed, from `.ascii` and `.zero`
+    data: Vec<u8>,
+}
+
+/// Resolve a number or label operand
+fn resolve_operand(
+    labels: &std::collections::HashMap<String, usize>,
+    column: usize,
+    token: &str,
+) -> Result<u64, (usize, String)> {
+    if let Some(value) = parse_number(token) {
+        Ok(value)
+    } else if let Some(&target) = labels.get(token) {
+        Ok(target as u64)
+    } else if is_label_name(token) {
+        Err((column, format!("undefined label `{}`", token)))
+    } else {
+        Err((column, format!("invalid operand `{}`", token)))
+    }
+}
+
+/// Split a line into tokens with their 1-based columns, dropping `;` comments.
+/// Double-quoted strings are kept whole, including any spaces, commas or `;`.
+pub fn tokenize(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = Vec::new();
+    let mut start = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut end = line.len();
+    
+    for (idx, ch) in line.char_indices() {
+        if in_string {
+            match ch {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        
+        if ch == ';' {
+            end = idx;
+            break;
+        }
+        if ch.is_whitespace() || ch == ',' {
+            if let Some(begin) = start.take() {
+                tokens.push((begin + 1, &line[begin..idx]));
+            }
+        } else {
+            if start.is_none() {
+                start = Some(idx);
+            }
+            in_string = ch == '"';
+        }
+    }
+    if let Some(begin) = start {
+        tokens.push((begin + 1, &line[begin..end]));
+    }
+    
+    tokens
+}
+
+/// Decode a double-quoted string literal as written by the disassembler
+fn parse_string(token: &str) -> Option<Vec<u8>> {
+    let inner = token.strip_prefix('"')?.strip_suffix('"')?;
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    
+    while let Some(ch) = chars.next() {
+        let ch = match ch {
+            '\\' => match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '0' => '\0',
+                escaped @ ('\\' | '"' | '\'') => escaped,
+                _ => return None,
+            },
+            '"' => return None,
+            _ => ch,
+        };
+        let mut buf = [0; 4];
+        bytes.extend_from_slice(ch.encode_utf8(&mut buf).as_bytes());
+    }
+    
+    Some(bytes)
+}
+
+/// Whether `name` can be used as a label: an identifier of ASCII letters,
+/// digits and underscores that doesn't start with a digit
+pub fn is_label_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Value of a decimal, `0x` hex or `0b` binary operand
+pub fn parse_number(token: &str) -> Option<u64> {
+    if let Some(hex) = token.strip_prefix("0x").or_else(|| token.strip_prefix("0X")) {
+        u64::from_str_radix(hex, 16).ok()
+    } else if let Some(bin) = token.strip_prefix("0b").or_else(|| token.strip_prefix("0B")) {
+        u64::from_str_radix(bin, 2).ok()
+    } else {
+        token.parse().ok()
+    }
+}
+
+/// Encode operands that are one byte each, reporting values that don't fit
+fn encode_byte_operands(
+    labels: &std::collections::HashMap<String, usize>,
+    line: usize,
+    name: &str,
+    operands: &[(usize, String)],
+    bytecode: &mut Vec<u8>,
+    problems: &mut Vec<Problem>,
+) {
+    for (column, token) in operands {
+        match resolve_operand(labels, *column, token) {
+            Ok(value) if value <= u8::MAX as u64 => bytecode.push(value as u8),
+            Ok(value) => problems.push(Problem {
+                line,
+                column: *column,
+                message: format!("value {} does not fit in a byte for {}", value, name),
+            }),
+            Err((column, message)) => problems.push(Problem { line, column, message }),
+        }
+    }
+}
+
+/// Assemble LessVM source text into bytecode.
+///
+/// Each line holds an optional `name:` label followed by an optional
+/// instruction; `;` starts a comment. Operands are numbers (decimal, `0x`
+/// hex or `0b` binary) or label names, which resolve to the label's offset.
+/// Operands are written one per byte, as the disassembler prints them, but
+/// PUSH2, PUSH4, JUMP2, JUMPIF2 and PUSHF also take a single value that is
+/// encoded the way the VM reads it.
+///
+/// Data is emitted with `.byte` (byte values or labels), `.ascii "text"`
+/// and `.zero <count>`, matching how the disassembler prints data regions.
+pub fn assemble(source: &str) -> Result<Vec<u8>, Vec<Problem>> {
+    assemble_with_lines(source).map(|(bytecode, _)| bytecode)
+}
+
+/// Offset and source line of each assembled instruction
+pub type SourceLines = Vec<(usize, usize)>;
+
+/// Assemble `source` like `assemble`, also returning where each instruction
+/// came from, for building a `SourceMap`
+pub fn assemble_with_lines(source: &str) -> Result<(Vec<u8>, SourceLines), Vec<Problem>> {
+    let mut problems = Vec::new();
+    let mut labels = std::collections::HashMap::new();
+    let mut pending = Vec::new();
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    
+    // First pass: collect labels and instruction sizes
+    for (line_idx, line) in source.lines().enumerate() {
+        let line_no = line_idx + 1;
+        let mut tokens = tokenize(line).into_iter().peekable();
+        
+        if let Some(&(column, token)) = tokens.peek() {
+            if let Some(name) = token.strip_suffix(':') {
+                tokens.next();
+                if !is_label_name(name) {
+                    problems.push(Problem {
+                        line: line_no,
+                        column,
+                        message: format!("invalid label name `{}`", name),
+                    });
+                } else if labels.insert(name.to_string(), offset).is_some() {
+                    problems.push(Problem {
+                        line: line_no,
+                        column,
+                        message: format!("label `{}` is already defined", name),
+                    });
+                }
+            }
+        }
+        
+        let Some((column, mnemonic)) = tokens.next() else {
+            continue;
+        };
+        let mut problem = |column: usize, message: String| {
+            problems.push(Problem { line: line_no, column, message });
+        };
+        
+        if let Some(directive) = mnemonic.strip_prefix('.') {
+            let operands: Vec<(usize, &str)> = tokens.collect();
+            let (data, operands) = match (directive, operands.as_slice()) {
+                ("byte", _) => (Vec::new(), operands),
+                ("ascii", [(col, token)]) => match parse_string(token) {
+                    Some(bytes) => (bytes, Vec::new()),
+                    None => {
+                        problem(*col, format!("invalid string `{}`", token));
+                        continue;
+                    }
+                },
+                ("zero", [(col, token)]) => match parse_number(token) {
+                    // The program has to fit in the VM's memory
+                    Some(count) if count > DEFAULT_MAX_MEMORY.saturating_sub(offset) as u64 => {
+                        problem(*col, format!("count {} doesn't fit in {} bytes of VM memory", token, DEFAULT_MAX_MEMORY));
+                        continue;
+                    }
+                    Some(count) => (vec![0; count as usize], Vec::new()),
+                    None => {
+                        problem(*col, format!("invalid count `{}`", token));
+                        continue;
+                    }
+                },
+                ("ascii" | "zero", _) => {
+                    problem(column, format!("{} takes 1 operand, found {}", mnemonic, operands.len()));
+                    continue;
+                }
+                _ => {
+                    problem(column, format!("unknown directive `{}`", mnemonic));
+                    continue;
+                }
+            };
+            
+            offset += data.len() + operands.len();
+            pending.push(PendingInstruction {
+                line: line_no,
+                column,
+                opcode: None,
+                operands: operands.into_iter().map(|(col, tok)| (col, tok.to_string())).collect(),
+                data,
+            });
+            continue;
+        }
+        
+        let Some(opcode) = opcode_from_name(mnemonic) else {
+            problem(column, format!("unknown instruction `{}`", mnemonic));
+            continue;
+        };
+        
+        lines.push((offset, line_no));
+        offset += 1 + opcode_param_count(opcode);
+        pending.push(PendingInstruction {
+            line: line_no,
+            column,
+            opcode: Some(opcode),
+            operands: tokens.map(|(col, tok)| (col, tok.to_string())).collect(),
+            data: Vec::new(),
+        });
+    }
+    
+    // Second pass: encode instructions with labels resolved
+    let mut bytecode = Vec::with_capacity(offset);
+    for instr in &pending {
+        let mut problem = |column: usize, message: String| {
+            problems.push(Problem { line: instr.line, column, message });
+        };
+        let resolve = |column: usize, token: &str| resolve_operand(&labels, column, token);
+        
+        let Some(opcode) = instr.opcode else {
+            bytecode.extend_from_slice(&instr.data);
+            let start = bytecode.len();
+            encode_byte_operands(&labels, instr.line, ".byte", &instr.operands, &mut bytecode, &mut problems);
+            bytecode.resize(start + instr.operands.len(), 0);
+            continue;
+        };
+        
+        let name = opcode_name(opcode);
+        let param_count = opcode_param_count(opcode);
+        bytecode.push(opcode);
+        let start = bytecode.len();
+        
+        match (wide_operand(opcode), instr.operands.as_slice()) {
+            (Some(WideOperand::Float), [(column, token)]) => match token.parse::<f64>() {
+                Ok(value) => bytecode.extend_from_slice(&value.to_le_bytes()),
+                Err(_) => problem(*column, format!("invalid floating point value `{}`", token)),
+            },
+            (Some(encoding), [(column, token)]) if param_count > 1 => {
+                match resolve(*column, token) {
+                    Ok(value) if value >> (8 * param_count) == 0 => {
+                        let bytes = value.to_le_bytes();
+                        let bytes = &bytes[..param_count];
+                        match encoding {
+                            WideOperand::BigEndian => bytecode.extend(bytes.iter().rev()),
+                            _ => bytecode.extend_from_slice(bytes),
+                        }
+                    }
+                    Ok(value) => problem(*column, format!(
+                        "value {} does not fit in {} bytes for {}", value, param_count, name
+                    )),
+                    Err((column, message)) => problem(column, message),
+                }
+            }
+            (_, operands) if operands.len() == param_count => {
+                encode_byte_operands(&labels, instr.line, &name, operands, &mut bytecode, &mut problems);
+            }
+            (_, operands) => {
+                let expected = if wide_operand(opcode).is_some() && param_count > 1 {
+                    format!("1 operand or {} bytes", param_count)
+                } else if param_count == 1 {
+                    "1 operand".to_string()
+                } else {
+                    format!("{} operands", param_count)
+                };
+                let column = operands.get(param_count).map_or(instr.column, |(col, _)| *col);
+                problem(column, format!("{} takes {}, found {}", name, expected, operands.len()));
+            }
+        }
+        
+        // Keep offsets stable after an error so later problems still make sense
+        bytecode.resize(start + param_count, 0);
+    }
+    
+    if problems.is_empty() {
+        Ok((bytecode, lines))
+    } else {
+        problems.sort_by_key(|p| (p.line, p.column));
+        Err(problems)
+    }
+}
+
+/// Why bytecode didn't survive being disassembled and assembled again
+#[derive(Debug, Clone, PartialEq)]
+pub enum RoundtripError {
+    /// The disassembly isn't valid assembler source
+    Reassemble(Vec<Problem>),
+    
+    /// The bytes differ from `offset` on. `None` is past the end.
+    Mismatch { offset: usize, original: Option<u8>, reassembled: Option<u8> },
+}
+
+impl fmt::Display for RoundtripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let byte = |b: &Option<u8>| b.map_or_else(|| "end of program".to_string(), |b| format!("{:#04X}", b));
+        match self {
+            RoundtripError::Reassemble(problems) => {
+                write!(f, "disassembly does not assemble:")?;
+                for problem in problems {
+                    write!(f, "\n  {}", problem)?;
+                }
+                Ok(())
+            }
+            RoundtripError::Mismatch { offset, original, reassembled } => write!(
+                f, "bytecode differs at offset {:#06X}: {} became {}", offset, byte(original), byte(reassembled),
+            ),
+        }
+    }
+}
+
+/// Disassemble `bytecode` and assemble the listing again, checking the same
+/// bytes come back. Catches instructions the disassembler prints in a form
+/// the assembler encodes differently.
+pub fn roundtrip(bytecode: &[u8]) -> Result<(), RoundtripError> {
+    let mut disasm = Disassembler::new(bytecode.to_vec());
+    disasm.run();
+    let reassembled = assemble(&disasm.to_source()).map_err(RoundtripError::Reassemble)?;
+    match first_difference(bytecode, &reassembled) {
+        Some(offset) => Err(RoundtripError::Mismatch {
+            offset,
+            original: bytecode.get(offset).copied(),
+            reassembled: reassembled.get(offset).copied(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// First offset where `a` and `b` differ, including one being longer
+fn first_difference(a: &[u8], b: &[u8]) -> Option<usize> {
+    a.iter()
+        .zip(b)
+        .position(|(x, y)| x != y)
+        .or_else(|| (a.len() != b.len()).then(|| a.len().min(b.len())))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -429,6 +1555,127 @@ mod tests {
         assert_eq!(disasm.disassembled[3].instruction, Some("HALT".to_string()));
     }
     
+    #[test]
+    fn test_program_from_bytecode() {
+        // PUSH1 42, PUSH2 0x1234, ADD, HALT, then an embedded string
+        let mut bytecode = vec![0x01, 42, 0x02, 0x12, 0x34, 0x10, 0xFF];
+        bytecode.extend_from_slice(b"hello, world");
+        
+        let program = Program::from_bytecode(&bytecode);
+        assert_eq!(program.instructions.len(), 4);
+        assert_eq!(program.data_regions.len(), 1);
+        
+        let push = &program.instructions[0];
+        assert_eq!((push.opcode, push.name.as_str(), push.offset, push.size), (0x01, "PUSH1", 0, 2));
+        assert_eq!(push.params, vec![42]);
+        assert_eq!(program.instructions[1].params, vec![0x12, 0x34]);
+        assert_eq!(program.instructions[2].name, "ADD");
+        
+        let json = program.to_json();
+        assert_eq!(json["instructions"][1]["offset"], 2);
+        assert_eq!(json["instructions"][1]["params"], serde_json::json!([0x12, 0x34]));
+        assert_eq!(json["data_regions"][0]["kind"], "Ascii");
+    }
+    
+    #[test]
+    fn test_build_cfg() {
+        // 0x00: PUSH1 3, PUSH1 0, JUMPIF 0x0B   (forward branch)
+        // 0x06: PUSH1 1, JUMP2 0x0E
+        // 0x0B: PUSH1 2, POP
+        // 0x0E: PUSH1 1, JUMPIF 0x06            (backward branch)
+        // 0x12: HALT
+        let program = vec![
+            0x01, 3, 0x01, 0, 0x31, 0x0B,
+            0x01, 1, 0x34, 0x0E, 0x00,
+            0x01, 2, 0x04,
+            0x01, 1, 0x31, 0x06,
+            0xFF,
+        ];
+        let mut disasm = Disassembler::new(program);
+        disasm.run();
+        let cfg = disasm.build_cfg();
+        
+        let starts: Vec<usize> = cfg.blocks.iter().map(|b| b.start).collect();
+        assert_eq!(starts, vec![0x00, 0x06, 0x0B, 0x0E, 0x12]);
+        assert_eq!(cfg.edges.len(), 6);
+        assert!(cfg.edges.contains(&(0, 2, EdgeKind::Jump)));
+        assert!(cfg.edges.contains(&(0, 1, EdgeKind::Fallthrough)));
+        assert!(cfg.edges.contains(&(3, 1, EdgeKind::Jump)));
+        assert!(!cfg.edges.iter().any(|&(from, _, _)| from == 4));
+        
+        let dot = cfg.to_dot(&disasm);
+        assert!(dot.starts_with("digraph cfg {"));
+        assert_eq!(dot.matches(" -> ").count(), 6);
+    }
+    
+    #[test]
+    fn test_jump_labels() {
+        // 0x00: PUSH1 1, JUMPIF 0x0C
+        // 0x04: PUSH1 0, JUMPIF2 0x000C
+        // 0x09: PUSH1 0x0C, JUMP
+        // 0x0C: HALT
+        let bytecode = vec![0x01, 1, 0x31, 0x0C, 0x01, 0, 0x35, 0x0C, 0x00, 0x01, 0x0C, 0x30, 0xFF];
+        let mut disasm = Disassembler::new(bytecode.clone());
+        disasm.run();
+        
+        assert_eq!(disasm.labels, HashMap::from([(0x0C, "L_000C".to_string())]));
+        let text: Vec<_> = disasm.disassembled.iter().map(|l| l.instruction.clone().unwrap()).collect();
+        assert_eq!(text, ["PUSH1 0x01", "JUMPIF L_000C", "PUSH1 0x00", "JUMPIF2 L_000C",
+                          "PUSH1 L_000C", "JUMP", "HALT"]);
+        
+        let listing = disasm.to_string();
+        assert_eq!(listing.matches("L_000C:").count(), 1);
+        assert!(listing.contains("| L_000C:\n00000C | FF "));
+        assert_eq!(assemble(&disasm.to_source()), Ok(bytecode));
+    }
+    
+    #[test]
+    fn test_operand_display() {
+        let mut bytecode = vec![
+            0x01, 0x2A,                   // PUSH1
+            0x02, 0x12, 0x34,             // PUSH2
+            0x03, 0x00, 0x00, 0xDE, 0xAD, // PUSH4
+            0x05, 3,                      // DUP
+            0x06, 1,                      // SWAP
+            0x44, 0x01, 0x00,             // LOADW
+            0x45, 0x01, 0x08,             // STOREW
+            0x81, 0x01, 0x23,             // MATVEC
+            0x35, 0x01, 0x02,             // JUMPIF2 past the end
+            0x70,                         // PUSHF
+        ];
+        bytecode.extend_from_slice(&(-2.5f64).to_le_bytes());
+        bytecode.push(0x70);
+        bytecode.extend_from_slice(&f64::NAN.to_le_bytes());
+        bytecode.push(0xFF);
+        
+        let mut disasm = Disassembler::new(bytecode.clone());
+        disasm.run();
+        let text: Vec<_> = disasm.disassembled.iter().map(|l| l.instruction.clone().unwrap()).collect();
+        assert_eq!(text, [
+            "PUSH1 0x2A",
+            "PUSH2 0x1234",
+            "PUSH4 0x0000DEAD",
+            "DUP 3",
+            "SWAP 1",
+            "LOADW 0x0100",
+            "STOREW 0x0108",
+            "MATVEC 0x01, 0x23",
+            "JUMPIF2 0x0201",
+            "PUSHF -2.5",
+            "PUSHF 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xF8, 0x7F",
+            "HALT",
+        ]);
+        let operands: Vec<_> = disasm.disassembled.iter().map(|l| l.operand.as_deref()).collect();
+        assert_eq!(&operands[..4], [None, Some("0x1234"), Some("0x0000DEAD"), Some("3")]);
+        assert!(disasm.to_string().contains("| 03 00 00 DE AD     | PUSH4 0x0000DEAD\n"));
+        assert_eq!(assemble(&disasm.to_source()), Ok(bytecode));
+        
+        // Labels replace the operand
+        let mut disasm = Disassembler::new(vec![0x34, 0x03, 0x00, 0xFF]);
+        disasm.run();
+        assert_eq!(disasm.disassembled[0].operand.as_deref(), Some("L_0003"));
+    }
+    
     #[test]
     fn test_analyze_compute_units() {
         // Create a simple program: PUSH1 42, PUSH1 21, ADD, HALT
@@ -440,4 +1687,184 @@ mod tests {
         // Expected CU: 2 * PUSH1 (3) + ADD (10) + HALT (1) = 17
         assert_eq!(cu, 17);
     }
+
+    
+    #[test]
+    fn test_assemble_loop() {
+        use crate::lessvm::VM;
+        
+        // Sum 5 + 4 + 3 + 2 + 1, keeping the counter at 0xF0
+        let source = "
+                PUSH1 5
+                STORE 0xF0      ; counter
+                PUSH1 0         ; running sum
+            loop:
+                LOAD 0xF0
+                ADD
+                LOAD 0xF0
+                PUSH1 1
+                SUB
+                DUP 0
+                STORE 0xF0
+                JUMPIF loop
+                halt
+        ";
+        let bytecode = assemble(source).unwrap();
+        assert_eq!(&bytecode[..7], &[0x01, 5, 0x41, 0xF0, 0x01, 0, 0x40]);
+        assert_eq!(&bytecode[bytecode.len() - 3..], &[0x31, 6, 0xFF]);
+        
+        let mut vm = VM::new();
+        vm.load_bytecode(&bytecode).unwrap();
+        vm.run(1000).unwrap();
+        assert_eq!(vm.stack, vec![15]);
+    }
+    
+    #[test]
+    fn test_assemble_wide_operands_and_forward_labels() {
+        let source = "JUMP2 end\nPUSH2 0x1234\nPUSH2 0x12, 0x34\nend: PUSH4 end\nPUSHF 1.5";
+        let bytecode = assemble(source).unwrap();
+        
+        let mut expected = vec![0x34, 9, 0, 0x02, 0x12, 0x34, 0x02, 0x12, 0x34, 0x03, 0, 0, 0, 9, 0x70];
+        expected.extend_from_slice(&1.5f64.to_le_bytes());
+        assert_eq!(bytecode, expected);
+        
+        // Disassembled output assembles back to the same bytes
+        let mut disasm = Disassembler::new(bytecode.clone());
+        disasm.run();
+        assert_eq!(assemble(&disasm.to_source()).unwrap(), bytecode);
+    }
+    
+    #[test]
+    fn test_roundtrip() {
+        let source = "
+                PUSH1 1
+                JUMPIF start
+                .ascii \"skipped\"
+            start:
+                PUSH2 0x1234
+                PUSH4 0x12345678
+                PUSHF -2.25
+                PUSH1 done
+                JUMP
+                JUMP2 start
+            done:
+                HALT
+        ";
+        let bytecode = assemble(source).unwrap();
+        assert_eq!(roundtrip(&bytecode), Ok(()));
+        
+        // A PUSH2 cut short by the end of the program disassembles as a
+        // one-byte value, which assembles zero-padded on the left
+        assert_eq!(
+            roundtrip(&[0x01, 1, 0x02, 0x12]),
+            Err(RoundtripError::Mismatch { offset: 3, original: Some(0x12), reassembled: Some(0x00) }),
+        );
+        
+        assert_eq!(first_difference(&[1, 2, 3], &[1, 2, 3]), None);
+        assert_eq!(first_difference(&[1, 2, 3], &[1, 5, 3]), Some(1));
+        assert_eq!(first_difference(&[1, 2, 3], &[1, 2]), Some(2));
+        let mismatch = RoundtripError::Mismatch { offset: 2, original: Some(3), reassembled: None };
+        assert_eq!(mismatch.to_string(), "bytecode differs at offset 0x0002: 0x03 became end of program");
+    }
+    
+    #[test]
+    fn test_assemble_reports_problems() {
+        let source = "PUSH1 1\n  FROB 2\nPUSH1 300\nJUMPIF nowhere\nADD 1\ndup: POP\ndup: POP";
+        let problems = assemble(source).unwrap_err();
+        
+        let found: Vec<(usize, usize)> = problems.iter().map(|p| (p.line, p.column)).collect();
+        assert_eq!(found, vec![(2, 3), (3, 7), (4, 8), (5, 5), (7, 1)]);
+        assert_eq!(problems[0].message, "unknown instruction `FROB`");
+        assert_eq!(problems[2].message, "undefined label `nowhere`");
+        assert_eq!(problems[3].to_string(), "5:5: ADD takes 0 operands, found 1");
+        
+        // Zero fill can't be larger than the address space, alone or together
+        let problems = assemble(".zero 0xFFFFFFFFFFFF").unwrap_err();
+        assert_eq!(problems[0].to_string(), "1:7: count 0xFFFFFFFFFFFF doesn't fit in 1048576 bytes of VM memory");
+        assert_eq!(assemble(".zero 0x80000\n.zero 0x80001").unwrap_err()[0].line, 2);
+    }
+    
+    #[test]
+    fn test_detect_embedded_string() {
+        // PUSH1 1, JUMPIF over the string, "Hello, LessVM!", zero padding, HALT
+        let mut bytecode = vec![0x01, 1, 0x31, 0];
+        bytecode.extend_from_slice(b"Hello, LessVM!");
+        bytecode.extend_from_slice(&[0; 6]);
+        bytecode.push(0xFF);
+        let halt = bytecode.len() - 1;
+        bytecode[3] = halt as u8;
+        
+        let mut disasm = Disassembler::new(bytecode);
+        disasm.run();
+        
+        assert_eq!(disasm.data_regions, vec![
+            DataRegion { start: 4, end: 18, kind: DataKind::Ascii },
+            DataRegion { start: 18, end: 24, kind: DataKind::Zeros },
+        ]);
+        let text: Vec<&str> = disasm.disassembled.iter()
+            .map(|line| line.instruction.as_deref().unwrap())
+            .collect();
+        assert_eq!(text, vec![
+            "PUSH1 0x01",
+            "JUMPIF L_0018",
+            ".ascii \"Hello, L\"",
+            ".ascii \"essVM!\"",
+            ".zero 6",
+            "HALT",
+        ]);
+        
+        // Data lines assemble back to the same bytes
+        assert_eq!(assemble(&disasm.to_source()).unwrap(), disasm.bytecode);
+        assert_eq!(assemble(".ascii \"a; \\\"b\\\"\" ; comment").unwrap(), b"a; \"b\"");
+        assert_eq!(assemble(".byte 1, end\nend: .zero 2").unwrap(), vec![1, 2, 0, 0]);
+    }
+    
+    #[test]
+    fn test_detect_data_with_computed_jump() {
+        // A JUMP whose target isn't a constant makes reachability unknown, so
+        // only long strings are flagged
+        let mut bytecode = vec![0x05, 0, 0x30, 0xFF];
+        bytecode.extend_from_slice(b"Hi!!");
+        bytecode.extend_from_slice(b"a long enough string");
+        
+        let mut disasm = Disassembler::new(bytecode);
+        disasm.run();
+        
+        assert_eq!(disasm.data_regions, vec![
+            DataRegion { start: 4, end: 28, kind: DataKind::Ascii },
+        ]);
+        
+        // Unreachable bytes with no recognisable content are still flagged
+        let mut disasm = Disassembler::new(vec![0xFF, 0x10, 0x93, 0x07]);
+        disasm.run();
+        assert_eq!(disasm.data_regions, vec![
+            DataRegion { start: 1, end: 4, kind: DataKind::Unreachable },
+        ]);
+        assert_eq!(disasm.disassembled[1].instruction.as_deref(), Some(".byte 0x10, 0x93, 0x07"));
+    }
+    
+    #[test]
+    fn test_json_reports() {
+        // PUSH1 2, PUSH1 3, ADD, SOLTRANSFER, HALT
+        let mut disasm = Disassembler::new(vec![0x01, 2, 0x01, 3, 0x10, 0x50, 0xFF]);
+        disasm.run();
+        
+        let report: serde_json::Value = serde_json::from_str(&disasm.cu_analysis_json().to_string()).unwrap();
+        let instructions = report["instructions"].as_array().unwrap();
+        assert_eq!(instructions.len(), 5);
+        assert_eq!(instructions[4]["offset"], 6);
+        assert_eq!(instructions[4]["instruction"], "HALT");
+        
+        let sum: u64 = instructions.iter().map(|i| i["cu"].as_u64().unwrap()).sum();
+        assert_eq!(report["total_cu"].as_u64(), Some(sum));
+        assert_eq!(sum, disasm.analyze_compute_units());
+        
+        // A PUSH2 cut short by the end of the program
+        let mut disasm = Disassembler::new(vec![0x01, 7, 0x02, 0x01]);
+        disasm.run();
+        let issues: serde_json::Value = serde_json::from_str(&disasm.issues_json().to_string()).unwrap();
+        assert_eq!(issues, json!([
+            { "offset": 2, "issue": "Incomplete instruction: missing parameter 2" },
+        ]));
+    }
 }
@@ -0,0 +1,128 @@
+use std::fmt;
+
+use rayon::prelude::*;
+use serde_json::json;
+
+use crate::lessvm::{VM, VMState};
+
+/// Options shared by every program in a batch run
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions {
+    /// Maximum number of cycles each program may execute
+    pub max_cycles: u32,
+
+    /// Gas limit applied to each VM (0 means unlimited)
+    pub gas_limit: u64,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        BatchOptions {
+            max_cycles: 10_000,
+            gas_limit: 0,
+        }
+    }
+}
+
+/// Result of running a single program in a batch
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunOutcome {
+    /// Final VM state
+    pub state: VMState,
+
+    /// Final stack contents, bottom first
+    pub stack: Vec<u32>,
+
+    /// Gas consumed
+    pub gas_used: u64,
+
+    /// Instructions executed
+    pub cycles: u32,
+
+    /// Error message if loading or execution failed
+    pub error: Option<String>,
+}
+
+impl RunOutcome {
+    /// Render the outcome as a JSON object
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "state": self.state.to_string(),
+            "stack": self.stack,
+            "gas_used": self.gas_used,
+            "cycles": self.cycles,
+            "error": self.error,
+        })
+    }
+}
+
+impl fmt::Display for RunOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}, stack {:?}, {} gas, {} cycles", self.state, self.stack, self.gas_used, self.cycles)?;
+        if let Some(error) = &self.error {
+            write!(f, " ({})", error)?;
+        }
+        Ok(())
+    }
+}
+
+/// Run a single program in a fresh VM
+pub fn run_one(program: &[u8], opts: &BatchOptions) -> RunOutcome {
+    let mut vm = VM::new();
+    vm.set_gas_limit(opts.gas_limit);
+
+    let result = vm
+        .load_bytecode(program)
+        .and_then(|_| vm.run(opts.max_cycles));
+
+    RunOutcome {
+        state: vm.state.clone(),
+        stack: vm.stack.clone(),
+        gas_used: vm.gas_used(),
+        cycles: vm.cycle_counter,
+        error: result.err().map(|e| e.to_string()),
+    }
+}
+
+/// Run many programs in parallel, each in its own VM.
+///
+/// Outcomes are returned in the same order as `programs`.
+pub fn run_batch(programs: &[Vec<u8>], opts: &BatchOptions) -> Vec<RunOutcome> {
+    programs
+        .par_iter()
+        .map(|program| run_one(program, opts))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_batch_identical_outcomes() {
+        // PUSH1 2, PUSH1 3, ADD, HALT
+        let program = vec![0x01, 2, 0x01, 3, 0x10, 0xFF];
+        let programs = vec![program; 100];
+
+        let outcomes = run_batch(&programs, &BatchOptions::default());
+
+        assert_eq!(outcomes.len(), 100);
+        assert_eq!(outcomes[0].state, VMState::Halted);
+        assert_eq!(outcomes[0].stack, vec![5]);
+        assert!(outcomes[0].error.is_none());
+        assert!(outcomes.iter().all(|o| *o == outcomes[0]));
+        assert_eq!(outcomes[0].to_json()["stack"], json!([5]));
+    }
+
+    #[test]
+    fn test_run_batch_reports_errors() {
+        // POP on an empty stack
+        let programs = vec![vec![0x04, 0xFF], vec![0x01, 1, 0xFF]];
+
+        let outcomes = run_batch(&programs, &BatchOptions::default());
+
+        assert!(outcomes[0].error.is_some());
+        assert!(matches!(outcomes[0].state, VMState::Error(_)));
+        assert_eq!(outcomes[1].stack, vec![1]);
+    }
+}
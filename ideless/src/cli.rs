@@ -31,6 +31,12 @@ pub enum EmulatorKind {
     Solana,
 }
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Text,
+    Json,
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None, disable_help_flag = true)]
 pub struct Cli {
@@ -48,6 +54,14 @@ pub enum CliCommand {
         #[arg(value_name = "FILE")]
         path: PathBuf,
 
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+
+        /// Also check the program disassembles and assembles back to the same bytes
+        #[arg(long)]
+        roundtrip: bool,
+
 /// Set logging level
         #[arg(short, long, value_enum)]
         log: Option<LogLevel>,
@@ -61,6 +75,18 @@ pub enum CliCommand {
         #[arg(value_name = "FILE")]
         path: PathBuf,
         
+        /// Run bytecode optimization passes before disassembling
+        #[arg(long)]
+        optimize: bool,
+        
+        /// Also write the control-flow graph to FILE in Graphviz DOT format
+        #[arg(long, value_name = "FILE")]
+        cfg: Option<PathBuf>,
+        
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
         /// Set logging level
         #[arg(short, long, value_enum)]
         log: Option<LogLevel>,
@@ -77,6 +103,30 @@ pub enum CliCommand {
         #[arg(short, long)]
         debug: bool,
         
+        /// Run bytecode optimization passes before loading
+        #[arg(long)]
+        optimize: bool,
+        
+        /// Run without the terminal UI and print the final VM state
+        #[arg(long, conflicts_with = "debug")]
+        headless: bool,
+        
+        /// Maximum cycles to execute in headless mode
+        #[arg(long, default_value_t = crate::headless::DEFAULT_MAX_CYCLES)]
+        max_cycles: u32,
+        
+        /// Output format for headless mode
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Stop with an out-of-gas error once this much gas is used
+        #[arg(long, value_name = "N")]
+        gas_limit: Option<u64>,
+        
+        /// Print per-opcode execution counts and gas when the run ends
+        #[arg(long, requires = "headless")]
+        profile: bool,
+        
         /// Cycles per frame
         #[arg(short, long)]
         cpf: Option<u32>,
@@ -85,6 +135,14 @@ pub enum CliCommand {
         #[arg(short, long)]
         hz: Option<u32>,
         
+        /// Columns between tab stops in the editor
+        #[arg(long, value_name = "N", default_value_t = 4)]
+        tab_width: usize,
+        
+        /// Indent with tabs instead of spaces in the editor
+        #[arg(long)]
+        hard_tabs: bool,
+        
         /// Set logging level
         #[arg(short, long, value_enum)]
         log: Option<LogLevel>,
@@ -98,6 +156,29 @@ pub enum CliCommand {
         network: String,
     },
     
+    /// Run several LessVM programs in parallel and print each final state
+    Batch {
+        /// Paths to LessVM program files
+        #[arg(value_name = "FILE", required = true)]
+        paths: Vec<PathBuf>,
+        
+        /// Maximum cycles each program may execute
+        #[arg(long, default_value_t = crate::headless::DEFAULT_MAX_CYCLES)]
+        max_cycles: u32,
+        
+        /// Stop each program with an out-of-gas error once this much gas is used
+        #[arg(long, value_name = "N")]
+        gas_limit: Option<u64>,
+        
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
+        /// Set logging level
+        #[arg(short, long, value_enum)]
+        log: Option<LogLevel>,
+    },
+    
     /// Run a Solana program
     RunSol {
         /// Program address
@@ -154,6 +235,18 @@ pub enum CliCommand {
         #[arg(short, long)]
         detailed: bool,
         
+        /// Warn about transfer accounts taken from untrusted input
+        #[arg(long)]
+        taint: bool,
+        
+        /// Flag programs whose estimated compute units exceed this limit
+        #[arg(long, value_name = "N")]
+        gas_limit: Option<u64>,
+        
+        /// Output format
+        #[arg(long, value_enum, default_value = "text")]
+        format: OutputFormat,
+        
         /// Set logging level
         #[arg(short, long, value_enum)]
         log: Option<LogLevel>,
@@ -0,0 +1,23 @@
+//! System clipboard access for copying text out of the TUI.
+
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Result};
+use arboard::Clipboard;
+
+/// Clipboard handle, kept open because on X11 the copied text is only
+/// served for as long as the handle that set it lives
+static CLIPBOARD: Mutex<Option<Clipboard>> = Mutex::new(None);
+
+/// Put `text` on the system clipboard
+pub fn copy(text: &str) -> Result<()> {
+    let mut clipboard = CLIPBOARD.lock().unwrap();
+    if clipboard.is_none() {
+        *clipboard = Some(Clipboard::new().map_err(|e| anyhow!("Clipboard is not available: {}", e))?);
+    }
+    clipboard
+        .as_mut()
+        .unwrap()
+        .set_text(text)
+        .map_err(|e| anyhow!("Failed to copy to the clipboard: {}", e))
+}
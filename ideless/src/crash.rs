@@ -0,0 +1,124 @@
+use std::{
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+
+use crate::asm::Disassembler;
+use crate::lessvm::{VM, VMState};
+
+/// Number of disassembled lines shown on each side of the faulting instruction
+const CONTEXT_LINES: usize = 4;
+
+/// Bytes past the faulting instruction included in the disassembly context
+const CONTEXT_BYTES: usize = 32;
+
+/// Build a human-readable crash report for a VM that ended in an error state
+pub fn crash_dump(vm: &VM) -> String {
+    let mut out = String::new();
+
+    // The PC may already have moved past the faulting instruction's opcode,
+    // so prefer the address recorded when the instruction was fetched
+    let (fault_pc, opcode) = vm
+        .last_instruction()
+        .unwrap_or((vm.pc, vm.memory.get(vm.pc).copied().unwrap_or(0)));
+
+    let error = match &vm.state {
+        VMState::Error(msg) => msg.clone(),
+        state => format!("{} (no error)", state),
+    };
+
+    let _ = writeln!(out, "=== LessVM crash dump ===");
+    let _ = writeln!(out, "Error: {}", error);
+    let _ = writeln!(out, "Faulting PC: {:#06X}", fault_pc);
+    let _ = writeln!(out, "Opcode: {:#04X}", opcode);
+    let _ = writeln!(out, "Current PC: {:#06X}", vm.pc);
+    let _ = writeln!(out, "Cycles: {}", vm.cycle_counter);
+    let _ = writeln!(out, "Gas used: {} / {}", vm.gas_used(), vm.gas_limit());
+
+    // Disassembly around the faulting instruction
+    let _ = writeln!(out, "\n--- Disassembly ---");
+    let end = (fault_pc + CONTEXT_BYTES).min(vm.memory.len());
+    let mut disasm = Disassembler::new(vm.memory[..end].to_vec());
+    disasm.run();
+    let fault_idx = disasm
+        .disassembled
+        .iter()
+        .rposition(|line| line.offset <= fault_pc)
+        .unwrap_or(0);
+    let first = fault_idx.saturating_sub(CONTEXT_LINES);
+    let last = (fault_idx + CONTEXT_LINES + 1).min(disasm.disassembled.len());
+    for (i, line) in disasm.disassembled[first..last].iter().enumerate() {
+        let marker = if first + i == fault_idx { "=>" } else { "  " };
+        let _ = writeln!(
+            out,
+            "{} {:06X}: {}",
+            marker,
+            line.offset,
+            line.instruction.as_deref().unwrap_or("")
+        );
+    }
+
+    // Stack, top first
+    let _ = writeln!(out, "\n--- Stack ({} items) ---", vm.stack.len());
+    for (i, value) in vm.stack.iter().enumerate().rev() {
+        let _ = writeln!(out, "{:4}: {:#010X}", i, value);
+    }
+
+    // General purpose registers
+    let _ = writeln!(out, "\n--- Registers ---");
+    for (i, reg) in vm.registers.iter().enumerate() {
+        let _ = writeln!(out, "R{:<2}: {:#010X}", i, reg);
+    }
+
+    // Recently executed instructions, oldest first
+    let _ = writeln!(out, "\n--- Recent history ---");
+    for (pc, op) in &vm.recent_history {
+        let _ = writeln!(out, "{:#06X}: {:#04X}", pc, op);
+    }
+
+    out
+}
+
+/// Write a crash dump for the VM into `dir`, returning the path of the file
+pub fn write_crash_dump(vm: &VM, dir: &Path) -> Result<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    let path = dir.join(format!("lessvm-crash-{}.txt", timestamp));
+
+    fs::write(&path, crash_dump(vm))
+        .context(format!("Failed to write crash dump: {}", path.display()))?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crash_dump_contains_fault() {
+        let mut vm = VM::new();
+
+        // PUSH1 1, PUSH1 2, ADD, POP, POP (underflow at offset 6)
+        let program = vec![0x01, 1, 0x01, 2, 0x10, 0x04, 0x04, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        assert!(vm.run(10).is_err());
+
+        let dump = crash_dump(&vm);
+        assert!(dump.contains("Faulting PC: 0x0006"));
+        assert!(dump.contains("Opcode: 0x04"));
+        assert!(dump.contains("Stack underflow"));
+        assert!(dump.contains("=> 000006: POP"));
+
+        let path = write_crash_dump(&vm, &std::env::temp_dir()).unwrap();
+        let written = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(written, dump);
+    }
+}
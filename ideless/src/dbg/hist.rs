@@ -34,7 +34,7 @@ impl HistoryEntry {
         vm.pc = self.pc;
         vm.stack = self.stack.clone();
         vm.memory = self.memory.clone();
-        vm.state = crate::lessvm::VMState::Ready;
+        vm.set_state(crate::lessvm::VMState::Ready);
     }
 }
 
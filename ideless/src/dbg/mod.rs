@@ -1,11 +1,15 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::path::Path;
 use std::sync::Arc;
 use anyhow::{Result, anyhow};
 use crossterm::event::KeyCode;
-use log::{debug, info, warn};
+use log::{debug, info, trace, warn};
 
-use crate::lessvm::{VM, VMState};
-use crate::asm::Disassembler;
+use crate::lessvm::{SnapshotDiff, VM, VMSnapshot, VMState, FLAG_CARRY, FLAG_OVERFLOW, FLAG_ZERO};
+use crate::asm::{opcode_name, Disassembler};
+use crate::clipboard;
+use crate::srcmap::SourceMap;
 
 /// Debugger state
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -33,6 +37,90 @@ pub enum Breakpoint {
     Register(usize, u32),
 }
 
+/// A watched location changed by the last instruction
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchHit {
+    /// Memory watch as (address, old, new), with values read in the VM's
+    /// byte order
+    Memory(usize, u64, u64),
+    
+    /// General purpose register as (index, old, new)
+    Register(usize, u32, u32),
+}
+
+/// A watched range of memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct MemoryWatch {
+    /// Number of bytes watched: 1, 2, 4 or 8
+    size: usize,
+    
+    /// Value last seen in the range
+    seen: u64,
+}
+
+/// CALL opcode, which pushes its return address
+const CALL: u8 = 0x32;
+
+/// Number of executed instructions kept for stepping back
+const MAX_HISTORY: usize = 100;
+
+/// Number of commands kept in the command history
+const MAX_COMMAND_HISTORY: usize = 100;
+
+/// Default number of lines kept in the console
+pub const DEFAULT_CONSOLE_CAPACITY: usize = 1000;
+
+/// Whether `opcode` can change the string buffer (0xB*), the map (0xD*)
+/// or account balances (transfers and syscalls)
+fn touches_collections(opcode: u8) -> bool {
+    matches!(opcode, 0x50..=0x52 | 0xB0..=0xBF | 0xD0..=0xDF)
+}
+
+/// An executed instruction and the state needed to undo it
+struct StepRecord {
+    /// PC the instruction was fetched from
+    pc: usize,
+    
+    /// Opcode that was executed
+    opcode: u8,
+    
+    /// VM state before the instruction, with memory left empty. The string
+    /// buffer, map and balances are only included if `collections` is set.
+    before: VMSnapshot,
+    
+    /// Whether the instruction could change the string buffer, the map or
+    /// balances, so `before` includes them
+    collections: bool,
+    
+    /// Memory bytes the instruction wrote as (offset, old, new)
+    memory: Vec<(usize, u8, u8)>,
+    
+    /// Memory size before the instruction, in case it grew memory
+    memory_len: usize,
+}
+
+/// An executed instruction and where it came from in the source
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// PC the instruction was fetched from
+    pub pc: usize,
+    
+    /// Opcode that was executed
+    pub opcode: u8,
+    
+    /// `file:line` of the instruction, if the program has a source map
+    pub location: Option<String>,
+}
+
+impl fmt::Display for TraceEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{} {}", location, opcode_name(self.opcode)),
+            None => write!(f, "{:#06X} {}", self.pc, opcode_name(self.opcode)),
+        }
+    }
+}
+
 /// LessVM Debugger
 pub struct Debugger {
     /// Debugger state
@@ -41,20 +129,57 @@ pub struct Debugger {
     /// Breakpoints
     breakpoints: HashSet<Breakpoint>,
     
+    /// Offsets of breakpoints set on source lines in the editor
+    line_breakpoints: HashSet<usize>,
+    
+    /// One-shot breakpoint set by step over and step out, cleared when
+    /// `run_until_breakpoint` stops
+    temp_breakpoint: Option<usize>,
+    
     /// Disassembler for code analysis
     disasm: Option<Disassembler>,
     
-    /// Instruction history
-    history: Vec<(usize, u8)>, // (PC, opcode)
+    /// Instruction history, oldest first
+    history: VecDeque<StepRecord>,
+    
+    /// Memory watches by start address. Only watches overlapping the bytes
+    /// an instruction wrote are re-read after it.
+    memory_watches: HashMap<usize, MemoryWatch>,
     
-    /// Memory watch addresses
-    memory_watches: HashSet<usize>,
+    /// Watched register indices, with the value last seen in each
+    register_watches: HashMap<usize, u32>,
+    
+    /// Watched location changed by the last instruction
+    watch_hit: Option<WatchHit>,
     
     /// Cycles per frame
     cycles_per_frame: u32,
     
-    /// Command history
-    command_history: Vec<String>,
+    /// Command history, oldest first
+    command_history: VecDeque<String>,
+    
+    /// Command being typed in command mode
+    command_input: String,
+    
+    /// History entry shown in the command input while browsing with
+    /// Up/Down, `None` when editing a new command
+    command_history_index: Option<usize>,
+    
+    /// Commands and their output, oldest line first
+    console: VecDeque<String>,
+    
+    /// Most lines the console keeps before dropping the oldest
+    console_capacity: usize,
+    
+    /// Active console search query and the line of the current match
+    console_search: Option<(String, Option<usize>)>,
+    
+    /// Changes made by the last single step
+    last_diff: Option<SnapshotDiff>,
+    
+    /// Source lines of the program's instructions, from the assembler or a
+    /// `.dbg` sidecar
+    source_map: Option<SourceMap>,
 }
 
 impl Debugger {
@@ -66,14 +191,40 @@ impl Debugger {
         Debugger {
             state: DebuggerState::Paused(vm.pc),
             breakpoints: HashSet::new(),
+            line_breakpoints: HashSet::new(),
+            temp_breakpoint: None,
             disasm: Some(disasm),
-            history: Vec::new(),
-            memory_watches: HashSet::new(),
+            history: VecDeque::new(),
+            memory_watches: HashMap::new(),
+            register_watches: HashMap::new(),
+            watch_hit: None,
             cycles_per_frame,
-            command_history: Vec::new(),
+            command_history: VecDeque::new(),
+            command_input: String::new(),
+            command_history_index: None,
+            console: VecDeque::new(),
+            console_capacity: DEFAULT_CONSOLE_CAPACITY,
+            console_search: None,
+            last_diff: None,
+            source_map: None,
         }
     }
     
+    /// Annotate the trace and history with source lines from `map`
+    pub fn set_source_map(&mut self, map: Option<SourceMap>) {
+        self.source_map = map;
+    }
+    
+    /// Executed instructions still in the history, oldest first
+    pub fn trace(&self) -> Vec<TraceEntry> {
+        self.history.iter().map(|record| self.trace_entry(record.pc, record.opcode)).collect()
+    }
+    
+    fn trace_entry(&self, pc: usize, opcode: u8) -> TraceEntry {
+        let location = self.source_map.as_ref().and_then(|map| map.location(pc));
+        TraceEntry { pc, opcode, location }
+    }
+    
     /// Get debugger state
     pub fn state(&self) -> &DebuggerState {
         &self.state
@@ -99,6 +250,88 @@ impl Debugger {
         self.cycles_per_frame = cpf;
     }
     
+    /// Lines in the console, oldest first
+    pub fn console(&self) -> &VecDeque<String> {
+        &self.console
+    }
+    
+    /// Append a line to the console, dropping the oldest line when full
+    pub fn add_to_console(&mut self, line: impl Into<String>) {
+        if self.console_capacity == 0 {
+            return;
+        }
+        if self.console.len() == self.console_capacity {
+            self.console.pop_front();
+        }
+        self.console.push_back(line.into());
+    }
+    
+    /// Set how many lines the console keeps, dropping the oldest lines if
+    /// it already holds more
+    pub fn set_console_capacity(&mut self, capacity: usize) {
+        self.console_capacity = capacity;
+        let excess = self.console.len().saturating_sub(capacity);
+        self.console.drain(..excess);
+    }
+    
+    /// Indices of the console lines containing `query`, ignoring case
+    pub fn find_in_console(&self, query: &str) -> Vec<usize> {
+        let query = query.to_lowercase();
+        self.console.iter()
+            .enumerate()
+            .filter(|(_, line)| line.to_lowercase().contains(&query))
+            .map(|(i, _)| i)
+            .collect()
+    }
+    
+    /// Start searching the console for `query`, returning the first match
+    pub fn search_console(&mut self, query: &str) -> Option<usize> {
+        let first = self.find_in_console(query).first().copied();
+        self.console_search = Some((query.to_string(), first));
+        first
+    }
+    
+    /// Move to the next (or previous) match of the active search, wrapping
+    /// around at either end
+    pub fn next_console_match(&mut self, forward: bool) -> Option<usize> {
+        let (query, current) = self.console_search.as_ref()?;
+        let matches = self.find_in_console(query);
+        let next = match (current, forward) {
+            (None, _) => matches.first(),
+            (Some(cur), true) => matches.iter().find(|&&i| i > *cur).or(matches.first()),
+            (Some(cur), false) => matches.iter().rev().find(|&&i| i < *cur).or(matches.last()),
+        }.copied();
+        if let Some((_, current)) = &mut self.console_search {
+            *current = next;
+        }
+        next
+    }
+    
+    /// Index of the console line of the current search match
+    pub fn selected_console_index(&self) -> Option<usize> {
+        self.console_search.as_ref()?.1
+    }
+    
+    /// Console line of the current search match
+    pub fn selected_console_line(&self) -> Option<&str> {
+        self.console.get(self.selected_console_index()?).map(String::as_str)
+    }
+    
+    /// Describe the current console match for command output
+    fn describe_console_match(&self, line: Option<usize>) -> String {
+        let Some((query, _)) = &self.console_search else {
+            return "No active search".to_string();
+        };
+        let matches = self.find_in_console(query);
+        match line {
+            Some(line) => {
+                let nth = matches.iter().position(|&i| i == line).map_or(0, |n| n + 1);
+                format!("Match {} of {} at line {}: {}", nth, matches.len(), line, self.console[line])
+            }
+            None => format!("No console lines match \"{}\"", query),
+        }
+    }
+    
     /// Add a breakpoint
     pub fn add_breakpoint(&mut self, bp: Breakpoint) {
         self.breakpoints.insert(bp);
@@ -114,8 +347,21 @@ impl Debugger {
         self.breakpoints.clear();
     }
     
+    /// Replace the breakpoints set on source lines, given as 1-based lines
+    /// of the source map's file. Lines without code after them are ignored.
+    pub fn set_line_breakpoints(&mut self, lines: &[usize]) {
+        self.line_breakpoints = match &self.source_map {
+            Some(map) => lines.iter().filter_map(|&line| map.offset(line)).collect(),
+            None => HashSet::new(),
+        };
+    }
+    
     /// Check if a breakpoint is hit
     pub fn is_breakpoint_hit(&self, vm: &VM) -> bool {
+        if self.temp_breakpoint == Some(vm.pc) || self.line_breakpoints.contains(&vm.pc) {
+            return true;
+        }
+        
         for bp in &self.breakpoints {
             match bp {
                 Breakpoint::PC(pc) => {
@@ -137,14 +383,19 @@ impl Debugger {
         false
     }
     
-    /// Add a memory watch
-    pub fn add_memory_watch(&mut self, addr: usize) {
-        self.memory_watches.insert(addr);
+    /// Add a watch on the `size` bytes at `addr`. Sizes other than 1, 2, 4
+    /// and 8 are rejected.
+    pub fn add_memory_watch(&mut self, vm: &VM, addr: usize, size: usize) -> Result<()> {
+        if !matches!(size, 1 | 2 | 4 | 8) {
+            return Err(anyhow!("Watch size must be 1, 2, 4 or 8 bytes, not {}", size));
+        }
+        self.memory_watches.insert(addr, MemoryWatch { size, seen: read_watched(vm, addr, size) });
+        Ok(())
     }
     
     /// Remove a memory watch
     pub fn remove_memory_watch(&mut self, addr: usize) -> bool {
-        self.memory_watches.remove(&addr)
+        self.memory_watches.remove(&addr).is_some()
     }
     
     /// Clear all memory watches
@@ -152,6 +403,64 @@ impl Debugger {
         self.memory_watches.clear();
     }
     
+    /// Add a register watch
+    pub fn add_register_watch(&mut self, vm: &VM, reg: usize) {
+        self.register_watches.insert(reg, vm.registers.get(reg).copied().unwrap_or(0));
+    }
+    
+    /// Remove a register watch
+    pub fn remove_register_watch(&mut self, reg: usize) -> bool {
+        self.register_watches.remove(&reg).is_some()
+    }
+    
+    /// Watched location changed by the last instruction
+    pub fn watch_hit(&self) -> Option<WatchHit> {
+        self.watch_hit
+    }
+    
+    /// Record the current values of everything watched, so changes made
+    /// without executing an instruction (step back, reset) don't fire
+    fn rebaseline_watches(&mut self, vm: &VM) {
+        self.check_memory_watches(vm, None);
+        self.check_register_watches(vm);
+        self.watch_hit = None;
+    }
+    
+    /// Compare watched memory with the values last seen there and record
+    /// the new values. With `written`, only watches covering one of those
+    /// addresses are read. Returns the lowest changed watch as
+    /// (address, old, new).
+    fn check_memory_watches(&mut self, vm: &VM, written: Option<&[usize]>) -> Option<(usize, u64, u64)> {
+        let mut hits = Vec::new();
+        for (&addr, watch) in self.memory_watches.iter_mut() {
+            let touched = written.is_none_or(|written| {
+                written.iter().any(|&w| (addr..addr + watch.size).contains(&w))
+            });
+            if !touched {
+                continue;
+            }
+            let value = read_watched(vm, addr, watch.size);
+            if value != watch.seen {
+                hits.push((addr, watch.seen, value));
+                watch.seen = value;
+            }
+        }
+        hits.into_iter().min()
+    }
+    
+    /// Same as `check_memory_watches`, for registers
+    fn check_register_watches(&mut self, vm: &VM) -> Option<(usize, u32, u32)> {
+        let mut hits = Vec::new();
+        for (&reg, seen) in self.register_watches.iter_mut() {
+            let value = vm.registers.get(reg).copied().unwrap_or(0);
+            if value != *seen {
+                hits.push((reg, *seen, value));
+                *seen = value;
+            }
+        }
+        hits.into_iter().min()
+    }
+    
     /// Get disassembly around PC
     pub fn get_disassembly_at_pc(&self, vm: &VM, count: usize) -> Vec<(usize, String)> {
         if let Some(disasm) = &self.disasm {
@@ -209,16 +518,128 @@ impl Debugger {
         Vec::new()
     }
     
+    /// Call frames, innermost first, as (pc, instruction) pairs
+    ///
+    /// Frame 0 is the current PC. CALL leaves its return address on the data
+    /// stack, so every stack entry that points just past a CALL instruction is
+    /// taken as a caller frame and reported at that call site. A plain value
+    /// that happens to look like a return address is indistinguishable.
+    pub fn backtrace(&self, vm: &VM) -> Vec<(usize, String)> {
+        let Some(disasm) = &self.disasm else {
+            return Vec::new();
+        };
+        let instruction_at = |offset: usize| {
+            disasm.disassembled
+                .binary_search_by_key(&offset, |line| line.offset)
+                .ok()
+                .map(|i| &disasm.disassembled[i])
+                .filter(|line| line.instruction.is_some())
+        };
+        
+        let mut frames = vec![(
+            vm.pc,
+            instruction_at(vm.pc)
+                .and_then(|line| line.instruction.clone())
+                .unwrap_or_else(|| "???".to_string()),
+        )];
+        for &ret in vm.stack.iter().rev() {
+            let Some(call_site) = (ret as usize).checked_sub(1) else {
+                continue;
+            };
+            if let Some(line) = instruction_at(call_site) {
+                if line.raw_bytes.first() == Some(&CALL) {
+                    frames.push((call_site, line.instruction.clone().unwrap_or_default()));
+                }
+            }
+        }
+        frames
+    }
+    
+    /// Execute one instruction, recording it in the history so it can be
+    /// stepped back over
+    fn execute(&mut self, vm: &mut VM) -> (Result<bool>, SnapshotDiff) {
+        // A halted or failed VM doesn't execute anything, so there's nothing to undo
+        if matches!(vm.state, VMState::Halted | VMState::Error(_)) {
+            return (vm.step(), SnapshotDiff::default());
+        }
+        
+        // Memory is recorded from the bytes the instruction writes rather
+        // than copied, and the string buffer, map and balances only when the
+        // opcode can change them
+        let pc = vm.pc;
+        let opcode = vm.memory.get(pc).copied().unwrap_or(0);
+        let collections = touches_collections(opcode);
+        let before = vm.snapshot_without_memory(collections);
+        let memory_len = vm.memory.len();
+        let result = vm.step();
+        let diff = vm.diff_since(&before);
+        // Both checks run so each keeps its last-seen values current
+        let memory_hit = self.check_memory_watches(vm, Some(vm.last_written()));
+        let register_hit = self.check_register_watches(vm);
+        self.watch_hit = memory_hit.map(|(addr, old, new)| WatchHit::Memory(addr, old, new))
+            .or(register_hit.map(|(reg, old, new)| WatchHit::Register(reg, old, new)));
+        match self.watch_hit {
+            Some(WatchHit::Memory(addr, old, new)) => {
+                info!("Watched memory at {:#06X} changed from {:#X} to {:#X}", addr, old, new);
+            }
+            Some(WatchHit::Register(reg, old, new)) => {
+                info!("Watched register R{} changed from {:#010X} to {:#010X}", reg, old, new);
+            }
+            None => {}
+        }
+        
+        trace!("{}", self.trace_entry(pc, opcode));
+        
+        self.history.push_back(StepRecord {
+            pc,
+            opcode,
+            before,
+            collections,
+            memory: vm.last_writes().collect(),
+            memory_len,
+        });
+        if self.history.len() > MAX_HISTORY {
+            self.history.pop_front();
+        }
+        
+        self.sync_disassembly(diff.memory.iter().map(|&(offset, _, new)| (offset, new)));
+        (result, diff)
+    }
+    
+    /// Copy memory writes into the disassembled bytes, re-disassembling if
+    /// any of them landed on an instruction (self-modifying code)
+    fn sync_disassembly(&mut self, writes: impl IntoIterator<Item = (usize, u8)>) {
+        let Some(disasm) = &mut self.disasm else {
+            return;
+        };
+        
+        let mut code_changed = false;
+        for (offset, value) in writes {
+            let Some(byte) = disasm.bytecode.get_mut(offset) else {
+                continue;
+            };
+            *byte = value;
+            
+            // Writes into data regions don't change how code decodes
+            let line = disasm.disassembled.partition_point(|l| l.offset <= offset);
+            code_changed |= line > 0 && disasm.disassembled[line - 1].instruction.is_some();
+        }
+        
+        if code_changed {
+            debug!("Code modified, refreshing disassembly");
+            let mut refreshed = Disassembler::new(std::mem::take(&mut disasm.bytecode));
+            refreshed.run();
+            *disasm = refreshed;
+        }
+    }
+    
     /// Run a single instruction
     pub fn step(&mut self, vm: &mut VM) -> Result<()> {
-        // Save current PC to history
-        self.history.push((vm.pc, vm.memory[vm.pc]));
-        if self.history.len() > 100 {
-            self.history.remove(0);
-        }
+        // Step the VM, remembering what the instruction changed
+        let (result, diff) = self.execute(vm);
+        self.last_diff = Some(diff);
         
-        // Step the VM
-        match vm.step() {
+        match result {
             Ok(_) => {
                 self.state = DebuggerState::Paused(vm.pc);
                 Ok(())
@@ -230,8 +651,74 @@ impl Debugger {
         }
     }
     
+    /// Undo the most recently executed instruction, restoring the VM to the
+    /// state it was in before that instruction ran
+    pub fn step_back(&mut self, vm: &mut VM) -> Result<()> {
+        let record = self.history
+            .pop_back()
+            .ok_or_else(|| anyhow!("No instruction history to step back over"))?;
+        
+        let mut before = record.before;
+        if !record.collections {
+            // The instruction couldn't have changed these, so they're current
+            let current = vm.snapshot_without_memory(true);
+            before.string_buffer = current.string_buffer;
+            before.map = current.map;
+            before.balances = current.balances;
+        }
+        before.memory = std::mem::take(&mut vm.memory);
+        before.memory.resize(record.memory_len, 0);
+        for &(offset, old, _) in record.memory.iter().rev() {
+            if let Some(byte) = before.memory.get_mut(offset) {
+                *byte = old;
+            }
+        }
+        vm.restore_owned(before);
+        self.sync_disassembly(record.memory.iter().map(|&(offset, old, _)| (offset, old)));
+        
+        // Watches compare against the restored values from here on
+        self.rebaseline_watches(vm);
+        
+        debug!("Stepped back over {:#04X} at {:#06X}", record.opcode, record.pc);
+        self.state = DebuggerState::Paused(vm.pc);
+        self.last_diff = None;
+        Ok(())
+    }
+    
+    /// Save the VM state to `path` so it can be loaded later
+    pub fn save_checkpoint(&self, vm: &VM, path: &Path) -> Result<()> {
+        vm.snapshot().save(path)
+    }
+    
+    /// Put the VM back into a state saved by `save_checkpoint`. The step
+    /// history doesn't lead to the loaded state, so it is dropped.
+    pub fn load_checkpoint(&mut self, vm: &mut VM, path: &Path) -> Result<()> {
+        let snapshot = VMSnapshot::load(path)?;
+        vm.restore(&snapshot);
+        
+        let mut disasm = Disassembler::new(vm.memory.clone());
+        disasm.run();
+        self.disasm = Some(disasm);
+        self.history.clear();
+        self.last_diff = None;
+        self.rebaseline_watches(vm);
+        self.state = DebuggerState::Paused(vm.pc);
+        Ok(())
+    }
+    
+    /// Get the changes made by the last single step
+    pub fn last_diff(&self) -> Option<&SnapshotDiff> {
+        self.last_diff.as_ref()
+    }
+    
     /// Run until breakpoint or end
     pub fn run_until_breakpoint(&mut self, vm: &mut VM) -> Result<()> {
+        let result = self.run_until_stop(vm);
+        self.temp_breakpoint = None;
+        result
+    }
+    
+    fn run_until_stop(&mut self, vm: &mut VM) -> Result<()> {
         self.state = DebuggerState::Running;
         
         loop {
@@ -242,16 +729,10 @@ impl Debugger {
             }
             
             // Step the VM
-            match vm.step() {
+            match self.execute(vm).0 {
                 Ok(continue_execution) => {
-                    // Save current PC to history
-                    self.history.push((vm.pc, vm.memory[vm.pc]));
-                    if self.history.len() > 100 {
-                        self.history.remove(0);
-                    }
-                    
                     // Check if we should stop execution
-                    if !continue_execution {
+                    if !continue_execution || self.watch_hit.is_some() {
                         self.state = DebuggerState::Paused(vm.pc);
                         return Ok(());
                     }
@@ -264,6 +745,34 @@ impl Debugger {
         }
     }
     
+    /// Step over the instruction at the PC. A CALL runs until the callee
+    /// returns to the next instruction; anything else is a single step.
+    pub fn step_over(&mut self, vm: &mut VM) -> Result<()> {
+        if vm.memory.get(vm.pc) != Some(&CALL) {
+            return self.step(vm);
+        }
+        self.run_to(vm, vm.pc + 1)
+    }
+    
+    /// Run until the current function returns to its caller
+    pub fn step_out(&mut self, vm: &mut VM) -> Result<()> {
+        let Some(&(call_site, _)) = self.backtrace(vm).get(1) else {
+            return Err(anyhow!("Not inside a call"));
+        };
+        self.run_to(vm, call_site + 1)
+    }
+    
+    /// Execute at least one instruction, then run until `target` unless
+    /// something else stops execution first
+    fn run_to(&mut self, vm: &mut VM, target: usize) -> Result<()> {
+        self.step(vm)?;
+        if vm.pc == target || self.watch_hit.is_some() {
+            return Ok(());
+        }
+        self.temp_breakpoint = Some(target);
+        self.run_until_breakpoint(vm)
+    }
+    
     /// Run a frame (multiple instructions)
     pub fn run_frame(&mut self, vm: &mut VM) -> Result<()> {
         match self.state {
@@ -288,18 +797,12 @@ impl Debugger {
                     }
                     
                     // Step the VM
-                    match vm.step() {
+                    match self.execute(vm).0 {
                         Ok(continue_execution) => {
-                            // Save current PC to history
-                            self.history.push((vm.pc, vm.memory[vm.pc]));
-                            if self.history.len() > 100 {
-                                self.history.remove(0);
-                            }
-                            
                             cycles += 1;
                             
                             // Check if we should stop execution
-                            if !continue_execution {
+                            if !continue_execution || self.watch_hit.is_some() {
                                 self.state = DebuggerState::Paused(vm.pc);
                                 break;
                             }
@@ -327,6 +830,22 @@ impl Debugger {
                     }
                 }
             },
+            KeyCode::Char('o') => {
+                // Step over a call
+                if let DebuggerState::Paused(_) = self.state {
+                    if let Err(e) = self.step_over(vm) {
+                        warn!("Step over error: {}", e);
+                    }
+                }
+            },
+            KeyCode::Char('f') => {
+                // Finish the current function
+                if let DebuggerState::Paused(_) = self.state {
+                    if let Err(e) = self.step_out(vm) {
+                        warn!("Step out error: {}", e);
+                    }
+                }
+            },
             KeyCode::Char('c') => {
                 // Continue execution
                 if let DebuggerState::Paused(_) = self.state {
@@ -347,6 +866,8 @@ impl Debugger {
                 vm.reset();
                 self.state = DebuggerState::Paused(0);
                 self.history.clear();
+                self.last_diff = None;
+                self.rebaseline_watches(vm);
                 info!("VM reset");
             },
             KeyCode::Char('q') => {
@@ -358,14 +879,83 @@ impl Debugger {
         Ok(())
     }
     
-    /// Parse and execute a debug command
+    /// Command being typed in command mode
+    pub fn command_input(&self) -> &str {
+        &self.command_input
+    }
+    
+    /// Handle a key in command mode: edit the command, run it on Enter, and
+    /// browse the command history with Up (older) and Down (newer). Down
+    /// from the newest entry goes back to an empty line.
+    pub fn handle_command_key(&mut self, vm: &mut VM, key: KeyCode) -> Result<()> {
+        match key {
+            KeyCode::Char(c) => {
+                self.command_history_index = None;
+                self.command_input.push(c);
+            }
+            KeyCode::Backspace => {
+                self.command_history_index = None;
+                self.command_input.pop();
+            }
+            KeyCode::Esc => {
+                self.command_history_index = None;
+                self.command_input.clear();
+            }
+            KeyCode::Enter => {
+                let command = std::mem::take(&mut self.command_input);
+                if !command.trim().is_empty() {
+                    self.execute_command(vm, command.trim())?;
+                }
+            }
+            KeyCode::Up => {
+                let Some(last) = self.command_history.len().checked_sub(1) else {
+                    return Ok(());
+                };
+                let index = self.command_history_index.map_or(last, |i| i.saturating_sub(1));
+                self.command_history_index = Some(index);
+                self.command_input = self.command_history[index].clone();
+            }
+            KeyCode::Down => match self.command_history_index {
+                Some(i) if i + 1 < self.command_history.len() => {
+                    self.command_history_index = Some(i + 1);
+                    self.command_input = self.command_history[i + 1].clone();
+                }
+                Some(_) => {
+                    self.command_history_index = None;
+                    self.command_input.clear();
+                }
+                None => {}
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+    
+    /// Parse and execute a debug command, echoing it and its output to the
+    /// console
     pub fn execute_command(&mut self, vm: &mut VM, command: &str) -> Result<String> {
-        // Save command to history
-        self.command_history.push(command.to_string());
-        if self.command_history.len() > 100 {
-            self.command_history.remove(0);
+        // Save command to history, which also ends any browsing since the
+        // indices shift when the oldest entry is dropped
+        self.command_history_index = None;
+        if self.command_history.len() == MAX_COMMAND_HISTORY {
+            self.command_history.pop_front();
         }
+        self.command_history.push_back(command.to_string());
 
+        let output = self.run_command(vm, command)?;
+        
+        // Searching doesn't add to the console, so matches stay put
+        if matches!(command.split_whitespace().next(), Some("find" | "findnext" | "findprev")) {
+            return Ok(output);
+        }
+        self.add_to_console(format!("> {}", command));
+        for line in output.lines() {
+            self.add_to_console(line);
+        }
+        Ok(output)
+    }
+    
+    fn run_command(&mut self, vm: &mut VM, command: &str) -> Result<String> {
         // Split command into words
         let words: Vec<&str> = command.split_whitespace().collect();
         if words.is_empty() {
@@ -378,11 +968,25 @@ impl Debugger {
                 Ok("Available commands:\n\
                     help, h - Show help\n\
                     step, s - Step one instruction\n\
+                    stepback, sb - Undo the last instruction\n\
+                    stepover, so - Step over a CALL\n\
+                    stepout, fin - Run until the current function returns\n\
                     continue, c - Continue execution\n\
                     break, b <addr> - Set breakpoint at address\n\
                     delete, d <addr> - Delete breakpoint at address\n\
                     list, l - List breakpoints\n\
+                    watch, w [addr [size]|Rn] - Stop when 1/2/4/8 bytes at address or register n change, or list watches\n\
+                    unwatch <addr|Rn> - Remove a watch\n\
                     info, i - Show VM info\n\
+                    backtrace, bt - Show call frames\n\
+                    history [n] - Show the last n executed instructions (default 10)\n\
+                    ds map - Show the MAPINIT map\n\
+                    diff - Show what the last step changed\n\
+                    find <text> - Search the console\n\
+                    findnext, findprev - Go to the next or previous console match\n\
+                    copy - Copy the current console match to the clipboard\n\
+                    save <file> - Save the VM state to a file\n\
+                    load <file> - Load a VM state saved with save\n\
                     reset, r - Reset VM\n\
                     quit, q - Quit debugger".to_string())
             },
@@ -393,6 +997,27 @@ impl Debugger {
                     Ok(format!("Stepped to {:#06X}", vm.pc))
                 }
             },
+            "stepback" | "sb" => {
+                if let Err(e) = self.step_back(vm) {
+                    Ok(format!("Step back error: {}", e))
+                } else {
+                    Ok(format!("Stepped back to {:#06X}", vm.pc))
+                }
+            },
+            "stepover" | "so" => {
+                if let Err(e) = self.step_over(vm) {
+                    Ok(format!("Step over error: {}", e))
+                } else {
+                    Ok(format!("Stepped to {:#06X}", vm.pc))
+                }
+            },
+            "stepout" | "fin" => {
+                if let Err(e) = self.step_out(vm) {
+                    Ok(format!("Step out error: {}", e))
+                } else {
+                    Ok(format!("Returned to {:#06X}", vm.pc))
+                }
+            },
             "continue" | "c" => {
                 self.state = DebuggerState::Running;
                 Ok("Continuing execution".to_string())
@@ -424,6 +1049,73 @@ impl Debugger {
                     }
                 }
             },
+            "watch" | "w" => {
+                let Some(arg) = words.get(1) else {
+                    if self.memory_watches.is_empty() && self.register_watches.is_empty() {
+                        return Ok("No watches set".to_string());
+                    }
+                    let mut result = String::new();
+                    if !self.memory_watches.is_empty() {
+                        let mut watches: Vec<_> = self.memory_watches.iter().collect();
+                        watches.sort_unstable();
+                        result.push_str("Memory watches:\n");
+                        for (addr, watch) in watches {
+                            let width = 2 + 2 * watch.size;
+                            result.push_str(&format!("  {:#06X} = {:#0width$X}\n", addr, watch.seen, width = width));
+                        }
+                    }
+                    if !self.register_watches.is_empty() {
+                        let mut watches: Vec<_> = self.register_watches.iter().collect();
+                        watches.sort_unstable();
+                        result.push_str("Register watches:\n");
+                        for (reg, value) in watches {
+                            result.push_str(&format!("  R{} = {:#010X}\n", reg, value));
+                        }
+                    }
+                    return Ok(result);
+                };
+                if let Some(reg) = parse_register(arg) {
+                    return match reg {
+                        Some(reg) if reg < vm.registers.len() => {
+                            self.add_register_watch(vm, reg);
+                            Ok(format!("Watching R{}", reg))
+                        }
+                        _ => Ok(format!("Invalid register: {}", arg)),
+                    };
+                }
+                let Ok(addr) = usize::from_str_radix(arg.trim_start_matches("0x"), 16) else {
+                    return Ok(format!("Invalid address: {}", arg));
+                };
+                let size = match words.get(2).map(|size| size.parse()) {
+                    None => 1,
+                    Some(Ok(size)) => size,
+                    Some(Err(_)) => return Ok(format!("Invalid size: {}", words[2])),
+                };
+                match self.add_memory_watch(vm, addr, size) {
+                    Ok(()) if size == 1 => Ok(format!("Watching {:#06X}", addr)),
+                    Ok(()) => Ok(format!("Watching {} bytes at {:#06X}", size, addr)),
+                    Err(e) => Ok(e.to_string()),
+                }
+            },
+            "unwatch" => {
+                let Some(arg) = words.get(1) else {
+                    return Ok("Usage: unwatch <addr|Rn>".to_string());
+                };
+                if let Some(reg) = parse_register(arg) {
+                    return match reg {
+                        Some(reg) if self.remove_register_watch(reg) => Ok(format!("Removed watch on R{}", reg)),
+                        Some(reg) => Ok(format!("No watch on R{}", reg)),
+                        None => Ok(format!("Invalid register: {}", arg)),
+                    };
+                }
+                match usize::from_str_radix(arg.trim_start_matches("0x"), 16) {
+                    Ok(addr) if self.remove_memory_watch(addr) => {
+                        Ok(format!("Removed watch at {:#06X}", addr))
+                    }
+                    Ok(addr) => Ok(format!("No watch at {:#06X}", addr)),
+                    Err(_) => Ok(format!("Invalid address: {}", arg)),
+                }
+            },
             "list" | "l" => {
                 if self.breakpoints.is_empty() {
                     Ok("No breakpoints set".to_string())
@@ -456,14 +1148,113 @@ impl Debugger {
                     result.push_str(&format!("  R{}: {:#010X}\n", i, reg));
                 }
                 
+                let flag = |bit: u8| (vm.flags & bit != 0) as u8;
+                result.push_str(&format!("Flags: Z={} C={} O={}\n",
+                    flag(FLAG_ZERO), flag(FLAG_CARRY), flag(FLAG_OVERFLOW)));
+                
                 result.push_str(&format!("Stack: {:?}\n", vm.stack));
                 
                 Ok(result)
             },
+            "backtrace" | "bt" => {
+                let frames = self.backtrace(vm);
+                if frames.len() <= 1 {
+                    return Ok(format!("No call frames, at top level ({:#06X})", vm.pc));
+                }
+                
+                let mut result = String::from("Backtrace:\n");
+                for (i, (pc, instr)) in frames.iter().enumerate() {
+                    result.push_str(&format!("  #{} {:#06X} {}\n", i, pc, instr));
+                }
+                Ok(result)
+            },
+            "history" => {
+                let count = match words.get(1).map(|n| n.parse::<usize>()) {
+                    None => 10,
+                    Some(Ok(n)) => n,
+                    Some(Err(_)) => return Ok("Usage: history [n]".to_string()),
+                };
+                let trace = self.trace();
+                if trace.is_empty() {
+                    return Ok("No instructions executed yet".to_string());
+                }
+                
+                let mut result = String::from("History:\n");
+                for entry in &trace[trace.len().saturating_sub(count)..] {
+                    result.push_str(&format!("  {}\n", entry));
+                }
+                Ok(result)
+            },
+            "ds" => {
+                // The ideless VM's only data structure is the MAPINIT map;
+                // BTreeMap, Graph and Trie exist on the lessvm-solana VM
+                if words.get(1) != Some(&"map") {
+                    return Ok("Usage: ds map".to_string());
+                }
+                let Some(map) = &vm.map else {
+                    return Ok("No map allocated".to_string());
+                };
+                
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_unstable();
+                let mut result = format!("Map ({} entries):\n", entries.len());
+                for (key, value) in entries {
+                    result.push_str(&format!("  {:#010X} => {:#010X}\n", key, value));
+                }
+                Ok(result)
+            },
+            "diff" => {
+                match &self.last_diff {
+                    Some(diff) => Ok(diff.to_string()),
+                    None => Ok("No step recorded yet".to_string()),
+                }
+            },
+            "find" => {
+                if words.len() < 2 {
+                    return Ok("Usage: find <text>".to_string());
+                }
+                // Keep the query's inner spacing
+                let query = command.trim_start()["find".len()..].trim();
+                let line = self.search_console(query);
+                Ok(self.describe_console_match(line))
+            },
+            "findnext" | "findprev" => {
+                let line = self.next_console_match(words[0] == "findnext");
+                Ok(self.describe_console_match(line))
+            },
+            "copy" => {
+                let Some(line) = self.selected_console_line() else {
+                    return Ok("No console line selected; use find first".to_string());
+                };
+                match clipboard::copy(line) {
+                    Ok(()) => Ok("Copied line to the clipboard".to_string()),
+                    Err(e) => Ok(format!("Copy error: {:#}", e)),
+                }
+            },
+            "save" => {
+                let Some(path) = words.get(1) else {
+                    return Ok("Usage: save <file>".to_string());
+                };
+                match self.save_checkpoint(vm, Path::new(path)) {
+                    Ok(()) => Ok(format!("Saved state to {}", path)),
+                    Err(e) => Ok(format!("Save error: {:#}", e)),
+                }
+            },
+            "load" => {
+                let Some(path) = words.get(1) else {
+                    return Ok("Usage: load <file>".to_string());
+                };
+                match self.load_checkpoint(vm, Path::new(path)) {
+                    Ok(()) => Ok(format!("Loaded state from {}, paused at {:#06X}", path, vm.pc)),
+                    Err(e) => Ok(format!("Load error: {:#}", e)),
+                }
+            },
             "reset" | "r" => {
                 vm.reset();
                 self.state = DebuggerState::Paused(0);
                 self.history.clear();
+                self.last_diff = None;
+                self.rebaseline_watches(vm);
                 Ok("VM reset".to_string())
             },
             "quit" | "q" => {
@@ -476,3 +1267,571 @@ impl Debugger {
         }
     }
 }
+
+/// Read `size` bytes at `addr` big-endian, like the VM's word accesses,
+/// counting bytes past the end of memory as zero
+fn read_watched(vm: &VM, addr: usize, size: usize) -> u64 {
+    (addr..addr + size)
+        .map(|a| vm.memory.get(a).copied().unwrap_or(0))
+        .fold(0, |acc, b| (acc << 8) | b as u64)
+}
+
+/// Parse a register name like `R3`. Returns `None` if `arg` isn't
+/// register syntax at all and `Some(None)` if the index is malformed.
+fn parse_register(arg: &str) -> Option<Option<usize>> {
+    let index = arg.strip_prefix('R').or_else(|| arg.strip_prefix('r'))?;
+    Some(index.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_history_navigation() {
+        let mut vm = VM::new();
+        vm.load_bytecode(&[0x01, 1, 0x01, 2, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        let mut press = |dbg: &mut Debugger, key: KeyCode| {
+            dbg.handle_command_key(&mut vm, key).unwrap();
+            dbg.command_input().to_string()
+        };
+        
+        // Nothing to browse yet
+        assert_eq!(press(&mut dbg, KeyCode::Up), "");
+        assert_eq!(press(&mut dbg, KeyCode::Down), "");
+        
+        for command in ["step", "info", "list"] {
+            for c in command.chars() {
+                press(&mut dbg, KeyCode::Char(c));
+            }
+            assert_eq!(press(&mut dbg, KeyCode::Enter), "");
+        }
+        
+        // Up walks back through every entry and stops at the oldest
+        let keys = [KeyCode::Up, KeyCode::Up, KeyCode::Up, KeyCode::Up,
+                    KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Down, KeyCode::Up];
+        let shown: Vec<String> = keys.iter().map(|&key| press(&mut dbg, key)).collect();
+        assert_eq!(shown, ["list", "info", "step", "step", "info", "list", "", "", "list"]);
+        
+        // Running an entry from the history adds it again, and browsing
+        // starts over from the newest
+        assert_eq!(press(&mut dbg, KeyCode::Up), "info");
+        press(&mut dbg, KeyCode::Enter);
+        let shown: Vec<String> = [KeyCode::Up, KeyCode::Up, KeyCode::Down, KeyCode::Down]
+            .iter().map(|&key| press(&mut dbg, key)).collect();
+        assert_eq!(shown, ["info", "list", "info", ""]);
+        
+        // Typing leaves the history
+        press(&mut dbg, KeyCode::Up);
+        assert_eq!(press(&mut dbg, KeyCode::Char('!')), "info!");
+        assert_eq!(press(&mut dbg, KeyCode::Down), "info!");
+        assert_eq!(press(&mut dbg, KeyCode::Esc), "");
+    }
+    
+    #[test]
+    fn test_disassembly_shows_operands() {
+        let mut vm = VM::new();
+        // PUSH4 0xDEAD, DUP 0, HALT
+        vm.load_bytecode(&[0x03, 0x00, 0x00, 0xDE, 0xAD, 0x05, 0, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        dbg.step(&mut vm).unwrap();
+        
+        let lines = dbg.get_disassembly_at_pc(&vm, 2);
+        assert_eq!(&lines[..3], [
+            (0, "PUSH4 0x0000DEAD".to_string()),
+            (5, "DUP 0".to_string()),
+            (7, "HALT".to_string()),
+        ]);
+    }
+    
+    #[test]
+    fn test_step_back_restores_stack() {
+        let mut vm = VM::new();
+        // PUSH1 1, PUSH1 2, ADD, PUSH1 3, ADD, PUSH1 4, ADD, HALT
+        vm.load_bytecode(&[
+            0x01, 1, 0x01, 2, 0x10, 0x01, 3, 0x10, 0x01, 4, 0x10, 0xFF,
+        ]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        
+        // Step through the first two ADDs and remember that state
+        for _ in 0..5 {
+            dbg.step(&mut vm).unwrap();
+        }
+        let earlier = vm.snapshot();
+        assert_eq!(vm.stack, vec![6]);
+        
+        dbg.step(&mut vm).unwrap();
+        dbg.step(&mut vm).unwrap();
+        assert_eq!(vm.stack, vec![10]);
+        
+        // Undo the third ADD and its PUSH1
+        dbg.execute_command(&mut vm, "sb").unwrap();
+        assert_eq!(vm.stack, vec![6, 4]);
+        dbg.step_back(&mut vm).unwrap();
+        assert_eq!(vm.snapshot(), earlier);
+        assert_eq!(dbg.state(), &DebuggerState::Paused(8));
+        
+        // Re-executing reaches the same result
+        dbg.step(&mut vm).unwrap();
+        dbg.step(&mut vm).unwrap();
+        assert_eq!(vm.stack, vec![10]);
+    }
+
+    #[test]
+    fn test_backtrace() {
+        let mut vm = VM::new();
+        // 0x00: PUSH1 0x07, CALL, HALT
+        // 0x07: PUSH1 0x0B, CALL, RETURN
+        // 0x0B: RETURN
+        vm.load_bytecode(&[
+            0x01, 0x07, 0x32, 0xFF, 0xFF, 0xFF, 0xFF,
+            0x01, 0x0B, 0x32, 0x33,
+            0x33,
+        ]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        
+        let out = dbg.execute_command(&mut vm, "bt").unwrap();
+        assert!(out.starts_with("No call frames"));
+        
+        for _ in 0..4 {
+            dbg.step(&mut vm).unwrap();
+        }
+        assert_eq!(vm.pc, 0x0B);
+        let frames = dbg.backtrace(&vm);
+        assert_eq!(frames.iter().map(|f| f.0).collect::<Vec<_>>(), vec![0x0B, 0x09, 0x02]);
+        assert!(frames[1].1.starts_with("CALL"));
+        
+        let out = dbg.execute_command(&mut vm, "backtrace").unwrap();
+        assert_eq!(out.lines().filter(|l| l.trim_start().starts_with('#')).count(), 3);
+        
+        // Returning pops a frame
+        dbg.step(&mut vm).unwrap();
+        assert_eq!(dbg.backtrace(&vm).len(), 2);
+    }
+
+    #[test]
+    fn test_step_over_and_out() {
+        let mut vm = VM::new();
+        // 0x00: PUSH1 0x0A, CALL
+        // 0x03: PUSH1 7, HALT
+        // 0x06: padding
+        // 0x0A: PUSH1 5, POP, RETURN
+        vm.load_bytecode(&[
+            0x01, 0x0A, 0x32,
+            0x01, 0x07, 0xFF,
+            0x00, 0x00, 0x00, 0x00,
+            0x01, 0x05, 0x04, 0x33,
+        ]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        
+        // Not a CALL: a single step
+        dbg.step_over(&mut vm).unwrap();
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x02));
+        
+        assert_eq!(dbg.execute_command(&mut vm, "so").unwrap(), "Stepped to 0x0003");
+        assert!(vm.stack.is_empty());
+        
+        // Step into the callee, then out again
+        dbg.execute_command(&mut vm, "reset").unwrap();
+        dbg.step(&mut vm).unwrap();
+        dbg.step(&mut vm).unwrap();
+        dbg.step(&mut vm).unwrap();
+        assert_eq!(vm.pc, 0x0C);
+        assert_eq!(dbg.execute_command(&mut vm, "fin").unwrap(), "Returned to 0x0003");
+        assert_eq!(dbg.execute_command(&mut vm, "fin").unwrap(), "Step out error: Not inside a call");
+        
+        // A breakpoint in the callee still stops a step over, and the
+        // one-shot breakpoint doesn't outlive it
+        dbg.execute_command(&mut vm, "reset").unwrap();
+        dbg.step(&mut vm).unwrap();
+        dbg.add_breakpoint(Breakpoint::PC(0x0C));
+        dbg.step_over(&mut vm).unwrap();
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x0C));
+        dbg.clear_breakpoints();
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(vm.state, VMState::Halted);
+        assert_eq!(vm.stack, vec![7]);
+    }
+
+    #[test]
+    fn test_memory_watch() {
+        let mut vm = VM::new();
+        // 0x00: PUSH1 7, STORE 0x80
+        // 0x04: PUSH1 7, STORE 0x80    (same value, no change)
+        // 0x08: PUSH1 9, STORE 0x81    (not watched)
+        // 0x0C: PUSH1 1, STORE 0x80
+        // 0x10: HALT
+        vm.load_bytecode(&[
+            0x01, 7, 0x41, 0x80,
+            0x01, 7, 0x41, 0x80,
+            0x01, 9, 0x41, 0x81,
+            0x01, 1, 0x41, 0x80,
+            0xFF,
+        ]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        assert_eq!(dbg.execute_command(&mut vm, "watch 80").unwrap(), "Watching 0x0080");
+        
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x04));
+        assert_eq!(dbg.watch_hit(), Some(WatchHit::Memory(0x80, 0, 7)));
+        
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x10));
+        assert_eq!(dbg.watch_hit(), Some(WatchHit::Memory(0x80, 7, 1)));
+        
+        // Stepping back restores the old byte without counting as a change,
+        // and redoing the write fires again
+        dbg.step_back(&mut vm).unwrap();
+        assert_eq!(dbg.watch_hit(), None);
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.watch_hit(), Some(WatchHit::Memory(0x80, 7, 1)));
+        
+        assert_eq!(dbg.execute_command(&mut vm, "w").unwrap(), "Memory watches:\n  0x0080 = 0x01\n");
+        assert_eq!(dbg.execute_command(&mut vm, "unwatch 80").unwrap(), "Removed watch at 0x0080");
+        dbg.execute_command(&mut vm, "reset").unwrap();
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.watch_hit(), None);
+        assert_eq!(vm.state, VMState::Halted);
+    }
+
+    #[test]
+    fn test_multi_byte_memory_watch() {
+        let mut vm = VM::new();
+        // 0x00: PUSH1 5, STORE 0x83    (last byte of the word)
+        // 0x04: PUSH1 5, STORE 0x84    (just past it)
+        // 0x08: PUSH4 0x01020305, STOREW 0x0080
+        // 0x10: HALT
+        vm.load_bytecode(&[
+            0x01, 5, 0x41, 0x83,
+            0x01, 5, 0x41, 0x84,
+            0x03, 0x01, 0x02, 0x03, 0x05, 0x45, 0x00, 0x80,
+            0xFF,
+        ]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        assert_eq!(dbg.execute_command(&mut vm, "watch 80 4").unwrap(), "Watching 4 bytes at 0x0080");
+        assert_eq!(dbg.execute_command(&mut vm, "watch 90 3").unwrap(), "Watch size must be 1, 2, 4 or 8 bytes, not 3");
+        
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x04));
+        assert_eq!(dbg.watch_hit(), Some(WatchHit::Memory(0x80, 0, 5)));
+        
+        // A word write covering the whole range fires once, with the old and
+        // new word
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x10));
+        assert_eq!(dbg.watch_hit(), Some(WatchHit::Memory(0x80, 5, 0x01020305)));
+        assert_eq!(dbg.execute_command(&mut vm, "w").unwrap(), "Memory watches:\n  0x0080 = 0x01020305\n");
+    }
+
+    #[test]
+    fn test_register_watch() {
+        let mut vm = VM::new();
+        // PUSH1 1, PUSH1 2, PUSH1 3, HALT
+        vm.load_bytecode(&[0x01, 1, 0x01, 2, 0x01, 3, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        assert_eq!(dbg.execute_command(&mut vm, "watch R3").unwrap(), "Watching R3");
+        assert_eq!(dbg.execute_command(&mut vm, "watch R16").unwrap(), "Invalid register: R16");
+        
+        // No opcode writes the general purpose registers, so stand in for
+        // one by writing R3 before the second instruction executes
+        dbg.step(&mut vm).unwrap();
+        assert_eq!(dbg.watch_hit(), None);
+        vm.registers[3] = 0xBEEF;
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x04));
+        assert_eq!(dbg.watch_hit(), Some(WatchHit::Register(3, 0, 0xBEEF)));
+        assert_eq!(dbg.execute_command(&mut vm, "w").unwrap(), "Register watches:\n  R3 = 0x0000BEEF\n");
+        
+        // Resetting clears R3 without firing
+        dbg.execute_command(&mut vm, "reset").unwrap();
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(dbg.watch_hit(), None);
+        assert_eq!(vm.state, VMState::Halted);
+        
+        assert_eq!(dbg.execute_command(&mut vm, "unwatch r3").unwrap(), "Removed watch on R3");
+        assert_eq!(dbg.execute_command(&mut vm, "w").unwrap(), "No watches set");
+    }
+
+    #[test]
+    fn test_console_is_bounded() {
+        let mut vm = VM::new();
+        vm.load_bytecode(&[0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        dbg.set_console_capacity(50);
+        
+        for i in 0..10_000 {
+            dbg.add_to_console(format!("line {}", i));
+        }
+        assert_eq!(dbg.console().len(), 50);
+        let expected: Vec<String> = (9_950..10_000).map(|i| format!("line {}", i)).collect();
+        assert!(dbg.console().iter().eq(expected.iter()));
+        
+        // Commands are echoed along with their output
+        dbg.execute_command(&mut vm, "list").unwrap();
+        assert_eq!(dbg.console().len(), 50);
+        assert_eq!(dbg.console().range(48..).collect::<Vec<_>>(), ["> list", "No breakpoints set"]);
+        assert_eq!(dbg.console()[0], "line 9952");
+        
+        // Shrinking keeps the newest lines
+        dbg.set_console_capacity(1);
+        assert_eq!(dbg.console().iter().collect::<Vec<_>>(), ["No breakpoints set"]);
+        dbg.set_console_capacity(0);
+        dbg.add_to_console("dropped");
+        assert!(dbg.console().is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load_checkpoint() {
+        let mut vm = VM::new();
+        // PUSH1 1, PUSH1 2, ADD, PUSH1 4, HALT
+        vm.load_bytecode(&[0x01, 1, 0x01, 2, 0x10, 0x01, 4, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        dbg.step(&mut vm).unwrap();
+        dbg.step(&mut vm).unwrap();
+        
+        let path = std::env::temp_dir().join(format!("lessvm-checkpoint-{}.json", std::process::id()));
+        let save = format!("save {}", path.display());
+        assert!(dbg.execute_command(&mut vm, &save).unwrap().starts_with("Saved state to"));
+        
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(vm.stack, vec![3, 4]);
+        
+        let load = format!("load {}", path.display());
+        assert!(dbg.execute_command(&mut vm, &load).unwrap().ends_with("paused at 0x0004"));
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(vm.stack, vec![1, 2]);
+        assert_eq!(dbg.state(), &DebuggerState::Paused(0x04));
+        // Nothing to step back over past the loaded state
+        assert!(dbg.step_back(&mut vm).is_err());
+        
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(vm.stack, vec![3, 4]);
+        assert!(dbg.execute_command(&mut vm, &load).unwrap().starts_with("Load error:"));
+    }
+
+    #[test]
+    fn test_console_search() {
+        let mut vm = VM::new();
+        vm.load_bytecode(&[0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        for line in ["loaded program", "Gas used: 10", "watch hit", "gas limit reached", "done"] {
+            dbg.add_to_console(line);
+        }
+        
+        assert_eq!(dbg.find_in_console("GAS"), vec![1, 3]);
+        assert!(dbg.find_in_console("missing").is_empty());
+        
+        assert_eq!(dbg.execute_command(&mut vm, "find gas").unwrap(), "Match 1 of 2 at line 1: Gas used: 10");
+        assert_eq!(dbg.execute_command(&mut vm, "findnext").unwrap(), "Match 2 of 2 at line 3: gas limit reached");
+        // Wraps around both ways
+        assert_eq!(dbg.next_console_match(true), Some(1));
+        assert_eq!(dbg.next_console_match(false), Some(3));
+        assert_eq!(dbg.execute_command(&mut vm, "findprev").unwrap(), "Match 1 of 2 at line 1: Gas used: 10");
+        assert_eq!(dbg.selected_console_line(), Some("Gas used: 10"));
+        
+        // Searching leaves the console alone
+        assert_eq!(dbg.console().len(), 5);
+        assert_eq!(dbg.execute_command(&mut vm, "find watch  hit").unwrap(), "No console lines match \"watch  hit\"");
+        assert_eq!(dbg.next_console_match(true), None);
+        assert_eq!(dbg.selected_console_line(), None);
+        assert_eq!(dbg.execute_command(&mut vm, "copy").unwrap(), "No console line selected; use find first");
+    }
+
+    #[test]
+    fn test_ds_map() {
+        let mut vm = VM::new();
+        // MAPINIT, PUSH1 42, PUSH1 100, MAPSET, HALT
+        vm.load_bytecode(&[0xD0, 0x01, 42, 0x01, 100, 0xD1, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        
+        assert_eq!(dbg.execute_command(&mut vm, "ds map").unwrap(), "No map allocated");
+        for _ in 0..4 {
+            dbg.step(&mut vm).unwrap();
+        }
+        assert_eq!(
+            dbg.execute_command(&mut vm, "ds map").unwrap(),
+            "Map (1 entries):\n  0x00000064 => 0x0000002A\n"
+        );
+        assert_eq!(dbg.execute_command(&mut vm, "ds").unwrap(), "Usage: ds map");
+    }
+
+    #[test]
+    fn test_info_shows_flags() {
+        let mut vm = VM::new();
+        // PUSH1 1, PUSH1 1, SUB, HALT
+        vm.load_bytecode(&[0x01, 1, 0x01, 1, 0x11, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        
+        assert!(dbg.execute_command(&mut vm, "info").unwrap().contains("Flags: Z=0 C=0 O=0\n"));
+        for _ in 0..3 {
+            dbg.step(&mut vm).unwrap();
+        }
+        assert!(dbg.execute_command(&mut vm, "info").unwrap().contains("Flags: Z=1 C=0 O=0\n"));
+    }
+
+    #[test]
+    fn test_trace_shows_source_lines() {
+        let source = "; add two numbers\nPUSH1 1\nPUSH1 2\n\nADD\nHALT\n";
+        let (bytecode, lines) = crate::asm::assemble_with_lines(source).unwrap();
+        let path = std::env::temp_dir().join(format!("lessvm-trace-{}.dbg", std::process::id()));
+        SourceMap::new("add.lasm", lines).save(&path).unwrap();
+        let map = SourceMap::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        
+        let mut vm = VM::new();
+        vm.load_bytecode(&bytecode).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        assert_eq!(dbg.execute_command(&mut vm, "history").unwrap(), "No instructions executed yet");
+        dbg.set_source_map(Some(map));
+        for _ in 0..3 {
+            dbg.step(&mut vm).unwrap();
+        }
+        
+        let trace = dbg.trace();
+        assert_eq!(trace[2], TraceEntry { pc: 4, opcode: 0x10, location: Some("add.lasm:5".to_string()) });
+        assert_eq!(trace[2].to_string(), "add.lasm:5 ADD");
+        assert_eq!(
+            dbg.execute_command(&mut vm, "history 2").unwrap(),
+            "History:\n  add.lasm:3 PUSH1\n  add.lasm:5 ADD\n"
+        );
+        
+        // Without a source map the offset is shown instead
+        dbg.set_source_map(None);
+        assert_eq!(dbg.trace()[2].to_string(), "0x0004 ADD");
+    }
+
+    #[test]
+    fn test_line_breakpoints() {
+        let source = "; add two numbers\nPUSH1 1\nPUSH1 2\n\nADD\nHALT\n";
+        let (bytecode, lines) = crate::asm::assemble_with_lines(source).unwrap();
+        let mut vm = VM::new();
+        vm.load_bytecode(&bytecode).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        dbg.set_source_map(Some(SourceMap::new("add.lasm", lines)));
+        
+        // The blank line 4 stops at the ADD after it, and line 7 past the
+        // last instruction at nothing
+        dbg.set_line_breakpoints(&[4, 7]);
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(vm.pc, 4);
+        
+        dbg.set_line_breakpoints(&[]);
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(vm.state, VMState::Halted);
+    }
+
+    #[test]
+    fn test_step_back_restores_memory() {
+        let mut vm = VM::new();
+        // PUSH1 0xAB, STORE 0x80, HALT
+        vm.load_bytecode(&[0x01, 0xAB, 0x41, 0x80, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        
+        dbg.step(&mut vm).unwrap();
+        let earlier = vm.snapshot();
+        dbg.step(&mut vm).unwrap();
+        assert_eq!(vm.memory[0x80], 0xAB);
+        
+        dbg.step_back(&mut vm).unwrap();
+        assert_eq!(vm.memory[0x80], 0);
+        assert_eq!(vm.snapshot(), earlier);
+        
+        // Stepping back past the start of the history is an error
+        dbg.step_back(&mut vm).unwrap();
+        assert!(dbg.step_back(&mut vm).is_err());
+        assert_eq!(vm.pc, 0);
+    }
+
+    /// Time stepping with the debugger recording history and checking a
+    /// watch, against copying and diffing the whole VM around every step.
+    /// Run with `cargo test bench_step_recording -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_step_recording() {
+        const STEPS: u32 = 2_000;
+        // loop: PUSH1 1, STORE 0x80, JUMP2 loop
+        let program = [0x01, 1, 0x41, 0x80, 0x34, 0x00, 0x00];
+        let mut vm = VM::new();
+        vm.load_bytecode(&program).unwrap();
+        
+        let start = std::time::Instant::now();
+        for _ in 0..STEPS {
+            let before = vm.snapshot();
+            vm.step().unwrap();
+            std::hint::black_box(before.diff(&vm.snapshot()));
+        }
+        let full_copy = start.elapsed();
+        
+        vm.load_bytecode(&program).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        dbg.add_memory_watch(&vm, 0x80, 1).unwrap();
+        let start = std::time::Instant::now();
+        for _ in 0..STEPS {
+            dbg.step(&mut vm).unwrap();
+        }
+        let recorded = start.elapsed();
+        
+        println!(
+            "{} steps with {} bytes of memory: full copy {:?}, recorded {:?} ({:.1}x)",
+            STEPS,
+            vm.memory.len(),
+            full_copy,
+            recorded,
+            full_copy.as_secs_f64() / recorded.as_secs_f64(),
+        );
+        assert!(recorded < full_copy);
+    }
+
+    #[test]
+    fn test_step_back_restores_grown_memory_and_map() {
+        let mut vm = VM::new();
+        // MAPINIT, PUSH1 9, PUSH1 1, MAPSET, PUSH1 5, STOREW 0xFFFE, HALT
+        vm.load_bytecode(&[0xD0, 0x01, 9, 0x01, 1, 0xD1, 0x01, 5, 0x45, 0xFF, 0xFE, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        let mut states = vec![vm.snapshot()];
+        for _ in 0..6 {
+            dbg.step(&mut vm).unwrap();
+            states.push(vm.snapshot());
+        }
+        assert!(vm.memory.len() > crate::lessvm::INITIAL_MEMORY_SIZE);
+        assert_eq!(dbg.last_diff().unwrap().memory, vec![(0x10001, 0, 5)]);
+        
+        // Every step back lands exactly on the state before that step,
+        // including the STOREW that grew memory and the MAPSET
+        while let Some(expected) = states.pop() {
+            assert_eq!(vm.snapshot(), expected);
+            if !states.is_empty() {
+                dbg.step_back(&mut vm).unwrap();
+            }
+        }
+        assert_eq!(vm.map, None);
+    }
+
+    #[test]
+    fn test_store_into_code_refreshes_disassembly() {
+        let instruction_at = |dbg: &Debugger, offset: usize| {
+            dbg.disasm.as_ref().unwrap().disassembled.iter()
+                .find(|line| line.offset == offset)
+                .and_then(|line| line.instruction.clone())
+        };
+        
+        let mut vm = VM::new();
+        // PUSH1 0x11, STORE 8, PUSH1 5, PUSH1 3, ADD, HALT
+        vm.load_bytecode(&[0x01, 0x11, 0x41, 0x08, 0x01, 5, 0x01, 3, 0x10, 0xFF]).unwrap();
+        let mut dbg = Debugger::new(&vm, 1);
+        assert!(instruction_at(&dbg, 8).unwrap().starts_with("ADD"));
+        
+        // The STORE rewrites the ADD into a SUB
+        dbg.step(&mut vm).unwrap();
+        dbg.step(&mut vm).unwrap();
+        assert!(instruction_at(&dbg, 8).unwrap().starts_with("SUB"));
+        
+        dbg.run_until_breakpoint(&mut vm).unwrap();
+        assert_eq!(vm.stack, vec![2]);
+        
+        // Stepping back over the STORE restores the original code
+        while dbg.step_back(&mut vm).is_ok() {}
+        assert!(instruction_at(&dbg, 8).unwrap().starts_with("ADD"));
+    }
+}
@@ -0,0 +1,793 @@
+//! Text buffer for editing LessVM assembly.
+//!
+//! The buffer is a list of lines with a cursor addressed by line and
+//! character column. It knows just enough about assembly syntax to indent
+//! new lines sensibly and to navigate between labels and their uses.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::asm::{is_label_name, tokenize};
+use crate::clipboard;
+
+/// How Tab indents and how wide tabs are drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EditorSettings {
+    /// Columns between tab stops
+    pub tab_width: usize,
+
+    /// Insert `\t` for Tab and indentation instead of spaces
+    pub hard_tabs: bool,
+}
+
+impl Default for EditorSettings {
+    fn default() -> Self {
+        EditorSettings { tab_width: 4, hard_tabs: false }
+    }
+}
+
+impl EditorSettings {
+    /// One indentation level
+    pub fn indent(&self) -> String {
+        if self.hard_tabs {
+            "\t".to_string()
+        } else {
+            " ".repeat(self.tab_width)
+        }
+    }
+}
+
+/// Indentation for a line inserted after `line`: its leading whitespace,
+/// plus one level if it defines a label
+pub fn auto_indent(line: &str, settings: &EditorSettings) -> String {
+    let mut indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+
+    let code = line.split(';').next().unwrap_or("").trim();
+    if code.ends_with(':') {
+        indent.push_str(&settings.indent());
+    }
+    indent
+}
+
+/// Screen column of character column `col` in `line`, with tabs advancing
+/// to the next multiple of `tab_width`
+pub fn display_column(line: &str, col: usize, tab_width: usize) -> usize {
+    let width = tab_width.max(1);
+    line.chars().take(col).fold(0, |column, c| match c {
+        '\t' => column + width - column % width,
+        _ => column + 1,
+    })
+}
+
+/// `line` with tabs replaced by spaces up to the next tab stop, for drawing
+pub fn expand_tabs(line: &str, tab_width: usize) -> String {
+    let width = tab_width.max(1);
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = width - column % width;
+            expanded.push_str(&" ".repeat(spaces));
+            column += spaces;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded
+}
+
+/// Label defined at the start of `line`, with its 1-based byte column
+fn label_definition(line: &str) -> Option<(usize, &str)> {
+    let (column, token) = *tokenize(line).first()?;
+    let name = token.strip_suffix(':')?;
+    is_label_name(name).then_some((column, name))
+}
+
+/// Byte offset of character column `col` in `line`, clamped to its end
+fn byte_offset(line: &str, col: usize) -> usize {
+    line.char_indices().nth(col).map_or(line.len(), |(i, _)| i)
+}
+
+/// Hash of a line's content, ignoring surrounding whitespace
+fn content_hash(line: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    line.trim().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// A position in the source tied to a line's content rather than its
+/// number, so it follows the line when others are inserted or removed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LineAnchor {
+    /// Content hash of the line
+    pub hash: u64,
+
+    /// Number of earlier lines with the same content
+    pub occurrence: usize,
+}
+
+impl LineAnchor {
+    /// Anchor to line `row` of `lines`
+    pub fn new(lines: &[String], row: usize) -> Self {
+        let hash = content_hash(&lines[row]);
+        let occurrence = lines[..row].iter().filter(|l| content_hash(l) == hash).count();
+        LineAnchor { hash, occurrence }
+    }
+
+    /// Line the anchor refers to, if that content is still present
+    pub fn resolve(&self, lines: &[String]) -> Option<usize> {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| content_hash(line) == self.hash)
+            .nth(self.occurrence)
+            .map(|(row, _)| row)
+    }
+}
+
+/// An editable assembly source buffer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Editor {
+    /// Buffer contents, one entry per line without the newline
+    lines: Vec<String>,
+
+    /// Cursor line
+    row: usize,
+
+    /// Cursor column in characters
+    col: usize,
+
+    /// Breakpoint lines, anchored by content
+    breakpoints: Vec<LineAnchor>,
+
+    /// Where the selection started; it runs from here to the cursor
+    selection_anchor: Option<(usize, usize)>,
+
+    /// Tab width and whether to indent with tabs
+    settings: EditorSettings,
+
+    /// Line ending the source was written with, `\n` or `\r\n`
+    line_ending: &'static str,
+
+    /// Whether the source ended with a line ending
+    trailing_newline: bool,
+}
+
+impl Default for Editor {
+    fn default() -> Self {
+        Self::new("")
+    }
+}
+
+impl Editor {
+    /// Create a buffer holding `source` with the cursor at the start
+    pub fn new(source: &str) -> Self {
+        let mut lines: Vec<String> = source.lines().map(str::to_string).collect();
+        if lines.is_empty() {
+            lines.push(String::new());
+        }
+
+        Editor {
+            lines,
+            row: 0,
+            col: 0,
+            breakpoints: Vec::new(),
+            selection_anchor: None,
+            settings: EditorSettings::default(),
+            line_ending: if source.contains("\r\n") { "\r\n" } else { "\n" },
+            trailing_newline: source.ends_with('\n'),
+        }
+    }
+
+    /// Replace the buffer contents, e.g. after reloading or reformatting,
+    /// keeping breakpoints on the lines whose content they were set on
+    pub fn set_text(&mut self, source: &str) {
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        *self = Editor {
+            breakpoints,
+            settings: self.settings,
+            ..Editor::new(source)
+        };
+    }
+
+    /// Tab width and indentation style
+    pub fn settings(&self) -> &EditorSettings {
+        &self.settings
+    }
+
+    /// Change the tab width and indentation style
+    pub fn set_settings(&mut self, settings: EditorSettings) {
+        self.settings = settings;
+    }
+
+    /// Line `row` as drawn, with tabs expanded to the tab width
+    pub fn display_line(&self, row: usize) -> String {
+        expand_tabs(&self.lines[row], self.settings.tab_width)
+    }
+
+    /// Screen column the cursor is drawn at
+    pub fn cursor_display_column(&self) -> usize {
+        display_column(&self.lines[self.row], self.col, self.settings.tab_width)
+    }
+
+    /// Buffer contents joined with the source's line ending, ending with
+    /// one if the source did
+    pub fn text(&self) -> String {
+        let mut text = self.lines.join(self.line_ending);
+        if self.trailing_newline {
+            text.push_str(self.line_ending);
+        }
+        text
+    }
+
+    /// Buffer lines
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Cursor position as (line, column)
+    pub fn cursor(&self) -> (usize, usize) {
+        (self.row, self.col)
+    }
+
+    /// Move the cursor, clamping it to the buffer
+    pub fn set_cursor(&mut self, row: usize, col: usize) {
+        self.row = row.min(self.lines.len() - 1);
+        self.col = col.min(self.lines[self.row].chars().count());
+    }
+
+    /// Start selecting from the cursor. Moving the cursor afterwards
+    /// extends the selection.
+    pub fn start_selection(&mut self) {
+        self.selection_anchor = Some((self.row, self.col));
+    }
+
+    /// Drop the selection
+    pub fn clear_selection(&mut self) {
+        self.selection_anchor = None;
+    }
+
+    /// Selected range as ((line, column), (line, column)), start first, or
+    /// `None` if nothing is selected
+    pub fn selection(&self) -> Option<((usize, usize), (usize, usize))> {
+        let anchor = self.selection_anchor?;
+        let cursor = (self.row, self.col);
+        match anchor.cmp(&cursor) {
+            std::cmp::Ordering::Less => Some((anchor, cursor)),
+            std::cmp::Ordering::Greater => Some((cursor, anchor)),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+
+    /// Text of the selection, with newlines between lines
+    pub fn selected_text(&self) -> Option<String> {
+        let ((start_row, start_col), (end_row, end_col)) = self.selection()?;
+        let first = &self.lines[start_row];
+        if start_row == end_row {
+            return Some(first[byte_offset(first, start_col)..byte_offset(first, end_col)].to_string());
+        }
+
+        let mut text = first[byte_offset(first, start_col)..].to_string();
+        for line in &self.lines[start_row + 1..end_row] {
+            text.push('\n');
+            text.push_str(line);
+        }
+        let last = &self.lines[end_row];
+        text.push('\n');
+        text.push_str(&last[..byte_offset(last, end_col)]);
+        Some(text)
+    }
+
+    /// Copy the selection to the system clipboard, returning false if
+    /// nothing is selected
+    pub fn copy_selection(&self) -> anyhow::Result<bool> {
+        let Some(text) = self.selected_text() else {
+            return Ok(false);
+        };
+        clipboard::copy(&text)?;
+        Ok(true)
+    }
+
+    /// Lines with a breakpoint, in order
+    pub fn breakpoint_lines(&self) -> Vec<usize> {
+        let mut rows: Vec<usize> = self
+            .breakpoints
+            .iter()
+            .filter_map(|bp| bp.resolve(&self.lines))
+            .collect();
+        rows.sort_unstable();
+        rows.dedup();
+        rows
+    }
+
+    /// Lines of `lines`, such as the source the running program was
+    /// assembled from, that breakpoints refer to. Breakpoints on lines
+    /// edited since aren't in it.
+    pub fn breakpoint_lines_in(&self, lines: &[String]) -> Vec<usize> {
+        let mut rows: Vec<usize> = self.breakpoints.iter().filter_map(|bp| bp.resolve(lines)).collect();
+        rows.sort_unstable();
+        rows.dedup();
+        rows
+    }
+
+    /// Set or clear a breakpoint on the cursor line, returning whether one
+    /// is now set
+    pub fn toggle_breakpoint(&mut self) -> bool {
+        let mut rows = self.breakpoint_lines();
+        let set = match rows.binary_search(&self.row) {
+            Ok(i) => {
+                rows.remove(i);
+                false
+            }
+            Err(i) => {
+                rows.insert(i, self.row);
+                true
+            }
+        };
+        self.anchor_breakpoints(rows);
+        set
+    }
+
+    /// Re-anchor breakpoints to `rows` of the current buffer
+    fn anchor_breakpoints(&mut self, mut rows: Vec<usize>) {
+        rows.dedup();
+        self.breakpoints = rows.into_iter().map(|row| LineAnchor::new(&self.lines, row)).collect();
+    }
+
+    /// Apply an edit, moving breakpoints from each line to `shift(line)`
+    /// and re-anchoring them to the edited content
+    fn edit(&mut self, shift: impl Fn(usize) -> usize, apply: impl FnOnce(&mut Self)) {
+        let rows = self.breakpoint_lines();
+        self.selection_anchor = None;
+        apply(self);
+        self.anchor_breakpoints(rows.into_iter().map(shift).collect());
+    }
+
+    /// Insert a character at the cursor
+    pub fn insert_char(&mut self, c: char) {
+        self.edit(|row| row, |editor| {
+            let line = &mut editor.lines[editor.row];
+            line.insert(byte_offset(line, editor.col), c);
+            editor.col += 1;
+        });
+    }
+
+    /// Insert a tab, or spaces up to the next tab stop
+    pub fn insert_tab(&mut self) {
+        if self.settings.hard_tabs {
+            self.insert_char('\t');
+            return;
+        }
+
+        let width = self.settings.tab_width.max(1);
+        let column = self.cursor_display_column();
+        for _ in 0..width - column % width {
+            self.insert_char(' ');
+        }
+    }
+
+    /// Split the line at the cursor, indenting the new line to match
+    pub fn newline(&mut self) {
+        // A breakpoint moves down with the line's code when the split is
+        // before it
+        let (row, line) = (self.row, &self.lines[self.row]);
+        let moves_code = line[..byte_offset(line, self.col)].trim().is_empty();
+        let shift = |r: usize| if r > row || (r == row && moves_code) { r + 1 } else { r };
+
+        self.edit(shift, |editor| {
+            let line = &mut editor.lines[editor.row];
+            let rest = line.split_off(byte_offset(line, editor.col));
+            let indent = auto_indent(line, &editor.settings);
+
+            editor.col = indent.chars().count();
+            editor.row += 1;
+            editor.lines.insert(editor.row, indent + rest.trim_start());
+        });
+    }
+
+    /// Lines defining each label in the buffer, keeping the first definition
+    /// of a duplicated label
+    pub fn label_definitions(&self) -> HashMap<&str, usize> {
+        let mut labels = HashMap::new();
+        for (row, line) in self.lines.iter().enumerate() {
+            if let Some((_, name)) = label_definition(line) {
+                labels.entry(name).or_insert(row);
+            }
+        }
+        labels
+    }
+
+    /// Lines using `label` as an operand
+    pub fn references(&self, label: &str) -> Vec<usize> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| {
+                let tokens = tokenize(line);
+                let skip = if label_definition(line).is_some() { 2 } else { 1 };
+                tokens.iter().skip(skip).any(|&(_, token)| token == label)
+            })
+            .map(|(row, _)| row)
+            .collect()
+    }
+
+    /// Label under the cursor, whether it's being defined or used
+    pub fn label_at_cursor(&self) -> Option<&str> {
+        let line = &self.lines[self.row];
+        let offset = byte_offset(line, self.col);
+        let (_, token) = tokenize(line)
+            .into_iter()
+            .find(|&(column, token)| (column - 1..column - 1 + token.len()).contains(&offset))?;
+
+        let name = token.strip_suffix(':').unwrap_or(token);
+        is_label_name(name).then_some(name)
+    }
+
+    /// Move the cursor to the definition of the label under it, returning
+    /// false if there is no such label
+    pub fn goto_definition(&mut self) -> bool {
+        let Some(row) = self
+            .label_at_cursor()
+            .and_then(|label| self.label_definitions().get(label).copied())
+        else {
+            return false;
+        };
+
+        let (column, _) = label_definition(&self.lines[row]).unwrap();
+        self.row = row;
+        self.col = self.lines[row][..column - 1].chars().count();
+        true
+    }
+
+    /// Lines referencing the label under the cursor
+    pub fn references_at_cursor(&self) -> Vec<usize> {
+        self.label_at_cursor()
+            .map(|label| self.references(label))
+            .unwrap_or_default()
+    }
+
+    /// Move the cursor to the next use of the label under it, wrapping
+    /// around to the first, returning false if the label is never used
+    pub fn goto_next_reference(&mut self) -> bool {
+        let rows = self.references_at_cursor();
+        let Some(&row) = rows.iter().find(|&&row| row > self.row).or(rows.first()) else {
+            return false;
+        };
+
+        let label = self.label_at_cursor().unwrap().to_owned();
+        let line = &self.lines[row];
+        let skip = if label_definition(line).is_some() { 2 } else { 1 };
+        let (column, _) = tokenize(line)
+            .into_iter()
+            .skip(skip)
+            .find(|&(_, token)| token == label)
+            .unwrap();
+        self.row = row;
+        self.col = line[..column - 1].chars().count();
+        true
+    }
+
+    /// Delete the character before the cursor, joining lines at column 0
+    pub fn backspace(&mut self) {
+        if self.col > 0 {
+            self.edit(|row| row, |editor| {
+                editor.col -= 1;
+                let line = &mut editor.lines[editor.row];
+                line.remove(byte_offset(line, editor.col));
+            });
+        } else if self.row > 0 {
+            let row = self.row;
+            self.edit(|r| if r >= row { r - 1 } else { r }, |editor| {
+                let line = editor.lines.remove(editor.row);
+                editor.row -= 1;
+                editor.col = editor.lines[editor.row].chars().count();
+                editor.lines[editor.row].push_str(&line);
+            });
+        }
+    }
+
+    /// Move the cursor for an arrow, Home or End key. Left and Right wrap
+    /// to the neighbouring line.
+    pub fn move_cursor(&mut self, key: KeyCode) {
+        let len = self.lines[self.row].chars().count();
+        match key {
+            KeyCode::Left if self.col > 0 => self.col -= 1,
+            KeyCode::Left if self.row > 0 => self.set_cursor(self.row - 1, usize::MAX),
+            KeyCode::Right if self.col < len => self.col += 1,
+            KeyCode::Right if self.row + 1 < self.lines.len() => self.set_cursor(self.row + 1, 0),
+            KeyCode::Up => self.set_cursor(self.row.saturating_sub(1), self.col),
+            KeyCode::Down => self.set_cursor(self.row + 1, self.col),
+            KeyCode::Home => self.col = 0,
+            KeyCode::End => self.col = len,
+            _ => {}
+        }
+    }
+
+    /// Handle a key press in edit mode, returning false if the key wasn't
+    /// used. Tab indents following the settings, Shift with the arrow keys
+    /// selects and Ctrl+C copies the
+    /// selection. F9 toggles a breakpoint on the cursor line, F12 goes to
+    /// the definition of the label under the cursor and Shift+F12 to its
+    /// next use.
+    pub fn handle_key(&mut self, key: KeyEvent) -> bool {
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            if let Err(err) = self.copy_selection() {
+                log::warn!("{:#}", err);
+            }
+            return true;
+        }
+        if key.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char(c) => self.insert_char(c),
+            KeyCode::Enter => self.newline(),
+            KeyCode::Tab => self.insert_tab(),
+            KeyCode::Backspace => self.backspace(),
+            KeyCode::F(9) => {
+                self.toggle_breakpoint();
+            }
+            KeyCode::F(12) if key.modifiers.contains(KeyModifiers::SHIFT) => {
+                self.goto_next_reference();
+            }
+            KeyCode::F(12) => {
+                self.goto_definition();
+            }
+            KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End => {
+                if !key.modifiers.contains(KeyModifiers::SHIFT) {
+                    self.clear_selection();
+                } else if self.selection_anchor.is_none() {
+                    self.start_selection();
+                }
+                self.move_cursor(key.code)
+            }
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_newline_keeps_indentation() {
+        let mut editor = Editor::new("    PUSH1 1");
+        editor.set_cursor(0, 11);
+        editor.newline();
+
+        assert_eq!(editor.lines(), ["    PUSH1 1", "    "]);
+        assert_eq!(editor.cursor(), (1, 4));
+
+        // Text after the cursor moves down, re-indented
+        editor.insert_char('A');
+        editor.insert_char('D');
+        editor.insert_char('D');
+        editor.set_cursor(0, 9);
+        editor.newline();
+        assert_eq!(editor.text(), "    PUSH1\n    1\n    ADD");
+    }
+
+    #[test]
+    fn test_newline_indents_after_label() {
+        let mut editor = Editor::new("loop: ; top of loop");
+        editor.set_cursor(0, usize::MAX);
+        editor.newline();
+        assert_eq!(editor.cursor(), (1, 4));
+
+        let settings = EditorSettings::default();
+        assert_eq!(auto_indent("  inner:", &settings), "      ");
+        assert_eq!(auto_indent("    JUMP ; not: a label", &settings), "    ");
+
+        // Backspace at column 0 joins the lines again
+        editor.set_cursor(1, 0);
+        editor.backspace();
+        assert_eq!(editor.text(), "loop: ; top of loop    ");
+    }
+
+    #[test]
+    fn test_tab_settings() {
+        let mut editor = Editor::new("");
+        editor.set_settings(EditorSettings { tab_width: 2, hard_tabs: false });
+        editor.insert_tab();
+        assert_eq!(editor.lines(), ["  "]);
+        // Spaces only go up to the next tab stop
+        editor.insert_char('A');
+        editor.insert_tab();
+        assert_eq!(editor.lines(), ["  A "]);
+
+        let mut editor = Editor::new("");
+        editor.set_settings(EditorSettings { tab_width: 2, hard_tabs: true });
+        editor.insert_tab();
+        editor.insert_char('A');
+        editor.insert_tab();
+        assert_eq!(editor.lines(), ["\tA\t"]);
+        assert_eq!(editor.display_line(0), "  A ");
+        assert_eq!(editor.cursor_display_column(), 4);
+
+        // Indentation after a label follows the settings too
+        editor.set_text("loop:");
+        editor.set_cursor(0, 5);
+        editor.newline();
+        assert_eq!(editor.lines(), ["loop:", "\t"]);
+    }
+
+    #[test]
+    fn test_goto_definition_and_references() {
+        let source = "\
+start:
+    PUSH1 0
+  loop:
+    PUSH1 1
+    ADD
+    JUMP2 loop
+    JUMP2 done ; not a label on this line: loop
+done:
+    JUMPIF2 loop
+    HALT";
+        let mut editor = Editor::new(source);
+
+        // Cursor in the middle of the `loop` operand of JUMP2
+        editor.set_cursor(5, 12);
+        assert_eq!(editor.label_at_cursor(), Some("loop"));
+        assert!(editor.goto_definition());
+        assert_eq!(editor.cursor(), (2, 2));
+
+        // On the definition itself, list the references and step through them
+        assert_eq!(editor.references_at_cursor(), vec![5, 8]);
+        assert!(editor.goto_next_reference());
+        assert_eq!(editor.cursor(), (5, 10));
+        assert!(editor.goto_next_reference());
+        assert_eq!(editor.cursor(), (8, 12));
+        assert!(editor.goto_next_reference());
+        assert_eq!(editor.cursor(), (5, 10));
+        assert!(editor.goto_definition());
+
+        editor.set_cursor(6, 10);
+        assert!(editor.goto_definition());
+        assert_eq!(editor.cursor(), (7, 0));
+
+        // Mnemonics and blank space aren't labels with definitions
+        editor.set_cursor(3, 5);
+        assert!(!editor.goto_definition());
+        editor.set_cursor(3, 0);
+        assert!(!editor.goto_definition());
+        assert_eq!(editor.cursor(), (3, 0));
+    }
+
+    #[test]
+    fn test_selected_text() {
+        let mut editor = Editor::new("start:\n    PUSH1 1 ; é\n    HALT");
+        assert_eq!(editor.selected_text(), None);
+
+        editor.set_cursor(1, 4);
+        editor.start_selection();
+        assert_eq!(editor.selected_text(), None);
+        editor.set_cursor(1, 11);
+        assert_eq!(editor.selected_text().as_deref(), Some("PUSH1 1"));
+
+        // Across lines, selecting backwards from the anchor
+        editor.set_cursor(0, 2);
+        assert_eq!(editor.selection(), Some(((0, 2), (1, 4))));
+        assert_eq!(editor.selected_text().as_deref(), Some("art:\n    "));
+        editor.set_cursor(2, 8);
+        assert_eq!(editor.selected_text().as_deref(), Some("PUSH1 1 ; é\n    HALT"));
+
+        // Editing drops the selection
+        editor.insert_char('!');
+        assert_eq!(editor.selected_text(), None);
+
+        editor.set_cursor(0, 0);
+        editor.start_selection();
+        editor.set_cursor(usize::MAX, usize::MAX);
+        assert_eq!(editor.selected_text(), Some(editor.text()));
+        editor.clear_selection();
+        assert!(!editor.copy_selection().unwrap());
+    }
+
+    #[test]
+    fn test_breakpoints_follow_their_line() {
+        let mut editor = Editor::new("PUSH1 1\nADD\nPUSH1 2\nADD\nHALT");
+        editor.set_cursor(3, 0);
+        assert!(editor.toggle_breakpoint());
+        assert_eq!(editor.breakpoint_lines(), vec![3]);
+
+        // Insert a line above; the breakpoint stays on the second ADD
+        editor.set_cursor(0, 0);
+        editor.newline();
+        editor.set_cursor(0, 0);
+        for c in "NOP".chars() {
+            editor.insert_char(c);
+        }
+        assert_eq!(editor.lines()[4], "ADD");
+        assert_eq!(editor.breakpoint_lines(), vec![4]);
+
+        // It still refers to the same line of the source as assembled
+        let assembled: Vec<String> = ["PUSH1 1", "ADD", "PUSH1 2", "ADD", "HALT"].map(String::from).into();
+        assert_eq!(editor.breakpoint_lines_in(&assembled), vec![3]);
+
+        // Editing the line itself keeps the breakpoint on it
+        editor.set_cursor(4, 0);
+        editor.insert_char(' ');
+        assert_eq!(editor.breakpoint_lines(), vec![4]);
+        assert_eq!(editor.breakpoint_lines_in(&assembled), vec![3]);
+        editor.insert_char('!');
+        assert!(editor.breakpoint_lines_in(&assembled).is_empty());
+        editor.backspace();
+
+        // Replacing the text re-resolves by content
+        editor.set_text("; header\nNOP\nPUSH1 1\nADD\nPUSH1 2\nADD\nHALT");
+        assert_eq!(editor.breakpoint_lines(), vec![5]);
+
+        // Joining it onto the line above carries the breakpoint along
+        editor.set_cursor(5, 0);
+        editor.backspace();
+        assert_eq!(editor.lines()[4], "PUSH1 2ADD");
+        assert_eq!(editor.breakpoint_lines(), vec![4]);
+
+        assert!(!editor.toggle_breakpoint());
+        assert!(editor.breakpoint_lines().is_empty());
+    }
+
+    #[test]
+    fn test_handle_key() {
+        let mut editor = Editor::new("loop:\nHALT");
+        let press = |editor: &mut Editor, code| editor.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        press(&mut editor, KeyCode::End);
+        assert!(press(&mut editor, KeyCode::Enter));
+        for c in "PUSH1 1".chars() {
+            press(&mut editor, KeyCode::Char(c));
+        }
+        assert_eq!(editor.lines(), ["loop:", "    PUSH1 1", "HALT"]);
+
+        // Left and Right wrap across lines, Up and Down clamp the column
+        press(&mut editor, KeyCode::Right);
+        assert_eq!(editor.cursor(), (2, 0));
+        press(&mut editor, KeyCode::Left);
+        assert_eq!(editor.cursor(), (1, 11));
+        press(&mut editor, KeyCode::Up);
+        assert_eq!(editor.cursor(), (0, 5));
+        press(&mut editor, KeyCode::Home);
+        press(&mut editor, KeyCode::Backspace);
+        assert_eq!(editor.cursor(), (0, 0));
+
+        // Tab follows the settings
+        editor.set_settings(EditorSettings { tab_width: 2, hard_tabs: false });
+        editor.set_cursor(2, 0);
+        press(&mut editor, KeyCode::Tab);
+        editor.set_settings(EditorSettings { tab_width: 2, hard_tabs: true });
+        press(&mut editor, KeyCode::Tab);
+        assert_eq!(editor.lines()[2], "  \tHALT");
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Backspace);
+        press(&mut editor, KeyCode::Backspace);
+
+        // Keys with Ctrl are left for the caller
+        assert!(!editor.handle_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL)));
+        assert!(!press(&mut editor, KeyCode::F(1)));
+        assert_eq!(editor.text(), "loop:\n    PUSH1 1\nHALT");
+
+        // Shift+arrows select, and other movement drops the selection
+        let shift = |code| KeyEvent::new(code, KeyModifiers::SHIFT);
+        editor.set_cursor(1, 4);
+        editor.handle_key(shift(KeyCode::End));
+        editor.handle_key(shift(KeyCode::Down));
+        assert_eq!(editor.selected_text().as_deref(), Some("PUSH1 1\nHALT"));
+        press(&mut editor, KeyCode::Left);
+        assert_eq!(editor.selection(), None);
+        assert!(editor.handle_key(KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL)));
+
+        // F12 on a jump operand goes to the label
+        editor.set_text("loop:\n    JUMP loop");
+        editor.set_cursor(1, 10);
+        assert!(press(&mut editor, KeyCode::F(12)));
+        assert_eq!(editor.cursor(), (0, 0));
+        assert!(editor.handle_key(KeyEvent::new(KeyCode::F(12), KeyModifiers::SHIFT)));
+        assert_eq!(editor.cursor(), (1, 9));
+    }
+}
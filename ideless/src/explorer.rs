@@ -0,0 +1,426 @@
+//! File explorer over the workspace directory.
+//!
+//! Directories are read when they're first expanded rather than all up
+//! front, so a large workspace opens quickly. Entries matched by the root's
+//! `.gitignore` are hidden, as is `.git` itself, and `refresh` rereads every
+//! expanded directory to pick up files created or deleted since.
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use crossterm::event::KeyCode;
+
+use crate::files::{OpenFile, OpenFiles};
+
+/// A file or directory in the tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntry {
+    pub path: PathBuf,
+
+    /// File name shown in the tree
+    pub name: String,
+
+    pub is_dir: bool,
+
+    /// Whether the directory's children are shown
+    pub expanded: bool,
+
+    /// Directory contents, `None` until the directory is first expanded
+    children: Option<Vec<FileEntry>>,
+}
+
+impl FileEntry {
+    fn new(path: PathBuf, is_dir: bool) -> Self {
+        let name = path.file_name().map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+        FileEntry { path, name, is_dir, expanded: false, children: None }
+    }
+
+    /// Directory contents, if they have been read
+    pub fn children(&self) -> Option<&[FileEntry]> {
+        self.children.as_deref()
+    }
+}
+
+/// One pattern line of a `.gitignore`
+#[derive(Debug, Clone)]
+struct IgnoreRule {
+    pattern: String,
+
+    /// `!pattern`, un-ignoring what earlier rules matched
+    negated: bool,
+
+    /// `pattern/`, matching only directories
+    dir_only: bool,
+
+    /// Whether the pattern is matched against the whole relative path
+    /// rather than just the file name
+    anchored: bool,
+}
+
+/// The subset of `.gitignore` syntax the explorer understands: `*`, `**`
+/// and `?` wildcards, `!` negation, trailing `/` for directories and
+/// patterns anchored to the root by a `/`
+#[derive(Debug, Clone, Default)]
+pub struct Gitignore {
+    rules: Vec<IgnoreRule>,
+}
+
+impl Gitignore {
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let anchored = line.contains('/');
+                let pattern = line.trim_start_matches('/').to_string();
+                IgnoreRule { pattern, negated, dir_only, anchored }
+            })
+            .collect();
+        Gitignore { rules }
+    }
+
+    /// Rules from `root/.gitignore`, or none if there isn't one
+    pub fn load(root: &Path) -> Self {
+        fs::read_to_string(root.join(".gitignore")).map(|text| Self::parse(&text)).unwrap_or_default()
+    }
+
+    /// Whether the entry at `relative`, a path from the root, is ignored
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = relative.to_string_lossy().replace('\\', "/");
+        let name = path.rsplit('/').next().unwrap_or(&path);
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let text = if rule.anchored { path.as_str() } else { name };
+            if glob_match(rule.pattern.as_bytes(), text.as_bytes()) {
+                ignored = !rule.negated;
+            }
+        }
+        ignored
+    }
+}
+
+/// Whether `text` matches `pattern`, where `*` and `?` stay within one
+/// path component and `**` crosses them. Runs in O(pattern * text) by
+/// filling in which suffixes of the pattern match which suffixes of the
+/// text, shortest first.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    // matches[i][j]: whether pattern[i..] matches text[j..]
+    let mut matches = vec![vec![false; text.len() + 1]; pattern.len() + 1];
+    matches[pattern.len()][text.len()] = true;
+
+    for i in (0..pattern.len()).rev() {
+        for j in (0..=text.len()).rev() {
+            let next = text.get(j).copied();
+            matches[i][j] = match &pattern[i..] {
+                // Match nothing, or one more byte of any kind
+                [b'*', b'*', rest @ ..] => {
+                    let skip = if rest.first() == Some(&b'/') { 3 } else { 2 };
+                    matches[i + skip][j] || (next.is_some() && matches[i][j + 1])
+                }
+                [b'*', ..] => matches[i + 1][j] || (next.is_some_and(|b| b != b'/') && matches[i][j + 1]),
+                [b'?', ..] => next.is_some_and(|b| b != b'/') && matches[i + 1][j + 1],
+                [c, ..] => next == Some(*c) && matches[i + 1][j + 1],
+                [] => unreachable!(),
+            };
+        }
+    }
+    matches[0][0]
+}
+
+/// Tree of the workspace with a selected row
+#[derive(Debug, Clone)]
+pub struct FileExplorer {
+    root: PathBuf,
+
+    ignore: Gitignore,
+
+    /// Top-level entries
+    entries: Vec<FileEntry>,
+
+    /// Index of the selected row among the visible rows
+    selected: usize,
+}
+
+impl FileExplorer {
+    /// Explorer showing the top level of `root`
+    pub fn new(root: &Path) -> Result<Self> {
+        let mut explorer = FileExplorer {
+            root: root.to_path_buf(),
+            ignore: Gitignore::load(root),
+            entries: Vec::new(),
+            selected: 0,
+        };
+        explorer.entries = explorer.read_dir(root)?;
+        Ok(explorer)
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Top-level entries
+    pub fn entries(&self) -> &[FileEntry] {
+        &self.entries
+    }
+
+    /// Visible entries in display order, with their depth in the tree
+    pub fn rows(&self) -> Vec<(usize, &FileEntry)> {
+        fn walk<'a>(entries: &'a [FileEntry], depth: usize, rows: &mut Vec<(usize, &'a FileEntry)>) {
+            for entry in entries {
+                rows.push((depth, entry));
+                if let (true, Some(children)) = (entry.expanded, &entry.children) {
+                    walk(children, depth + 1, rows);
+                }
+            }
+        }
+        let mut rows = Vec::new();
+        walk(&self.entries, 0, &mut rows);
+        rows
+    }
+
+    /// Index of the selected row
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    /// The selected entry, if there are any
+    pub fn selected_entry(&self) -> Option<&FileEntry> {
+        self.rows().get(self.selected).map(|&(_, entry)| entry)
+    }
+
+    pub fn select_next(&mut self) {
+        self.selected = (self.selected + 1).min(self.rows().len().saturating_sub(1));
+    }
+
+    pub fn select_previous(&mut self) {
+        self.selected = self.selected.saturating_sub(1);
+    }
+
+    /// Expand or collapse the selected directory, reading it the first time
+    /// it's expanded. Returns the selected file's path if a file is selected.
+    pub fn activate(&mut self) -> Result<Option<PathBuf>> {
+        let Some(entry) = self.selected_entry() else {
+            return Ok(None);
+        };
+        if !entry.is_dir {
+            return Ok(Some(entry.path.clone()));
+        }
+        let children = match entry.children {
+            Some(_) => None,
+            None => Some(self.read_dir(&entry.path)?),
+        };
+        let entry = nth_row_mut(&mut self.entries, &mut self.selected.clone()).expect("selected row exists");
+        if let Some(children) = children {
+            entry.children = Some(children);
+        }
+        entry.expanded = !entry.expanded;
+        Ok(None)
+    }
+
+    /// Collapse the selected directory, or the one containing the selected file
+    pub fn collapse(&mut self) {
+        let rows = self.rows();
+        let Some(&(depth, entry)) = rows.get(self.selected) else {
+            return;
+        };
+        if !(entry.is_dir && entry.expanded) && depth > 0 {
+            self.selected = rows[..self.selected].iter().rposition(|&(d, _)| d < depth).unwrap_or(0);
+        }
+        if let Some(entry) = nth_row_mut(&mut self.entries, &mut self.selected.clone()) {
+            entry.expanded = false;
+        }
+    }
+
+    /// Open the selected file in a new tab, or switch to its tab if it's
+    /// already open. Directories are expanded or collapsed instead.
+    /// Returns whether a file was opened.
+    pub fn open_selected(&mut self, files: &mut OpenFiles) -> Result<bool> {
+        let Some(path) = self.activate()? else {
+            return Ok(false);
+        };
+        match files.files().iter().position(|file| file.path.as_deref() == Some(path.as_path())) {
+            Some(index) => files.set_active(index),
+            None => files.add(OpenFile::open(&path)?),
+        }
+        Ok(true)
+    }
+
+    /// Reread `.gitignore`, the root and every expanded directory,
+    /// keeping expanded directories expanded
+    pub fn refresh(&mut self) -> Result<()> {
+        fn expanded_dirs(entries: &[FileEntry], dirs: &mut HashSet<PathBuf>) {
+            for entry in entries.iter().filter(|entry| entry.expanded) {
+                dirs.insert(entry.path.clone());
+                expanded_dirs(entry.children().unwrap_or_default(), dirs);
+            }
+        }
+        let mut dirs = HashSet::new();
+        expanded_dirs(&self.entries, &mut dirs);
+        let selected = self.selected_entry().map(|entry| entry.path.clone());
+
+        self.ignore = Gitignore::load(&self.root);
+        let mut entries = self.read_dir(&self.root)?;
+        self.expand_dirs(&mut entries, &dirs)?;
+        self.entries = entries;
+
+        let rows = self.rows();
+        self.selected = selected
+            .and_then(|path| rows.iter().position(|(_, entry)| entry.path == path))
+            .unwrap_or_else(|| self.selected.min(rows.len().saturating_sub(1)));
+        Ok(())
+    }
+
+    /// Handle a key in the explorer. Returns whether the key was used.
+    pub fn handle_key(&mut self, key: KeyCode, files: &mut OpenFiles) -> Result<bool> {
+        match key {
+            KeyCode::Up => self.select_previous(),
+            KeyCode::Down => self.select_next(),
+            KeyCode::Enter | KeyCode::Right => {
+                self.open_selected(files)?;
+            }
+            KeyCode::Left => self.collapse(),
+            KeyCode::F(5) => self.refresh()?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Read `entries`' children that are in `dirs` and expand them
+    fn expand_dirs(&self, entries: &mut [FileEntry], dirs: &HashSet<PathBuf>) -> Result<()> {
+        for entry in entries.iter_mut().filter(|entry| entry.is_dir && dirs.contains(&entry.path)) {
+            let mut children = self.read_dir(&entry.path)?;
+            self.expand_dirs(&mut children, dirs)?;
+            entry.children = Some(children);
+            entry.expanded = true;
+        }
+        Ok(())
+    }
+
+    /// Entries of `dir` that aren't ignored, directories first, each group
+    /// sorted by name
+    fn read_dir(&self, dir: &Path) -> Result<Vec<FileEntry>> {
+        let mut entries = Vec::new();
+        for entry in fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))? {
+            let entry = entry?;
+            let path = entry.path();
+            let is_dir = entry.file_type()?.is_dir();
+            let relative = path.strip_prefix(&self.root).unwrap_or(&path);
+            if entry.file_name() == ".git" || self.ignore.is_ignored(relative, is_dir) {
+                continue;
+            }
+            entries.push(FileEntry::new(path, is_dir));
+        }
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+        Ok(entries)
+    }
+}
+
+/// The entry on visible row `*row`, counting rows down from `entries`
+fn nth_row_mut<'a>(entries: &'a mut [FileEntry], row: &mut usize) -> Option<&'a mut FileEntry> {
+    for entry in entries {
+        if *row == 0 {
+            return Some(entry);
+        }
+        *row -= 1;
+        if entry.expanded {
+            if let Some(found) = entry.children.as_deref_mut().and_then(|children| nth_row_mut(children, row)) {
+                return Some(found);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Rows as indented names, directories with a trailing `/`
+    fn tree(explorer: &FileExplorer) -> Vec<String> {
+        explorer
+            .rows()
+            .iter()
+            .map(|(depth, entry)| format!("{}{}{}", "  ".repeat(*depth), entry.name, if entry.is_dir { "/" } else { "" }))
+            .collect()
+    }
+
+    #[test]
+    fn test_explorer_reads_workspace() {
+        let dir = std::env::temp_dir().join(format!("lessvm-explorer-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        for path in ["src/nested", "target/debug", ".git", "logs"] {
+            fs::create_dir_all(dir.join(path)).unwrap();
+        }
+        for path in ["main.lasm", "src/lib.lasm", "src/nested/deep.lasm", "src/scratch.tmp", "target/debug/out.bin", "logs/keep.log"] {
+            fs::write(dir.join(path), "HALT").unwrap();
+        }
+        fs::write(dir.join(".gitignore"), "# build output\n/target/\n*.tmp\nlogs/\n").unwrap();
+
+        // Only the top level is read until a directory is expanded
+        let mut explorer = FileExplorer::new(&dir).unwrap();
+        assert_eq!(tree(&explorer), vec!["src/", ".gitignore", "main.lasm"]);
+        assert_eq!(explorer.entries()[0].children(), None);
+
+        let mut files = OpenFiles::default();
+        assert!(explorer.handle_key(KeyCode::Enter, &mut files).unwrap());
+        explorer.select_next();
+        explorer.activate().unwrap();
+        assert_eq!(tree(&explorer), vec!["src/", "  nested/", "    deep.lasm", "  lib.lasm", ".gitignore", "main.lasm"]);
+
+        // Opening a file adds a tab, and opening it again switches to it
+        explorer.select_next();
+        explorer.select_next();
+        assert!(explorer.open_selected(&mut files).unwrap());
+        assert_eq!(files.active().unwrap().title(), "lib.lasm");
+        files.add(OpenFile::untitled());
+        assert!(explorer.open_selected(&mut files).unwrap());
+        assert_eq!((files.files().len(), files.active_index()), (2, 0));
+
+        // Refreshing picks up new files and keeps directories expanded
+        fs::write(dir.join("src/added.lasm"), "HALT").unwrap();
+        explorer.refresh().unwrap();
+        assert_eq!(explorer.selected_entry().unwrap().name, "lib.lasm");
+        assert_eq!(tree(&explorer)[1..5], ["  nested/", "    deep.lasm", "  added.lasm", "  lib.lasm"]);
+
+        // Left from a file collapses its directory
+        explorer.collapse();
+        assert_eq!(explorer.selected(), 0);
+        assert_eq!(tree(&explorer), vec!["src/", ".gitignore", "main.lasm"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_gitignore_patterns() {
+        let ignore = Gitignore::parse("/build\n*.o\n!keep.o\ndocs/**/*.md\ncache/\n");
+        assert!(ignore.is_ignored(Path::new("build"), true));
+        assert!(!ignore.is_ignored(Path::new("src/build"), true));
+        assert!(ignore.is_ignored(Path::new("src/a.o"), false));
+        assert!(!ignore.is_ignored(Path::new("src/keep.o"), false));
+        assert!(ignore.is_ignored(Path::new("docs/a/b/c.md"), false));
+        assert!(ignore.is_ignored(Path::new("docs/c.md"), false));
+        assert!(!ignore.is_ignored(Path::new("docs/c.txt"), false));
+        assert!(ignore.is_ignored(Path::new("src/cache"), true));
+        assert!(!ignore.is_ignored(Path::new("src/cache"), false));
+
+        // Many wildcards against a long name that almost matches
+        let name = "a".repeat(200);
+        assert!(!glob_match("*a*a*a*a*a*a*a*a*a*a*a*a*b".as_bytes(), name.as_bytes()));
+        assert!(!glob_match("**a**a**a**a**a**a**a**a**b".as_bytes(), name.as_bytes()));
+        assert!(glob_match("**a**a**a**a**a**a**a**a**".as_bytes(), name.as_bytes()));
+        assert!(glob_match(b"a?c", b"abc"));
+        assert!(!glob_match(b"a?c", b"a/c"));
+    }
+}
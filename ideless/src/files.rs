@@ -0,0 +1,368 @@
+//! Source files open in the editor.
+//!
+//! Each open file is an `Editor` buffer plus the path it is saved to, if
+//! any, and the text it was last saved with so unsaved changes can be
+//! detected. Commands that act on files are dispatched by id, e.g.
+//! `file.saveAll`, so key bindings and a command palette can share them;
+//! `command_for_key` has the editor's bindings.
+//!
+//! The modification time of each file is remembered when it's loaded or
+//! saved, and `tick` asks whether to reload one that changed on disk since,
+//! so external edits aren't silently overwritten by the next save.
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, Context, Result};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::editor::Editor;
+
+/// Title shown for a buffer that has never been saved
+pub const UNTITLED: &str = "Untitled";
+
+/// Id of the file command bound to `key`, if any
+pub fn command_for_key(key: KeyEvent) -> Option<&'static str> {
+    if !key.modifiers.contains(KeyModifiers::CONTROL) {
+        return None;
+    }
+    match key.code {
+        KeyCode::Char('n') => Some("file.new"),
+        KeyCode::Char('s') => Some("file.saveAll"),
+        KeyCode::Char('w') => Some("file.close"),
+        KeyCode::PageDown => Some("file.nextTab"),
+        KeyCode::PageUp => Some("file.previousTab"),
+        _ => None,
+    }
+}
+
+/// A buffer and where it is saved
+#[derive(Debug, Clone)]
+pub struct OpenFile {
+    /// Where the buffer is saved, `None` until it's saved for the first time
+    pub path: Option<PathBuf>,
+
+    /// Buffer contents
+    pub editor: Editor,
+
+    /// Text as of the last load or save
+    saved_text: String,
+
+    /// Modification time of the file on disk as of the last load or save
+    disk_mtime: Option<SystemTime>,
+}
+
+/// Modification time of the file at `path`, if it can be read
+fn mtime(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+impl OpenFile {
+    /// A new, unsaved buffer
+    pub fn untitled() -> Self {
+        OpenFile { path: None, editor: Editor::default(), saved_text: String::new(), disk_mtime: None }
+    }
+
+    /// Read the file at `path` into a buffer
+    pub fn open(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(OpenFile {
+            path: Some(path.to_path_buf()),
+            editor: Editor::new(&text),
+            saved_text: text,
+            disk_mtime: mtime(path),
+        })
+    }
+
+    /// File name for the tab, or `Untitled`
+    pub fn title(&self) -> String {
+        self.path
+            .as_ref()
+            .and_then(|path| path.file_name())
+            .map_or_else(|| UNTITLED.to_string(), |name| name.to_string_lossy().into_owned())
+    }
+
+    /// Whether the buffer has changed since it was loaded or saved
+    pub fn is_modified(&self) -> bool {
+        self.editor.text() != self.saved_text
+    }
+
+    /// Write the buffer to its path
+    pub fn save(&mut self) -> Result<()> {
+        let path = self.path.as_ref().ok_or_else(|| anyhow!("{} has no path to save to", UNTITLED))?;
+        let text = self.editor.text();
+        fs::write(path, &text).with_context(|| format!("Failed to write {}", path.display()))?;
+        self.saved_text = text;
+        self.disk_mtime = mtime(path);
+        Ok(())
+    }
+
+    /// Whether the file on disk is newer than the version loaded or saved
+    pub fn changed_on_disk(&self) -> bool {
+        let Some(path) = &self.path else {
+            return false;
+        };
+        match (mtime(path), self.disk_mtime) {
+            (Some(current), Some(seen)) => current > seen,
+            _ => false,
+        }
+    }
+
+    /// Replace the buffer with the file on disk
+    pub fn reload(&mut self) -> Result<()> {
+        let path = self.path.as_ref().ok_or_else(|| anyhow!("{} has no file to reload", UNTITLED))?;
+        let text = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        self.editor.set_text(&text);
+        self.saved_text = text;
+        self.disk_mtime = mtime(path);
+        Ok(())
+    }
+}
+
+/// The editor's tabs
+#[derive(Debug, Clone, Default)]
+pub struct OpenFiles {
+    /// Open buffers in tab order
+    files: Vec<OpenFile>,
+
+    /// Index of the tab being edited
+    active: usize,
+
+    /// Tab whose file changed on disk, while asking whether to reload it
+    reload_prompt: Option<usize>,
+}
+
+impl OpenFiles {
+    /// Open buffers in tab order
+    pub fn files(&self) -> &[OpenFile] {
+        &self.files
+    }
+
+    /// The tab being edited, if any are open
+    pub fn active(&self) -> Option<&OpenFile> {
+        self.files.get(self.active)
+    }
+
+    /// The tab being edited, for changing its buffer
+    pub fn active_mut(&mut self) -> Option<&mut OpenFile> {
+        self.files.get_mut(self.active)
+    }
+
+    /// Index of the tab being edited
+    pub fn active_index(&self) -> usize {
+        self.active
+    }
+
+    /// Switch to tab `index`
+    pub fn set_active(&mut self, index: usize) {
+        if index < self.files.len() {
+            self.active = index;
+        }
+    }
+
+    /// Add a tab and switch to it
+    pub fn add(&mut self, file: OpenFile) {
+        self.files.push(file);
+        self.active = self.files.len() - 1;
+    }
+
+    /// Close tab `index`, keeping the same tab active when another one closes
+    pub fn close(&mut self, index: usize) {
+        if index >= self.files.len() {
+            return;
+        }
+        self.files.remove(index);
+        self.reload_prompt = None;
+        if index < self.active || self.active >= self.files.len() {
+            self.active = self.active.saturating_sub(1);
+        }
+    }
+
+    /// Close every tab
+    pub fn close_all(&mut self) {
+        self.files.clear();
+        self.active = 0;
+        self.reload_prompt = None;
+    }
+
+    /// Check open files for changes on disk, raising a reload prompt for
+    /// the first changed one unless a prompt is already up
+    pub fn tick(&mut self) {
+        if self.reload_prompt.is_none() {
+            self.reload_prompt = self.files.iter().position(OpenFile::changed_on_disk);
+        }
+    }
+
+    /// Tab the reload prompt is asking about
+    pub fn reload_prompt(&self) -> Option<usize> {
+        self.reload_prompt
+    }
+
+    /// Answer the reload prompt: reload the file from disk, or keep the
+    /// buffer and stop asking until the file changes again
+    pub fn answer_reload_prompt(&mut self, reload: bool) -> Result<()> {
+        let Some(file) = self.reload_prompt.take().and_then(|index| self.files.get_mut(index)) else {
+            return Ok(());
+        };
+        if reload {
+            return file.reload();
+        }
+        file.disk_mtime = file.path.as_deref().and_then(mtime);
+        Ok(())
+    }
+
+    /// Save every modified buffer, asking `prompt_path` where to save
+    /// untitled ones. Buffers it returns `None` for stay unsaved. Returns
+    /// the number of files written.
+    pub fn save_all(&mut self, mut prompt_path: impl FnMut(&OpenFile) -> Option<PathBuf>) -> Result<usize> {
+        let mut saved = 0;
+        for file in self.files.iter_mut().filter(|file| file.is_modified()) {
+            if file.path.is_none() {
+                let Some(path) = prompt_path(file) else {
+                    continue;
+                };
+                file.path = Some(path);
+            }
+            file.save()?;
+            saved += 1;
+        }
+        Ok(saved)
+    }
+
+    /// Run the file command `id`, returning `Ok(false)` if there is no
+    /// such command
+    pub fn execute_command_by_id(
+        &mut self,
+        id: &str,
+        prompt_path: impl FnMut(&OpenFile) -> Option<PathBuf>,
+    ) -> Result<bool> {
+        match id {
+            "file.new" => self.add(OpenFile::untitled()),
+            "file.close" => self.close(self.active),
+            "file.saveAll" => {
+                self.save_all(prompt_path)?;
+            }
+            "file.closeAll" => self.close_all(),
+            "file.nextTab" | "file.previousTab" if !self.files.is_empty() => {
+                let step = if id == "file.nextTab" { 1 } else { self.files.len() - 1 };
+                self.active = (self.active + step) % self.files.len();
+            }
+            "file.nextTab" | "file.previousTab" => {}
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_all_and_close_all() {
+        let dir = std::env::temp_dir().join(format!("lessvm-files-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (first, second) = (dir.join("first.lasm"), dir.join("second.lasm"));
+        fs::write(&first, "PUSH1 1").unwrap();
+        fs::write(&second, "HALT").unwrap();
+
+        let mut files = OpenFiles::default();
+        files.add(OpenFile::open(&first).unwrap());
+        files.add(OpenFile::open(&second).unwrap());
+        assert!(files.execute_command_by_id("file.new", |_| None).unwrap());
+        assert_eq!(files.active().unwrap().title(), UNTITLED);
+        for file in &mut files.files {
+            file.editor.set_cursor(0, usize::MAX);
+            file.editor.insert_char('!');
+        }
+        assert!(files.files().iter().all(OpenFile::is_modified));
+
+        // The untitled buffer is saved wherever the prompt says
+        let untitled = dir.join("untitled.lasm");
+        let mut prompted = 0;
+        assert!(files.execute_command_by_id("file.saveAll", |file| {
+            prompted += 1;
+            assert_eq!(file.title(), UNTITLED);
+            Some(untitled.clone())
+        }).unwrap());
+        assert_eq!(prompted, 1);
+        assert!(!files.files().iter().any(OpenFile::is_modified));
+        assert_eq!(fs::read_to_string(&first).unwrap(), "PUSH1 1!");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "HALT!");
+        assert_eq!(fs::read_to_string(&untitled).unwrap(), "!");
+        assert_eq!(files.files()[2].title(), "untitled.lasm");
+
+        // Ctrl+PageDown and Ctrl+PageUp cycle through the tabs
+        let ctrl = |code| command_for_key(KeyEvent::new(code, KeyModifiers::CONTROL));
+        assert_eq!(ctrl(KeyCode::Char('s')), Some("file.saveAll"));
+        assert_eq!(command_for_key(KeyEvent::new(KeyCode::Char('s'), KeyModifiers::NONE)), None);
+        assert!(files.execute_command_by_id(ctrl(KeyCode::PageDown).unwrap(), |_| None).unwrap());
+        assert_eq!(files.active_index(), 0);
+        assert!(files.execute_command_by_id(ctrl(KeyCode::PageUp).unwrap(), |_| None).unwrap());
+        assert_eq!(files.active_index(), 2);
+
+        // Closing an earlier tab keeps the same one active
+        files.close(0);
+        assert_eq!(files.active_index(), 1);
+        assert_eq!(files.active().unwrap().title(), "untitled.lasm");
+
+        assert!(files.execute_command_by_id("file.closeAll", |_| None).unwrap());
+        assert!(files.files().is_empty());
+        assert!(files.active().is_none());
+        assert!(!files.execute_command_by_id("file.unknown", |_| None).unwrap());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_reload_prompt_on_external_change() {
+        let path = std::env::temp_dir().join(format!("lessvm-reload-{}.lasm", std::process::id()));
+        fs::write(&path, "PUSH1 1").unwrap();
+        let mut files = OpenFiles::default();
+        files.add(OpenFile::open(&path).unwrap());
+        files.tick();
+        assert_eq!(files.reload_prompt(), None);
+
+        // Another program rewrites the file later on
+        let touch = |files: &OpenFiles, text: &str| {
+            fs::write(&path, text).unwrap();
+            let later = files.files()[0].disk_mtime.unwrap() + std::time::Duration::from_secs(10);
+            fs::File::options().write(true).open(&path).unwrap().set_modified(later).unwrap();
+        };
+        touch(&files, "PUSH1 2");
+        files.tick();
+        assert_eq!(files.reload_prompt(), Some(0));
+
+        // Keeping the buffer stops the prompt until the next change
+        files.answer_reload_prompt(false).unwrap();
+        assert_eq!(files.active().unwrap().editor.text(), "PUSH1 1");
+        files.tick();
+        assert_eq!(files.reload_prompt(), None);
+
+        touch(&files, "PUSH1 3");
+        files.tick();
+        assert_eq!(files.reload_prompt(), Some(0));
+        files.answer_reload_prompt(true).unwrap();
+        assert_eq!(files.active().unwrap().editor.text(), "PUSH1 3");
+        assert!(!files.active().unwrap().is_modified());
+        files.tick();
+        assert_eq!(files.reload_prompt(), None);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_open_keeps_line_endings() {
+        let path = std::env::temp_dir().join(format!("lessvm-endings-{}.lasm", std::process::id()));
+        for text in ["PUSH1 1\nHALT\n", "PUSH1 1\r\nHALT\r\n", "PUSH1 1\r\nHALT", "\n"] {
+            fs::write(&path, text).unwrap();
+            let mut file = OpenFile::open(&path).unwrap();
+            assert!(!file.is_modified(), "{:?}", text);
+            assert_eq!(file.editor.lines()[0], if text == "\n" { "" } else { "PUSH1 1" });
+
+            file.editor.set_cursor(0, 0);
+            file.editor.insert_char(';');
+            file.save().unwrap();
+            assert_eq!(fs::read_to_string(&path).unwrap(), format!(";{}", text));
+        }
+        fs::remove_file(&path).unwrap();
+    }
+}
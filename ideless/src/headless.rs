@@ -0,0 +1,176 @@
+use std::fmt;
+
+use serde_json::json;
+
+use crate::asm::opcode_name;
+use crate::lessvm::{VM, VMState};
+
+/// Default cycle cap for headless runs
+pub const DEFAULT_MAX_CYCLES: u32 = 1_000_000;
+
+/// Final VM state after a headless run
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeadlessReport {
+    /// Final VM state
+    pub state: VMState,
+
+    /// Final stack contents, bottom first
+    pub stack: Vec<u32>,
+
+    /// General purpose registers
+    pub registers: [u32; 16],
+
+    /// Gas consumed
+    pub gas_used: u64,
+
+    /// Instructions executed, including the one the run stopped on
+    pub cycles: u64,
+
+    /// Error message if execution failed
+    pub error: Option<String>,
+
+    /// Whether the run stopped at the gas limit
+    pub out_of_gas: bool,
+
+    /// Per-opcode (opcode, count, gas), hottest first, if profiling was on
+    pub profile: Vec<(u8, u64, u64)>,
+}
+
+impl HeadlessReport {
+    /// Whether the run stopped on a VM error
+    pub fn failed(&self) -> bool {
+        self.error.is_some()
+    }
+
+    /// Render the report as a JSON object
+    pub fn to_json(&self) -> serde_json::Value {
+        json!({
+            "state": self.state.to_string(),
+            "stack": self.stack,
+            "registers": self.registers,
+            "gas_used": self.gas_used,
+            "cycles": self.cycles,
+            "error": self.error,
+            "out_of_gas": self.out_of_gas,
+            "profile": self.profile.iter().map(|&(opcode, count, gas)| json!({
+                "opcode": opcode,
+                "name": opcode_name(opcode),
+                "count": count,
+                "gas": gas,
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl fmt::Display for HeadlessReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "State: {}", self.state)?;
+        writeln!(f, "Stack: {:?}", self.stack)?;
+        writeln!(f, "Registers:")?;
+        for (i, reg) in self.registers.iter().enumerate() {
+            writeln!(f, "  R{}: {:#010X}", i, reg)?;
+        }
+        writeln!(f, "Gas used: {}", self.gas_used)?;
+        write!(f, "Cycles: {}", self.cycles)?;
+        if let Some(error) = &self.error {
+            write!(f, "\nError: {}", error)?;
+        }
+        if !self.profile.is_empty() {
+            write!(f, "\n\nProfile:\n{:<14} {:>10} {:>12}", "Opcode", "Count", "Gas")?;
+            for &(opcode, count, gas) in &self.profile {
+                write!(f, "\n{:<14} {:>10} {:>12}", opcode_name(opcode), count, gas)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run a loaded VM to HALT or `max_cycles` without any terminal UI
+pub fn run(vm: &mut VM, max_cycles: u32) -> HeadlessReport {
+    let result = vm.run(max_cycles);
+
+    HeadlessReport {
+        state: vm.state.clone(),
+        stack: vm.stack.clone(),
+        registers: vm.registers,
+        gas_used: vm.gas_used(),
+        cycles: vm.instruction_count(),
+        error: result.err().map(|e| e.to_string()),
+        out_of_gas: vm.is_out_of_gas(),
+        profile: vm.profile(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_headless_run_report() {
+        let mut vm = VM::new();
+        // PUSH1 2, PUSH1 3, ADD, HALT
+        vm.load_bytecode(&[0x01, 2, 0x01, 3, 0x10, 0xFF]).unwrap();
+
+        let report = run(&mut vm, DEFAULT_MAX_CYCLES);
+        assert!(!report.failed());
+        assert_eq!(report.state, VMState::Halted);
+        assert_eq!(report.stack, vec![5]);
+        // Every opcode of the program, HALT included
+        assert_eq!(report.cycles, 4);
+
+        let json = report.to_json();
+        assert_eq!(json["state"], "Halted");
+        assert_eq!(json["stack"], json!([5]));
+        assert_eq!(json["gas_used"], report.gas_used);
+        assert_eq!(json["cycles"], 4);
+        assert!(report.to_string().contains("\nCycles: 4"));
+        assert!(json["error"].is_null());
+        assert!(report.to_string().starts_with("State: Halted\nStack: [5]\n"));
+        assert!(!report.to_string().contains("Profile:"));
+
+        vm.reset();
+        vm.enable_profiling(true);
+        let report = run(&mut vm, DEFAULT_MAX_CYCLES);
+        assert_eq!(report.profile.len(), 3);
+        let text = report.to_string();
+        let table = text.split_once("\n\nProfile:\n").unwrap().1;
+        assert_eq!(table.lines().count(), 4);
+        assert!(table.lines().any(|line| line.starts_with("PUSH1") && line.contains(" 2 ")));
+        let push = report.profile.iter().position(|p| p.0 == 0x01).unwrap();
+        assert_eq!(report.to_json()["profile"][push]["count"], 2);
+    }
+
+    #[test]
+    fn test_headless_run_error() {
+        let mut vm = VM::new();
+        // ADD on an empty stack
+        vm.load_bytecode(&[0x10, 0xFF]).unwrap();
+
+        let report = run(&mut vm, DEFAULT_MAX_CYCLES);
+        assert!(report.failed());
+        assert!(matches!(report.state, VMState::Error(_)));
+        assert!(report.to_string().contains("\nError: "));
+        assert!(!report.out_of_gas);
+    }
+
+    #[test]
+    fn test_headless_run_out_of_gas() {
+        let mut vm = VM::new();
+        // loop: PUSH1 1, POP, JUMP2 loop
+        vm.load_bytecode(&[0x01, 1, 0x04, 0x34, 0x00, 0x00]).unwrap();
+        vm.set_gas_limit(500);
+
+        let report = run(&mut vm, DEFAULT_MAX_CYCLES);
+        assert!(report.out_of_gas);
+        assert!(report.failed());
+        assert!(report.gas_used >= 500);
+        assert!(report.cycles < u64::from(DEFAULT_MAX_CYCLES));
+        assert_eq!(report.to_json()["out_of_gas"], true);
+
+        // HALT is not an out-of-gas stop
+        vm.load_bytecode(&[0xFF]).unwrap();
+        let report = run(&mut vm, DEFAULT_MAX_CYCLES);
+        assert_eq!(report.state, VMState::Halted);
+        assert!(!report.out_of_gas);
+    }
+}
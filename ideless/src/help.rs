@@ -0,0 +1,183 @@
+use crossterm::event::KeyCode;
+
+use crate::{
+    asm::{opcode_description, opcode_from_name, opcode_name, opcode_param_count, opcode_stack_effect},
+    lessvm::GasSchedule,
+};
+
+/// Lines moved by PageUp/PageDown
+const PAGE_SIZE: usize = 10;
+
+/// One row of the opcode reference
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelpEntry {
+    pub opcode: u8,
+    pub mnemonic: String,
+    pub operands: usize,
+    pub stack_effect: &'static str,
+    pub gas: u64,
+    pub description: String,
+}
+
+impl HelpEntry {
+    /// Whether the entry matches a lowercase search query
+    fn matches(&self, query: &str) -> bool {
+        self.mnemonic.to_lowercase().contains(query)
+            || self.description.to_lowercase().contains(query)
+            || format!("{:#04x}", self.opcode).contains(query)
+    }
+}
+
+/// Scrollable, searchable opcode reference shown on the help tab
+#[derive(Debug, Clone)]
+pub struct HelpModel {
+    /// Every implemented opcode, in byte order
+    pub entries: Vec<HelpEntry>,
+
+    /// Current search text
+    pub query: String,
+
+    /// Whether keystrokes are going into the search box
+    pub searching: bool,
+
+    /// Index of the first visible match
+    pub scroll: usize,
+}
+
+impl Default for HelpModel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HelpModel {
+    /// Build the reference from the assembler's opcode table
+    pub fn new() -> Self {
+        let gas = GasSchedule::default();
+        let entries = (0..=u8::MAX)
+            .filter(|&opcode| opcode_from_name(&opcode_name(opcode)) == Some(opcode))
+            .map(|opcode| HelpEntry {
+                opcode,
+                mnemonic: opcode_name(opcode),
+                operands: opcode_param_count(opcode),
+                stack_effect: opcode_stack_effect(opcode),
+                gas: gas.cost(opcode),
+                description: opcode_description(opcode),
+            })
+            .collect();
+
+        HelpModel {
+            entries,
+            query: String::new(),
+            searching: false,
+            scroll: 0,
+        }
+    }
+
+    /// Entries matching the current search, in byte order
+    pub fn matches(&self) -> Vec<&HelpEntry> {
+        let query = self.query.to_lowercase();
+        self.entries.iter().filter(|e| e.matches(&query)).collect()
+    }
+
+    /// Entries from the scroll position onwards
+    pub fn visible(&self) -> Vec<&HelpEntry> {
+        self.matches().into_iter().skip(self.scroll).collect()
+    }
+
+    /// Scroll by `delta` lines, clamped to the matching entries
+    pub fn scroll_by(&mut self, delta: isize) {
+        let last = self.matches().len().saturating_sub(1);
+        self.scroll = self.scroll.saturating_add_signed(delta).min(last);
+    }
+
+    /// Handle a key press, returning false if the key wasn't used
+    pub fn handle_key(&mut self, key: KeyCode) -> bool {
+        if self.searching {
+            match key {
+                KeyCode::Char(c) => self.query.push(c),
+                KeyCode::Backspace => {
+                    self.query.pop();
+                }
+                KeyCode::Enter => self.searching = false,
+                KeyCode::Esc => {
+                    self.query.clear();
+                    self.searching = false;
+                }
+                _ => return false,
+            }
+            self.scroll = 0;
+            return true;
+        }
+
+        match key {
+            KeyCode::Char('/') => {
+                self.searching = true;
+            }
+            KeyCode::Esc => {
+                self.query.clear();
+                self.scroll = 0;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_by(-1),
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_by(1),
+            KeyCode::PageUp => self.scroll_by(-(PAGE_SIZE as isize)),
+            KeyCode::PageDown => self.scroll_by(PAGE_SIZE as isize),
+            KeyCode::Home => self.scroll = 0,
+            KeyCode::End => self.scroll_by(isize::MAX),
+            _ => return false,
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lessvm::VM;
+
+    #[test]
+    fn test_help_covers_every_opcode() {
+        let help = HelpModel::new();
+
+        for opcode in 0..=u8::MAX {
+            // Anything the VM doesn't reject as invalid is implemented
+            let mut vm = VM::new();
+            vm.load_bytecode(&[opcode, 0, 0, 0, 0, 0, 0, 0, 0]).unwrap();
+            let implemented = match vm.step() {
+                Ok(_) => true,
+                Err(e) => !e.to_string().starts_with("Invalid opcode"),
+            };
+
+            let entry = help.entries.iter().find(|e| e.opcode == opcode);
+            assert_eq!(entry.is_some(), implemented, "opcode {:#04X}", opcode);
+            if let Some(entry) = entry {
+                assert!(entry.gas > 0, "{} has no gas cost", entry.mnemonic);
+                assert_ne!(entry.stack_effect, "?", "{} has no stack effect", entry.mnemonic);
+            }
+        }
+    }
+
+    #[test]
+    fn test_help_search_and_scroll() {
+        let mut help = HelpModel::new();
+
+        assert!(help.handle_key(KeyCode::Char('/')));
+        for c in "jump".chars() {
+            help.handle_key(KeyCode::Char(c));
+        }
+        help.handle_key(KeyCode::Enter);
+
+        let names: Vec<_> = help.matches().iter().map(|e| e.mnemonic.clone()).collect();
+        assert_eq!(names, ["JUMP", "JUMPIF", "JUMP2", "JUMPIF2", "JUMPC", "JUMPZ", "JUMPO"]);
+
+        help.handle_key(KeyCode::Down);
+        assert_eq!(help.visible()[0].mnemonic, "JUMPIF");
+        help.handle_key(KeyCode::End);
+        assert_eq!(help.visible().len(), 1);
+
+        // Esc clears the search
+        help.handle_key(KeyCode::Esc);
+        assert_eq!(help.visible().len(), help.entries.len());
+        assert!(!help.handle_key(KeyCode::Char('x')));
+    }
+}
@@ -0,0 +1,234 @@
+//! Syntax highlighting for source files in the editor.
+//!
+//! `SyntaxHighlighter::language_for_file` picks a grammar from the file's
+//! extension and `highlight_lines` turns each line into styled spans. LessVM
+//! assembly is the only grammar; other files are drawn unstyled.
+use std::path::Path;
+
+use tui::{
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+};
+
+use crate::asm::{is_label_name, opcode_from_name, parse_number, tokenize};
+
+/// Register names by prefix and count, one for each of the VM's register
+/// files: general, vector, floating point, matrix and complex
+const REGISTER_FILES: [(char, u32); 5] = [('R', 16), ('V', 4), ('F', 8), ('M', 2), ('C', 4)];
+
+/// A grammar the highlighter knows
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Language {
+    /// LessVM assembly, `.lasm` or `.asm`
+    Assembly,
+}
+
+/// What a piece of a line is
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    /// An opcode mnemonic
+    Keyword,
+    /// `.byte`, `.ascii` or `.zero`
+    Directive,
+    /// A decimal, hex, binary or float immediate
+    Number,
+    /// A double-quoted string
+    String,
+    /// A `label:` definition
+    Label,
+    /// A register name such as `R3` or `V0`
+    Register,
+    /// A `;` comment
+    Comment,
+    /// Whitespace, separators and anything else
+    Text,
+}
+
+impl TokenKind {
+    pub fn style(self) -> Style {
+        let style = Style::default();
+        match self {
+            TokenKind::Keyword => style.fg(Color::Cyan).add_modifier(Modifier::BOLD),
+            TokenKind::Directive => style.fg(Color::Magenta),
+            TokenKind::Number => style.fg(Color::Yellow),
+            TokenKind::String => style.fg(Color::Green),
+            TokenKind::Label => style.fg(Color::LightBlue).add_modifier(Modifier::BOLD),
+            TokenKind::Register => style.fg(Color::LightMagenta),
+            TokenKind::Comment => style.fg(Color::DarkGray),
+            TokenKind::Text => style,
+        }
+    }
+}
+
+/// Whether `token` names a register, ignoring case
+fn is_register(token: &str) -> bool {
+    let mut chars = token.chars();
+    let Some(prefix) = chars.next().map(|c| c.to_ascii_uppercase()) else {
+        return false;
+    };
+    let index = chars.as_str();
+    if index.is_empty() || !index.bytes().all(|b| b.is_ascii_digit()) || (index.len() > 1 && index.starts_with('0')) {
+        return false;
+    }
+    REGISTER_FILES
+        .iter()
+        .any(|&(file, count)| file == prefix && index.parse::<u32>().is_ok_and(|i| i < count))
+}
+
+/// Whether `token` is a float immediate such as `-1.5`, but not a name
+/// like `inf` that Rust would also parse
+fn is_float(token: &str) -> bool {
+    token.starts_with(|c: char| c.is_ascii_digit() || c == '-' || c == '.') && token.parse::<f64>().is_ok()
+}
+
+/// Kind of an operand token
+fn operand_kind(token: &str) -> TokenKind {
+    if token.starts_with('"') {
+        TokenKind::String
+    } else if parse_number(token).is_some() || is_float(token) {
+        TokenKind::Number
+    } else if is_register(token) {
+        TokenKind::Register
+    } else {
+        TokenKind::Text
+    }
+}
+
+/// Split an assembly line into pieces that together cover the whole line
+pub fn classify_line(line: &str) -> Vec<(TokenKind, &str)> {
+    let tokens = tokenize(line);
+    let mut pieces = Vec::new();
+    let mut end = 0;
+    let mut expect_mnemonic = true;
+
+    for (index, &(column, token)) in tokens.iter().enumerate() {
+        let start = column - 1;
+        if start > end {
+            pieces.push((TokenKind::Text, &line[end..start]));
+        }
+        end = start + token.len();
+
+        let kind = match token.strip_suffix(':') {
+            Some(name) if index == 0 && is_label_name(name) => TokenKind::Label,
+            _ if expect_mnemonic => {
+                expect_mnemonic = false;
+                if token.starts_with('.') {
+                    TokenKind::Directive
+                } else if opcode_from_name(token).is_some() {
+                    TokenKind::Keyword
+                } else {
+                    TokenKind::Text
+                }
+            }
+            _ => operand_kind(token),
+        };
+        pieces.push((kind, token));
+    }
+
+    // Whatever tokenize stopped at is the comment
+    let rest = &line[end..];
+    match rest.find(';') {
+        Some(comment) => {
+            if comment > 0 {
+                pieces.push((TokenKind::Text, &rest[..comment]));
+            }
+            pieces.push((TokenKind::Comment, &rest[comment..]));
+        }
+        None if !rest.is_empty() => pieces.push((TokenKind::Text, rest)),
+        None => {}
+    }
+    pieces
+}
+
+/// Highlights lines of one file
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SyntaxHighlighter {
+    /// Grammar to use, `None` to leave lines unstyled
+    language: Option<Language>,
+}
+
+impl SyntaxHighlighter {
+    pub fn new(language: Option<Language>) -> Self {
+        SyntaxHighlighter { language }
+    }
+
+    /// Highlighter for the file at `path`
+    pub fn for_file(path: &Path) -> Self {
+        Self::new(Self::language_for_file(path))
+    }
+
+    /// Grammar for a file, from its extension
+    pub fn language_for_file(path: &Path) -> Option<Language> {
+        let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "lasm" | "asm" => Some(Language::Assembly),
+            _ => None,
+        }
+    }
+
+    /// Styled spans for each line
+    pub fn highlight_lines<'a>(&self, lines: &'a [String]) -> Vec<Spans<'a>> {
+        lines
+            .iter()
+            .map(|line| match self.language {
+                Some(Language::Assembly) => Spans::from(
+                    classify_line(line)
+                        .into_iter()
+                        .map(|(kind, text)| Span::styled(text, kind.style()))
+                        .collect::<Vec<_>>(),
+                ),
+                None => Spans::from(line.as_str()),
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use TokenKind::*;
+
+    #[test]
+    fn test_highlight_assembly() {
+        assert_eq!(classify_line("  PUSH1 0x2A ; answer"), vec![
+            (Text, "  "),
+            (Keyword, "PUSH1"),
+            (Text, " "),
+            (Number, "0x2A"),
+            (Text, " "),
+            (Comment, "; answer"),
+        ]);
+        assert_eq!(classify_line("loop: jumpif2 loop"), vec![
+            (Label, "loop:"),
+            (Text, " "),
+            (Keyword, "jumpif2"),
+            (Text, " "),
+            (Text, "loop"),
+        ]);
+        assert_eq!(classify_line(".ascii \"a; b\";c"), vec![
+            (Directive, ".ascii"),
+            (Text, " "),
+            (String, "\"a; b\""),
+            (Comment, ";c"),
+        ]);
+        assert_eq!(classify_line("LOADF f7, R16"), vec![
+            (Keyword, "LOADF"),
+            (Text, " "),
+            (Register, "f7"),
+            (Text, ", "),
+            (Text, "R16"),
+        ]);
+        assert_eq!(classify_line("PUSHF -1.5"), vec![(Keyword, "PUSHF"), (Text, " "), (Number, "-1.5")]);
+        assert_eq!(classify_line("PUSH1 inf")[2], (Text, "inf"));
+        assert!(classify_line("").is_empty());
+
+        // Only assembly files are highlighted, and every character is kept
+        assert_eq!(SyntaxHighlighter::language_for_file(Path::new("prog.LASM")), Some(Language::Assembly));
+        assert_eq!(SyntaxHighlighter::language_for_file(Path::new("prog.asm")), Some(Language::Assembly));
+        assert_eq!(SyntaxHighlighter::language_for_file(Path::new("main.rs")), None);
+        let lines = vec!["PUSH1 1 ; one".to_string(), "HALT".to_string()];
+        let highlighted = SyntaxHighlighter::for_file(Path::new("prog.lasm")).highlight_lines(&lines);
+        assert_eq!(highlighted.iter().map(|spans| spans.width()).collect::<Vec<_>>(), vec![13, 4]);
+        assert_eq!(SyntaxHighlighter::default().highlight_lines(&lines).len(), 2);
+    }
+}
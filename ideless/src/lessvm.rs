@@ -1,6 +1,12 @@
+use std::collections::{HashMap, VecDeque};
 use std::fmt;
+use std::fs;
+use std::path::Path;
 use thiserror::Error;
 use anyhow::{Result, Context};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 
 /// LessVM Error Types
 #[derive(Error, Debug)]
@@ -28,10 +34,142 @@ pub enum VMError {
     
     #[error("System error: {0}")]
     SystemError(String),
+    
+    #[error("String buffer overflow: limit of {0} bytes reached")]
+    StringBufferOverflow(usize),
+    
+    #[error("Insufficient funds: account {0:#010X} has {1} lamports, transfer needs {2}")]
+    InsufficientFunds(u32, u64, u64),
+    
+    #[error("Division by zero")]
+    DivisionByZero,
+}
+
+/// Account SOLTRANSFER debits: the running program's own
+pub const PROGRAM_ACCOUNT: u32 = 0;
+
+/// What STRPUSH does when the string buffer is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StringOverflowPolicy {
+    /// Stop execution with `VMError::StringBufferOverflow`
+    Error,
+    /// Silently drop characters that don't fit
+    Truncate,
+}
+
+/// Combine up to four bytes into an integer, most significant byte first
+///
+/// Multi-byte integers are always big-endian: PUSH2/PUSH4 operands and
+/// LOADW/STOREW addresses and words, which is also how the assembler,
+/// optimizer and disassembler encode them. PUSHF operands are IEEE-754
+/// doubles and always little-endian; jump targets are code addresses and
+/// always little-endian.
+fn read_be(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0, |acc, &b| (acc << 8) | b as u32)
+}
+
+/// Default maximum length of the string buffer in bytes
+pub const DEFAULT_STRING_BUFFER_LIMIT: usize = 1024;
+
+/// Memory allocated when the VM is created or reset
+pub const INITIAL_MEMORY_SIZE: usize = 64 * 1024;
+
+/// Granularity of memory growth in bytes
+pub const MEMORY_PAGE_SIZE: usize = 4 * 1024;
+
+/// Default ceiling for memory growth
+pub const DEFAULT_MAX_MEMORY: usize = 1024 * 1024;
+
+/// Gas charged for each page the memory grows by
+pub const MEMORY_PAGE_GAS: u64 = 10;
+
+/// Number of recently executed instructions kept for crash reports
+pub const RECENT_HISTORY_LEN: usize = 32;
+
+/// Flag set when the last ADD, SUB or MUL produced zero
+pub const FLAG_ZERO: u8 = 1 << 0;
+
+/// Flag set when the last ADD or MUL overflowed, or SUB borrowed, as
+/// unsigned numbers
+pub const FLAG_CARRY: u8 = 1 << 1;
+
+/// Flag set when the last ADD, SUB or MUL overflowed as signed numbers
+pub const FLAG_OVERFLOW: u8 = 1 << 2;
+
+/// Default gas cost of every implemented opcode
+const DEFAULT_GAS_COSTS: &[(u8, u64)] = &[
+    // Stack
+    (0x01, 3), (0x02, 3), (0x03, 3), (0x04, 5), (0x05, 5), (0x06, 5),
+    // Arithmetic and bitwise
+    (0x10, 10), (0x11, 10), (0x12, 20), (0x13, 20), (0x14, 20),
+    (0x20, 10), (0x21, 10), (0x22, 10), (0x23, 10),
+    // Comparison
+    (0x24, 10), (0x25, 10), (0x26, 10), (0x27, 10),
+    // Control flow
+    (0x30, 15), (0x31, 15), (0x34, 15), (0x35, 15), (0x32, 25), (0x33, 25),
+    (0x36, 15), (0x37, 15), (0x38, 15),
+    // Memory
+    (0x40, 30), (0x41, 30), (0x44, 40), (0x45, 40),
+    // Solana
+    (0x50, 100), (0x51, 100), (0x52, 100),
+    // SIMD
+    (0x60, 20), (0x61, 20), (0x62, 15), (0x63, 15), (0x64, 25), (0x65, 20), (0x66, 10),
+    // Floating point
+    (0x70, 5), (0x71, 3), (0x72, 3), (0x73, 5), (0x74, 5), (0x75, 8), (0x76, 10),
+    // Matrix operations are expensive
+    (0x80, 40), (0x81, 20), (0x82, 10),
+    // Cryptographic operations are expensive
+    (0x90, 100),
+    // Complex numbers
+    (0xA0, 8), (0xA1, 15),
+    // Strings and JSON
+    (0xB0, 2), (0xB1, 2), (0xB2, 1), (0xB3, 1), (0xB8, 5), (0xB9, 5), (0xC0, 25),
+    // Map
+    (0xD0, 10), (0xD1, 5), (0xD2, 5),
+    // Random
+    (0xE0, 20),
+    // HALT
+    (0xFF, 1),
+];
+
+/// Gas charged per opcode, indexed by opcode byte.
+///
+/// Memory growth is charged separately at `MEMORY_PAGE_GAS` per page.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasSchedule {
+    costs: [u64; 256],
+}
+
+impl GasSchedule {
+    /// Gas charged for executing `opcode`
+    pub fn cost(&self, opcode: u8) -> u64 {
+        self.costs[opcode as usize]
+    }
+    
+    /// Change the gas charged for `opcode`
+    pub fn set_cost(&mut self, opcode: u8, cost: u64) {
+        self.costs[opcode as usize] = cost;
+    }
+    
+    /// Builder-style variant of `set_cost`
+    pub fn with_cost(mut self, opcode: u8, cost: u64) -> Self {
+        self.set_cost(opcode, cost);
+        self
+    }
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        let mut costs = [0; 256];
+        for &(opcode, cost) in DEFAULT_GAS_COSTS {
+            costs[opcode as usize] = cost;
+        }
+        Self { costs }
+    }
 }
 
 /// VM State
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum VMState {
     Ready,
     Running,
@@ -50,6 +188,162 @@ impl fmt::Display for VMState {
     }
 }
 
+/// Point-in-time copy of the VM state used for inspection, diffing and
+/// checkpoints saved to disk
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VMSnapshot {
+    pub pc: usize,
+    pub registers: [u32; 16],
+    #[serde(default)]
+    pub flags: u8,
+    pub vector_registers: [[u32; 4]; 4],
+    pub fp_registers: [f64; 8],
+    pub matrix_registers: [[[f32; 4]; 4]; 2],
+    pub complex_registers: [[f64; 2]; 4],
+    pub string_buffer: Vec<u8>,
+    pub map: Option<HashMap<u32, u32>>,
+    pub stack: Vec<u32>,
+    pub memory: Vec<u8>,
+    pub state: VMState,
+    pub cycle_counter: u32,
+    pub gas_used: u64,
+    #[serde(default)]
+    pub balances: HashMap<u32, u64>,
+}
+
+/// Differences between two VM snapshots
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SnapshotDiff {
+    /// PC change as (old, new), if it moved
+    pub pc: Option<(usize, usize)>,
+    
+    /// Changed memory bytes as (offset, old, new)
+    pub memory: Vec<(usize, u8, u8)>,
+    
+    /// Values popped off the old stack, bottom to top
+    pub stack_popped: Vec<u32>,
+    
+    /// Values pushed onto the new stack, bottom to top
+    pub stack_pushed: Vec<u32>,
+    
+    /// Changed general purpose registers as (index, old, new)
+    pub registers: Vec<(usize, u32, u32)>,
+}
+
+impl SnapshotDiff {
+    /// Check whether the two snapshots were identical
+    pub fn is_empty(&self) -> bool {
+        self.pc.is_none()
+            && self.memory.is_empty()
+            && self.stack_popped.is_empty()
+            && self.stack_pushed.is_empty()
+            && self.registers.is_empty()
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return write!(f, "No changes");
+        }
+        
+        if let Some((old, new)) = self.pc {
+            writeln!(f, "PC: {:#06X} -> {:#06X}", old, new)?;
+        }
+        for value in self.stack_popped.iter().rev() {
+            writeln!(f, "Stack pop: {:#010X}", value)?;
+        }
+        for value in &self.stack_pushed {
+            writeln!(f, "Stack push: {:#010X}", value)?;
+        }
+        for (idx, old, new) in &self.registers {
+            writeln!(f, "R{}: {:#010X} -> {:#010X}", idx, old, new)?;
+        }
+        for (offset, old, new) in &self.memory {
+            writeln!(f, "[{:#06X}]: {:#04X} -> {:#04X}", offset, old, new)?;
+        }
+        
+        Ok(())
+    }
+}
+
+impl VMSnapshot {
+    /// Write the snapshot to `path` as JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec(self).context("Failed to serialize snapshot")?;
+        fs::write(path, json).with_context(|| format!("Failed to write snapshot to {}", path.display()))
+    }
+    
+    /// Read a snapshot written by `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path).with_context(|| format!("Failed to read snapshot from {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| format!("Invalid snapshot in {}", path.display()))
+    }
+    
+    /// Compute what changed going from this snapshot to `other`
+    pub fn diff(&self, other: &VMSnapshot) -> SnapshotDiff {
+        let mut diff = self.diff_registers(other.pc, &other.stack, &other.registers);
+        
+        // Memory sizes only differ if the memory was resized; treat missing bytes as zero
+        let len = self.memory.len().max(other.memory.len());
+        for offset in 0..len {
+            let old = self.memory.get(offset).copied().unwrap_or(0);
+            let new = other.memory.get(offset).copied().unwrap_or(0);
+            if old != new {
+                diff.memory.push((offset, old, new));
+            }
+        }
+        
+        diff
+    }
+    
+    /// PC, stack and register changes going from this snapshot to the
+    /// given state, leaving memory out
+    fn diff_registers(&self, pc: usize, stack: &[u32], registers: &[u32; 16]) -> SnapshotDiff {
+        let mut diff = SnapshotDiff::default();
+        
+        if self.pc != pc {
+            diff.pc = Some((self.pc, pc));
+        }
+        
+        // Everything above the common stack prefix was popped or pushed
+        let common = self.stack
+            .iter()
+            .zip(stack)
+            .take_while(|(a, b)| a == b)
+            .count();
+        diff.stack_popped = self.stack[common..].to_vec();
+        diff.stack_pushed = stack[common..].to_vec();
+        
+        for (idx, (old, new)) in self.registers.iter().zip(registers).enumerate() {
+            if old != new {
+                diff.registers.push((idx, *old, *new));
+            }
+        }
+        
+        diff
+    }
+}
+
+/// Everything outside the bytecode that can influence a run, so that a
+/// reported execution can be replayed bit-for-bit
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReplayContext {
+    /// Seed for RAND and the random-number syscall
+    pub rng_seed: u64,
+    
+    /// Fixed value for the timestamp syscall instead of the wall clock
+    pub clock: Option<u32>,
+    
+    /// Values returned by the timestamp and random syscalls, in the order
+    /// they were made. They take precedence over `clock` and the RNG until
+    /// the log runs out.
+    pub syscall_inputs: Vec<u32>,
+}
+
+/// Callback notified of VM state transitions
+pub type StateHook = Box<dyn Fn(&VMState) + Send>;
+
 /// LessVM - Virtual Machine for the Solana blockchain
 pub struct VM {
     /// Program counter
@@ -67,15 +361,36 @@ pub struct VM {
     /// Complex number registers (4 complex numbers with real/imaginary parts)
     pub complex_registers: [[f64; 2]; 4],
 
-    /// String buffer for string operations
-    pub string_buffer: String,
+    /// String buffer for string operations. The buffer is byte-oriented:
+    /// STRPUSH/STRPOP move single raw bytes, so multi-byte UTF-8 text
+    /// round-trips unchanged one byte at a time
+    pub string_buffer: Vec<u8>,
+    
+    /// Maximum string buffer length in bytes
+    string_buffer_limit: usize,
+    
+    /// What to do when STRPUSH would exceed the string buffer limit
+    string_overflow_policy: StringOverflowPolicy,
+    
+    /// Whether DIV and MOD by zero push 0 instead of failing
+    div_by_zero_returns_zero: bool,
 
-    /// Memory
+    /// Key-value map allocated by MAPINIT
+    pub map: Option<HashMap<u32, u32>>,
+    
+    /// Memory, grown in `MEMORY_PAGE_SIZE` steps up to `max_memory`
     pub memory: Vec<u8>,
     
+    /// Ceiling for memory growth in bytes
+    max_memory: usize,
+    
     /// Registers (16 general purpose registers)
     pub registers: [u32; 16],
     
+    /// Arithmetic flags (`FLAG_ZERO`, `FLAG_CARRY`, `FLAG_OVERFLOW`) set by
+    /// ADD, SUB and MUL
+    pub flags: u8,
+    
     /// Stack
     pub stack: Vec<u32>,
     
@@ -85,11 +400,60 @@ pub struct VM {
     /// Cycle counter for execution tracking
     pub cycle_counter: u32,
     
+    /// Per-offset execution counts, keyed by instruction address
+    pub exec_counts: HashMap<usize, u64>,
+    
+    /// Instructions executed since the last reset, including the final
+    /// HALT and any that failed
+    instruction_count: u64,
+    
+    /// Most recently executed instructions as (pc, opcode), oldest first
+    pub recent_history: VecDeque<(usize, u8)>,
+    
+    /// Memory addresses written by the last instruction
+    last_written: Vec<usize>,
+    
+    /// Bytes that were at `last_written` before the last instruction wrote
+    /// them, in the same order
+    last_overwritten: Vec<u8>,
+    
+    /// Whether `profile` is being collected
+    profiling: bool,
+    
+    /// Executions and gas charged per opcode since the last reset, while
+    /// profiling
+    profile: HashMap<u8, (u64, u64)>,
+    
     /// Gas usage
     gas_used: u64,
     
     /// Gas limit (0 means unlimited)
     gas_limit: u64,
+    
+    /// Per-opcode gas costs
+    gas_schedule: GasSchedule,
+    
+    /// Random number generator backing RAND
+    rng: StdRng,
+    
+    /// Fixed seed for RAND, re-applied on reset for reproducible runs
+    rng_seed: Option<u64>,
+    
+    /// Callback fired whenever `state` changes
+    state_hook: Option<StateHook>,
+    
+    /// Fixed timestamp returned by the timestamp syscall
+    clock_override: Option<u32>,
+    
+    /// Syscall inputs to replay, and how many have been consumed
+    replay_inputs: Vec<u32>,
+    replay_pos: usize,
+    
+    /// Syscall inputs seen since the last reset
+    syscall_log: Vec<u32>,
+    
+    /// Lamport balances by account address
+    balances: HashMap<u32, u64>,
 }
 
 impl Default for VM {
@@ -101,54 +465,264 @@ impl Default for VM {
 impl VM {
     /// Create a new VM instance
     pub fn new() -> Self {
+        Self::with_gas_schedule(GasSchedule::default())
+    }
+    
+    /// Create a VM that charges opcodes according to `gas_schedule`
+    pub fn with_gas_schedule(gas_schedule: GasSchedule) -> Self {
         VM {
             pc: 0,
-            memory: vec![0; 65536], // 64KB memory space
+            map: None,
+            memory: vec![0; INITIAL_MEMORY_SIZE],
+            max_memory: DEFAULT_MAX_MEMORY,
             registers: [0; 16],
+            flags: 0,
             vector_registers: [[0; 4]; 4],
             fp_registers: [0.0; 8],
             matrix_registers: [[[0.0; 4]; 4]; 2],
             complex_registers: [[0.0; 2]; 4],
-            string_buffer: String::with_capacity(1024),
+            string_buffer: Vec::with_capacity(DEFAULT_STRING_BUFFER_LIMIT),
+            string_buffer_limit: DEFAULT_STRING_BUFFER_LIMIT,
+            string_overflow_policy: StringOverflowPolicy::Error,
+            div_by_zero_returns_zero: false,
             stack: Vec::with_capacity(1024),
             state: VMState::Ready,
             cycle_counter: 0,
+            exec_counts: HashMap::new(),
+            instruction_count: 0,
+            recent_history: VecDeque::with_capacity(RECENT_HISTORY_LEN),
+            last_written: Vec::new(),
+            last_overwritten: Vec::new(),
+            profiling: false,
+            profile: HashMap::new(),
             gas_used: 0,
             gas_limit: 0, // Unlimited gas by default
+            gas_schedule,
+            rng: StdRng::from_entropy(),
+            rng_seed: None,
+            state_hook: None,
+            clock_override: None,
+            replay_inputs: Vec::new(),
+            replay_pos: 0,
+            syscall_log: Vec::new(),
+            balances: HashMap::new(),
         }
     }
+    
+    /// Create a VM whose RNG, clock and syscall inputs all come from `ctx`
+    pub fn with_replay_context(ctx: ReplayContext) -> Self {
+        let mut vm = Self::new();
+        vm.set_rng_seed(ctx.rng_seed);
+        vm.clock_override = ctx.clock;
+        vm.replay_inputs = ctx.syscall_inputs;
+        vm
+    }
+    
+    /// Capture what is needed to replay the run so far, or `None` if the RNG
+    /// was never seeded
+    pub fn replay_context(&self) -> Option<ReplayContext> {
+        Some(ReplayContext {
+            rng_seed: self.rng_seed?,
+            clock: self.clock_override,
+            syscall_inputs: self.syscall_log.clone(),
+        })
+    }
 
     /// Reset the VM to its initial state
     pub fn reset(&mut self) {
         self.pc = 0;
         self.registers = [0; 16];
+        self.flags = 0;
         self.vector_registers = [[0; 4]; 4];
         self.fp_registers = [0.0; 8];
         self.matrix_registers = [[[0.0; 4]; 4]; 2];
         self.complex_registers = [[0.0; 2]; 4];
         self.string_buffer.clear();
+        self.map = None;
+        self.memory.truncate(INITIAL_MEMORY_SIZE.min(self.max_memory));
         self.stack.clear();
-        self.state = VMState::Ready;
+        self.set_state(VMState::Ready);
         self.cycle_counter = 0;
+        self.exec_counts.clear();
+        self.instruction_count = 0;
+        self.profile.clear();
+        self.recent_history.clear();
+        self.last_written.clear();
+        self.last_overwritten.clear();
         self.gas_used = 0;
+        self.replay_pos = 0;
+        self.syscall_log.clear();
+        
+        // Restart a seeded RNG so replays produce the same sequence
+        if let Some(seed) = self.rng_seed {
+            self.rng = StdRng::seed_from_u64(seed);
+        }
     }
     
     /// Load bytecode into memory
     pub fn load_bytecode(&mut self, bytecode: &[u8]) -> Result<()> {
-        if bytecode.len() > self.memory.len() {
+        if bytecode.len() > self.max_memory {
             return Err(anyhow::anyhow!("Bytecode too large: {} bytes (max {})", 
-                       bytecode.len(), self.memory.len()));
+                       bytecode.len(), self.max_memory));
         }
         
         // Reset VM state
         self.reset();
         
+        // Make room for large programs without charging gas
+        if bytecode.len() > self.memory.len() {
+            self.memory.resize(Self::page_align(bytecode.len()).min(self.max_memory), 0);
+        }
+        
         // Copy bytecode to memory
         self.memory[..bytecode.len()].copy_from_slice(bytecode);
         
         Ok(())
     }
     
+    /// Take a snapshot of the current VM state
+    pub fn snapshot(&self) -> VMSnapshot {
+        VMSnapshot {
+            memory: self.memory.clone(),
+            ..self.snapshot_without_memory(true)
+        }
+    }
+    
+    /// Take a snapshot with memory left empty, for recording single steps
+    /// without copying memory. Unless `with_collections` is set, the string
+    /// buffer, map and balances are left empty too.
+    pub fn snapshot_without_memory(&self, with_collections: bool) -> VMSnapshot {
+        VMSnapshot {
+            pc: self.pc,
+            registers: self.registers,
+            flags: self.flags,
+            vector_registers: self.vector_registers,
+            fp_registers: self.fp_registers,
+            matrix_registers: self.matrix_registers,
+            complex_registers: self.complex_registers,
+            string_buffer: if with_collections { self.string_buffer.clone() } else { Vec::new() },
+            map: if with_collections { self.map.clone() } else { None },
+            stack: self.stack.clone(),
+            memory: Vec::new(),
+            state: self.state.clone(),
+            cycle_counter: self.cycle_counter,
+            gas_used: self.gas_used,
+            balances: if with_collections { self.balances.clone() } else { HashMap::new() },
+        }
+    }
+    
+    /// What changed since `before`, a snapshot taken just before the last
+    /// instruction. Memory changes are the bytes that instruction wrote, so
+    /// memory isn't compared and `before` may leave it out.
+    pub fn diff_since(&self, before: &VMSnapshot) -> SnapshotDiff {
+        let mut diff = before.diff_registers(self.pc, &self.stack, &self.registers);
+        diff.memory = self.last_writes().filter(|(_, old, new)| old != new).collect();
+        diff
+    }
+
+    /// Put the VM back into a previously snapshotted state.
+    ///
+    /// Execution counts and the recent instruction history are left alone;
+    /// they describe what ran, not the machine state.
+    pub fn restore(&mut self, snapshot: &VMSnapshot) {
+        self.restore_owned(snapshot.clone());
+    }
+    
+    /// Same as `restore`, taking the snapshot's buffers instead of copying them
+    pub fn restore_owned(&mut self, snapshot: VMSnapshot) {
+        self.pc = snapshot.pc;
+        self.registers = snapshot.registers;
+        self.flags = snapshot.flags;
+        self.vector_registers = snapshot.vector_registers;
+        self.fp_registers = snapshot.fp_registers;
+        self.matrix_registers = snapshot.matrix_registers;
+        self.complex_registers = snapshot.complex_registers;
+        self.string_buffer = snapshot.string_buffer;
+        self.map = snapshot.map;
+        self.stack = snapshot.stack;
+        self.memory = snapshot.memory;
+        self.set_state(snapshot.state);
+        self.cycle_counter = snapshot.cycle_counter;
+        self.gas_used = snapshot.gas_used;
+        self.balances = snapshot.balances;
+        self.last_written.clear();
+        self.last_overwritten.clear();
+    }
+
+    /// Seed the RAND generator for deterministic runs.
+    ///
+    /// The seed is kept across `reset()`, so reloading a program replays the
+    /// same random sequence.
+    pub fn set_rng_seed(&mut self, seed: u64) {
+        self.rng_seed = Some(seed);
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+    
+    /// Make the timestamp syscall return `timestamp` instead of the wall
+    /// clock. It is kept across `reset()`.
+    pub fn set_clock(&mut self, timestamp: Option<u32>) {
+        self.clock_override = timestamp;
+    }
+    
+    /// Produce a syscall input, taking it from the replay log while entries
+    /// remain, and record it
+    fn syscall_input(&mut self, live: impl FnOnce(&mut Self) -> u32) -> u32 {
+        let value = match self.replay_inputs.get(self.replay_pos) {
+            Some(&value) => {
+                self.replay_pos += 1;
+                value
+            }
+            None => live(self),
+        };
+        self.syscall_log.push(value);
+        value
+    }
+    
+    /// Set the lamport balance of `address`. Balances are kept across
+    /// `reset()`.
+    pub fn set_balance(&mut self, address: u32, lamports: u64) {
+        self.balances.insert(address, lamports);
+    }
+    
+    /// Get the lamport balance of `address`, 0 for accounts never funded
+    pub fn get_balance(&self, address: u32) -> u64 {
+        self.balances.get(&address).copied().unwrap_or(0)
+    }
+    
+    /// Set the ceiling memory may grow to, in bytes
+    pub fn set_max_memory(&mut self, max_memory: usize) {
+        self.max_memory = max_memory;
+    }
+    
+    /// Get the memory growth ceiling
+    pub fn max_memory(&self) -> usize {
+        self.max_memory
+    }
+    
+    /// Round `len` up to a whole number of memory pages
+    fn page_align(len: usize) -> usize {
+        (len + MEMORY_PAGE_SIZE - 1) / MEMORY_PAGE_SIZE * MEMORY_PAGE_SIZE
+    }
+    
+    /// Make sure `addr..addr + len` is backed by memory, growing it a page at
+    /// a time and charging `MEMORY_PAGE_GAS` per new page
+    fn ensure_memory(&mut self, addr: usize, len: usize) -> Result<()> {
+        let end = addr.saturating_add(len);
+        if end <= self.memory.len() {
+            return Ok(());
+        }
+        if end > self.max_memory {
+            return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(end - 1)));
+        }
+        
+        let new_len = Self::page_align(end).min(self.max_memory);
+        let pages = (new_len - self.memory.len() + MEMORY_PAGE_SIZE - 1) / MEMORY_PAGE_SIZE;
+        self.memory.resize(new_len, 0);
+        self.gas_used += pages as u64 * MEMORY_PAGE_GAS;
+        
+        Ok(())
+    }
+    
     /// Set gas limit
     pub fn set_gas_limit(&mut self, limit: u64) {
         self.gas_limit = limit;
@@ -159,15 +733,138 @@ impl VM {
         self.gas_used
     }
     
+    /// Instructions executed since the last reset. Unlike `cycle_counter`
+    /// this includes single steps and the instruction a run stopped on.
+    pub fn instruction_count(&self) -> u64 {
+        self.instruction_count
+    }
+    
     /// Get current gas limit
     pub fn gas_limit(&self) -> u64 {
         self.gas_limit
     }
     
+    /// VM memory, including the loaded program
+    pub fn get_memory(&self) -> &[u8] {
+        &self.memory
+    }
+    
+    /// Overwrite the byte at `addr`, e.g. from the memory editor. Memory is
+    /// not grown to make room.
+    pub fn write_memory(&mut self, addr: usize, byte: u8) -> Result<()> {
+        let slot = self.memory.get_mut(addr)
+            .ok_or_else(|| anyhow::anyhow!("{}", VMError::OutOfMemory(addr)))?;
+        *slot = byte;
+        Ok(())
+    }
+    
+    /// Whether `run` stopped because the gas limit was reached
+    pub fn is_out_of_gas(&self) -> bool {
+        matches!(&self.state, VMState::Error(msg) if *msg == VMError::OutOfGas.to_string())
+    }
+    
+    /// Get the gas schedule used to charge opcodes
+    pub fn gas_schedule(&self) -> &GasSchedule {
+        &self.gas_schedule
+    }
+    
+    /// Set the maximum string buffer length and what happens when it's exceeded
+    pub fn set_string_buffer_limit(&mut self, limit: usize, policy: StringOverflowPolicy) {
+        self.string_buffer_limit = limit;
+        self.string_overflow_policy = policy;
+    }
+    
+    /// Make DIV and MOD by zero push 0, as older versions of the emulator
+    /// did, instead of failing with `VMError::DivisionByZero`
+    pub fn set_div_by_zero_returns_zero(&mut self, returns_zero: bool) {
+        self.div_by_zero_returns_zero = returns_zero;
+    }
+    
+    /// Get the string buffer contents as text, replacing invalid UTF-8 sequences
+    pub fn string_buffer_lossy(&self) -> String {
+        String::from_utf8_lossy(&self.string_buffer).into_owned()
+    }
+    
+    /// Get the maximum string buffer length
+    pub fn string_buffer_limit(&self) -> usize {
+        self.string_buffer_limit
+    }
+    
+    /// Get how many times the instruction at `offset` has been executed
+    pub fn exec_count(&self, offset: usize) -> u64 {
+        self.exec_counts.get(&offset).copied().unwrap_or(0)
+    }
+    
+    /// Get the PC and opcode of the last instruction that was fetched
+    pub fn last_instruction(&self) -> Option<(usize, u8)> {
+        self.recent_history.back().copied()
+    }
+    
+    /// Memory addresses written by the last instruction, in write order
+    pub fn last_written(&self) -> &[usize] {
+        &self.last_written
+    }
+    
+    /// Bytes written by the last instruction as (address, old, new), in
+    /// write order
+    pub fn last_writes(&self) -> impl Iterator<Item = (usize, u8, u8)> + '_ {
+        self.last_written
+            .iter()
+            .zip(&self.last_overwritten)
+            .map(|(&addr, &old)| (addr, old, self.memory[addr]))
+    }
+    
+    /// Store `bytes` at `addr`, which must already be backed by memory,
+    /// recording the addresses and what they held for `last_writes`
+    fn write_bytes(&mut self, addr: usize, bytes: &[u8]) {
+        let slots = &mut self.memory[addr..addr + bytes.len()];
+        self.last_written.extend(addr..addr + bytes.len());
+        self.last_overwritten.extend_from_slice(slots);
+        slots.copy_from_slice(bytes);
+    }
+    
+    /// Turn per-opcode profiling on or off. It is kept across `reset()`.
+    pub fn enable_profiling(&mut self, enabled: bool) {
+        self.profiling = enabled;
+    }
+    
+    /// Executions and gas charged per opcode as (opcode, count, gas),
+    /// hottest first by gas then count. Empty unless profiling is enabled.
+    pub fn profile(&self) -> Vec<(u8, u64, u64)> {
+        let mut profile: Vec<(u8, u64, u64)> = self.profile
+            .iter()
+            .map(|(&opcode, &(count, gas))| (opcode, count, gas))
+            .collect();
+        profile.sort_by(|a, b| b.2.cmp(&a.2).then(b.1.cmp(&a.1)).then(a.0.cmp(&b.0)));
+        profile
+    }
+    
+    /// Get the highest execution count of any instruction
+    pub fn max_exec_count(&self) -> u64 {
+        self.exec_counts.values().copied().max().unwrap_or(0)
+    }
+    
+    /// Register a callback invoked with the new state on every transition
+    /// (Ready→Running→Halted/Error). Assigning `state` directly bypasses it.
+    ///
+    /// The hook must be `Send` because the TUI runs the VM on its own thread.
+    pub fn set_state_hook(&mut self, hook: StateHook) {
+        self.state_hook = Some(hook);
+    }
+    
+    /// Change `state`, notifying the state hook if it actually differs
+    pub(crate) fn set_state(&mut self, state: VMState) {
+        if self.state == state {
+            return;
+        }
+        self.state = state;
+        if let Some(hook) = &self.state_hook {
+            hook(&self.state);
+        }
+    }
+    
     /// Run the VM for a specified number of cycles
     pub fn run(&mut self, max_cycles: u32) -> Result<()> {
-        self.state = VMState::Running;
-        
         for _ in 0..max_cycles {
             match self.step() {
                 Ok(true) => {
@@ -175,7 +872,7 @@ impl VM {
                     
                     // Check if we've hit the gas limit
                     if self.gas_limit > 0 && self.gas_used >= self.gas_limit {
-                        self.state = VMState::Error(VMError::OutOfGas.to_string());
+                        self.set_state(VMState::Error(VMError::OutOfGas.to_string()));
                         return Err(anyhow::anyhow!("{}", VMError::OutOfGas));
                     }
                 }
@@ -184,7 +881,7 @@ impl VM {
                     return Ok(());
                 }
                 Err(e) => {
-                    self.state = VMState::Error(e.to_string());
+                    // step() has already moved the VM into the error state
                     return Err(e);
                 }
             }
@@ -195,6 +892,8 @@ impl VM {
     
     /// Execute a single instruction
     pub fn step(&mut self) -> Result<bool> {
+        self.last_written.clear();
+        self.last_overwritten.clear();
         if let VMState::Halted = self.state {
             return Ok(false);
         }
@@ -203,25 +902,77 @@ impl VM {
             return Err(anyhow::anyhow!("VM is in error state"));
         }
         
+        if self.state == VMState::Ready {
+            self.set_state(VMState::Running);
+        }
+        
         // Check if we're at the end of memory
         if self.pc >= self.memory.len() {
-            self.state = VMState::Error(format!("{}", VMError::OutOfMemory(self.pc)));
+            self.set_state(VMState::Error(format!("{}", VMError::OutOfMemory(self.pc))));
             return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
         }
 
         // Fetch the opcode
         let opcode = self.memory[self.pc];
+        *self.exec_counts.entry(self.pc).or_insert(0) += 1;
+        self.instruction_count += 1;
+        if self.recent_history.len() == RECENT_HISTORY_LEN {
+            self.recent_history.pop_front();
+        }
+        self.recent_history.push_back((self.pc, opcode));
         
-        // Execute the instruction
-        match self.execute_instruction(opcode) {
+        // Execute the instruction, charging whatever gas it used to the
+        // opcode when profiling
+        let gas_before = self.gas_used;
+        let result = self.execute_instruction(opcode);
+        if self.profiling {
+            let entry = self.profile.entry(opcode).or_insert((0, 0));
+            entry.0 += 1;
+            entry.1 += self.gas_used - gas_before;
+        }
+        match result {
             Ok(continue_execution) => Ok(continue_execution),
             Err(e) => {
-                self.state = VMState::Error(e.to_string());
+                self.set_state(VMState::Error(e.to_string()));
                 Err(e)
             }
         }
     }
     
+    /// Fail on a zero divisor unless division by zero is set to return 0
+    fn check_divisor(&self, divisor: u32) -> Result<()> {
+        if divisor == 0 && !self.div_by_zero_returns_zero {
+            return Err(anyhow::anyhow!("{}", VMError::DivisionByZero));
+        }
+        Ok(())
+    }
+    
+    /// Set the flags from the result of an arithmetic instruction
+    fn set_flags(&mut self, result: u32, carry: bool, overflow: bool) {
+        self.flags = 0;
+        if result == 0 {
+            self.flags |= FLAG_ZERO;
+        }
+        if carry {
+            self.flags |= FLAG_CARRY;
+        }
+        if overflow {
+            self.flags |= FLAG_OVERFLOW;
+        }
+    }
+    
+    /// Append bytes to the string buffer, applying the overflow policy if
+    /// they don't all fit
+    fn push_string_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        let available = self.string_buffer_limit.saturating_sub(self.string_buffer.len());
+        if bytes.len() > available && self.string_overflow_policy == StringOverflowPolicy::Error {
+            return Err(anyhow::anyhow!("{}", VMError::StringBufferOverflow(self.string_buffer_limit)));
+        }
+        
+        self.string_buffer.extend_from_slice(&bytes[..bytes.len().min(available)]);
+        Ok(())
+    }
+    
     /// Execute a single instruction
     fn execute_instruction(&mut self, opcode: u8) -> Result<bool> {
         match opcode {
@@ -235,7 +986,7 @@ impl VM {
                 let value = self.memory[self.pc] as u32;
                 self.stack.push(value);
                 self.pc += 1;
-                self.gas_used += 3;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // PUSH2 - Push 2-byte value onto stack
@@ -245,10 +996,10 @@ impl VM {
                     return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
                 }
                 
-                let value = ((self.memory[self.pc] as u32) << 8) | (self.memory[self.pc + 1] as u32);
+                let value = read_be(&self.memory[self.pc..self.pc + 2]);
                 self.stack.push(value);
                 self.pc += 2;
-                self.gas_used += 3;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // PUSH4 - Push 4-byte value onto stack
@@ -258,13 +1009,10 @@ impl VM {
                     return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
                 }
                 
-                let value = ((self.memory[self.pc] as u32) << 24) |
-                            ((self.memory[self.pc + 1] as u32) << 16) |
-                            ((self.memory[self.pc + 2] as u32) << 8) |
-                            (self.memory[self.pc + 3] as u32);
+                let value = read_be(&self.memory[self.pc..self.pc + 4]);
                 self.stack.push(value);
                 self.pc += 4;
-                self.gas_used += 3;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // POP - Remove top item from stack
@@ -275,7 +1023,7 @@ impl VM {
                 
                 self.stack.pop();
                 self.pc += 1;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // DUP - Duplicate nth stack item
@@ -293,7 +1041,7 @@ impl VM {
                 let value = self.stack[self.stack.len() - 1 - n];
                 self.stack.push(value);
                 self.pc += 1;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SWAP - Swap nth stack item with top
@@ -312,7 +1060,7 @@ impl VM {
                 let swap_idx = self.stack.len() - 1 - n;
                 self.stack.swap(top_idx, swap_idx);
                 self.pc += 1;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // ADD - Addition
@@ -323,9 +1071,11 @@ impl VM {
                 
                 let b = self.stack.pop().unwrap();
                 let a = self.stack.pop().unwrap();
-                self.stack.push(a.wrapping_add(b));
+                let (result, carry) = a.overflowing_add(b);
+                self.set_flags(result, carry, (a as i32).overflowing_add(b as i32).1);
+                self.stack.push(result);
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SUB - Subtraction
@@ -336,9 +1086,11 @@ impl VM {
                 
                 let b = self.stack.pop().unwrap();
                 let a = self.stack.pop().unwrap();
-                self.stack.push(a.wrapping_sub(b));
+                let (result, borrow) = a.overflowing_sub(b);
+                self.set_flags(result, borrow, (a as i32).overflowing_sub(b as i32).1);
+                self.stack.push(result);
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // MUL - Multiplication
@@ -349,9 +1101,11 @@ impl VM {
                 
                 let b = self.stack.pop().unwrap();
                 let a = self.stack.pop().unwrap();
-                self.stack.push(a.wrapping_mul(b));
+                let (result, carry) = a.overflowing_mul(b);
+                self.set_flags(result, carry, (a as i32).overflowing_mul(b as i32).1);
+                self.stack.push(result);
                 self.pc += 1;
-                self.gas_used += 20;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // DIV - Division
@@ -362,15 +1116,24 @@ impl VM {
                 
                 let b = self.stack.pop().unwrap();
                 let a = self.stack.pop().unwrap();
-                
-                if b == 0 {
-                    self.stack.push(0);
-                } else {
-                    self.stack.push(a.wrapping_div(b));
+                self.check_divisor(b)?;
+                self.stack.push(a.checked_div(b).unwrap_or(0));
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // MOD - Remainder
+            0x14 => {
+                if self.stack.len() < 2 {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
                 }
                 
+                let b = self.stack.pop().unwrap();
+                let a = self.stack.pop().unwrap();
+                self.check_divisor(b)?;
+                self.stack.push(a.checked_rem(b).unwrap_or(0));
                 self.pc += 1;
-                self.gas_used += 20;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // AND - Bitwise AND
@@ -383,7 +1146,7 @@ impl VM {
                 let a = self.stack.pop().unwrap();
                 self.stack.push(a & b);
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // OR - Bitwise OR
@@ -396,7 +1159,7 @@ impl VM {
                 let a = self.stack.pop().unwrap();
                 self.stack.push(a | b);
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // XOR - Bitwise XOR
@@ -409,7 +1172,7 @@ impl VM {
                 let a = self.stack.pop().unwrap();
                 self.stack.push(a ^ b);
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // NOT - Bitwise NOT
@@ -421,33 +1184,123 @@ impl VM {
                 let a = self.stack.pop().unwrap();
                 self.stack.push(!a);
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
-            // JUMP - Unconditional jump
-            0x30 => {
-                if self.stack.is_empty() {
+            // LT - Unsigned less than, pushes 1 or 0
+            0x24 => {
+                if self.stack.len() < 2 {
                     return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
                 }
                 
-                let dest = self.stack.pop().unwrap() as usize;
-                if dest >= self.memory.len() {
-                    return Err(anyhow::anyhow!("{}", VMError::InvalidJumpDestination(dest)));
+                let b = self.stack.pop().unwrap();
+                let a = self.stack.pop().unwrap();
+                self.stack.push((a < b) as u32);
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // GT - Unsigned greater than, pushes 1 or 0
+            0x25 => {
+                if self.stack.len() < 2 {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
                 }
                 
-                self.pc = dest;
-                self.gas_used += 15;
+                let b = self.stack.pop().unwrap();
+                let a = self.stack.pop().unwrap();
+                self.stack.push((a > b) as u32);
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
-            // JUMPIF - Conditional jump
-            0x31 => {
-                self.pc += 1;
-                if self.pc >= self.memory.len() {
-                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+            // EQ - Equality, pushes 1 or 0
+            0x26 => {
+                if self.stack.len() < 2 {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
                 }
                 
-                let dest = self.memory[self.pc] as usize;
-                self.pc += 1;
+                let b = self.stack.pop().unwrap();
+                let a = self.stack.pop().unwrap();
+                self.stack.push((a == b) as u32);
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // ISZERO - Pushes 1 if the top of stack is zero, otherwise 0
+            0x27 => {
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                
+                let a = self.stack.pop().unwrap();
+                self.stack.push((a == 0) as u32);
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // JUMP - Unconditional jump
+            0x30 => {
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                
+                let dest = self.stack.pop().unwrap() as usize;
+                if dest >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::InvalidJumpDestination(dest)));
+                }
+                
+                self.pc = dest;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // JUMPIF - Conditional jump
+            0x31 => {
+                self.pc += 1;
+                if self.pc >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                let dest = self.memory[self.pc] as usize;
+                self.pc += 1;
+                
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                
+                let condition = self.stack.pop().unwrap();
+                if condition != 0 {
+                    if dest >= self.memory.len() {
+                        return Err(anyhow::anyhow!("{}", VMError::InvalidJumpDestination(dest)));
+                    }
+                    self.pc = dest;
+                }
+                
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // JUMP2 - Unconditional jump to a 2-byte little-endian destination
+            0x34 => {
+                if self.pc + 2 >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc + 2)));
+                }
+                
+                let dest = u16::from_le_bytes([self.memory[self.pc + 1], self.memory[self.pc + 2]]) as usize;
+                if dest >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::InvalidJumpDestination(dest)));
+                }
+                
+                self.pc = dest;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // JUMPIF2 - Conditional jump to a 2-byte little-endian destination
+            0x35 => {
+                if self.pc + 2 >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc + 2)));
+                }
+                
+                let dest = u16::from_le_bytes([self.memory[self.pc + 1], self.memory[self.pc + 2]]) as usize;
+                self.pc += 3;
                 
                 if self.stack.is_empty() {
                     return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
@@ -461,7 +1314,33 @@ impl VM {
                     self.pc = dest;
                 }
                 
-                self.gas_used += 15;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // JUMPC, JUMPZ, JUMPO - Jump if the carry, zero or overflow flag
+            // is set
+            0x36..=0x38 => {
+                self.pc += 1;
+                if self.pc >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                let dest = self.memory[self.pc] as usize;
+                self.pc += 1;
+                
+                let flag = match opcode {
+                    0x36 => FLAG_CARRY,
+                    0x37 => FLAG_ZERO,
+                    _ => FLAG_OVERFLOW,
+                };
+                if self.flags & flag != 0 {
+                    if dest >= self.memory.len() {
+                        return Err(anyhow::anyhow!("{}", VMError::InvalidJumpDestination(dest)));
+                    }
+                    self.pc = dest;
+                }
+                
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // CALL - Function call
@@ -477,7 +1356,7 @@ impl VM {
                 
                 self.stack.push(self.pc as u32 + 1);
                 self.pc = dest;
-                self.gas_used += 25;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // RETURN - Return from function
@@ -492,7 +1371,7 @@ impl VM {
                 }
                 
                 self.pc = ret_addr;
-                self.gas_used += 25;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // LOAD - Load from memory
@@ -503,13 +1382,11 @@ impl VM {
                 }
                 
                 let addr = self.memory[self.pc] as usize;
-                if addr >= self.memory.len() {
-                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(addr)));
-                }
+                self.ensure_memory(addr, 1)?;
                 
                 self.stack.push(self.memory[addr] as u32);
                 self.pc += 1;
-                self.gas_used += 30;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // STORE - Store to memory
@@ -520,18 +1397,52 @@ impl VM {
                 }
                 
                 let addr = self.memory[self.pc] as usize;
-                if addr >= self.memory.len() {
-                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(addr)));
-                }
+                self.ensure_memory(addr, 1)?;
                 
                 if self.stack.is_empty() {
                     return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
                 }
                 
                 let value = self.stack.pop().unwrap() as u8;
-                self.memory[addr] = value;
+                self.write_bytes(addr, &[value]);
                 self.pc += 1;
-                self.gas_used += 30;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // LOADW - Load a 32-bit word from a 2-byte address
+            0x44 => {
+                self.pc += 1;
+                if self.pc + 1 >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                let addr = read_be(&self.memory[self.pc..self.pc + 2]) as usize;
+                self.ensure_memory(addr, 4)?;
+                
+                let value = read_be(&self.memory[addr..addr + 4]);
+                self.stack.push(value);
+                self.pc += 2;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // STOREW - Store a 32-bit word at a 2-byte address
+            0x45 => {
+                self.pc += 1;
+                if self.pc + 1 >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                let addr = read_be(&self.memory[self.pc..self.pc + 2]) as usize;
+                self.ensure_memory(addr, 4)?;
+                
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                
+                let value = self.stack.pop().unwrap();
+                self.write_bytes(addr, &value.to_be_bytes());
+                self.pc += 2;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SOLTRANSFER - Transfer SOL
@@ -540,15 +1451,20 @@ impl VM {
                     return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
                 }
                 
-                let amount = self.stack.pop().unwrap();
+                let amount = self.stack.pop().unwrap() as u64;
                 let to_addr = self.stack.pop().unwrap();
                 
-                // In a real implementation, this would interact with the Solana system
-                // For now, we'll just log the transfer
+                let balance = self.get_balance(PROGRAM_ACCOUNT);
+                if balance < amount {
+                    return Err(anyhow::anyhow!("{}", VMError::InsufficientFunds(PROGRAM_ACCOUNT, balance, amount)));
+                }
+                self.balances.insert(PROGRAM_ACCOUNT, balance - amount);
+                let to_balance = self.balances.entry(to_addr).or_insert(0);
+                *to_balance = to_balance.saturating_add(amount);
                 log::info!("SOL transfer: {} lamports to address {:#010X}", amount, to_addr);
                 
                 self.pc += 1;
-                self.gas_used += 100;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // TOKENTRANSFER - Transfer tokens
@@ -567,7 +1483,7 @@ impl VM {
                            amount, token_addr, to_addr);
                 
                 self.pc += 1;
-                self.gas_used += 100;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SYSCALL - System call
@@ -590,16 +1506,22 @@ impl VM {
                     }
                     1 => {
                         // Get timestamp
-                        let timestamp = std::time::SystemTime::now()
-                            .duration_since(std::time::UNIX_EPOCH)
-                            .unwrap_or_default()
-                            .as_secs() as u32;
+                        let timestamp = self.syscall_input(|vm| {
+                            vm.clock_override.unwrap_or_else(|| {
+                                std::time::SystemTime::now()
+                                    .duration_since(std::time::UNIX_EPOCH)
+                                    .unwrap_or_default()
+                                    .as_secs() as u32
+                            })
+                        });
                         self.stack.push(timestamp);
                     }
                     2 => {
-                        // Get random number
-                        use rand::Rng;
-                        let value = rand::thread_rng().gen::<u32>();
+                        // Get random number, from the same generator as RAND.
+                        // Draw even when replaying so a later RAND sees the
+                        // generator in the same state as the recorded run.
+                        let drawn = self.rng.gen();
+                        let value = self.syscall_input(|_| drawn);
                         self.stack.push(value);
                     }
                     _ => {
@@ -608,7 +1530,7 @@ impl VM {
                 }
                 
                 self.pc += 1;
-                self.gas_used += 100;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SIMD - Vector load - Load 4 consecutive values into vector register
@@ -627,10 +1549,8 @@ impl VM {
                 }
                 let addr = self.stack.pop().unwrap() as usize;
                 
-                // Check if all addresses are in range
-                if addr + 3 >= self.memory.len() {
-                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(addr + 3)));
-                }
+                // Grow memory if needed so all 4 addresses are in range
+                self.ensure_memory(addr, 4)?;
                 
                 // Load 4 consecutive values into the vector register
                 for i in 0..4 {
@@ -638,7 +1558,7 @@ impl VM {
                 }
                 
                 self.pc += 1;
-                self.gas_used += 20;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SIMD - Vector store - Store vector register to 4 consecutive memory locations
@@ -657,18 +1577,15 @@ impl VM {
                 }
                 let addr = self.stack.pop().unwrap() as usize;
                 
-                // Check if all addresses are in range
-                if addr + 3 >= self.memory.len() {
-                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(addr + 3)));
-                }
+                // Grow memory if needed so all 4 addresses are in range
+                self.ensure_memory(addr, 4)?;
                 
                 // Store vector register to consecutive memory locations
-                for i in 0..4 {
-                    self.memory[addr + i] = (self.vector_registers[vreg_idx as usize][i] & 0xFF) as u8;
-                }
+                let bytes = self.vector_registers[vreg_idx as usize].map(|lane| (lane & 0xFF) as u8);
+                self.write_bytes(addr, &bytes);
                 
                 self.pc += 1;
-                self.gas_used += 20;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SIMD - Vector add
@@ -690,7 +1607,7 @@ impl VM {
                 }
                 
                 self.pc += 1;
-                self.gas_used += 15;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SIMD - Vector subtract
@@ -712,7 +1629,7 @@ impl VM {
                 }
                 
                 self.pc += 1;
-                self.gas_used += 15;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SIMD - Vector dot product
@@ -737,7 +1654,51 @@ impl VM {
                 
                 self.stack.push(dot_product);
                 self.pc += 1;
-                self.gas_used += 25;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // SIMD - Vector multiply
+            0x65 => {
+                self.pc += 1;
+                if self.pc >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                // Get vector register indices
+                let reg_byte = self.memory[self.pc];
+                let dest_reg = (reg_byte >> 4) & 0x3; // Top 4 bits (but use only 2)
+                let src_reg = reg_byte & 0x3; // Bottom 4 bits (but use only 2)
+                
+                // Perform element-wise multiplication
+                for i in 0..4 {
+                    self.vector_registers[dest_reg as usize][i] = 
+                        self.vector_registers[dest_reg as usize][i].wrapping_mul(self.vector_registers[src_reg as usize][i]);
+                }
+                
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // SIMD - Vector broadcast - Copy a scalar into all 4 lanes
+            0x66 => {
+                self.pc += 1;
+                if self.pc >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                // Get vector register idx (0-3)
+                let vreg_idx = (self.memory[self.pc] >> 4) & 0x3; // Top 4 bits (but use only 2)
+                
+                // Get the scalar from the stack
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                let value = self.stack.pop().unwrap();
+                
+                self.vector_registers[vreg_idx as usize] = [value; 4];
+                
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
 
             // PUSHF - Push floating point value onto stack
@@ -760,7 +1721,7 @@ impl VM {
                 self.stack.push(bytes_as_u32[1]);
                 
                 self.pc += 8;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // LOADF - Load floating point value from register to stack
@@ -783,7 +1744,7 @@ impl VM {
                 self.stack.push(bytes_as_u32[1]);
                 
                 self.pc += 1;
-                self.gas_used += 3;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // STOREF - Store floating point value from stack to register
@@ -814,7 +1775,7 @@ impl VM {
                 self.fp_registers[reg_idx] = value;
                 
                 self.pc += 1;
-                self.gas_used += 3;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // ADDF - Floating point addition
@@ -833,7 +1794,7 @@ impl VM {
                 self.fp_registers[dest_reg as usize] += self.fp_registers[src_reg as usize];
                 
                 self.pc += 1;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // SUBF - Floating point subtraction
@@ -852,7 +1813,7 @@ impl VM {
                 self.fp_registers[dest_reg as usize] -= self.fp_registers[src_reg as usize];
                 
                 self.pc += 1;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // MULF - Floating point multiplication
@@ -871,7 +1832,7 @@ impl VM {
                 self.fp_registers[dest_reg as usize] *= self.fp_registers[src_reg as usize];
                 
                 self.pc += 1;
-                self.gas_used += 8;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // DIVF - Floating point division
@@ -895,7 +1856,7 @@ impl VM {
                 }
                 
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // MATMUL - Matrix multiplication
@@ -927,19 +1888,82 @@ impl VM {
                 self.matrix_registers[dest_idx as usize] = result;
                 
                 self.pc += 1;
-                self.gas_used += 40; // Matrix multiplication is expensive
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // MATVEC - Matrix-vector multiplication
+            // Operands: matrix index, then destination and source vector
+            // registers. Vector lanes hold f32 bit patterns.
+            0x81 => {
+                self.pc += 1;
+                if self.pc + 1 >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                let mat_idx = self.memory[self.pc] & 0x1; // Use only 1 bit
+                let reg_byte = self.memory[self.pc + 1];
+                let dest_reg = (reg_byte >> 4) & 0x3; // Top 4 bits (but use only 2)
+                let src_reg = reg_byte & 0x3; // Bottom 4 bits (but use only 2)
+                
+                let matrix = &self.matrix_registers[mat_idx as usize];
+                let vector = self.vector_registers[src_reg as usize].map(f32::from_bits);
+                let mut result = [0u32; 4];
+                for (i, row) in matrix.iter().enumerate() {
+                    let sum: f32 = row.iter().zip(&vector).map(|(m, v)| m * v).sum();
+                    result[i] = sum.to_bits();
+                }
+                self.vector_registers[dest_reg as usize] = result;
+                
+                self.pc += 2;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // MATTRANS - Transpose a matrix register in place
+            0x82 => {
+                self.pc += 1;
+                if self.pc >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
+                }
+                
+                let mat_idx = self.memory[self.pc] & 0x1; // Use only 1 bit
+                let matrix = self.matrix_registers[mat_idx as usize];
+                self.matrix_registers[mat_idx as usize] =
+                    std::array::from_fn(|i| std::array::from_fn(|j| matrix[j][i]));
+                
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // CRYPTOHASH - Compute SHA-256 hash of data in memory
+            // Operands: destination address, length. Source address is popped from the stack.
             0x90 => {
                 self.pc += 1;
                 if self.pc + 1 >= self.memory.len() {
                     return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
                 }
                 
-                // TODO: Implement SHA-256 hashing logic
-                self.pc += 2; 
-                self.gas_used += 100; // Cryptographic operations are expensive
+                let dest = self.memory[self.pc] as usize;
+                let len = self.memory[self.pc + 1] as usize;
+                
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                let src = self.stack.pop().unwrap() as usize;
+                
+                // Check that both the input and the 32-byte digest fit in memory
+                if src + len > self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(src + len)));
+                }
+                if dest + 32 > self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(dest + 32)));
+                }
+                
+                let digest = Sha256::digest(&self.memory[src..src + len]);
+                self.write_bytes(dest, &digest);
+                self.stack.push(dest as u32);
+                
+                self.pc += 2;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
 
             // CPLXADD - Complex number addition
@@ -959,7 +1983,7 @@ impl VM {
                 self.complex_registers[dest_reg as usize][1] += self.complex_registers[src_reg as usize][1]; // Imaginary part
                 
                 self.pc += 1;
-                self.gas_used += 8;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // CPLXMUL - Complex number multiplication
@@ -991,42 +2015,34 @@ impl VM {
                 self.complex_registers[dest_reg as usize][1] = imag;
                 
                 self.pc += 1;
-                self.gas_used += 15;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
 
-            // STRPUSH - Push a character to string buffer
+            // STRPUSH - Push a byte to string buffer
             0xB0 => {
                 self.pc += 1;
                 if self.pc >= self.memory.len() {
                     return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
                 }
                 
-                // Get the character from memory
-                let ch = self.memory[self.pc] as char;
+                // Get the byte from memory
+                let byte = self.memory[self.pc];
                 
-                // Push it to the string buffer
-                self.string_buffer.push(ch);
+                // Push it to the string buffer, respecting the configured limit
+                self.push_string_bytes(&[byte])?;
                 
                 self.pc += 1;
-                self.gas_used += 2;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
-            // STRPOP - Pop a character from string buffer
+            // STRPOP - Pop a byte from string buffer
             0xB1 => {
-                // Check if string buffer is empty
-                if self.string_buffer.is_empty() {
-                    self.stack.push(0); // Push 0 if empty
-                } else {
-                    // Pop the last character
-                    if let Some(ch) = self.string_buffer.pop() {
-                        self.stack.push(ch as u32);
-                    } else {
-                        self.stack.push(0);
-                    }
-                }
+                // Pop the last byte, pushing 0 if the buffer is empty
+                let byte = self.string_buffer.pop().unwrap_or(0);
+                self.stack.push(byte as u32);
                 
                 self.pc += 1;
-                self.gas_used += 2;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // STRCLEAR - Clear the string buffer
@@ -1034,7 +2050,7 @@ impl VM {
                 self.string_buffer.clear();
                 
                 self.pc += 1;
-                self.gas_used += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // STRLEN - Get string length onto stack
@@ -1042,7 +2058,42 @@ impl VM {
                 self.stack.push(self.string_buffer.len() as u32);
                 
                 self.pc += 1;
-                self.gas_used += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // ITOA - Append decimal representation of top of stack to string buffer
+            0xB8 => {
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                
+                let value = self.stack.pop().unwrap();
+                self.push_string_bytes(value.to_string().as_bytes())?;
+                
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
+            }
+            
+            // ATOI - Parse string buffer as a decimal integer
+            // Pushes the value followed by an error flag (0 = ok, 1 = not a valid u32)
+            0xB9 => {
+                let parsed = std::str::from_utf8(&self.string_buffer)
+                    .ok()
+                    .and_then(|text| text.parse::<u32>().ok());
+                
+                match parsed {
+                    Some(value) => {
+                        self.stack.push(value);
+                        self.stack.push(0);
+                    }
+                    None => {
+                        self.stack.push(0);
+                        self.stack.push(1);
+                    }
+                }
+                
+                self.pc += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
 
             // JSONPARSE - Parse string buffer as JSON and extract value
@@ -1052,22 +2103,36 @@ impl VM {
                     return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(self.pc)));
                 }
                 
-                // In a real implementation, this would parse the JSON in the string buffer
-                // and extract the value at the specified path.
-                // For now, we'll just return a dummy value
-                self.stack.push(42);
+                // The operand byte is reserved; the path address comes from the stack
+                if self.stack.is_empty() {
+                    return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
+                }
+                let path_addr = self.stack.pop().unwrap() as usize;
+                if path_addr >= self.memory.len() {
+                    return Err(anyhow::anyhow!("{}", VMError::OutOfMemory(path_addr)));
+                }
+                
+                // Path is a null-terminated, dot-separated key string (e.g. "a.b.0")
+                let path_end = self.memory[path_addr..]
+                    .iter()
+                    .position(|&b| b == 0)
+                    .map_or(self.memory.len(), |n| path_addr + n);
+                let path = String::from_utf8_lossy(&self.memory[path_addr..path_end]).into_owned();
+                
+                let value = json_lookup(&self.string_buffer, &path).unwrap_or(0);
+                self.stack.push(value);
                 
                 self.pc += 1;
-                self.gas_used += 25;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // MAPINIT - Initialize a key-value map 
             0xD0 => {
-                // In a real implementation, this would allocate a map data structure
-                // For this prototype, we'll just modify our state to indicate a map is active
+                // Allocate a fresh map, discarding any previous contents
+                self.map = Some(HashMap::new());
                 
                 self.pc += 1;
-                self.gas_used += 10;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // MAPSET - Set a key-value pair in the map
@@ -1077,16 +2142,16 @@ impl VM {
                     return Err(anyhow::anyhow!("{}", VMError::StackUnderflow));
                 }
                 
-                // Pop value and key from stack
-                let value = self.stack.pop().unwrap();
+                // Pop key (top of stack) and value from stack
                 let key = self.stack.pop().unwrap();
+                let value = self.stack.pop().unwrap();
                 
-                // In a real implementation, this would add the key-value pair to a map
-                // For this prototype, we'll just log it
-                log::info!("Map set: key={}, value={}", key, value);
+                let map = self.map.as_mut()
+                    .ok_or_else(|| anyhow::anyhow!("{}", VMError::SystemError("Map not initialized".to_string())))?;
+                map.insert(key, value);
                 
                 self.pc += 1;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // MAPGET - Get a value from the map by key
@@ -1099,27 +2164,31 @@ impl VM {
                 // Pop key from stack
                 let key = self.stack.pop().unwrap();
                 
-                // In a real implementation, this would look up the key in a map
-                // For this prototype, we'll just return a dummy value
-                self.stack.push(key); // Echo back the key as a dummy value
+                // Push the stored value, or 0 if the key is absent
+                let map = self.map.as_ref()
+                    .ok_or_else(|| anyhow::anyhow!("{}", VMError::SystemError("Map not initialized".to_string())))?;
+                self.stack.push(map.get(&key).copied().unwrap_or(0));
                 
                 self.pc += 1;
-                self.gas_used += 5;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // RAND - Generate a cryptographically secure random number
             0xE0 => {
-                // In a real implementation, this would use a CSPRNG
-                self.stack.push(42); // Dummy value
+                // Emulator only: StdRng is seeded from the OS unless set_rng_seed
+                // was called. There is no on-chain equivalent, since programs
+                // running on Solana have no trustworthy source of randomness.
+                let value: u32 = self.rng.gen();
+                self.stack.push(value);
                 self.pc += 1;
-                self.gas_used += 20;
+                self.gas_used += self.gas_schedule.cost(opcode);
             }
             
             // HALT - Stop execution
             0xFF => {
-                self.state = VMState::Halted;
+                self.set_state(VMState::Halted);
                 self.pc += 1;
-                self.gas_used += 1;
+                self.gas_used += self.gas_schedule.cost(opcode);
                 return Ok(false);
             }
             
@@ -1133,6 +2202,33 @@ impl VM {
     }
 }
 
+/// Parse `json` and follow a dot-separated `path` to an integer value.
+///
+/// Object keys are matched by name and array elements by index. Numbers are
+/// truncated to `u32` and booleans become 0/1; anything else yields `None`.
+fn json_lookup(json: &[u8], path: &str) -> Option<u32> {
+    let root: serde_json::Value = serde_json::from_slice(json).ok()?;
+    
+    let mut current = &root;
+    for segment in path.split('.').filter(|s| !s.is_empty()) {
+        current = match current {
+            serde_json::Value::Object(map) => map.get(segment)?,
+            serde_json::Value::Array(items) => items.get(segment.parse::<usize>().ok()?)?,
+            _ => return None,
+        };
+    }
+    
+    match current {
+        serde_json::Value::Number(n) => n
+            .as_u64()
+            .map(|v| v as u32)
+            .or_else(|| n.as_i64().map(|v| v as u32))
+            .or_else(|| n.as_f64().map(|v| v as u32)),
+        serde_json::Value::Bool(b) => Some(*b as u32),
+        _ => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1171,6 +2267,67 @@ mod tests {
         }
     }
     
+    #[test]
+    fn test_comparison_opcodes() {
+        let compare = |op: u8, operands: &[u32]| {
+            let mut vm = VM::new();
+            vm.load_bytecode(&[op, 0xFF]).unwrap();
+            vm.stack.extend_from_slice(operands);
+            vm.run(10).unwrap();
+            vm.stack.pop().unwrap()
+        };
+        
+        assert_eq!(compare(0x24, &[3, 5]), 1); // LT
+        assert_eq!(compare(0x24, &[5, 5]), 0);
+        assert_eq!(compare(0x25, &[5, 3]), 1); // GT
+        assert_eq!(compare(0x25, &[3, 5]), 0);
+        assert_eq!(compare(0x26, &[7, 7]), 1); // EQ
+        assert_eq!(compare(0x26, &[7, 8]), 0);
+        assert_eq!(compare(0x27, &[0]), 1);    // ISZERO
+        assert_eq!(compare(0x27, &[9]), 0);
+        
+        // Comparisons are unsigned: -1 in two's complement is the largest value
+        let minus_one = -1i32 as u32;
+        assert_eq!(compare(0x24, &[minus_one, 1]), 0);
+        assert_eq!(compare(0x25, &[minus_one, 1]), 1);
+        assert_eq!(compare(0x27, &[minus_one]), 0);
+        
+        // PUSH1 200, PUSH1 100, GT, JUMPIF 10, PUSH1 0, HALT, 10: PUSH1 7, HALT
+        let mut vm = VM::new();
+        vm.load_bytecode(&[0x01, 200, 0x01, 100, 0x25, 0x31, 10, 0x01, 0, 0xFF, 0x01, 7, 0xFF]).unwrap();
+        vm.run(20).unwrap();
+        assert_eq!(vm.stack, vec![7]);
+    }
+    
+    #[test]
+    fn test_wide_jumps() {
+        let mut vm = VM::new();
+        
+        // 0: JUMP2 0x0150
+        // 3: HALT
+        // 0x150: PUSH1 1, JUMPIF2 0x0200
+        // 0x155: HALT
+        // 0x200: PUSH1 42, HALT
+        let mut bytecode = vec![0; 0x203];
+        bytecode[..4].copy_from_slice(&[0x34, 0x50, 0x01, 0xFF]);
+        bytecode[0x150..0x156].copy_from_slice(&[0x01, 1, 0x35, 0x00, 0x02, 0xFF]);
+        bytecode[0x200..0x203].copy_from_slice(&[0x01, 42, 0xFF]);
+        vm.load_bytecode(&bytecode).unwrap();
+        vm.run(10).unwrap();
+        
+        // HALT leaves the PC just past itself
+        assert_eq!(vm.stack, vec![42]);
+        assert_eq!(vm.pc, 0x203);
+        
+        // A false condition falls through past the 2-byte operand
+        bytecode[0x151] = 0;
+        vm.load_bytecode(&bytecode).unwrap();
+        vm.run(10).unwrap();
+        
+        assert!(vm.stack.is_empty());
+        assert_eq!(vm.pc, 0x156);
+    }
+    
     #[test]
     fn test_jump() {
         let mut vm = VM::new();
@@ -1205,28 +2362,172 @@ mod tests {
     }
     
     #[test]
-    fn test_simd_instructions() {
+    fn test_memory_growth() {
         let mut vm = VM::new();
+        assert_eq!(vm.memory.len(), INITIAL_MEMORY_SIZE);
         
-        // Create a program that uses SIMD instructions:
-        // 1. Push memory address 100 to stack
-        // 2. Load 4 values at address 100 into vector register 0
-        // 3. Push memory address 104 to stack
-        // 4. Load 4 values at address 104 into vector register 1
-        // 5. Add vector registers 0 and 1, store result in vector register 0
-        // 6. Push memory address 108 to stack 
-        // 7. Store vector register 0 to memory at address 108
-        // 8. Calculate dot product of vector registers 0 and 1
-        // 9. Halt
+        // PUSH4 0x00010010, VSTORE v1, PUSH4 0x00010010, VLOAD v2, HALT
         let program = vec![
-            0x01, 100,            // PUSH1 100 (address for first vector)
-            0x60, 0x00,           // VLOAD register 0
-            0x01, 104,            // PUSH1 104 (address for second vector)
-            0x60, 0x10,           // VLOAD register 1
-            0x62, 0x01,           // VADD registers 0 and 1, result in 0
-            0x01, 108,            // PUSH1 108 (store result address)
-            0x61, 0x00,           // VSTORE register 0
-            0x64, 0x01,           // VDOT registers 0 and 1
+            0x03, 0x00, 0x01, 0x00, 0x10, 0x61, 0x10,
+            0x03, 0x00, 0x01, 0x00, 0x10, 0x60, 0x20,
+            0xFF,
+        ];
+        vm.load_bytecode(&program).unwrap();
+        vm.vector_registers[1] = [0xAA, 0xBB, 0xCC, 0xDD];
+        vm.run(10).unwrap();
+        
+        // Memory grew by one page and the value reads back
+        assert_eq!(vm.memory.len(), INITIAL_MEMORY_SIZE + MEMORY_PAGE_SIZE);
+        assert_eq!(&vm.memory[0x10010..0x10014], &[0xAA, 0xBB, 0xCC, 0xDD]);
+        assert_eq!(vm.vector_registers[2], [0xAA, 0xBB, 0xCC, 0xDD]);
+        assert_eq!(vm.gas_used(), 3 + 20 + MEMORY_PAGE_GAS + 3 + 20 + 1);
+        
+        // Reset shrinks memory back to its initial size
+        vm.reset();
+        assert_eq!(vm.memory.len(), INITIAL_MEMORY_SIZE);
+    }
+    
+    #[test]
+    fn test_memory_ceiling() {
+        let mut vm = VM::new();
+        vm.set_max_memory(INITIAL_MEMORY_SIZE);
+        
+        // PUSH4 0x00010000, VSTORE v0, HALT
+        let program = vec![0x03, 0x00, 0x01, 0x00, 0x00, 0x61, 0x00, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        
+        let err = vm.run(10).unwrap_err();
+        assert!(err.to_string().contains("Out of memory"));
+        assert_eq!(vm.memory.len(), INITIAL_MEMORY_SIZE);
+    }
+    
+    #[test]
+    fn test_gas_schedule() {
+        // PUSH1 6, PUSH1 7, MUL, HALT
+        let program = vec![0x01, 6, 0x01, 7, 0x12, 0xFF];
+        
+        let mut vm = VM::new();
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        assert_eq!(vm.gas_used(), 3 + 3 + 20 + 1);
+        
+        let schedule = GasSchedule::default()
+            .with_cost(0x01, 1)
+            .with_cost(0x12, 100);
+        let mut vm = VM::with_gas_schedule(schedule);
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        assert_eq!(vm.stack, vec![42]);
+        assert_eq!(vm.gas_used(), 1 + 1 + 100 + 1);
+        
+        // Survives a reset
+        vm.reset();
+        assert_eq!(vm.gas_schedule().cost(0x12), 100);
+    }
+    
+    #[test]
+    fn test_endianness() {
+        // PUSH2 0x12 0x34, PUSH4 0x01 0x02 0x03 0x04, HALT
+        let program = vec![0x02, 0x12, 0x34, 0x03, 0x01, 0x02, 0x03, 0x04, 0xFF];
+        
+        let mut vm = VM::new();
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        assert_eq!(vm.stack, vec![0x1234, 0x01020304]);
+        
+        // The assembler encodes wide operands the same way
+        let assembled = crate::asm::assemble("PUSH2 0x1234\nPUSH4 0x01020304\nHALT").unwrap();
+        assert_eq!(assembled, program);
+    }
+    
+    #[test]
+    fn test_word_load_store() {
+        // PUSH4 0xDEADBEEF, STOREW 0x1000, LOADW 0x1000, HALT
+        let program = vec![
+            0x03, 0xDE, 0xAD, 0xBE, 0xEF, 0x45, 0x10, 0x00, 0x44, 0x10, 0x00, 0xFF,
+        ];
+        
+        let mut vm = VM::new();
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        assert_eq!(vm.stack, vec![0xDEADBEEF]);
+        assert_eq!(vm.memory[0x1000..0x1004], [0xDE, 0xAD, 0xBE, 0xEF]);
+        
+        // Only the instruction that wrote reports its addresses
+        vm.reset();
+        vm.load_bytecode(&program).unwrap();
+        vm.step().unwrap();
+        vm.step().unwrap();
+        assert_eq!(vm.last_written(), [0x1000, 0x1001, 0x1002, 0x1003]);
+        vm.step().unwrap();
+        assert!(vm.last_written().is_empty());
+        
+        // Addresses past the memory ceiling fail
+        vm.set_max_memory(0x1002);
+        vm.load_bytecode(&[0x44, 0x10, 0x00, 0xFF]).unwrap();
+        assert!(vm.run(10).is_err());
+    }
+    
+    #[test]
+    fn test_state_hook() {
+        use std::sync::{Arc, Mutex};
+        
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let mut vm = VM::new();
+        let log = Arc::clone(&seen);
+        vm.set_state_hook(Box::new(move |state| log.lock().unwrap().push(state.clone())));
+        
+        // PUSH1 1, PUSH1 2, ADD, HALT, run in slices like the TUI does
+        vm.load_bytecode(&[0x01, 0x01, 0x01, 0x02, 0x10, 0xFF]).unwrap();
+        vm.run(1).unwrap();
+        vm.run(1).unwrap();
+        vm.run(10).unwrap();
+        vm.run(10).unwrap();
+        assert_eq!(*seen.lock().unwrap(), vec![VMState::Running, VMState::Halted]);
+        
+        // Reloading goes back to Ready; errors are reported once
+        seen.lock().unwrap().clear();
+        vm.load_bytecode(&[0x10]).unwrap();
+        assert!(vm.run(10).is_err());
+        assert!(vm.run(10).is_err());
+        let states = seen.lock().unwrap();
+        assert_eq!(states.len(), 3);
+        assert_eq!(states[..2], [VMState::Ready, VMState::Running]);
+        assert!(matches!(states[2], VMState::Error(_)));
+        drop(states);
+        
+        // Stepping by hand goes through Running too
+        seen.lock().unwrap().clear();
+        vm.load_bytecode(&[0x01, 0x01, 0xFF]).unwrap();
+        assert!(vm.step().unwrap());
+        assert_eq!(vm.state, VMState::Running);
+        assert!(!vm.step().unwrap());
+        assert_eq!(*seen.lock().unwrap(), vec![VMState::Ready, VMState::Running, VMState::Halted]);
+    }
+    
+    #[test]
+    fn test_simd_instructions() {
+        let mut vm = VM::new();
+        
+        // Create a program that uses SIMD instructions:
+        // 1. Push memory address 100 to stack
+        // 2. Load 4 values at address 100 into vector register 0
+        // 3. Push memory address 104 to stack
+        // 4. Load 4 values at address 104 into vector register 1
+        // 5. Add vector registers 0 and 1, store result in vector register 0
+        // 6. Push memory address 108 to stack 
+        // 7. Store vector register 0 to memory at address 108
+        // 8. Calculate dot product of vector registers 0 and 1
+        // 9. Halt
+        let program = vec![
+            0x01, 100,            // PUSH1 100 (address for first vector)
+            0x60, 0x00,           // VLOAD register 0
+            0x01, 104,            // PUSH1 104 (address for second vector)
+            0x60, 0x10,           // VLOAD register 1
+            0x62, 0x01,           // VADD registers 0 and 1, result in 0
+            0x01, 108,            // PUSH1 108 (store result address)
+            0x61, 0x00,           // VSTORE register 0
+            0x64, 0x01,           // VDOT registers 0 and 1
             0xFF                  // HALT
         ];
         
@@ -1261,6 +2562,67 @@ mod tests {
         assert_eq!(vm.stack[0], 244);
     }
     
+    #[test]
+    fn test_vector_multiply_and_broadcast() {
+        let mut vm = VM::new();
+        
+        let program = vec![
+            0x01, 100,            // PUSH1 100
+            0x60, 0x00,           // VLOAD register 0
+            0x01, 2,              // PUSH1 2
+            0x66, 0x10,           // VBROADCAST into register 1
+            0x65, 0x01,           // VMUL registers 0 and 1, result in 0
+            0x01, 7,              // PUSH1 7
+            0x66, 0x20,           // VBROADCAST into register 2
+            0xFF                  // HALT
+        ];
+        vm.load_bytecode(&program).unwrap();
+        vm.memory[100..104].copy_from_slice(&[1, 2, 3, 4]);
+        vm.run(20).unwrap();
+        
+        assert_eq!(vm.vector_registers[0], [2, 4, 6, 8]);
+        assert_eq!(vm.vector_registers[1], [2, 2, 2, 2]);
+        assert_eq!(vm.vector_registers[2], [7, 7, 7, 7]);
+        assert!(vm.stack.is_empty());
+        
+        // VBROADCAST needs a value to broadcast
+        vm.load_bytecode(&[0x66, 0x00, 0xFF]).unwrap();
+        assert!(vm.run(20).is_err());
+    }
+    
+    #[test]
+    fn test_matrix_vector_and_transpose() {
+        let mut vm = VM::new();
+        
+        let program = vec![
+            0x81, 0x01, 0x30,     // MATVEC matrix 1 (identity), register 0 into 3
+            0x81, 0x00, 0x10,     // MATVEC matrix 0 (rotation), register 0 into 1
+            0x82, 0x00,           // MATTRANS matrix 0, giving the inverse rotation
+            0x81, 0x00, 0x21,     // MATVEC matrix 0, register 1 into 2
+            0xFF                  // HALT
+        ];
+        vm.load_bytecode(&program).unwrap();
+        // 90 degree rotation about the z axis
+        vm.matrix_registers[0] = [
+            [0.0, -1.0, 0.0, 0.0],
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ];
+        for i in 0..4 {
+            vm.matrix_registers[1][i][i] = 1.0;
+        }
+        vm.vector_registers[0] = [1.0f32, 2.0, 3.0, 1.0].map(f32::to_bits);
+        vm.run(20).unwrap();
+        
+        let lanes = |reg: usize| vm.vector_registers[reg].map(f32::from_bits);
+        assert_eq!(lanes(3), [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(lanes(1), [-2.0, 1.0, 3.0, 1.0]);
+        assert_eq!(vm.matrix_registers[0][0], [0.0, 1.0, 0.0, 0.0]);
+        assert_eq!(lanes(2), [1.0, 2.0, 3.0, 1.0]);
+        assert_eq!(vm.gas_used, 20 * 3 + 10 + 1);
+    }
+    
     #[test]
     fn test_floating_point_operations() {
         let mut vm = VM::new();
@@ -1376,7 +2738,134 @@ mod tests {
         assert_eq!(vm.stack[1], 'o' as u32); // Popped character
         
         // Check that the string buffer now contains "Hell"
-        assert_eq!(vm.string_buffer, "Hell");
+        assert_eq!(vm.string_buffer, b"Hell");
+    }
+    
+    #[test]
+    fn test_itoa() {
+        let mut vm = VM::new();
+        
+        // PUSH2 12345 (0x3039), ITOA, HALT
+        let program = vec![0x02, 0x30, 0x39, 0xB8, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        
+        assert_eq!(vm.string_buffer, b"12345");
+        assert!(vm.stack.is_empty());
+    }
+    
+    #[test]
+    fn test_atoi() {
+        let mut vm = VM::new();
+        
+        // STRPUSH '6', STRPUSH '7', STRPUSH '8', ATOI, HALT
+        let program = vec![0xB0, b'6', 0xB0, b'7', 0xB0, b'8', 0xB9, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        
+        // Value followed by a cleared error flag
+        assert_eq!(vm.stack, vec![678, 0]);
+    }
+    
+    #[test]
+    fn test_atoi_invalid() {
+        let mut vm = VM::new();
+        
+        // STRPUSH '4', STRPUSH 'x', ATOI, HALT
+        let program = vec![0xB0, b'4', 0xB0, b'x', 0xB9, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        
+        // Zero followed by a set error flag
+        assert_eq!(vm.stack, vec![0, 1]);
+    }
+    
+    #[test]
+    fn test_string_multibyte_round_trip() {
+        let mut vm = VM::new();
+        
+        // "é" is 0xC3 0xA9 in UTF-8, "€" is 0xE2 0x82 0xAC
+        let program = vec![
+            0xB0, 0xC3, 0xB0, 0xA9, // STRPUSH "é"
+            0xB0, 0xE2, 0xB0, 0x82, 0xB0, 0xAC, // STRPUSH "€"
+            0xB3, // STRLEN (byte length)
+            0xB1, // STRPOP
+            0xFF // HALT
+        ];
+        
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        
+        // STRLEN counts bytes, STRPOP returns the last raw byte
+        assert_eq!(vm.stack, vec![5, 0xAC]);
+        assert_eq!(vm.string_buffer, vec![0xC3, 0xA9, 0xE2, 0x82]);
+        
+        // Pushing the popped byte back restores the original text
+        vm.string_buffer.push(0xAC);
+        assert_eq!(vm.string_buffer_lossy(), "é€");
+    }
+    
+    #[test]
+    fn test_string_buffer_limit_error() {
+        let mut vm = VM::new();
+        vm.set_string_buffer_limit(2, StringOverflowPolicy::Error);
+        
+        // STRPUSH 'a', STRPUSH 'b', STRPUSH 'c' (over the limit), HALT
+        let program = vec![0xB0, b'a', 0xB0, b'b', 0xB0, b'c', 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        
+        assert!(vm.run(10).is_err());
+        assert!(matches!(vm.state, VMState::Error(_)));
+        assert_eq!(vm.string_buffer, b"ab");
+    }
+    
+    #[test]
+    fn test_string_buffer_limit_truncate() {
+        let mut vm = VM::new();
+        vm.set_string_buffer_limit(2, StringOverflowPolicy::Truncate);
+        
+        // STRPUSH 'a', STRPUSH 'b', STRPUSH 'c' (dropped), STRLEN, HALT
+        let program = vec![0xB0, b'a', 0xB0, b'b', 0xB0, b'c', 0xB3, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        
+        assert_eq!(vm.string_buffer, b"ab");
+        assert_eq!(vm.stack, vec![2]);
+        assert_eq!(vm.string_buffer_limit(), 2);
+    }
+    
+    #[test]
+    fn test_division_by_zero_traps() {
+        for opcode in [0x13, 0x14] {
+            let mut vm = VM::new();
+            
+            // PUSH1 7, PUSH1 0, DIV or MOD, HALT
+            vm.load_bytecode(&[0x01, 7, 0x01, 0, opcode, 0xFF]).unwrap();
+            let err = vm.run(10).unwrap_err();
+            assert_eq!(err.to_string(), "Division by zero");
+            assert!(matches!(vm.state, VMState::Error(_)));
+            assert_eq!(vm.pc, 4);
+            
+            // Non-zero divisors are unaffected
+            // PUSH1 17, PUSH1 5, DIV or MOD, HALT
+            vm.load_bytecode(&[0x01, 17, 0x01, 5, opcode, 0xFF]).unwrap();
+            vm.run(10).unwrap();
+            assert_eq!(vm.stack, vec![if opcode == 0x13 { 3 } else { 2 }]);
+        }
+    }
+    
+    #[test]
+    fn test_division_by_zero_returns_zero() {
+        for opcode in [0x13, 0x14] {
+            let mut vm = VM::new();
+            vm.set_div_by_zero_returns_zero(true);
+            
+            // PUSH1 7, PUSH1 0, DIV or MOD, HALT
+            vm.load_bytecode(&[0x01, 7, 0x01, 0, opcode, 0xFF]).unwrap();
+            vm.run(10).unwrap();
+            assert_eq!(vm.stack, vec![0]);
+            assert_eq!(vm.state, VMState::Halted);
+        }
     }
     
     #[test]
@@ -1438,8 +2927,435 @@ mod tests {
         vm.load_bytecode(&program).unwrap();
         vm.run(10).unwrap();
         
-        // In our implementation, MAPGET just echoes back the key as a dummy value
+        // MAPGET returns the value stored for key 100
         assert_eq!(vm.stack.len(), 1);
-        assert_eq!(vm.stack[0], 100);
+        assert_eq!(vm.stack[0], 42);
+    }
+    
+    #[test]
+    fn test_map_missing_key_and_reset() {
+        let mut vm = VM::new();
+        
+        // MAPINIT, PUSH1 7 (key), MAPGET, HALT
+        let program = vec![0xD0, 0x01, 7, 0xD2, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        
+        // Absent keys read as 0
+        assert_eq!(vm.stack, vec![0]);
+        assert!(vm.map.is_some());
+        
+        vm.reset();
+        assert!(vm.map.is_none());
+        
+        // Using the map before MAPINIT is an error
+        let program = vec![0x01, 7, 0xD2, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        assert!(vm.run(10).is_err());
+    }
+    
+    #[test]
+    fn test_jsonparse_nested_lookup() {
+        let mut vm = VM::new();
+        
+        // PUSH1 0x80 (path address), JSONPARSE, HALT
+        let program = vec![0x01, 0x80, 0xC0, 0x00, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.string_buffer = br#"{"account":{"balance":1234.9,"frozen":true}}"#.to_vec();
+        vm.memory[0x80..0x80 + 16].copy_from_slice(b"account.balance\0");
+        vm.run(10).unwrap();
+        
+        assert_eq!(vm.stack, vec![1234]);
+        
+        // Booleans become 0/1
+        vm.load_bytecode(&program).unwrap();
+        vm.string_buffer = br#"{"account":{"balance":1234.9,"frozen":true}}"#.to_vec();
+        vm.memory[0x80..0x80 + 15].copy_from_slice(b"account.frozen\0");
+        vm.run(10).unwrap();
+        
+        assert_eq!(vm.stack, vec![1]);
+        assert_eq!(vm.gas_used(), 3 + 25 + 1); // PUSH1 + JSONPARSE + HALT
+    }
+    
+    #[test]
+    fn test_jsonparse_malformed_and_missing() {
+        let mut vm = VM::new();
+        
+        // PUSH1 0x80 (path address), JSONPARSE, HALT
+        let program = vec![0x01, 0x80, 0xC0, 0x00, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.string_buffer = br#"{"a": 1"#.to_vec();
+        vm.memory[0x80..0x82].copy_from_slice(b"a\0");
+        vm.run(10).unwrap();
+        
+        // Parse errors push 0
+        assert_eq!(vm.stack, vec![0]);
+        
+        vm.load_bytecode(&program).unwrap();
+        vm.string_buffer = br#"{"a": 1}"#.to_vec();
+        vm.memory[0x80..0x82].copy_from_slice(b"b\0");
+        vm.run(10).unwrap();
+        
+        // Missing keys push 0
+        assert_eq!(vm.stack, vec![0]);
+    }
+    
+    #[test]
+    fn test_cryptohash() {
+        let mut vm = VM::new();
+        
+        // PUSH1 0x80 (source), CRYPTOHASH dest=0xA0 len=3, HALT
+        let program = vec![0x01, 0x80, 0x90, 0xA0, 3, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.memory[0x80..0x83].copy_from_slice(b"abc");
+        vm.run(10).unwrap();
+        
+        // SHA-256("abc")
+        let expected: [u8; 32] = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae, 0x22, 0x23,
+            0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61, 0xf2, 0x00, 0x15, 0xad,
+        ];
+        assert_eq!(&vm.memory[0xA0..0xC0], &expected);
+        assert_eq!(vm.stack, vec![0xA0]);
+        assert_eq!(vm.gas_used(), 3 + 100 + 1);
+    }
+    
+    #[test]
+    fn test_rand_seeded() {
+        const SEED: u64 = 0x1E55;
+        
+        // RAND, RAND, HALT
+        let program = vec![0xE0, 0xE0, 0xFF];
+        
+        let mut vm = VM::new();
+        vm.set_rng_seed(SEED);
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        
+        // Reference sequence from an identically seeded generator
+        let mut reference = StdRng::seed_from_u64(SEED);
+        let expected: Vec<u32> = vec![reference.gen(), reference.gen()];
+        assert_eq!(vm.stack, expected);
+        
+        // Reloading replays the same sequence
+        vm.load_bytecode(&program).unwrap();
+        vm.run(10).unwrap();
+        assert_eq!(vm.stack, expected);
+    }
+    
+    #[test]
+    fn test_replay_context() {
+        // RAND, SYSCALL 1 (timestamp), SYSCALL 2 (random), store both
+        // syscall results, RAND, HALT
+        let program = vec![
+            0xE0, 0x01, 0x01, 0x52, 0x01, 0x02, 0x52,
+            0x41, 0x10, 0x41, 0x20, 0xE0, 0xFF,
+        ];
+        let ctx = ReplayContext {
+            rng_seed: 7,
+            clock: Some(1_700_000_000),
+            syscall_inputs: Vec::new(),
+        };
+        
+        let run = |ctx: ReplayContext| {
+            let mut vm = VM::with_replay_context(ctx);
+            vm.load_bytecode(&program).unwrap();
+            vm.run(100).unwrap();
+            vm
+        };
+        let first = run(ctx.clone());
+        let second = run(ctx);
+        assert_eq!(first.snapshot(), second.snapshot());
+        assert_eq!(first.stack.len(), 2);
+        
+        // A run against the wall clock replays from its recorded inputs
+        let mut live = VM::new();
+        live.set_rng_seed(99);
+        live.load_bytecode(&program).unwrap();
+        live.run(100).unwrap();
+        let recorded = live.replay_context().unwrap();
+        assert_eq!(recorded.syscall_inputs.len(), 2);
+        
+        let replayed = run(recorded);
+        assert_eq!(live.snapshot(), replayed.snapshot());
+    }
+    
+    #[test]
+    fn test_reset_restores_initial_snapshot() {
+        let mut vm = VM::new();
+        
+        // Memory keeps its contents across reset, so compare everything else
+        let without_memory = |snap: VMSnapshot| VMSnapshot { memory: Vec::new(), ..snap };
+        let fresh = vm.snapshot();
+        
+        // Touch the stack, registers, string buffer, map and gas:
+        // PUSH1 7, PUSHF 1.5, STOREF f0, STRPUSH 'x', MAPINIT,
+        // PUSH1 1, PUSH1 2, MAPSET, PUSH1 9, STORE 0x80, HALT
+        let mut program = vec![0x01, 7, 0x70];
+        program.extend_from_slice(&1.5f64.to_le_bytes());
+        program.extend_from_slice(&[
+            0x72, 0, 0xB0, b'x', 0xD0,
+            0x01, 1, 0x01, 2, 0xD1, 0x01, 9, 0x41, 0x80, 0xFF,
+        ]);
+        vm.load_bytecode(&program).unwrap();
+        vm.run(100).unwrap();
+        vm.registers[3] = 0xDEAD;
+        vm.vector_registers[1] = [1, 2, 3, 4];
+        vm.matrix_registers[0][0][0] = 2.0;
+        vm.complex_registers[2] = [1.0, -1.0];
+        assert_ne!(without_memory(vm.snapshot()), without_memory(fresh.clone()));
+        
+        vm.reset();
+        
+        let after = vm.snapshot();
+        assert_eq!(after.memory.len(), fresh.memory.len());
+        assert_eq!(without_memory(after), without_memory(fresh));
+        assert!(vm.exec_counts.is_empty());
+        assert!(vm.recent_history.is_empty());
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_snapshot_restore_replays() {
+        let mut vm = VM::new();
+        
+        // 0x00: PUSH1 3, STORE 0x80
+        // 0x04: PUSH1 5
+        // 0x06: PUSH1 1, SUB, DUP 0, JUMPIF 0x06 (count down to zero)
+        // 0x0D: STRPUSH 'a', HALT
+        let program = vec![
+            0x01, 3, 0x41, 0x80,
+            0x01, 5,
+            0x01, 1, 0x11, 0x05, 0, 0x31, 0x06,
+            0xB0, b'a', 0xFF,
+        ];
+        vm.load_bytecode(&program).unwrap();
+        for _ in 0..6 {
+            vm.step().unwrap();
+        }
+        
+        // Round trip through JSON like a checkpoint on disk would
+        let json = serde_json::to_string(&vm.snapshot()).unwrap();
+        let checkpoint: VMSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(checkpoint, vm.snapshot());
+        
+        vm.run(100).unwrap();
+        let finished = vm.snapshot();
+        assert_eq!(finished.state, VMState::Halted);
+        assert_eq!(finished.string_buffer, b"a");
+        
+        vm.restore(&checkpoint);
+        assert_eq!(vm.snapshot(), checkpoint);
+        vm.run(100).unwrap();
+        assert_eq!(vm.snapshot(), finished);
+        
+        let path = std::env::temp_dir().join(format!("lessvm-snapshot-{}.json", std::process::id()));
+        finished.save(&path).unwrap();
+        let loaded = VMSnapshot::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded, finished);
+    }
+    
+    #[test]
+    fn test_sol_transfer_moves_lamports() {
+        let mut vm = VM::new();
+        vm.set_balance(PROGRAM_ACCOUNT, 1_000);
+        vm.set_balance(0x42, 5);
+        
+        // PUSH1 0x42, PUSH2 600, SOLTRANSFER, HALT
+        vm.load_bytecode(&[0x01, 0x42, 0x02, 0x02, 0x58, 0x50, 0xFF]).unwrap();
+        vm.run(10).unwrap();
+        assert_eq!(vm.get_balance(PROGRAM_ACCOUNT), 400);
+        assert_eq!(vm.get_balance(0x42), 605);
+        
+        // Balances survive a reset, so a second run can't afford it
+        vm.reset();
+        let err = vm.run(10).unwrap_err();
+        assert_eq!(err.to_string(), "Insufficient funds: account 0x00000000 has 400 lamports, transfer needs 600");
+        assert!(matches!(vm.state, VMState::Error(_)));
+        assert_eq!(vm.get_balance(PROGRAM_ACCOUNT), 400);
+        assert_eq!(vm.get_balance(0x42), 605);
+        assert_eq!(vm.get_balance(0x43), 0);
+    }
+    
+    #[test]
+    fn test_arithmetic_flags() {
+        let mut vm = VM::new();
+        
+        // 0x00: PUSH4 0xFFFFFFFF, PUSH1 1, ADD (wraps to 0), JUMPC 0x0D
+        // 0x0A: PUSH1 7, HALT
+        // 0x0D: PUSH1 9, HALT
+        let program = vec![
+            0x03, 0xFF, 0xFF, 0xFF, 0xFF, 0x01, 1, 0x10, 0x36, 0x0D,
+            0x01, 7, 0xFF,
+            0x01, 9, 0xFF,
+        ];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(100).unwrap();
+        assert_eq!(vm.stack, vec![0, 9]);
+        assert_eq!(vm.flags, FLAG_ZERO | FLAG_CARRY);
+        
+        // Signed overflow without a carry; JUMPZ falls through
+        // PUSH4 0x7FFFFFFF, PUSH1 1, ADD, JUMPZ 0x0D, PUSH1 7, HALT
+        vm.load_bytecode(&[0x03, 0x7F, 0xFF, 0xFF, 0xFF, 0x01, 1, 0x10, 0x37, 0x0D, 0x01, 7, 0xFF]).unwrap();
+        vm.run(100).unwrap();
+        assert_eq!(vm.stack, vec![0x8000_0000, 7]);
+        assert_eq!(vm.flags, FLAG_OVERFLOW);
+        
+        // SUB sets the carry flag on a borrow, MUL on an unsigned overflow
+        // PUSH1 1, PUSH1 2, SUB, HALT
+        vm.load_bytecode(&[0x01, 1, 0x01, 2, 0x11, 0xFF]).unwrap();
+        vm.run(100).unwrap();
+        assert_eq!(vm.flags, FLAG_CARRY);
+        // PUSH4 0x00010000, DUP 0, MUL, HALT
+        vm.load_bytecode(&[0x03, 0x00, 0x01, 0x00, 0x00, 0x05, 0, 0x12, 0xFF]).unwrap();
+        vm.run(100).unwrap();
+        assert_eq!(vm.flags, FLAG_ZERO | FLAG_CARRY | FLAG_OVERFLOW);
+        
+        vm.reset();
+        assert_eq!(vm.flags, 0);
+    }
+    
+    #[test]
+    fn test_snapshot_diff() {
+        let mut vm = VM::new();
+        
+        // PUSH1 0x2A, STORE 0x80, HALT
+        let program = vec![0x01, 0x2A, 0x41, 0x80, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        
+        vm.step().unwrap();
+        let before = vm.snapshot();
+        vm.step().unwrap();
+        let after = vm.snapshot();
+        
+        let diff = before.diff(&after);
+        assert_eq!(diff.memory, vec![(0x80, 0, 0x2A)]);
+        assert_eq!(diff.stack_popped, vec![0x2A]);
+        assert!(diff.stack_pushed.is_empty());
+        assert!(diff.registers.is_empty());
+        assert_eq!(diff.pc, Some((2, 4)));
+        
+        assert!(after.diff(&after).is_empty());
+    }
+    
+    #[test]
+    fn test_exec_counts() {
+        let mut vm = VM::new();
+        
+        // 0: PUSH1 3
+        // 2: PUSH1 1, SUB, DUP 0, JUMPIF 2 (loop until zero)
+        // 9: HALT
+        let program = vec![0x01, 3, 0x01, 1, 0x11, 0x05, 0, 0x31, 2, 0xFF];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(100).unwrap();
+        
+        assert_eq!(vm.exec_count(0), 1);
+        assert_eq!(vm.exec_count(2), 3);
+        assert_eq!(vm.exec_count(7), 3);
+        assert_eq!(vm.exec_count(9), 1);
+        assert_eq!(vm.exec_count(1), 0);
+        assert_eq!(vm.max_exec_count(), 3);
+        
+        vm.reset();
+        assert_eq!(vm.max_exec_count(), 0);
+    }
+    
+    #[test]
+    fn test_profile() {
+        let mut vm = VM::new();
+        
+        // 0: PUSH1 4
+        // 2: PUSH1 1, PUSH1 2, ADD, POP, PUSH1 1, SUB, DUP 0, JUMPIF 2
+        // 17: HALT
+        let program = vec![
+            0x01, 4,
+            0x01, 1, 0x01, 2, 0x10, 0x04, 0x01, 1, 0x11, 0x05, 0, 0x31, 2,
+            0xFF,
+        ];
+        vm.load_bytecode(&program).unwrap();
+        vm.run(100).unwrap();
+        assert!(vm.profile().is_empty());
+        
+        vm.reset();
+        vm.enable_profiling(true);
+        vm.run(100).unwrap();
+        let profile = vm.profile();
+        let add = profile.iter().find(|&&(opcode, _, _)| opcode == 0x10).unwrap();
+        assert_eq!(*add, (0x10, 4, 4 * vm.gas_schedule.cost(0x10)));
+        assert_eq!(profile.iter().find(|p| p.0 == 0x01).unwrap().1, 13);
+        assert_eq!(profile.iter().map(|p| p.2).sum::<u64>(), vm.gas_used());
+        assert!(profile.windows(2).all(|w| w[0].2 >= w[1].2));
+        
+        // Counts start over after a reset
+        vm.reset();
+        assert!(vm.profile().is_empty());
+    }
+    
+    #[test]
+    fn test_opcode_vectors() {
+        let vectors: serde_json::Value =
+            serde_json::from_str(include_str!("../../test-vectors/opcodes.json")).unwrap();
+        let values = |v: &serde_json::Value| -> Vec<u64> {
+            v.as_array().map_or(Vec::new(), |a| a.iter().map(|x| x.as_u64().unwrap()).collect())
+        };
+        
+        let mut failures = Vec::new();
+        let mut ran = 0;
+        for vector in vectors.as_array().unwrap() {
+            if vector.get("vm").is_some_and(|vm| vm != "ideless") {
+                continue;
+            }
+            let name = vector["name"].as_str().unwrap();
+            let op = vector["op"].as_str().unwrap();
+            let opcode = crate::asm::opcode_from_name(op)
+                .unwrap_or_else(|| panic!("{}: unknown opcode {}", name, op));
+            
+            let mut code = vec![opcode];
+            code.extend(values(&vector["operands"]).iter().map(|&b| b as u8));
+            code.push(0xFF);
+            
+            let mut vm = VM::new();
+            vm.load_bytecode(&code).unwrap();
+            vm.stack = values(&vector["stack"]).iter().map(|&v| v as u32).collect();
+            for region in vector["memory"].as_array().into_iter().flatten() {
+                let offset = region["offset"].as_u64().unwrap() as usize;
+                for (i, byte) in values(&region["bytes"]).into_iter().enumerate() {
+                    vm.memory[offset + i] = byte as u8;
+                }
+            }
+            
+            let result = vm.step();
+            ran += 1;
+            if vector["error"].as_bool().unwrap_or(false) {
+                if result.is_ok() {
+                    failures.push(format!("{}: expected an error", name));
+                }
+                continue;
+            }
+            if let Err(e) = result {
+                failures.push(format!("{}: unexpected error {}", name, e));
+                continue;
+            }
+            
+            let expected: Vec<u32> = values(&vector["expected_stack"]).iter().map(|&v| v as u32).collect();
+            if vm.stack != expected {
+                failures.push(format!("{}: stack {:?}, expected {:?}", name, vm.stack, expected));
+            }
+            for region in vector["expected_memory"].as_array().into_iter().flatten() {
+                let offset = region["offset"].as_u64().unwrap() as usize;
+                let bytes: Vec<u8> = values(&region["bytes"]).iter().map(|&b| b as u8).collect();
+                if vm.memory[offset..offset + bytes.len()] != bytes[..] {
+                    failures.push(format!("{}: memory at {:#X} differs", name, offset));
+                }
+            }
+            if let Some(gas) = vector["expected_gas"].as_u64() {
+                if vm.gas_used() != gas {
+                    failures.push(format!("{}: used {} gas, expected {}", name, vm.gas_used(), gas));
+                }
+            }
+        }
+        
+        assert!(ran >= 30, "only {} vectors ran", ran);
+        assert!(failures.is_empty(), "failing vectors:\n{}", failures.join("\n"));
+    }
+}
@@ -1,17 +1,32 @@
 mod asm;
+mod batch;
 mod cli;
+mod clipboard;
+mod crash;
 mod render;
 mod run;
 mod dbg;
+mod editor;
+mod explorer;
+mod files;
+mod headless;
+mod help;
+mod highlight;
 mod lessvm;
+mod memview;
+mod opt;
+mod srcmap;
+mod taint;
 
-use std::{fs, path::PathBuf, process::ExitCode};
+use std::{fs, path::{Path, PathBuf}, process::ExitCode};
 
 use anyhow::{Context, Result};
 use clap::Parser;
-use cli::{Cli, CliCommand};
+use cli::{Cli, CliCommand, OutputFormat};
+use editor::EditorSettings;
 use lessvm::VM;
 use run::{Runner, spawn_run_thread};
+use srcmap::SourceMap;
 
 fn main() -> ExitCode {
     // Initialize logger
@@ -31,19 +46,71 @@ fn main() -> ExitCode {
     }
 }
 
+/// Read a program from disk, assembling it first if it's a `.lasm` source file
+fn read_program(path: &Path) -> Result<Vec<u8>> {
+    if path.extension().is_some_and(|ext| ext == "lasm") {
+        let source = fs::read_to_string(path)
+            .context(format!("Failed to read file: {}", path.display()))?;
+        return asm::assemble(&source).map_err(|problems| {
+            let report: Vec<String> = problems
+                .iter()
+                .map(|problem| format!("{}:{}", path.display(), problem))
+                .collect();
+            anyhow::anyhow!("Failed to assemble {}:\n{}", path.display(), report.join("\n"))
+        });
+    }
+    
+    fs::read(path).context(format!("Failed to read file: {}", path.display()))
+}
+
+/// Source lines for a program: from the assembler for `.lasm` files,
+/// otherwise from a `.dbg` sidecar next to the program if there is one
+fn read_source_map(path: &Path) -> Option<SourceMap> {
+    if path.extension().is_some_and(|ext| ext == "lasm") {
+        let source = fs::read_to_string(path).ok()?;
+        let (_, lines) = asm::assemble_with_lines(&source).ok()?;
+        return Some(SourceMap::new(path.display().to_string(), lines));
+    }
+    
+    let sidecar = SourceMap::sidecar_path(path);
+    if !sidecar.exists() {
+        return None;
+    }
+    match SourceMap::load(&sidecar) {
+        Ok(map) => Some(map),
+        Err(e) => {
+            log::warn!("Ignoring source map: {:#}", e);
+            None
+        }
+    }
+}
+
 fn execute_command(command: CliCommand) -> Result<()> {
     match command {
-        CliCommand::Check { path, log, kind: _ } => {
+        CliCommand::Check { path, format, roundtrip, log, kind: _ } => {
             if let Some(log_level) = log {
                 tui_logger::set_level_for_target("ideless", log_level.to_level_filter());
             }
             
-            let bytecode = fs::read(&path)
-                .context(format!("Failed to read file: {}", path.display()))?;
+            let bytecode = read_program(&path)?;
             
-            println!("Checking LessVM bytecode file: {}", path.display());
-            let disasm = asm::Disassembler::new(bytecode);
+            if roundtrip {
+                asm::roundtrip(&bytecode)
+                    .map_err(|e| anyhow::anyhow!("Roundtrip check failed for {}: {}", path.display(), e))?;
+                if format == OutputFormat::Text {
+                    println!("Roundtrip check passed ({} bytes)", bytecode.len());
+                }
+            }
+            
+            let mut disasm = asm::Disassembler::new(bytecode);
+            disasm.run();
             
+            if format == OutputFormat::Json {
+                println!("{}", disasm.issues_json());
+                return Ok(());
+            }
+            
+            println!("Checking LessVM bytecode file: {}", path.display());
             for line in disasm.disassembled {
                 if !line.issues.is_empty() {
                     for issue in &line.issues {
@@ -56,33 +123,73 @@ fn execute_command(command: CliCommand) -> Result<()> {
             Ok(())
         },
         
-        CliCommand::Dasm { path, log, kind: _ } => {
+        CliCommand::Dasm { path, optimize, cfg, format, log, kind: _ } => {
             if let Some(log_level) = log {
                 tui_logger::set_level_for_target("ideless", log_level.to_level_filter());
             }
             
-            let bytecode = fs::read(&path)
-                .context(format!("Failed to read file: {}", path.display()))?;
+            let mut bytecode = read_program(&path)?;
+            if optimize {
+                bytecode = opt::optimize(&bytecode);
+            }
+            
+            if format == OutputFormat::Json {
+                println!("{}", asm::Program::from_bytecode(&bytecode).to_json());
+            } else {
+                println!("Disassembling LessVM bytecode file: {}", path.display());
+            }
             
-            println!("Disassembling LessVM bytecode file: {}", path.display());
             let mut disasm = asm::Disassembler::new(bytecode);
             disasm.run();
             
-            println!("{}", disasm);
+            if format == OutputFormat::Text {
+                println!("{}", disasm);
+            }
+            
+            if let Some(cfg_path) = cfg {
+                let graph = disasm.build_cfg();
+                fs::write(&cfg_path, graph.to_dot(&disasm))
+                    .with_context(|| format!("Failed to write {}", cfg_path.display()))?;
+                if format == OutputFormat::Text {
+                    println!("Wrote control-flow graph ({} blocks, {} edges) to {}",
+                             graph.blocks.len(), graph.edges.len(), cfg_path.display());
+                }
+            }
             Ok(())
         },
         
-        CliCommand::Run { path, debug, cpf, hz, log, kind: _, rpc_url: _, network: _ } => {
+        CliCommand::Run { path, debug, optimize, headless, max_cycles, format, gas_limit, profile, cpf, hz, tab_width, hard_tabs, log, kind: _, rpc_url: _, network: _ } => {
             if let Some(log_level) = log {
                 tui_logger::set_level_for_target("ideless", log_level.to_level_filter());
             }
             
-            let bytecode = fs::read(&path)
-                .context(format!("Failed to read file: {}", path.display()))?;
+            let mut bytecode = read_program(&path)?;
+            if optimize {
+                bytecode = opt::optimize(&bytecode);
+            }
             
             // Create VM and load bytecode
             let mut vm = VM::new();
             vm.load_bytecode(&bytecode)?;
+            if let Some(limit) = gas_limit {
+                vm.set_gas_limit(limit);
+            }
+            
+            if headless {
+                vm.enable_profiling(profile);
+                let report = headless::run(&mut vm, max_cycles);
+                match format {
+                    OutputFormat::Text => println!("{}", report),
+                    OutputFormat::Json => println!("{}", report.to_json()),
+                }
+                if report.out_of_gas {
+                    return Err(anyhow::anyhow!("Out of gas after {} cycles ({} gas used)", report.cycles, report.gas_used));
+                }
+                if let Some(error) = report.error {
+                    return Err(anyhow::anyhow!("VM error: {}", error));
+                }
+                return Ok(());
+            }
             
             // Set cycles per frame if specified
             let cycles_per_frame = cpf.unwrap_or_else(|| {
@@ -95,7 +202,12 @@ fn execute_command(command: CliCommand) -> Result<()> {
             
             // Create debugger if needed
             let debugger = if debug {
-                Some(dbg::Debugger::new(&vm, cycles_per_frame))
+                let mut debugger = dbg::Debugger::new(&vm, cycles_per_frame);
+                // Optimizing moves instructions, so the source lines no longer match
+                if !optimize {
+                    debugger.set_source_map(read_source_map(&path));
+                }
+                Some(debugger)
             } else {
                 None
             };
@@ -104,8 +216,11 @@ fn execute_command(command: CliCommand) -> Result<()> {
             let runner = Runner::new_lessvm(vm, debugger);
             
             // Create render controller
+            let source = path.extension().is_some_and(|ext| ext == "lasm").then_some(path.as_path());
             let (render_controller, render_thread) = render::spawn_render_thread(
                 runner.lessvm(),
+                source,
+                EditorSettings { tab_width, hard_tabs },
                 log.is_some()
             );
             
@@ -123,6 +238,38 @@ fn execute_command(command: CliCommand) -> Result<()> {
             Ok(())
         },
         
+        CliCommand::Batch { paths, max_cycles, gas_limit, format, log } => {
+            if let Some(log_level) = log {
+                tui_logger::set_level_for_target("ideless", log_level.to_level_filter());
+            }
+            
+            let programs = paths.iter().map(|path| read_program(path)).collect::<Result<Vec<_>>>()?;
+            let opts = batch::BatchOptions { max_cycles, gas_limit: gas_limit.unwrap_or(0) };
+            let outcomes = batch::run_batch(&programs, &opts);
+            
+            match format {
+                OutputFormat::Text => {
+                    for (path, outcome) in paths.iter().zip(&outcomes) {
+                        println!("{}: {}", path.display(), outcome);
+                    }
+                }
+                OutputFormat::Json => {
+                    let reports = paths.iter().zip(&outcomes).map(|(path, outcome)| {
+                        let mut report = outcome.to_json();
+                        report["path"] = serde_json::json!(path.display().to_string());
+                        report
+                    }).collect::<Vec<_>>();
+                    println!("{}", serde_json::Value::Array(reports));
+                }
+            }
+            
+            let failed = outcomes.iter().filter(|outcome| outcome.error.is_some()).count();
+            if failed > 0 {
+                return Err(anyhow::anyhow!("{} of {} programs failed", failed, outcomes.len()));
+            }
+            Ok(())
+        },
+        
         CliCommand::RunSol { address, debug, cpf, hz, log, rpc_url, network } => {
             println!("Running Solana program from address: {}", address);
             println!("This feature is not yet implemented");
@@ -170,20 +317,40 @@ fn execute_command(command: CliCommand) -> Result<()> {
             Ok(())
         },
         
-        CliCommand::Analyze { path, detailed, log } => {
+        CliCommand::Analyze { path, detailed, taint, gas_limit, format, log } => {
             if let Some(log_level) = log {
                 tui_logger::set_level_for_target("ideless", log_level.to_level_filter());
             }
             
-            let bytecode = fs::read(&path)
-                .context(format!("Failed to read file: {}", path.display()))?;
+            let bytecode = read_program(&path)?;
             
-            println!("Analyzing compute units for: {}", path.display());
             let mut disasm = asm::Disassembler::new(bytecode);
             disasm.run();
             
             let total_cu = disasm.analyze_compute_units();
+            let over_limit = gas_limit.filter(|&limit| total_cu > limit);
+            
+            if format == OutputFormat::Json {
+                let mut report = disasm.cu_analysis_json();
+                if let Some(limit) = gas_limit {
+                    report["gas_limit"] = serde_json::json!(limit);
+                    report["exceeds_gas_limit"] = serde_json::json!(over_limit.is_some());
+                }
+                if taint {
+                    let warnings: Vec<String> = taint::analyze(&disasm).iter()
+                        .map(ToString::to_string)
+                        .collect();
+                    report["taint_warnings"] = serde_json::json!(warnings);
+                }
+                println!("{}", report);
+                return Ok(());
+            }
+            
+            println!("Analyzing compute units for: {}", path.display());
             println!("Total compute units: {}", total_cu);
+            if let Some(limit) = over_limit {
+                println!("warning: estimated {} compute units exceed the gas limit of {}", total_cu, limit);
+            }
             
             if detailed {
                 println!("\nDetailed breakdown:");
@@ -196,6 +363,17 @@ fn execute_command(command: CliCommand) -> Result<()> {
                 }
             }
             
+            if taint {
+                let warnings = taint::analyze(&disasm);
+                println!("\nTaint analysis:");
+                if warnings.is_empty() {
+                    println!("No transfer accounts taken from untrusted input");
+                }
+                for warning in warnings {
+                    println!("warning: {}", warning);
+                }
+            }
+            
             Ok(())
         },
         
@@ -0,0 +1,139 @@
+use std::collections::BTreeSet;
+
+use crossterm::event::KeyCode;
+
+/// Bytes per row of the memory tab
+pub const ROW_BYTES: usize = 16;
+
+/// Selection and hex editing on the memory tab.
+///
+/// The render thread only has a copy of VM memory, so edits are queued as
+/// `(address, byte)` writes for the run thread to apply with
+/// `VM::write_memory`.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryViewState {
+    /// Selected byte, or `None` to follow the PC
+    pub selected_address: Option<usize>,
+
+    /// Whether hex digits overwrite the selected byte
+    pub editing: bool,
+
+    /// First digit typed for the selected byte, waiting for the second
+    pub pending_nibble: Option<u8>,
+
+    /// Addresses written since the view was created
+    pub dirty: BTreeSet<usize>,
+
+    /// Writes not yet taken by `take_writes`
+    writes: Vec<(usize, u8)>,
+}
+
+impl MemoryViewState {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether any byte has been edited
+    pub fn is_dirty(&self) -> bool {
+        !self.dirty.is_empty()
+    }
+
+    /// Writes made since the last call, oldest first
+    pub fn take_writes(&mut self) -> Vec<(usize, u8)> {
+        std::mem::take(&mut self.writes)
+    }
+
+    /// Handle a key on the memory tab, editing `memory` in place. Returns
+    /// whether the key was used; in edit mode every key but Tab is, so
+    /// typed digits don't reach the debugger.
+    pub fn handle_key(&mut self, key: KeyCode, memory: &mut [u8], pc: usize) -> bool {
+        if memory.is_empty() {
+            return false;
+        }
+        let selected = self.selected_address.unwrap_or(pc).min(memory.len() - 1);
+
+        match key {
+            KeyCode::Left => self.select(selected.saturating_sub(1)),
+            KeyCode::Right => self.select((selected + 1).min(memory.len() - 1)),
+            KeyCode::Up => self.select(selected.saturating_sub(ROW_BYTES)),
+            KeyCode::Down => self.select((selected + ROW_BYTES).min(memory.len() - 1)),
+            KeyCode::Char('e') if !self.editing => {
+                self.editing = true;
+                self.selected_address = Some(selected);
+            }
+            KeyCode::Esc if self.editing => {
+                self.editing = false;
+                self.pending_nibble = None;
+            }
+            KeyCode::Char(c) if self.editing => {
+                let Some(digit) = c.to_digit(16) else {
+                    return true;
+                };
+                let Some(high) = self.pending_nibble.take() else {
+                    self.pending_nibble = Some(digit as u8);
+                    return true;
+                };
+                let byte = (high << 4) | digit as u8;
+                memory[selected] = byte;
+                self.dirty.insert(selected);
+                self.writes.push((selected, byte));
+                self.selected_address = Some((selected + 1).min(memory.len() - 1));
+            }
+            KeyCode::Tab | KeyCode::BackTab => return false,
+            _ => return self.editing,
+        }
+        true
+    }
+
+    /// Move the selection, dropping a half-typed byte
+    fn select(&mut self, address: usize) {
+        self.selected_address = Some(address);
+        self.pending_nibble = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lessvm::VM;
+
+    #[test]
+    fn test_edit_byte() {
+        let mut vm = VM::new();
+        vm.load_bytecode(&[0x01, 0x2A, 0xFF]).unwrap();
+        let mut memory = vm.get_memory().to_vec();
+        let mut view = MemoryViewState::new();
+        let mut press = |view: &mut MemoryViewState, key: KeyCode| view.handle_key(key, &mut memory, 0);
+
+        // Digits only edit in edit mode
+        assert!(!press(&mut view, KeyCode::Char('7')));
+        assert!(press(&mut view, KeyCode::Right));
+        assert!(press(&mut view, KeyCode::Char('e')));
+        assert!(press(&mut view, KeyCode::Char('b')));
+        assert!(!view.is_dirty());
+        assert!(press(&mut view, KeyCode::Char('E')));
+        assert!(view.is_dirty());
+        assert_eq!(view.selected_address, Some(2));
+
+        // Moving away drops a half-typed byte, and non-hex keys are swallowed
+        press(&mut view, KeyCode::Char('1'));
+        press(&mut view, KeyCode::Down);
+        assert_eq!(view.pending_nibble, None);
+        assert!(press(&mut view, KeyCode::Char('x')));
+        assert!(press(&mut view, KeyCode::Up));
+        assert_eq!(view.selected_address, Some(2));
+        assert!(press(&mut view, KeyCode::Esc));
+        assert!(!view.editing);
+
+        let writes = view.take_writes();
+        assert_eq!(writes, vec![(1, 0xBE)]);
+        assert!(view.take_writes().is_empty());
+        assert_eq!(memory[1], 0xBE);
+
+        for (address, byte) in writes {
+            vm.write_memory(address, byte).unwrap();
+        }
+        assert_eq!(&vm.get_memory()[..3], &[0x01, 0xBE, 0xFF]);
+        assert!(vm.write_memory(vm.get_memory().len(), 0).is_err());
+    }
+}
@@ -0,0 +1,742 @@
+//! Bytecode optimization passes for LessVM programs.
+//!
+//! Passes work on decoded instructions and re-encode the program afterwards,
+//! relocating jump targets to account for removed bytes. Programs whose
+//! control flow can't be resolved statically are returned unchanged.
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use crate::asm::{opcode_name, opcode_param_count, opcode_stack_counts};
+
+const PUSH1: u8 = 0x01;
+const PUSH2: u8 = 0x02;
+const PUSH4: u8 = 0x03;
+const POP: u8 = 0x04;
+const DUP: u8 = 0x05;
+const SWAP: u8 = 0x06;
+const ADD: u8 = 0x10;
+const SUB: u8 = 0x11;
+const MUL: u8 = 0x12;
+const DIV: u8 = 0x13;
+const MOD: u8 = 0x14;
+const AND: u8 = 0x20;
+const OR: u8 = 0x21;
+const XOR: u8 = 0x22;
+const NOT: u8 = 0x23;
+const LT: u8 = 0x24;
+const GT: u8 = 0x25;
+const EQ: u8 = 0x26;
+const ISZERO: u8 = 0x27;
+const RETURN: u8 = 0x33;
+const LOAD: u8 = 0x40;
+const STORE: u8 = 0x41;
+const LOADF: u8 = 0x71;
+const STOREF: u8 = 0x72;
+const HALT: u8 = 0xFF;
+
+/// Number of floating point registers in the VM
+const FP_REGISTERS: u8 = 8;
+const JUMP: u8 = 0x30;
+const JUMPIF: u8 = 0x31;
+const CALL: u8 = 0x32;
+const JUMP2: u8 = 0x34;
+const JUMPIF2: u8 = 0x35;
+const JUMPC: u8 = 0x36;
+const JUMPO: u8 = 0x38;
+
+/// A decoded instruction
+#[derive(Debug, Clone, PartialEq)]
+struct Op {
+    /// Offset in the original bytecode
+    offset: usize,
+    opcode: u8,
+    operands: Vec<u8>,
+    /// PUSH whose value is the destination of the following JUMP/CALL
+    jump_ref: bool,
+}
+
+impl Op {
+    /// Value pushed by a PUSH1/PUSH2/PUSH4 instruction
+    fn push_value(&self) -> Option<u32> {
+        match self.opcode {
+            PUSH1 | PUSH2 | PUSH4 => Some(
+                self.operands
+                    .iter()
+                    .fold(0u32, |acc, &b| (acc << 8) | b as u32),
+            ),
+            _ => None,
+        }
+    }
+
+    fn size(&self) -> usize {
+        1 + self.operands.len()
+    }
+
+    /// Smallest PUSH instruction for `value`, placed at `offset`
+    fn push(offset: usize, value: u32) -> Self {
+        let (opcode, width) = if value <= 0xFF {
+            (PUSH1, 1)
+        } else if value <= 0xFFFF {
+            (PUSH2, 2)
+        } else {
+            (PUSH4, 4)
+        };
+        Op {
+            offset,
+            opcode,
+            operands: value.to_be_bytes()[4 - width..].to_vec(),
+            jump_ref: false,
+        }
+    }
+}
+
+/// A program decoded into instructions, with its static jump targets
+struct Program {
+    ops: Vec<Op>,
+    targets: HashSet<usize>,
+}
+
+impl Program {
+    /// Decode `bytecode`, or `None` if it contains unknown opcodes, flag
+    /// jumps, truncated instructions or jumps whose destination isn't a
+    /// constant
+    fn decode(bytecode: &[u8]) -> Option<Self> {
+        let mut ops: Vec<Op> = Vec::new();
+        let mut offset = 0;
+
+        while offset < bytecode.len() {
+            let opcode = bytecode[offset];
+            // Folding or dropping arithmetic would change the flags that
+            // JUMPC, JUMPZ and JUMPO branch on
+            if opcode_name(opcode).starts_with("UNKNOWN") || (JUMPC..=JUMPO).contains(&opcode) {
+                return None;
+            }
+            let end = offset + 1 + opcode_param_count(opcode);
+            if end > bytecode.len() {
+                return None;
+            }
+            ops.push(Op {
+                offset,
+                opcode,
+                operands: bytecode[offset + 1..end].to_vec(),
+                jump_ref: false,
+            });
+            offset = end;
+        }
+
+        let mut targets = HashSet::new();
+        for i in 0..ops.len() {
+            let target = match ops[i].opcode {
+                JUMPIF => ops[i].operands[0] as usize,
+                JUMP2 | JUMPIF2 => {
+                    u16::from_le_bytes([ops[i].operands[0], ops[i].operands[1]]) as usize
+                }
+                JUMP | CALL => {
+                    // Only destinations pushed immediately before the jump are known
+                    let push = i.checked_sub(1).map(|p| &mut ops[p])?;
+                    let target = push.push_value()? as usize;
+                    push.jump_ref = true;
+                    target
+                }
+                _ => continue,
+            };
+            targets.insert(target);
+        }
+
+        // Every destination must be an instruction boundary or the end
+        let boundaries: HashSet<usize> = ops.iter().map(|op| op.offset).collect();
+        if !targets.iter().all(|t| boundaries.contains(t) || *t == bytecode.len()) {
+            return None;
+        }
+
+        Some(Program { ops, targets })
+    }
+
+    /// Whether execution can enter at `op` other than by falling through
+    fn is_target(&self, op: &Op) -> bool {
+        self.targets.contains(&op.offset)
+    }
+
+    /// Split the program into basic blocks, as ranges of instruction indices
+    fn basic_blocks(&self) -> Vec<Range<usize>> {
+        let mut blocks = Vec::new();
+        let mut start = 0;
+        for (i, op) in self.ops.iter().enumerate() {
+            if i > start && self.is_target(op) {
+                blocks.push(start..i);
+                start = i;
+            }
+            if matches!(op.opcode, JUMP | JUMPIF | JUMP2 | JUMPIF2 | CALL | RETURN | HALT) {
+                blocks.push(start..i + 1);
+                start = i + 1;
+            }
+        }
+        if start < self.ops.len() {
+            blocks.push(start..self.ops.len());
+        }
+        blocks
+    }
+
+    /// Indices of the blocks that can follow `blocks[b]`, or `None` if control
+    /// may leave the program or go somewhere not known statically
+    fn successors(&self, blocks: &[Range<usize>], b: usize) -> Option<Vec<usize>> {
+        let block_at = |offset: usize| {
+            blocks.iter().position(|r| self.ops[r.start].offset == offset)
+        };
+        let last = blocks[b].end - 1;
+        let op = &self.ops[last];
+        let next = || (b + 1 < blocks.len()).then_some(b + 1);
+
+        match op.opcode {
+            JUMP => {
+                let target = self.ops[last - 1].push_value()? as usize;
+                Some(vec![block_at(target)?])
+            }
+            JUMP2 => {
+                let target = u16::from_le_bytes([op.operands[0], op.operands[1]]) as usize;
+                Some(vec![block_at(target)?])
+            }
+            JUMPIF => Some(vec![block_at(op.operands[0] as usize)?, next()?]),
+            JUMPIF2 => {
+                let target = u16::from_le_bytes([op.operands[0], op.operands[1]]) as usize;
+                Some(vec![block_at(target)?, next()?])
+            }
+            CALL | RETURN | HALT => None,
+            _ => Some(vec![next()?]),
+        }
+    }
+
+    /// Lower bound on the stack depth before each instruction, for a run
+    /// that starts at offset 0 with an empty stack
+    fn min_stack_depths(&self) -> Vec<usize> {
+        let blocks = self.basic_blocks();
+        let mut entry_depths: Vec<Option<usize>> = vec![None; blocks.len()];
+        if let Some(first) = entry_depths.first_mut() {
+            *first = Some(0);
+        }
+        // A return lands after its CALL with whatever the callee left behind
+        for (b, range) in blocks.iter().enumerate().skip(1) {
+            if self.ops[range.start - 1].opcode == CALL {
+                entry_depths[b] = Some(0);
+            }
+        }
+
+        let mut depths = vec![0; self.ops.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (b, range) in blocks.iter().enumerate() {
+                let Some(mut depth) = entry_depths[b] else {
+                    continue;
+                };
+                for i in range.clone() {
+                    depths[i] = depth;
+                    depth = depth_after(&self.ops[i], depth);
+                }
+                for s in self.successors(&blocks, b).unwrap_or_default() {
+                    let merged = entry_depths[s].map_or(depth, |d| d.min(depth));
+                    if entry_depths[s] != Some(merged) {
+                        entry_depths[s] = Some(merged);
+                        changed = true;
+                    }
+                }
+            }
+        }
+        depths
+    }
+
+    /// Re-encode the program, relocating jump destinations
+    fn encode(&self) -> Vec<u8> {
+        // New offset of every kept instruction, keyed by its original offset
+        let mut new_offsets = Vec::with_capacity(self.ops.len());
+        let mut offset = 0;
+        for op in &self.ops {
+            new_offsets.push((op.offset, offset));
+            offset += op.size();
+        }
+        let new_len = offset;
+
+        // A removed destination moves to the next surviving instruction
+        let relocate = |old: usize| -> usize {
+            new_offsets
+                .iter()
+                .find(|(orig, _)| *orig >= old)
+                .map_or(new_len, |(_, new)| *new)
+        };
+
+        let mut out = Vec::with_capacity(new_len);
+        for op in &self.ops {
+            out.push(op.opcode);
+            if op.opcode == JUMPIF {
+                out.push(relocate(op.operands[0] as usize) as u8);
+            } else if op.opcode == JUMP2 || op.opcode == JUMPIF2 {
+                let old = u16::from_le_bytes([op.operands[0], op.operands[1]]) as usize;
+                out.extend_from_slice(&(relocate(old) as u16).to_le_bytes());
+            } else if op.jump_ref {
+                let target = relocate(op.push_value().unwrap_or(0) as usize) as u32;
+                let width = op.operands.len();
+                for i in (0..width).rev() {
+                    out.push((target >> (8 * i)) as u8);
+                }
+            } else {
+                out.extend_from_slice(&op.operands);
+            }
+        }
+        out
+    }
+}
+
+/// Lower bound on the stack depth after `op` runs, given one for before it.
+/// An instruction that would underflow faults, so getting past it means
+/// the stack held at least the values it pops.
+fn depth_after(op: &Op, depth: usize) -> usize {
+    match op.opcode {
+        DUP => depth.max(op.operands[0] as usize + 1) + 1,
+        SWAP => depth.max(op.operands[0] as usize + 1),
+        _ => match opcode_stack_counts(op.opcode) {
+            Some((pops, pushes)) => depth.max(pops) - pops + pushes,
+            None => 0,
+        },
+    }
+}
+
+/// Run all optimization passes until the program stops shrinking
+pub fn optimize(bytecode: &[u8]) -> Vec<u8> {
+    let mut current = bytecode.to_vec();
+    loop {
+        let next = fold_constants(&peephole(&eliminate_dead_stores(&current)));
+        if next.len() >= current.len() {
+            return current;
+        }
+        current = next;
+    }
+}
+
+/// Remove instruction pairs that have no effect:
+/// `PUSH1 0; ADD`, `PUSHn x; POP` and `DUP n; POP`
+///
+/// `PUSH1 0; ADD` and `DUP n; POP` fault on a stack that is too shallow, so
+/// they are only removed where the stack is known to be deep enough.
+/// `PUSHn x; POP` can't fault, as the VM's stack has no size limit.
+pub fn peephole(bytecode: &[u8]) -> Vec<u8> {
+    let mut program = match Program::decode(bytecode) {
+        Some(program) => program,
+        None => return bytecode.to_vec(),
+    };
+    let depths = program.min_stack_depths();
+
+    let mut kept = Vec::with_capacity(program.ops.len());
+    let mut i = 0;
+    while i < program.ops.len() {
+        if let Some(next) = program.ops.get(i + 1) {
+            let op = &program.ops[i];
+            let redundant = match (op.opcode, next.opcode) {
+                (PUSH1, ADD) => op.push_value() == Some(0) && depths[i] >= 1,
+                (PUSH1 | PUSH2 | PUSH4, POP) => true,
+                (DUP, POP) => depths[i] > op.operands[0] as usize,
+                _ => false,
+            };
+
+            // Jumping into the second instruction would observe the first one
+            if redundant && !op.jump_ref && !program.is_target(next) {
+                i += 2;
+                continue;
+            }
+        }
+        kept.push(program.ops[i].clone());
+        i += 1;
+    }
+
+    program.ops = kept;
+    program.encode()
+}
+
+/// Evaluate arithmetic on constant operands at compile time, so that
+/// `PUSH1 2; PUSH1 3; ADD` becomes `PUSH1 5`.
+///
+/// Constants are tracked with a simulated stack over straight-line code; a
+/// sequence is only folded when nothing can jump into its middle.
+pub fn fold_constants(bytecode: &[u8]) -> Vec<u8> {
+    let mut program = match Program::decode(bytecode) {
+        Some(program) => program,
+        None => return bytecode.to_vec(),
+    };
+
+    // Output doubles as the simulated stack: trailing PUSHes are known values
+    let mut kept: Vec<Op> = Vec::with_capacity(program.ops.len());
+    for op in program.ops.iter() {
+        let n = kept.len();
+        let folded = match op.opcode {
+            NOT | ISZERO if n >= 1 && !program.is_target(op) => {
+                let a = &kept[n - 1];
+                match a.push_value() {
+                    Some(v) if !a.jump_ref => Some((1, if op.opcode == NOT { !v } else { (v == 0) as u32 })),
+                    _ => None,
+                }
+            }
+            ADD | SUB | MUL | DIV | MOD | AND | OR | XOR | LT | GT | EQ if n >= 2 && !program.is_target(op) => {
+                let (a, b) = (&kept[n - 2], &kept[n - 1]);
+                match (a.push_value(), b.push_value()) {
+                    (Some(x), Some(y)) if !a.jump_ref && !b.jump_ref && !program.is_target(b) => {
+                        match op.opcode {
+                            ADD => Some((2, x.wrapping_add(y))),
+                            SUB => Some((2, x.wrapping_sub(y))),
+                            MUL => Some((2, x.wrapping_mul(y))),
+                            // Leave division by zero to the VM
+                            DIV if y != 0 => Some((2, x / y)),
+                            MOD if y != 0 => Some((2, x % y)),
+                            AND => Some((2, x & y)),
+                            OR => Some((2, x | y)),
+                            XOR => Some((2, x ^ y)),
+                            LT => Some((2, (x < y) as u32)),
+                            GT => Some((2, (x > y) as u32)),
+                            EQ => Some((2, (x == y) as u32)),
+                            _ => None,
+                        }
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        match folded {
+            Some((operands, value)) => {
+                // The folded PUSH takes the place of the first operand
+                let offset = kept[n - operands].offset;
+                kept.truncate(n - operands);
+                kept.push(Op::push(offset, value));
+            }
+            None => kept.push(op.clone()),
+        }
+    }
+
+    program.ops = kept;
+    program.encode()
+}
+
+/// A storage location a STORE-like instruction can write
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Location {
+    Mem(u8),
+    FpReg(u8),
+}
+
+/// How an instruction interacts with memory and floating point registers
+enum Access {
+    /// Touches neither
+    None,
+    /// Overwrites a single location
+    Write(Location),
+    /// Reads the given locations
+    Read(Vec<Location>),
+    /// May read any memory
+    ReadMemory,
+    /// Effect unknown; assume it reads everything
+    Unknown,
+}
+
+fn access(op: &Op) -> Access {
+    match op.opcode {
+        STORE => Access::Write(Location::Mem(op.operands[0])),
+        STOREF if op.operands[0] < FP_REGISTERS => Access::Write(Location::FpReg(op.operands[0])),
+        LOAD => Access::Read(vec![Location::Mem(op.operands[0])]),
+        LOADF => Access::Read(vec![Location::FpReg(op.operands[0])]),
+        // ADDF/SUBF/MULF/DIVF read both the destination and source register
+        0x73..=0x76 => Access::Read(vec![
+            Location::FpReg((op.operands[0] >> 4) & 0x7),
+            Location::FpReg(op.operands[0] & 0x7),
+        ]),
+        // LOADW reads four bytes, and VLOAD, CRYPTOHASH and JSONPARSE read
+        // memory at runtime addresses
+        0x44 | 0x60 | 0x90 | 0xC0 => Access::ReadMemory,
+        // STOREW and VSTORE aren't tracked, which never makes an earlier
+        // store dead
+        0x45 | 0x61 => Access::None,
+        0x01..=0x06 | 0x10..=0x14 | 0x20..=0x27 | 0x30..=0x35 | 0x52 |
+        0x62..=0x66 | 0x70 | 0x80..=0x82 | 0xA0 | 0xA1 | 0xB0..=0xB3 | 0xB8 | 0xB9 |
+        0xD0..=0xD2 | 0xE0 | 0xFF => Access::None,
+        _ => Access::Unknown,
+    }
+}
+
+/// Walk `ops` backwards from `dead`, the set of locations overwritten before
+/// being read after the block, returning the set at block entry. Dead stores
+/// found along the way are reported through `on_dead`.
+fn dead_before(ops: &[Op], mut dead: HashSet<Location>, mut on_dead: impl FnMut(usize)) -> HashSet<Location> {
+    for (i, op) in ops.iter().enumerate().rev() {
+        match access(op) {
+            Access::None => {}
+            Access::Write(loc) => {
+                if dead.contains(&loc) {
+                    on_dead(i);
+                }
+                dead.insert(loc);
+            }
+            Access::Read(locs) => {
+                for loc in locs {
+                    dead.remove(&loc);
+                }
+            }
+            Access::ReadMemory => dead.retain(|loc| matches!(loc, Location::FpReg(_))),
+            Access::Unknown => dead.clear(),
+        }
+    }
+    dead
+}
+
+/// Replace STORE/STOREF instructions whose value is overwritten before it can
+/// be read with POPs that discard the stored value.
+///
+/// Liveness is computed over basic blocks; control leaving the program and
+/// any instruction with unknown memory effects make every location live.
+/// Programs that store into their own bytes are returned unchanged.
+pub fn eliminate_dead_stores(bytecode: &[u8]) -> Vec<u8> {
+    let mut program = match Program::decode(bytecode) {
+        Some(program) => program,
+        None => return bytecode.to_vec(),
+    };
+
+    // The program is loaded at address 0 and read by running it, which the
+    // liveness below doesn't see, so stores that may land in it are left alone
+    let writes_code = program.ops.iter().any(|op| {
+        matches!(access(op), Access::Write(Location::Mem(addr)) if (addr as usize) < bytecode.len())
+    });
+    if writes_code {
+        return bytecode.to_vec();
+    }
+
+    let blocks = program.basic_blocks();
+    let successors: Vec<_> = (0..blocks.len())
+        .map(|b| program.successors(&blocks, b))
+        .collect();
+
+    // Start every block from "everything stored is dead" and shrink to a fixpoint
+    let universe: HashSet<Location> = program.ops.iter()
+        .filter_map(|op| match access(op) {
+            Access::Write(loc) => Some(loc),
+            _ => None,
+        })
+        .collect();
+    let mut dead_in = vec![universe.clone(); blocks.len()];
+    let dead_out = |dead_in: &[HashSet<Location>], b: usize| -> HashSet<Location> {
+        match &successors[b] {
+            Some(succ) => succ
+                .iter()
+                .map(|&s| dead_in[s].clone())
+                .reduce(|a, b| a.intersection(&b).copied().collect())
+                .unwrap_or_default(),
+            None => HashSet::new(),
+        }
+    };
+
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for b in (0..blocks.len()).rev() {
+            let out = dead_out(&dead_in, b);
+            let entry = dead_before(&program.ops[blocks[b].clone()], out, |_| {});
+            if entry != dead_in[b] {
+                dead_in[b] = entry;
+                changed = true;
+            }
+        }
+    }
+
+    let mut dead_stores = HashSet::new();
+    for (b, range) in blocks.iter().enumerate() {
+        let out = dead_out(&dead_in, b);
+        dead_before(&program.ops[range.clone()], out, |i| {
+            dead_stores.insert(range.start + i);
+        });
+    }
+    if dead_stores.is_empty() {
+        return bytecode.to_vec();
+    }
+
+    let mut kept = Vec::with_capacity(program.ops.len());
+    for (i, op) in program.ops.iter().enumerate() {
+        if !dead_stores.contains(&i) {
+            kept.push(op.clone());
+            continue;
+        }
+        // STORE pops one value, STOREF pops the two halves of an f64
+        let pops = if op.opcode == STOREF { 2 } else { 1 };
+        for _ in 0..pops {
+            kept.push(Op { offset: op.offset, opcode: POP, operands: Vec::new(), jump_ref: false });
+        }
+    }
+
+    program.ops = kept;
+    program.encode()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lessvm::VM;
+
+    /// Run a program to completion and return its final stack
+    fn run(bytecode: &[u8]) -> Vec<u32> {
+        let mut vm = VM::new();
+        vm.load_bytecode(bytecode).unwrap();
+        vm.run(1000).unwrap();
+        vm.stack.clone()
+    }
+
+    #[test]
+    fn test_peephole_patterns() {
+        // PUSH1 7, PUSH1 0, ADD, HALT
+        let program = vec![0x01, 7, 0x01, 0, 0x10, 0xFF];
+        assert_eq!(peephole(&program), vec![0x01, 7, 0xFF]);
+
+        // PUSH1 7, PUSH2 0x1234, POP, HALT
+        let program = vec![0x01, 7, 0x02, 0x12, 0x34, 0x04, 0xFF];
+        assert_eq!(peephole(&program), vec![0x01, 7, 0xFF]);
+
+        // PUSH1 7, DUP 0, POP, HALT
+        let program = vec![0x01, 7, 0x05, 0, 0x04, 0xFF];
+        assert_eq!(peephole(&program), vec![0x01, 7, 0xFF]);
+
+        // PUSH1 1, ADD is not a no-op
+        let program = vec![0x01, 7, 0x01, 1, 0x10, 0xFF];
+        assert_eq!(peephole(&program), program);
+    }
+
+    #[test]
+    fn test_peephole_keeps_underflows() {
+        // PUSH1 0, ADD, HALT: the ADD underflows
+        let program = vec![0x01, 0, 0x10, 0xFF];
+        assert_eq!(peephole(&program), program);
+
+        // PUSH1 7, DUP 1, POP, HALT: the DUP underflows
+        let program = vec![0x01, 7, 0x05, 1, 0x04, 0xFF];
+        assert_eq!(peephole(&program), program);
+
+        // PUSH1 7, PUSH1 1, JUMPIF 7, POP, 7: PUSH1 0, ADD, HALT: the jump
+        // reaches the ADD with one value, falling through with none
+        let program = vec![0x01, 7, 0x01, 1, 0x31, 7, 0x04, 0x01, 0, 0x10, 0xFF];
+        assert_eq!(peephole(&program), program);
+
+        // Values left by a callee aren't counted on:
+        // PUSH1 6, CALL, PUSH1 0, ADD, 6: HALT
+        let program = vec![0x01, 6, 0x32, 0x01, 0, 0x10, 0xFF];
+        assert_eq!(peephole(&program), program);
+    }
+
+    #[test]
+    fn test_optimize_preserves_semantics() {
+        // PUSH1 3, PUSH1 0, ADD, PUSH1 1, JUMPIF skip, PUSH1 9, POP,
+        // skip: DUP 0, POP, PUSH1 4, ADD, HALT
+        let program = vec![
+            0x01, 3, 0x01, 0, 0x10, 0x01, 1, 0x31, 13,
+            0x01, 9, 0x04, 0xFF,
+            0x05, 0, 0x04, 0x01, 4, 0x10, 0xFF,
+        ];
+        let optimized = optimize(&program);
+
+        assert!(optimized.len() < program.len());
+        assert_eq!(run(&optimized), run(&program));
+        assert_eq!(run(&optimized), vec![7]);
+    }
+
+    #[test]
+    fn test_peephole_keeps_jump_targets() {
+        // PUSH1 5, JUMP, PUSH1 1, POP(target), HALT: the POP is a jump target
+        let program = vec![0x01, 5, 0x30, 0x01, 1, 0x04, 0xFF];
+        assert_eq!(peephole(&program), program);
+
+        // JUMP2 destinations are relocated past removed instructions:
+        // PUSH1 1, JUMP2 7, PUSH1 1, PUSH1 0(target), ADD, HALT
+        let program = vec![0x01, 1, 0x34, 7, 0, 0x01, 1, 0x01, 0, 0x10, 0xFF];
+        assert_eq!(peephole(&program), vec![0x01, 1, 0x34, 7, 0, 0x01, 1, 0xFF]);
+
+        // Computed jumps are left alone entirely
+        let program = vec![0x01, 2, 0x01, 3, 0x10, 0x30, 0x01, 0, 0x10, 0xFF];
+        assert_eq!(peephole(&program), program);
+    }
+
+    #[test]
+    fn test_fold_constants() {
+        // PUSH1 2, PUSH1 3, ADD, PUSH1 4, MUL, HALT
+        let program = vec![0x01, 2, 0x01, 3, 0x10, 0x01, 4, 0x12, 0xFF];
+        let folded = fold_constants(&program);
+        assert_eq!(folded, vec![0x01, 20, 0xFF]);
+        assert_eq!(run(&folded), run(&program));
+
+        // Results wider than a byte use a wider PUSH
+        let program = vec![0x01, 0xFF, 0x01, 0xFF, 0x12, 0xFF];
+        assert_eq!(fold_constants(&program), vec![0x02, 0xFE, 0x01, 0xFF]);
+
+        // PUSH1 3, PUSH1 5, LT, ISZERO, HALT
+        let program = vec![0x01, 3, 0x01, 5, 0x24, 0x27, 0xFF];
+        assert_eq!(fold_constants(&program), vec![0x01, 0, 0xFF]);
+        assert_eq!(run(&fold_constants(&program)), run(&program));
+
+        // PUSH1 17, PUSH1 5, MOD, HALT
+        let program = vec![0x01, 17, 0x01, 5, 0x14, 0xFF];
+        assert_eq!(fold_constants(&program), vec![0x01, 2, 0xFF]);
+
+        // Division by zero is left for the VM
+        let program = vec![0x01, 1, 0x01, 0, 0x13, 0xFF];
+        assert_eq!(fold_constants(&program), program);
+        let program = vec![0x01, 1, 0x01, 0, 0x14, 0xFF];
+        assert_eq!(fold_constants(&program), program);
+    }
+
+    #[test]
+    fn test_fold_constants_blocked_by_label() {
+        // PUSH1 10, PUSH1 1, JUMPIF 8, PUSH1 2, 8: PUSH1 3, ADD, HALT
+        // Jumping to the second operand skips the first, so no folding
+        let program = vec![0x01, 10, 0x01, 1, 0x31, 8, 0x01, 2, 0x01, 3, 0x10, 0xFF];
+        assert_eq!(fold_constants(&program), program);
+        assert_eq!(run(&optimize(&program)), run(&program));
+    }
+
+    #[test]
+    fn test_eliminate_dead_stores() {
+        // PUSHF 1.0, STOREF 2, PUSHF 2.0, STOREF 2, LOADF 2, HALT
+        let mut program = vec![0x70];
+        program.extend_from_slice(&1.0f64.to_le_bytes());
+        program.extend_from_slice(&[0x72, 2, 0x70]);
+        program.extend_from_slice(&2.0f64.to_le_bytes());
+        program.extend_from_slice(&[0x72, 2, 0x71, 2, 0xFF]);
+
+        // The first STOREF becomes two POPs; the second is read and kept
+        let optimized = eliminate_dead_stores(&program);
+        assert_eq!(&optimized[9..11], &[0x04, 0x04]);
+        assert_eq!(&optimized[20..], &[0x72, 2, 0x71, 2, 0xFF]);
+        assert_eq!(run(&optimized), run(&program));
+
+        // PUSH1 1, STORE 0x80, PUSH1 2, STORE 0x80, HALT: the first store and
+        // its value disappear entirely once the peephole pass runs
+        let program = vec![0x01, 1, 0x41, 0x80, 0x01, 2, 0x41, 0x80, 0xFF];
+        assert_eq!(optimize(&program), vec![0x01, 2, 0x41, 0x80, 0xFF]);
+    }
+
+    #[test]
+    fn test_dead_stores_respect_reads() {
+        // PUSH1 1, STORE 0x80, LOAD 0x80, PUSH1 2, STORE 0x80, HALT
+        let program = vec![0x01, 1, 0x41, 0x80, 0x40, 0x80, 0x01, 2, 0x41, 0x80, 0xFF];
+        assert_eq!(eliminate_dead_stores(&program), program);
+
+        // A read on one branch keeps the store alive:
+        // PUSH1 1, STORE 0x80, PUSH1 0, JUMPIF 11, LOAD 0x80, HALT,
+        // 11: PUSH1 2, STORE 0x80, HALT
+        let program = vec![
+            0x01, 1, 0x41, 0x80, 0x01, 0, 0x31, 11, 0x40, 0x80, 0xFF,
+            0x01, 2, 0x41, 0x80, 0xFF,
+        ];
+        assert_eq!(eliminate_dead_stores(&program), program);
+
+        // Stores are observable after HALT
+        let program = vec![0x01, 1, 0x41, 0x80, 0xFF];
+        assert_eq!(eliminate_dead_stores(&program), program);
+
+        // PUSH1 0x10, STORE 7, PUSH1 2, PUSH1 3, STORE 7, HALT: the first
+        // store rewrites the operand of PUSH1 3 before it runs
+        let program = vec![0x01, 0x10, 0x41, 7, 0x01, 2, 0x01, 3, 0x41, 7, 0xFF];
+        assert_eq!(eliminate_dead_stores(&program), program);
+    }
+}
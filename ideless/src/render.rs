@@ -1,5 +1,7 @@
 use std::{
+    collections::HashMap,
     io,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
     thread::{self, JoinHandle},
     time::{Duration, Instant},
@@ -17,12 +19,91 @@ use tui::{
     layout::{Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Span, Spans},
-    widgets::{Block, Borders, List, ListItem, Paragraph, Tabs},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Tabs, Wrap},
     Frame, Terminal,
 };
 // use tui_logger::TuiLoggerWidget;
 
-use crate::lessvm::{VM, VMState};
+use crate::{
+    editor::{display_column, EditorSettings},
+    explorer::FileExplorer,
+    files::{self, OpenFile, OpenFiles},
+    help::HelpModel,
+    highlight::{Language, SyntaxHighlighter},
+    lessvm::{VM, VMState},
+    memview::{MemoryViewState, ROW_BYTES},
+    run::RunStats,
+};
+
+/// Tab titles, in display order
+const TAB_TITLES: [&str; 7] = ["VM", "Memory", "Stack", "Help", "Editor", "Files", "Console"];
+
+/// Index of the memory tab
+const MEMORY_TAB: usize = 1;
+
+/// Index of the opcode reference tab
+const HELP_TAB: usize = 3;
+
+/// Index of the source editor tab
+const EDITOR_TAB: usize = 4;
+
+/// Index of the file explorer tab
+const FILES_TAB: usize = 5;
+
+/// Index of the debugger console tab
+const CONSOLE_TAB: usize = 6;
+
+/// Width of the line number column on the editor tab
+const GUTTER_WIDTH: u16 = 6;
+
+/// How often open files are checked for changes on disk
+const FILE_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Smallest terminal the tab bar, content and status bar fit in
+const MIN_WIDTH: u16 = 30;
+const MIN_HEIGHT: u16 = 11;
+
+/// Smallest terminal with room for the log panel and side-by-side panes
+const FULL_WIDTH: u16 = 60;
+const FULL_HEIGHT: u16 = 20;
+
+/// How much of the UI fits in the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LayoutMode {
+    /// Only a message asking for a larger terminal
+    TooSmall,
+    
+    /// No margin or log panel, and the VM tab's panes stacked
+    Compact,
+    
+    /// Everything
+    Full,
+}
+
+impl LayoutMode {
+    fn for_size(size: Rect) -> Self {
+        if size.width < MIN_WIDTH || size.height < MIN_HEIGHT {
+            LayoutMode::TooSmall
+        } else if size.width < FULL_WIDTH || size.height < FULL_HEIGHT {
+            LayoutMode::Compact
+        } else {
+            LayoutMode::Full
+        }
+    }
+}
+
+/// Debugger console as last sent by the run thread
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConsoleView {
+    /// Newest console lines, oldest first
+    pub lines: Vec<String>,
+    
+    /// Command being typed
+    pub input: String,
+    
+    /// Index in `lines` of the current search match
+    pub selected: Option<usize>,
+}
 
 /// Render state
 struct RenderState {
@@ -40,6 +121,21 @@ struct RenderState {
     
     /// Terminal size
     terminal_size: Rect,
+    
+    /// Bytes edited on the memory tab, for the run thread to write
+    memory_writes: Vec<(usize, u8)>,
+    
+    /// Source lines with a breakpoint set in the editor, when they change
+    line_breakpoints: Option<Vec<usize>>,
+    
+    /// Keys typed in command mode, for the run thread's debugger
+    command_keys: Vec<KeyCode>,
+    
+    /// Debugger console, `None` when not debugging
+    console: Option<ConsoleView>,
+    
+    /// Frame rate and instruction counts of the run thread
+    stats: Option<RunStats>,
 }
 
 /// Render controller for communicating with the render thread
@@ -58,6 +154,11 @@ impl RenderController {
                 running: true,
                 tab_index: 0,
                 terminal_size: Rect::new(0, 0, 0, 0),
+                memory_writes: Vec::new(),
+                line_breakpoints: None,
+                command_keys: Vec::new(),
+                console: None,
+                stats: None,
             })),
         }
     }
@@ -70,6 +171,67 @@ impl RenderController {
         key
     }
     
+    /// Take bytes edited on the memory tab since the last call, oldest first
+    pub fn take_memory_writes(&mut self) -> Vec<(usize, u8)> {
+        let mut state = self.state.lock().unwrap();
+        std::mem::take(&mut state.memory_writes)
+    }
+    
+    /// Queue bytes edited on the memory tab
+    fn push_memory_writes(&mut self, writes: Vec<(usize, u8)>) {
+        let mut state = self.state.lock().unwrap();
+        state.memory_writes.extend(writes);
+    }
+    
+    /// Take the 1-based source lines with a breakpoint set in the editor,
+    /// if they changed since the last call
+    pub fn take_line_breakpoints(&mut self) -> Option<Vec<usize>> {
+        let mut state = self.state.lock().unwrap();
+        state.line_breakpoints.take()
+    }
+    
+    /// Queue the source lines with a breakpoint for the run thread
+    fn set_line_breakpoints(&mut self, lines: Vec<usize>) {
+        let mut state = self.state.lock().unwrap();
+        state.line_breakpoints = Some(lines);
+    }
+    
+    /// Take keys typed in command mode since the last call, oldest first
+    pub fn take_command_keys(&mut self) -> Vec<KeyCode> {
+        let mut state = self.state.lock().unwrap();
+        std::mem::take(&mut state.command_keys)
+    }
+    
+    /// Queue a key typed in command mode
+    fn push_command_key(&mut self, key: KeyCode) {
+        let mut state = self.state.lock().unwrap();
+        state.command_keys.push(key);
+    }
+    
+    /// Set the debugger console to show
+    pub fn set_console(&mut self, console: ConsoleView) {
+        let mut state = self.state.lock().unwrap();
+        state.console = Some(console);
+    }
+    
+    /// Get the debugger console, if debugging
+    fn console(&self) -> Option<ConsoleView> {
+        let state = self.state.lock().unwrap();
+        state.console.clone()
+    }
+    
+    /// Set the run thread's latest stats
+    pub fn set_stats(&mut self, stats: RunStats) {
+        let mut state = self.state.lock().unwrap();
+        state.stats = Some(stats);
+    }
+    
+    /// Get the run thread's latest stats, once it has reported any
+    fn stats(&self) -> Option<RunStats> {
+        let state = self.state.lock().unwrap();
+        state.stats
+    }
+    
     /// Request a new frame render
     pub fn request_render(&mut self) {
         let mut state = self.state.lock().unwrap();
@@ -131,9 +293,11 @@ impl RenderController {
     }
 }
 
-/// Spawn a render thread
+/// Spawn a render thread, with `source` open on the editor tab
 pub fn spawn_render_thread(
     vm: &VM,
+    source: Option<&Path>,
+    editor_settings: EditorSettings,
     with_logging: bool,
 ) -> (Arc<Mutex<RenderController>>, JoinHandle<Result<()>>) {
     let controller = Arc::new(Mutex::new(RenderController::new()));
@@ -142,6 +306,34 @@ pub fn spawn_render_thread(
     // Clone VM fields for the rendering thread
     let vm_render = VMRender::from_vm(vm);
     
+    let source = source.map(Path::to_path_buf);
+    let mut program_source = None;
+    let mut file = match source.as_deref().map(OpenFile::open) {
+        Some(Ok(file)) => {
+            program_source = Some(file.editor.lines().to_vec());
+            file
+        }
+        Some(Err(err)) => {
+            log::warn!("Not opening the source in the editor: {:#}", err);
+            OpenFile::untitled()
+        }
+        None => OpenFile::untitled(),
+    };
+    file.editor.set_settings(editor_settings);
+    let mut files = OpenFiles::default();
+    files.add(file);
+    
+    let explorer = std::env::current_dir()
+        .map_err(anyhow::Error::from)
+        .and_then(|dir| FileExplorer::new(&dir));
+    let explorer = match explorer {
+        Ok(explorer) => Some(explorer),
+        Err(err) => {
+            log::warn!("No file explorer: {:#}", err);
+            None
+        }
+    };
+    
     let handle = thread::spawn(move || {
         // Setup terminal
         enable_raw_mode()?;
@@ -157,6 +349,14 @@ pub fn spawn_render_thread(
             fps: 0.0,
             frame_time: 0.0,
             show_logs: with_logging,
+            help: HelpModel::new(),
+            memory_view: MemoryViewState::new(),
+            source,
+            program_source,
+            files,
+            editor_settings,
+            explorer,
+            command_mode: false,
         };
         
         let res = render_loop(&mut terminal, &mut app);
@@ -175,24 +375,133 @@ pub fn spawn_render_thread(
 /// Rendering loop
 fn render_loop<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<()> {
     let mut last_frame = Instant::now();
+    let mut last_file_check = Instant::now();
     
     while app.controller.lock().unwrap().is_running() {
+        // Ask about open files changed by another program
+        if last_file_check.elapsed() >= FILE_CHECK_INTERVAL {
+            last_file_check = Instant::now();
+            let prompting = app.files.reload_prompt().is_some();
+            app.files.tick();
+            if !prompting && app.files.reload_prompt().is_some() {
+                app.controller.lock().unwrap().request_render();
+            }
+        }
+        
         // Handle events
         if event::poll(Duration::from_millis(16))? {
-            if let Event::Key(KeyEvent { code, kind: KeyEventKind::Press, .. }) = event::read()? {
+            if let Event::Key(key @ KeyEvent { code, kind: KeyEventKind::Press, .. }) = event::read()? {
                 let mut controller = app.controller.lock().unwrap();
                 
+                // Command mode sends every key to the debugger, which runs
+                // the command on Enter
+                if app.command_mode {
+                    controller.push_command_key(code);
+                    if matches!(code, KeyCode::Enter | KeyCode::Esc) {
+                        app.command_mode = false;
+                    }
+                    controller.request_render();
+                    continue;
+                }
+                
+                // A reload prompt takes y or n before the editor sees them
+                if let (EDITOR_TAB, Some(_), KeyCode::Char(answer @ ('y' | 'n'))) =
+                    (controller.tab_index(), app.files.reload_prompt(), code)
+                {
+                    if let Err(err) = app.files.answer_reload_prompt(answer == 'y') {
+                        log::error!("{:#}", err);
+                    }
+                    if let Some(lines) = app.line_breakpoints() {
+                        controller.set_line_breakpoints(lines);
+                    }
+                    controller.request_render();
+                    continue;
+                }
+                
+                // The editor takes the keys it types with, so q doesn't quit
+                if controller.tab_index() == EDITOR_TAB
+                    && app.files.active_mut().is_some_and(|file| file.editor.handle_key(key))
+                {
+                    if let Some(lines) = app.line_breakpoints() {
+                        controller.set_line_breakpoints(lines);
+                    }
+                    controller.request_render();
+                    continue;
+                }
+                
+                // The explorer opens files on the editor tab
+                if let (FILES_TAB, Some(explorer)) = (controller.tab_index(), &mut app.explorer) {
+                    let open = app.files.files().len();
+                    match explorer.handle_key(code, &mut app.files) {
+                        Ok(true) => {
+                            if app.files.files().len() > open {
+                                if let Some(file) = app.files.active_mut() {
+                                    file.editor.set_settings(app.editor_settings);
+                                }
+                            }
+                            let opened_file = matches!(code, KeyCode::Enter | KeyCode::Right)
+                                && explorer.selected_entry().is_some_and(|entry| !entry.is_dir);
+                            if opened_file {
+                                controller.set_tab_index(EDITOR_TAB);
+                            }
+                            controller.request_render();
+                            continue;
+                        }
+                        Ok(false) => {}
+                        Err(err) => {
+                            log::error!("{:#}", err);
+                            controller.request_render();
+                            continue;
+                        }
+                    }
+                }
+                
+                // Saving, opening and closing tabs. Untitled buffers stay
+                // unsaved since there's nowhere to ask for a path.
+                if let (EDITOR_TAB, Some(id)) = (controller.tab_index(), files::command_for_key(key)) {
+                    if let Err(err) = app.files.execute_command_by_id(id, |_| None) {
+                        log::error!("{:#}", err);
+                    }
+                    if id == "file.new" {
+                        if let Some(file) = app.files.active_mut() {
+                            file.editor.set_settings(app.editor_settings);
+                        }
+                    }
+                    controller.request_render();
+                    continue;
+                }
+                
+                // The help tab handles its own scrolling and search keys
+                if controller.tab_index() == HELP_TAB && app.help.handle_key(code) {
+                    controller.request_render();
+                    continue;
+                }
+                
+                // So does the memory tab, which queues edits for the run thread
+                if controller.tab_index() == MEMORY_TAB
+                    && app.memory_view.handle_key(code, &mut app.vm.memory, app.vm.pc)
+                {
+                    controller.push_memory_writes(app.memory_view.take_writes());
+                    controller.request_render();
+                    continue;
+                }
+                
                 match code {
                     KeyCode::Char('q') => {
                         controller.set_running(false);
                     },
+                    KeyCode::Char(':') if controller.console().is_some() => {
+                        app.command_mode = true;
+                        controller.set_tab_index(CONSOLE_TAB);
+                        controller.request_render();
+                    },
                     KeyCode::Tab => {
-                        let next_tab = (controller.tab_index() + 1) % 3;
+                        let next_tab = (controller.tab_index() + 1) % TAB_TITLES.len();
                         controller.set_tab_index(next_tab);
                         controller.request_render();
                     },
                     KeyCode::BackTab => {
-                        let next_tab = (controller.tab_index() + 2) % 3;
+                        let next_tab = (controller.tab_index() + TAB_TITLES.len() - 1) % TAB_TITLES.len();
                         controller.set_tab_index(next_tab);
                         controller.request_render();
                     },
@@ -233,11 +542,20 @@ fn render_loop<B: Backend>(terminal: &mut Terminal<B>, app: &mut App) -> Result<
 /// Rendering UI
 fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     let size = f.size();
+    let mode = LayoutMode::for_size(size);
+    if mode == LayoutMode::TooSmall {
+        let message = format!(
+            "Terminal too small ({}x{}), need at least {}x{}",
+            size.width, size.height, MIN_WIDTH, MIN_HEIGHT
+        );
+        f.render_widget(Paragraph::new(message).wrap(Wrap { trim: true }), size);
+        return;
+    }
     
     // Create main layout
     let chunks = Layout::default()
         .direction(Direction::Vertical)
-        .margin(1)
+        .margin(if mode == LayoutMode::Full { 1 } else { 0 })
         .constraints([
             Constraint::Length(3), // Tab bar
             Constraint::Min(5),    // Content
@@ -246,9 +564,8 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
         .split(size);
     
     // Render tab bar
-    let tab_titles = vec!["VM", "Memory", "Stack"];
     let tabs = Tabs::new(
-        tab_titles.iter().map(|t| Spans::from(Span::raw(*t))).collect()
+        TAB_TITLES.iter().map(|t| Spans::from(Span::raw(*t))).collect()
     )
     .block(Block::default().borders(Borders::ALL).title("LessVM Debugger"))
     .highlight_style(Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))
@@ -259,24 +576,41 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
     // Render content based on selected tab
     let tab_index = app.controller.lock().unwrap().tab_index();
     match tab_index {
-        0 => render_vm_tab(f, &app.vm, chunks[1]),
-        1 => render_memory_tab(f, &app.vm, chunks[1]),
+        0 => {
+            let stats = app.controller.lock().unwrap().stats();
+            render_vm_tab(f, &app.vm, stats, chunks[1], mode);
+        }
+        MEMORY_TAB => render_memory_tab(f, &app.vm, &app.memory_view, chunks[1]),
         2 => render_stack_tab(f, &app.vm, chunks[1]),
+        HELP_TAB => render_help_tab(f, &app.help, chunks[1]),
+        EDITOR_TAB => render_editor_tab(f, &app.files, chunks[1]),
+        FILES_TAB => render_files_tab(f, app.explorer.as_ref(), chunks[1]),
+        CONSOLE_TAB => {
+            let console = app.controller.lock().unwrap().console();
+            render_console_tab(f, console.as_ref(), app.command_mode, chunks[1]);
+        }
         _ => {}
     }
     
-    // Render status bar
-    let status = format!(
-        "Status: {} | PC: {:#06X} | FPS: {:.1} | Frame time: {:.2}ms | Press 'q' to quit",
-        app.vm.state, app.vm.pc, app.fps, app.frame_time
-    );
-    let status_bar = Paragraph::new(status)
-        .style(Style::default().fg(Color::White));
+    // Render status bar, or the reload prompt while one is up
+    let reload = app.files.reload_prompt().and_then(|index| app.files.files().get(index));
+    let status_bar = match reload {
+        Some(file) => Paragraph::new(format!(
+            "{} changed on disk. Reload it? Press y or n on the editor tab",
+            file.title()
+        ))
+        .style(Style::default().fg(Color::Yellow)),
+        None => Paragraph::new(format!(
+            "Status: {} | PC: {:#06X} | FPS: {:.1} | Frame time: {:.2}ms | Press 'q' to quit",
+            app.vm.state, app.vm.pc, app.fps, app.frame_time
+        ))
+        .style(Style::default().fg(Color::White)),
+    };
     
     f.render_widget(status_bar, chunks[2]);
     
-    // Render logs if enabled
-    if app.show_logs {
+    // Render logs if enabled and there's room
+    if app.show_logs && mode == LayoutMode::Full {
         let logs_area = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
@@ -295,9 +629,9 @@ fn ui<B: Backend>(f: &mut Frame<B>, app: &mut App) {
 }
 
 /// Render VM tab
-fn render_vm_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, area: Rect) {
+fn render_vm_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, stats: Option<RunStats>, area: Rect, mode: LayoutMode) {
     let vm_layout = Layout::default()
-        .direction(Direction::Horizontal)
+        .direction(if mode == LayoutMode::Full { Direction::Horizontal } else { Direction::Vertical })
         .constraints([
             Constraint::Percentage(50),
             Constraint::Percentage(50),
@@ -324,10 +658,16 @@ fn render_vm_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, area: Rect) {
     f.render_widget(registers_list, vm_layout[0]);
     
     // Render VM state
-    let state = format!(
+    let mut state = format!(
         "State: {}\nPC: {:#06X}\nCycles: {}\nGas used: {}\nGas limit: {}",
         vm.state, vm.pc, vm.cycle_counter, vm.gas_used, vm.gas_limit
     );
+    if let Some(stats) = stats {
+        state.push_str(&format!(
+            "\nInstructions: {} ({:.0}/s)",
+            stats.instructions, stats.instructions_per_second
+        ));
+    }
     
     let state_widget = Paragraph::new(state)
         .block(Block::default().title("VM State").borders(Borders::ALL))
@@ -337,70 +677,113 @@ fn render_vm_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, area: Rect) {
 }
 
 /// Render memory tab
-fn render_memory_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, area: Rect) {
-    // Calculate visible memory range
+fn render_memory_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, view: &MemoryViewState, area: Rect) {
+    // Calculate visible memory range, around the selection once there is one
     let pc = vm.pc;
-    let start_addr = pc.saturating_sub(64);
-    let end_addr = (pc + 64).min(vm.memory.len());
+    let center = view.selected_address.unwrap_or(pc);
+    let start_addr = center.saturating_sub(64) / ROW_BYTES * ROW_BYTES;
+    let end_addr = (start_addr + 128).min(vm.memory.len());
     
-    // Format memory blocks
-    let mut memory_lines = Vec::new();
-    for addr in (start_addr..end_addr).step_by(16) {
-        let mut line = format!("{:#06X}: ", addr);
+    // Format memory blocks, coloring executed bytes by how hot they are
+    let max_count = vm.exec_counts.values().copied().max().unwrap_or(0);
+    let pc_index = pc.checked_sub(start_addr).map(|offset| offset / ROW_BYTES);
+    let mut memory_items = Vec::new();
+    for (row, addr) in (start_addr..end_addr).step_by(16).enumerate() {
+        let addr_style = if Some(row) == pc_index {
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD)
+        } else {
+            Style::default()
+        };
+        let mut spans = vec![Span::styled(format!("{:#06X}: ", addr), addr_style)];
         
         // Add hex representation
         for i in 0..16 {
             if addr + i < end_addr {
-                line.push_str(&format!("{:02X} ", vm.memory[addr + i]));
+                let count = vm.exec_counts.get(&(addr + i)).copied().unwrap_or(0);
+                let mut style = Style::default();
+                if count > 0 {
+                    style = style.fg(heat_color(count, max_count));
+                }
+                if view.dirty.contains(&(addr + i)) {
+                    style = style.fg(Color::Magenta).add_modifier(Modifier::BOLD);
+                }
+                if addr + i == pc {
+                    style = style.add_modifier(Modifier::REVERSED);
+                }
+                if Some(addr + i) == view.selected_address {
+                    style = style.add_modifier(Modifier::UNDERLINED);
+                }
+                let text = match view.pending_nibble {
+                    Some(high) if Some(addr + i) == view.selected_address => format!("{:X}_", high),
+                    _ => format!("{:02X}", vm.memory[addr + i]),
+                };
+                spans.push(Span::styled(text, style));
+                spans.push(Span::raw(" "));
             } else {
-                line.push_str("   ");
+                spans.push(Span::raw("   "));
             }
         }
         
-        line.push_str("  ");
-        
         // Add ASCII representation
+        let mut ascii = String::from("  ");
         for i in 0..16 {
             if addr + i < end_addr {
                 let byte = vm.memory[addr + i];
                 if byte >= 32 && byte <= 126 {
-                    line.push(byte as char);
+                    ascii.push(byte as char);
                 } else {
-                    line.push('.');
+                    ascii.push('.');
                 }
             } else {
-                line.push(' ');
+                ascii.push(' ');
             }
         }
+        spans.push(Span::raw(ascii));
         
-        memory_lines.push(line);
+        memory_items.push(ListItem::new(vec![Spans::from(spans)]));
     }
     
-    // Highlight current PC
-    let pc_index = (pc - start_addr) / 16;
-    
-    let memory_items: Vec<ListItem> = memory_lines
-        .iter()
-        .enumerate()
-        .map(|(i, line)| {
-            if i == pc_index {
-                ListItem::new(vec![Spans::from(Span::styled(
-                    line,
-                    Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
-                ))])
-            } else {
-                ListItem::new(vec![Spans::from(Span::raw(line))])
-            }
-        })
-        .collect();
-    
+    let mut title = String::from("Memory");
+    if view.is_dirty() {
+        title.push_str(" [modified]");
+    }
+    title.push_str(if view.editing {
+        " | EDIT: type hex to overwrite, Esc to stop"
+    } else {
+        " | arrows select, e to edit"
+    });
     let memory_list = List::new(memory_items)
-        .block(Block::default().title("Memory").borders(Borders::ALL))
+        .block(Block::default().title(title).borders(Borders::ALL))
         .style(Style::default().fg(Color::White));
     
     f.render_widget(memory_list, area);
 }
 
+/// Colors used for the execution heat overlay, from coldest to hottest
+const HEAT_COLORS: [Color; 5] = [
+    Color::Blue,
+    Color::Cyan,
+    Color::Green,
+    Color::Yellow,
+    Color::Red,
+];
+
+/// Map an execution count to a heat bucket (0 = coldest, 4 = hottest)
+/// relative to the hottest instruction seen so far
+fn heat_bucket(count: u64, max_count: u64) -> usize {
+    if count == 0 || max_count == 0 {
+        return 0;
+    }
+    let last = (HEAT_COLORS.len() - 1) as u64;
+    let bucket = (count.min(max_count) * last + max_count - 1) / max_count;
+    bucket.min(last) as usize
+}
+
+/// Get the heat overlay color for an execution count
+fn heat_color(count: u64, max_count: u64) -> Color {
+    HEAT_COLORS[heat_bucket(count, max_count)]
+}
+
 /// Render stack tab
 fn render_stack_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, area: Rect) {
     let mut stack_items = Vec::new();
@@ -422,6 +805,206 @@ fn render_stack_tab<B: Backend>(f: &mut Frame<B>, vm: &VMRender, area: Rect) {
     f.render_widget(stack_list, area);
 }
 
+/// Render the opcode reference tab
+fn render_help_tab<B: Backend>(f: &mut Frame<B>, help: &HelpModel, area: Rect) {
+    let header = Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD);
+    let mut items = vec![ListItem::new(Spans::from(Span::styled(
+        format!("{:<6}{:<15}{:<4}{:<28}{:>5}  {}", "Byte", "Mnemonic", "Ops", "Stack", "Gas", "Description"),
+        header,
+    )))];
+    
+    for entry in help.visible() {
+        items.push(ListItem::new(Spans::from(vec![
+            Span::styled(format!("{:<6}", format!("{:#04X}", entry.opcode)), Style::default().fg(Color::Cyan)),
+            Span::styled(format!("{:<15}", entry.mnemonic), Style::default().add_modifier(Modifier::BOLD)),
+            Span::raw(format!("{:<4}{:<28}{:>5}  {}", entry.operands, entry.stack_effect, entry.gas, entry.description)),
+        ])));
+    }
+    
+    let title = if help.searching {
+        format!("Opcode Reference | Search: {}_", help.query)
+    } else if !help.query.is_empty() {
+        format!("Opcode Reference | Search: {} (Esc to clear)", help.query)
+    } else {
+        "Opcode Reference | / search, ↑/↓ scroll".to_string()
+    };
+    
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(Style::default().fg(Color::White));
+    
+    f.render_widget(list, area);
+}
+
+/// Render the editor tab, scrolled to keep the cursor in view
+fn render_editor_tab<B: Backend>(f: &mut Frame<B>, files: &OpenFiles, area: Rect) {
+    let Some(file) = files.active() else {
+        let message = Paragraph::new("No file open")
+            .block(Block::default().title("Editor").borders(Borders::ALL));
+        f.render_widget(message, area);
+        return;
+    };
+    let editor = &file.editor;
+    
+    // Every tab's title, the active one in brackets and modified ones starred
+    let titles: Vec<String> = files
+        .files()
+        .iter()
+        .enumerate()
+        .map(|(index, file)| {
+            let title = file.title() + if file.is_modified() { "*" } else { "" };
+            if index == files.active_index() { format!("[{}]", title) } else { title }
+        })
+        .collect();
+    let mut title = titles.join(" ");
+    if let Some(label) = editor.label_at_cursor() {
+        let lines: Vec<String> = editor.references(label).iter().map(|row| (row + 1).to_string()).collect();
+        if lines.is_empty() {
+            title.push_str(&format!(" | {} is never used", label));
+        } else {
+            title.push_str(&format!(" | {} used on lines {}, F12 to go to definition, Shift+F12 to the next use", label, lines.join(", ")));
+        }
+    }
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    
+    let columns = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(GUTTER_WIDTH), Constraint::Min(1)])
+        .split(inner);
+    let (height, width) = (columns[1].height as usize, columns[1].width as usize);
+    if height == 0 || width == 0 {
+        return;
+    }
+    
+    let (row, _) = editor.cursor();
+    let column = editor.cursor_display_column();
+    let top = (row + 1).saturating_sub(height);
+    let left = (column + 1).saturating_sub(width);
+    let rows = top..editor.lines().len().min(top + height);
+    
+    let breakpoints = editor.breakpoint_lines();
+    let gutter: Vec<Spans> = rows
+        .clone()
+        .map(|r| {
+            let marker = if breakpoints.contains(&r) {
+                Span::styled("●", Style::default().fg(Color::Red))
+            } else {
+                Span::raw(" ")
+            };
+            Spans::from(vec![marker, Span::styled(format!("{:>4} ", r + 1), Style::default().fg(Color::DarkGray))])
+        })
+        .collect();
+    // Untitled buffers are most likely new assembly
+    let highlighter = file
+        .path
+        .as_deref()
+        .map_or(SyntaxHighlighter::new(Some(Language::Assembly)), SyntaxHighlighter::for_file);
+    let display: Vec<String> = rows.map(|r| editor.display_line(r)).collect();
+    let text = highlighter.highlight_lines(&display);
+    f.render_widget(Paragraph::new(gutter), columns[0]);
+    f.render_widget(Paragraph::new(text).scroll((0, left as u16)), columns[1]);
+    
+    // Draw the selection over the text, including the line breaks in it
+    if let Some(((start_row, start_col), (end_row, end_col))) = editor.selection() {
+        let tab_width = editor.settings().tab_width;
+        for r in start_row.max(top)..=end_row.min(top + height - 1) {
+            let line = &editor.lines()[r];
+            let start = if r == start_row { display_column(line, start_col, tab_width) } else { 0 };
+            let end = if r == end_row {
+                display_column(line, end_col, tab_width)
+            } else {
+                display_column(line, usize::MAX, tab_width) + 1
+            };
+            let (start, end) = (start.max(left) - left, (end.max(left) - left).min(width));
+            if start < end {
+                let selected = Rect::new(columns[1].x + start as u16, columns[1].y + (r - top) as u16, (end - start) as u16, 1);
+                f.render_widget(Block::default().style(Style::default().add_modifier(Modifier::REVERSED)), selected);
+            }
+        }
+    }
+    f.set_cursor(columns[1].x + (column - left) as u16, columns[1].y + (row - top) as u16);
+}
+
+/// Render the file explorer tab
+fn render_files_tab<B: Backend>(f: &mut Frame<B>, explorer: Option<&FileExplorer>, area: Rect) {
+    let Some(explorer) = explorer else {
+        let message = Paragraph::new("The workspace directory couldn't be read")
+            .block(Block::default().title("Files").borders(Borders::ALL));
+        f.render_widget(message, area);
+        return;
+    };
+    
+    let items: Vec<ListItem> = explorer
+        .rows()
+        .into_iter()
+        .map(|(depth, entry)| {
+            let (marker, style) = match (entry.is_dir, entry.expanded) {
+                (true, true) => ("▾ ", Style::default().fg(Color::LightBlue)),
+                (true, false) => ("▸ ", Style::default().fg(Color::LightBlue)),
+                (false, _) => ("  ", Style::default()),
+            };
+            ListItem::new(Spans::from(Span::styled(format!("{}{}{}", "  ".repeat(depth), marker, entry.name), style)))
+        })
+        .collect();
+    
+    let title = format!("Files: {} | Enter opens, Left collapses, F5 refreshes", explorer.root().display());
+    let list = List::new(items)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    let mut state = ListState::default();
+    state.select(Some(explorer.selected()));
+    f.render_stateful_widget(list, area, &mut state);
+}
+
+/// Render the debugger console tab, scrolled to the newest line or the
+/// current search match, with the command line below it
+fn render_console_tab<B: Backend>(f: &mut Frame<B>, console: Option<&ConsoleView>, command_mode: bool, area: Rect) {
+    let block = Block::default().title("Console").borders(Borders::ALL);
+    let Some(console) = console else {
+        f.render_widget(Paragraph::new("The console is only available in debug mode (--debug)").block(block), area);
+        return;
+    };
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+    
+    let rows = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(1), Constraint::Length(1)])
+        .split(inner);
+    let height = rows[0].height as usize;
+    let last_top = console.lines.len().saturating_sub(height);
+    let top = console.selected.map_or(last_top, |line| line.saturating_sub(height / 2).min(last_top));
+    
+    let items: Vec<ListItem> = console
+        .lines
+        .iter()
+        .enumerate()
+        .skip(top)
+        .take(height)
+        .map(|(index, line)| {
+            let style = if Some(index) == console.selected {
+                Style::default().add_modifier(Modifier::REVERSED)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Spans::from(Span::styled(line.as_str(), style)))
+        })
+        .collect();
+    f.render_widget(List::new(items), rows[0]);
+    
+    let input = if command_mode {
+        Spans::from(format!(":{}_", console.input))
+    } else {
+        Spans::from(Span::styled(
+            ": to type a command, help lists them, Up/Down browse the history",
+            Style::default().fg(Color::DarkGray),
+        ))
+    };
+    f.render_widget(Paragraph::new(input), rows[1]);
+}
+
 /// VM Render State - simplified version of VM for rendering
 #[derive(Clone)]
 struct VMRender {
@@ -433,6 +1016,7 @@ struct VMRender {
     pub cycle_counter: u32,
     pub gas_used: u64,
     pub gas_limit: u64,
+    pub exec_counts: HashMap<usize, u64>,
 }
 
 impl VMRender {
@@ -447,6 +1031,7 @@ impl VMRender {
             cycle_counter: vm.cycle_counter,
             gas_used: vm.gas_used(),
             gas_limit: vm.gas_limit(),
+            exec_counts: vm.exec_counts.clone(),
         }
     }
     
@@ -460,6 +1045,7 @@ impl VMRender {
         self.cycle_counter = vm.cycle_counter;
         self.gas_used = vm.gas_used();
         self.gas_limit = vm.gas_limit();
+        self.exec_counts = vm.exec_counts.clone();
     }
 }
 
@@ -479,4 +1065,86 @@ struct App {
     
     /// Whether to show logs
     show_logs: bool,
+    
+    /// Opcode reference shown on the help tab
+    help: HelpModel,
+    
+    /// Selection and edits on the memory tab
+    memory_view: MemoryViewState,
+    
+    /// Files open on the editor tab
+    files: OpenFiles,
+    
+    /// Source file the program was assembled from
+    source: Option<PathBuf>,
+    
+    /// Lines of `source` as assembled, which editor breakpoints are
+    /// resolved against since the program doesn't change with the buffer
+    program_source: Option<Vec<String>>,
+    
+    /// Tab width and indentation style for files opened in the editor
+    editor_settings: EditorSettings,
+    
+    /// Workspace tree on the files tab, if the working directory was readable
+    explorer: Option<FileExplorer>,
+    
+    /// Whether keys go to the debugger's command line
+    command_mode: bool,
+}
+
+impl App {
+    /// 1-based lines of the program's source with a breakpoint, if the
+    /// active editor tab is that source
+    fn line_breakpoints(&self) -> Option<Vec<usize>> {
+        let file = self.files.active()?;
+        if file.path.is_none() || file.path != self.source {
+            return None;
+        }
+        let lines = file.editor.breakpoint_lines_in(self.program_source.as_deref()?);
+        Some(lines.into_iter().map(|row| row + 1).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_heat_bucket() {
+        // Never executed is always coldest
+        assert_eq!(heat_bucket(0, 0), 0);
+        assert_eq!(heat_bucket(0, 100), 0);
+        
+        // Hottest instruction lands in the top bucket
+        assert_eq!(heat_bucket(100, 100), 4);
+        assert_eq!(heat_bucket(1, 1), 4);
+        
+        // Everything in between is spread across the remaining buckets
+        assert_eq!(heat_bucket(1, 100), 1);
+        assert_eq!(heat_bucket(25, 100), 1);
+        assert_eq!(heat_bucket(26, 100), 2);
+        assert_eq!(heat_bucket(50, 100), 2);
+        assert_eq!(heat_bucket(75, 100), 3);
+        assert_eq!(heat_bucket(99, 100), 4);
+        
+        // Counts above the max are clamped
+        assert_eq!(heat_bucket(500, 100), 4);
+        
+        assert_eq!(heat_color(0, 10), Color::Blue);
+        assert_eq!(heat_color(10, 10), Color::Red);
+    }
+    
+    #[test]
+    fn test_layout_mode() {
+        let mode = |width, height| LayoutMode::for_size(Rect::new(0, 0, width, height));
+        
+        assert_eq!(mode(0, 0), LayoutMode::TooSmall);
+        assert_eq!(mode(20, 40), LayoutMode::TooSmall);
+        assert_eq!(mode(120, 10), LayoutMode::TooSmall);
+        assert_eq!(mode(MIN_WIDTH, MIN_HEIGHT), LayoutMode::Compact);
+        assert_eq!(mode(45, 30), LayoutMode::Compact);
+        assert_eq!(mode(120, 15), LayoutMode::Compact);
+        assert_eq!(mode(FULL_WIDTH, FULL_HEIGHT), LayoutMode::Full);
+        assert_eq!(mode(80, 24), LayoutMode::Full);
+    }
 }
@@ -1,4 +1,6 @@
 use std::{
+    collections::VecDeque,
+    path::Path,
     sync::{Arc, atomic::{AtomicBool, Ordering}, Mutex},
     thread::{self, JoinHandle},
     time::{Duration, Instant},
@@ -9,11 +11,16 @@ use crossterm::event::KeyCode;
 use log::{debug, error, info};
 
 use crate::{
+    crash,
     lessvm::{VM, VMState},
     dbg::Debugger,
-    render::RenderController,
+    render::{ConsoleView, RenderController},
 };
 
+/// Newest console lines sent to the render thread each frame, more than
+/// fit on the console tab
+const CONSOLE_VIEW_LINES: usize = 200;
+
 /// Runner for the VM emulation
 pub struct Runner {
     /// VM reference
@@ -118,13 +125,21 @@ impl Runner {
         // Otherwise, run cycles directly on the VM
         match self.lessvm.state {
             VMState::Ready | VMState::Running => {
-                self.lessvm.run(self.cycles_per_frame)?;
-                Ok(())
+                match self.lessvm.run(self.cycles_per_frame) {
+                    // Running out of gas is an expected stop, not a crash
+                    Err(_) if self.lessvm.is_out_of_gas() => {
+                        info!("VM out of gas at PC={:#06X} after {} of {} gas",
+                              self.lessvm.pc, self.lessvm.gas_used(), self.lessvm.gas_limit());
+                        Ok(())
+                    }
+                    result => result,
+                }
             }
             VMState::Halted => {
                 debug!("VM halted at PC={:#06X}", self.lessvm.pc);
                 Ok(())
             }
+            VMState::Error(_) if self.lessvm.is_out_of_gas() => Ok(()),
             VMState::Error(ref msg) => {
                 error!("VM error: {}", msg);
                 Ok(())
@@ -156,17 +171,55 @@ impl Runner {
         Ok(())
     }
     
-    /// Get FPS statistics
-    pub fn fps_stats(&self) -> (f64, f64) {
-        self.fps_counter.stats()
+    /// Handle a key typed in command mode. Without a debugger there are no
+    /// commands, so the key is ignored.
+    pub fn handle_command_key(&mut self, key: KeyCode) -> Result<()> {
+        match &mut self.debugger {
+            Some(debugger) => debugger.handle_command_key(&mut self.lessvm, key),
+            None => Ok(()),
+        }
+    }
+    
+    /// The newest debugger console lines and the command being typed, for
+    /// the console tab
+    pub fn console_view(&self) -> Option<ConsoleView> {
+        let debugger = self.debugger.as_ref()?;
+        let console = debugger.console();
+        let skip = console.len().saturating_sub(CONSOLE_VIEW_LINES);
+        Some(ConsoleView {
+            lines: console.iter().skip(skip).cloned().collect(),
+            input: debugger.command_input().to_string(),
+            selected: debugger.selected_console_index().and_then(|line| line.checked_sub(skip)),
+        })
+    }
+    
+    /// Get FPS and instruction statistics
+    pub fn fps_stats(&self) -> RunStats {
+        self.fps_counter.stats(self.lessvm.instruction_count())
     }
     
     /// Update FPS counter
     pub fn update_fps(&mut self) {
-        self.fps_counter.update();
+        self.fps_counter.update(self.lessvm.instruction_count());
     }
 }
 
+/// Frame rate and work done by a `Runner`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunStats {
+    /// Frames per second over the last full second
+    pub fps: f64,
+    
+    /// Average frame time in seconds over the last 100 frames
+    pub avg_frame_time: f64,
+    
+    /// Instructions executed since the VM was last reset
+    pub instructions: u64,
+    
+    /// Instructions per second over the last full second
+    pub instructions_per_second: f64,
+}
+
 /// Run thread function
 pub fn spawn_run_thread(
     mut runner: Runner,
@@ -186,9 +239,13 @@ pub fn spawn_run_thread(
             if debug_mode {
                 info!("Debug mode enabled");
                 info!("Press 'n' for next instruction, 'c' to continue, 'b' to set breakpoint");
+                info!("Press ':' to type a debugger command");
             }
         }
         
+        // Whether a crash dump has been written for the current error
+        let mut crash_dumped = false;
+        
         // Main loop
         while running.load(Ordering::Relaxed) {
             let frame_start = Instant::now();
@@ -201,6 +258,19 @@ pub fn spawn_run_thread(
                         error!("Error handling key: {}", err);
                     }
                 }
+                for key in controller.take_command_keys() {
+                    if let Err(err) = runner.handle_command_key(key) {
+                        error!("Error running command: {}", err);
+                    }
+                }
+                if let (Some(lines), Some(debugger)) = (controller.take_line_breakpoints(), runner.debugger_mut()) {
+                    debugger.set_line_breakpoints(&lines);
+                }
+                for (address, byte) in controller.take_memory_writes() {
+                    if let Err(err) = runner.lessvm_mut().write_memory(address, byte) {
+                        error!("Error editing memory: {}", err);
+                    }
+                }
             }
             
             // Run a frame if not paused
@@ -210,15 +280,32 @@ pub fn spawn_run_thread(
                 }
             }
             
+            // Write a crash dump the first time the VM ends up in an error state
+            match runner.lessvm().state {
+                VMState::Error(_) if !crash_dumped && !runner.lessvm().is_out_of_gas() => {
+                    crash_dumped = true;
+                    match crash::write_crash_dump(runner.lessvm(), Path::new(".")) {
+                        Ok(path) => error!("Crash dump written to {}", path.display()),
+                        Err(err) => error!("Failed to write crash dump: {}", err),
+                    }
+                }
+                VMState::Error(_) => {}
+                _ => crash_dumped = false,
+            }
+            
+            // Update FPS counter
+            runner.update_fps();
+            
             // Update the render controller
             {
                 let mut controller = render_controller.lock().unwrap();
+                if let Some(console) = runner.console_view() {
+                    controller.set_console(console);
+                }
+                controller.set_stats(runner.fps_stats());
                 controller.request_render();
             }
             
-            // Update FPS counter
-            runner.update_fps();
-            
             // Frame timing
             let frame_time = frame_start.elapsed();
             let target_frame_time = Duration::from_secs_f64(1.0 / 60.0);
@@ -237,7 +324,15 @@ struct FpsCounter {
     frame_count: u32,
     fps: f64,
     last_update: Instant,
-    frame_times: Vec<Duration>,
+    frame_times: VecDeque<Duration>,
+    
+    /// Start of the one-second window rates are measured over
+    window_start: Instant,
+    
+    /// Instruction count at the start of the window
+    window_instructions: u64,
+    
+    instructions_per_second: f64,
 }
 
 impl FpsCounter {
@@ -246,11 +341,14 @@ impl FpsCounter {
             frame_count: 0,
             fps: 0.0,
             last_update: Instant::now(),
-            frame_times: Vec::with_capacity(100),
+            frame_times: VecDeque::with_capacity(100),
+            window_start: Instant::now(),
+            window_instructions: 0,
+            instructions_per_second: 0.0,
         }
     }
     
-    fn update(&mut self) {
+    fn update(&mut self, instructions: u64) {
         self.frame_count += 1;
         
         let now = Instant::now();
@@ -258,20 +356,23 @@ impl FpsCounter {
         self.last_update = now;
         
         // Track frame times
-        self.frame_times.push(frame_time);
-        if self.frame_times.len() > 100 {
-            self.frame_times.remove(0);
+        if self.frame_times.len() == 100 {
+            self.frame_times.pop_front();
         }
+        self.frame_times.push_back(frame_time);
         
-        // Update FPS every second
-        let elapsed = now.duration_since(self.last_update).as_secs_f64();
+        // Update rates every second. The count drops when the VM is reset.
+        let elapsed = now.duration_since(self.window_start).as_secs_f64();
         if elapsed >= 1.0 {
             self.fps = self.frame_count as f64 / elapsed;
+            self.instructions_per_second = instructions.saturating_sub(self.window_instructions) as f64 / elapsed;
             self.frame_count = 0;
+            self.window_start = now;
+            self.window_instructions = instructions;
         }
     }
     
-    fn stats(&self) -> (f64, f64) {
+    fn stats(&self, instructions: u64) -> RunStats {
         // Calculate average frame time
         let avg_frame_time = if self.frame_times.is_empty() {
             0.0
@@ -280,6 +381,66 @@ impl FpsCounter {
             sum.as_secs_f64() / self.frame_times.len() as f64
         };
         
-        (self.fps, avg_frame_time)
+        RunStats {
+            fps: self.fps,
+            avg_frame_time,
+            instructions,
+            instructions_per_second: self.instructions_per_second,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instruction_count() {
+        let mut vm = VM::new();
+        // PUSH1 2, PUSH1 3, ADD, HALT
+        vm.load_bytecode(&[0x01, 2, 0x01, 3, 0x10, 0xFF]).unwrap();
+        let mut runner = Runner::new_lessvm(vm, None);
+
+        runner.run_frame().unwrap();
+        runner.update_fps();
+        assert_eq!(runner.lessvm().state, VMState::Halted);
+        assert_eq!(runner.fps_stats().instructions, 4);
+
+        // Frames after HALT don't run anything
+        runner.run_frame().unwrap();
+        assert_eq!(runner.fps_stats().instructions, 4);
+
+        runner.lessvm_mut().reset();
+        assert_eq!(runner.fps_stats().instructions, 0);
+    }
+
+    #[test]
+    fn test_command_keys_reach_debugger() {
+        let vm = || {
+            let mut vm = VM::new();
+            vm.load_bytecode(&[0x01, 2, 0x01, 3, 0x10, 0xFF]).unwrap();
+            vm
+        };
+        let mut runner = Runner::new_lessvm(vm(), None);
+        runner.handle_command_key(KeyCode::Char('s')).unwrap();
+        assert_eq!(runner.console_view(), None);
+
+        let debugger = Debugger::new(&vm(), 1);
+        let mut runner = Runner::new_lessvm(vm(), Some(debugger));
+        for key in "step".chars().map(KeyCode::Char) {
+            runner.handle_command_key(key).unwrap();
+        }
+        assert_eq!(runner.console_view().unwrap().input, "step");
+        runner.handle_command_key(KeyCode::Enter).unwrap();
+        assert_eq!(runner.lessvm().pc, 2);
+
+        let console = runner.console_view().unwrap();
+        assert_eq!(console.input, "");
+        assert_eq!(console.lines[0], "> step");
+        assert_eq!(console.selected, None);
+
+        // Up brings the last command back
+        runner.handle_command_key(KeyCode::Up).unwrap();
+        assert_eq!(runner.console_view().unwrap().input, "step");
     }
 }
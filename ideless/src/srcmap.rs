@@ -0,0 +1,63 @@
+//! Source locations for assembled programs.
+//!
+//! A `.dbg` sidecar next to a program (`prog.bin` -> `prog.dbg`) maps the
+//! offset of each instruction to the line of the `.lasm` source it was
+//! assembled from, so the debugger can show source lines instead of raw
+//! offsets. Programs run straight from a `.lasm` file get their map from the
+//! assembler instead.
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+/// Source line of each instruction in a program
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// Source file the program was assembled from
+    pub file: String,
+
+    /// Source line by instruction offset
+    lines: BTreeMap<usize, usize>,
+}
+
+impl SourceMap {
+    /// Map for `file` from the `(offset, line)` pairs `assemble_with_lines` returns
+    pub fn new(file: impl Into<String>, lines: impl IntoIterator<Item = (usize, usize)>) -> Self {
+        SourceMap { file: file.into(), lines: lines.into_iter().collect() }
+    }
+
+    /// Source line of the instruction at `offset`
+    pub fn line(&self, offset: usize) -> Option<usize> {
+        self.lines.get(&offset).copied()
+    }
+
+    /// Offset of the first instruction on or after source line `line`, so
+    /// a label or blank line maps to the code that follows it
+    pub fn offset(&self, line: usize) -> Option<usize> {
+        self.lines.iter().find(|&(_, &l)| l >= line).map(|(&offset, _)| offset)
+    }
+
+    /// `file:line` of the instruction at `offset`
+    pub fn location(&self, offset: usize) -> Option<String> {
+        self.line(offset).map(|line| format!("{}:{}", self.file, line))
+    }
+
+    /// Where the sidecar for `program` lives
+    pub fn sidecar_path(program: &Path) -> PathBuf {
+        program.with_extension("dbg")
+    }
+
+    /// Write the map to `path` as JSON
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let json = serde_json::to_vec(self).context("Failed to serialize source map")?;
+        fs::write(path, json).with_context(|| format!("Failed to write source map to {}", path.display()))
+    }
+
+    /// Read a map written by `save`
+    pub fn load(path: &Path) -> Result<Self> {
+        let json = fs::read(path).with_context(|| format!("Failed to read source map from {}", path.display()))?;
+        serde_json::from_slice(&json).with_context(|| format!("Invalid source map in {}", path.display()))
+    }
+}
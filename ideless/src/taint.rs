@@ -0,0 +1,361 @@
+//! Taint tracking for the account addresses passed to transfers.
+//!
+//! Values are followed through the control-flow graph SSA-style: each one is
+//! named after the instruction that produced it, and DUP and SWAP move names
+//! around instead of creating values. Values read from memory or parsed from
+//! input are untrusted. One stops being untrusted on the branch where an LT
+//! or GT comparison with a trusted value bounds it from above, or once it is
+//! masked by AND with a trusted value. An untrusted value reaching the address operand of SOLTRANSFER or
+//! TOKENTRANSFER is reported.
+//!
+//! Calls are followed into the callee, but the code after a CALL is not
+//! analyzed through that path since the callee's effect on the stack isn't
+//! tracked.
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::asm::{opcode_name, opcode_stack_counts, Disassembler, EdgeKind};
+
+const PUSH1: u8 = 0x01;
+const PUSH2: u8 = 0x02;
+const PUSH4: u8 = 0x03;
+const DUP: u8 = 0x05;
+const SWAP: u8 = 0x06;
+const AND: u8 = 0x20;
+const LT: u8 = 0x24;
+const GT: u8 = 0x25;
+const EQ: u8 = 0x26;
+const ISZERO: u8 = 0x27;
+const JUMPIF: u8 = 0x31;
+const CALL: u8 = 0x32;
+const JUMPIF2: u8 = 0x35;
+const LOAD: u8 = 0x40;
+const LOADW: u8 = 0x44;
+const SOLTRANSFER: u8 = 0x50;
+const TOKENTRANSFER: u8 = 0x51;
+const SYSCALL: u8 = 0x52;
+const ATOI: u8 = 0xB9;
+const JSONPARSE: u8 = 0xC0;
+
+/// An account address that may come from untrusted input
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct TaintWarning {
+    /// Offset of the transfer instruction
+    pub offset: usize,
+
+    /// Opcode of the transfer instruction
+    pub opcode: u8,
+
+    /// Which operand of the transfer is affected
+    pub operand: &'static str,
+
+    /// Offset of the instruction the untrusted value was read by
+    pub source: usize,
+}
+
+impl fmt::Display for TaintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:#06X}: {} {} comes from untrusted input read at {:#06X} without a bounds check",
+               self.offset, opcode_name(self.opcode), self.operand, self.source)
+    }
+}
+
+/// Abstract stack value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Value {
+    /// Instruction offset and output index of the definition
+    id: (usize, usize),
+
+    /// Offset of the instruction that read it, while untrusted
+    untrusted: Option<usize>,
+
+    /// For LT/GT results, the untrusted value that was compared and the
+    /// result for which it is bounded from above
+    bound_of: Option<((usize, usize), bool)>,
+
+    /// Value pushed by a PUSH instruction
+    constant: Option<u32>,
+}
+
+impl Value {
+    fn new(offset: usize, index: usize) -> Self {
+        Value { id: (offset, index), untrusted: None, bound_of: None, constant: None }
+    }
+}
+
+/// Find untrusted values used as transfer account addresses
+pub fn analyze(disasm: &Disassembler) -> Vec<TaintWarning> {
+    let cfg = disasm.build_cfg();
+    let Some(entry) = cfg.blocks.iter().position(|b| b.start == 0) else {
+        return Vec::new();
+    };
+
+    let mut states: HashMap<usize, Vec<Value>> = HashMap::from([(entry, Vec::new())]);
+    let mut work = vec![entry];
+    let mut warnings = Vec::new();
+
+    while let Some(b) = work.pop() {
+        let mut stack = states[&b].clone();
+        let mut last = None;
+        let mut condition = None;
+        for &idx in &cfg.blocks[b].lines {
+            let line = &disasm.disassembled[idx];
+            if matches!(line.raw_bytes.first(), Some(&JUMPIF | &JUMPIF2)) {
+                condition = stack.last().and_then(|v| v.bound_of);
+            }
+            if !step(line.offset, &line.raw_bytes, &mut stack, &mut warnings) {
+                // The VM would fault here, or we can't follow the stack
+                last = None;
+                break;
+            }
+            last = line.raw_bytes.first().copied();
+        }
+        let Some(last) = last else {
+            continue;
+        };
+
+        for &(_, to, kind) in cfg.edges.iter().filter(|e| e.0 == b) {
+            if kind == EdgeKind::Fallthrough && last == CALL {
+                continue;
+            }
+
+            // The compared value is only checked on the branch that bounds it
+            let mut out = stack.clone();
+            if let Some((checked, bounded_when)) = condition {
+                if (kind == EdgeKind::Jump) == bounded_when {
+                    for value in out.iter_mut().filter(|v| v.id == checked) {
+                        value.untrusted = None;
+                    }
+                }
+            }
+
+            let changed = match states.get_mut(&to) {
+                Some(existing) => merge(existing, &out),
+                None => {
+                    states.insert(to, out);
+                    true
+                }
+            };
+            if changed {
+                work.push(to);
+            }
+        }
+    }
+
+    warnings.sort();
+    warnings.dedup();
+    warnings
+}
+
+/// Combine the stack reaching a block along another path into the one
+/// recorded for it, returning whether anything changed. Stacks of different
+/// heights keep the first one seen.
+fn merge(into: &mut [Value], other: &[Value]) -> bool {
+    if into.len() != other.len() {
+        return false;
+    }
+
+    let mut changed = false;
+    for (a, b) in into.iter_mut().zip(other) {
+        let merged = Value {
+            id: a.id.min(b.id),
+            untrusted: a.untrusted.or(b.untrusted),
+            bound_of: if a.bound_of == b.bound_of { a.bound_of } else { None },
+            constant: if a.constant == b.constant { a.constant } else { None },
+        };
+        if merged != *a {
+            *a = merged;
+            changed = true;
+        }
+    }
+    changed
+}
+
+/// Apply one instruction to the abstract stack. Returns false if the stack
+/// can't be followed past it.
+fn step(offset: usize, bytes: &[u8], stack: &mut Vec<Value>, warnings: &mut Vec<TaintWarning>) -> bool {
+    let Some((&opcode, operands)) = bytes.split_first() else {
+        return false;
+    };
+
+    match opcode {
+        DUP | SWAP => {
+            let n = operands.first().copied().unwrap_or(0) as usize;
+            if n >= stack.len() {
+                return false;
+            }
+            let top = stack.len() - 1;
+            if opcode == DUP {
+                stack.push(stack[top - n]);
+            } else {
+                stack.swap(top, top - n);
+            }
+            return true;
+        }
+        SYSCALL => {
+            // Only print (0), timestamp (1) and random (2) are known
+            let Some(id) = stack.pop().and_then(|v| v.constant) else {
+                return false;
+            };
+            match id {
+                0 => return stack.pop().is_some(),
+                1 | 2 => {
+                    stack.push(Value::new(offset, 0));
+                    return true;
+                }
+                _ => return false,
+            }
+        }
+        _ => {}
+    }
+
+    let Some((pops, pushes)) = opcode_stack_counts(opcode) else {
+        return false;
+    };
+    if pops > stack.len() {
+        return false;
+    }
+    let inputs = stack.split_off(stack.len() - pops);
+    let untrusted_inputs: Vec<&Value> = inputs.iter().filter(|v| v.untrusted.is_some()).collect();
+
+    let mut output = Value::new(offset, 0);
+    match opcode {
+        PUSH1 | PUSH2 | PUSH4 => {
+            output.constant = Some(operands.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32));
+        }
+        LOAD | LOADW | ATOI | JSONPARSE => output.untrusted = Some(offset),
+        LT | GT => {
+            // a < b bounds a when true and b when false, a > b the reverse
+            if let [value] = untrusted_inputs[..] {
+                let is_left = value.id == inputs[0].id;
+                output.bound_of = Some((value.id, (opcode == LT) == is_left));
+            }
+        }
+        EQ | ISZERO => {}
+        AND => {
+            // Masking with a trusted value bounds the result
+            if untrusted_inputs.len() == inputs.len() {
+                output.untrusted = untrusted_inputs[0].untrusted;
+            }
+        }
+        SOLTRANSFER | TOKENTRANSFER => {
+            let addresses: &[&'static str] = if opcode == SOLTRANSFER {
+                &["destination"]
+            } else {
+                &["token account", "destination"]
+            };
+            for (value, &operand) in inputs.iter().zip(addresses) {
+                if let Some(source) = value.untrusted {
+                    warnings.push(TaintWarning { offset, opcode, operand, source });
+                }
+            }
+        }
+        _ => output.untrusted = untrusted_inputs.first().and_then(|v| v.untrusted),
+    }
+
+    for index in 0..pushes {
+        stack.push(Value { id: (offset, index), ..output });
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn warnings_for(program: Vec<u8>) -> Vec<TaintWarning> {
+        let mut disasm = Disassembler::new(program);
+        disasm.run();
+        analyze(&disasm)
+    }
+
+    #[test]
+    fn test_memory_loaded_transfer_address() {
+        // LOAD 0x80, PUSH1 100, SOLTRANSFER, HALT
+        let warnings = warnings_for(vec![0x40, 0x80, 0x01, 100, 0x50, 0xFF]);
+        assert_eq!(warnings, vec![TaintWarning {
+            offset: 0x04,
+            opcode: SOLTRANSFER,
+            operand: "destination",
+            source: 0x00,
+        }]);
+        assert!(warnings[0].to_string().starts_with("0x0004: SOLTRANSFER destination"));
+
+        // Arithmetic on the loaded value keeps it untrusted
+        assert_eq!(warnings_for(vec![0x40, 0x80, 0x01, 1, 0x10, 0x01, 100, 0x50, 0xFF]).len(), 1);
+    }
+
+    #[test]
+    fn test_bounds_checked_transfer_address() {
+        // 0x00: LOAD 0x80, DUP 0, PUSH1 4, LT, JUMPIF 0x0A
+        // 0x09: HALT
+        // 0x0A: PUSH1 100, SOLTRANSFER, HALT
+        let program = vec![
+            0x40, 0x80, 0x05, 0, 0x01, 4, 0x24, 0x31, 0x0A,
+            0xFF,
+            0x01, 100, 0x50, 0xFF,
+        ];
+        assert!(warnings_for(program).is_empty());
+
+        // Masking works as a bounds check too
+        assert!(warnings_for(vec![0x40, 0x80, 0x01, 3, 0x20, 0x01, 100, 0x50, 0xFF]).is_empty());
+
+        // A check on one path doesn't cover a path that skips it
+        // 0x00: LOAD 0x80, PUSH1 0, JUMPIF 0x0D
+        // 0x06: DUP 0, PUSH1 4, LT, JUMPIF 0x0D
+        // 0x0D: PUSH1 100, SOLTRANSFER, HALT
+        let program = vec![
+            0x40, 0x80, 0x01, 0, 0x31, 0x0D,
+            0x05, 0, 0x01, 4, 0x24, 0x31, 0x0D,
+            0x01, 100, 0x50, 0xFF,
+        ];
+        assert_eq!(warnings_for(program).len(), 1);
+    }
+
+    #[test]
+    fn test_bounds_check_only_covers_bounded_branch() {
+        // 0x00: LOAD 0x80, DUP 0, PUSH1 4, LT, JUMPIF 0x0D
+        // 0x09: PUSH1 100, SOLTRANSFER, HALT
+        // 0x0D: HALT
+        // The transfer runs when x >= 4
+        let program = vec![
+            0x40, 0x80, 0x05, 0, 0x01, 4, 0x24, 0x31, 0x0D,
+            0x01, 100, 0x50, 0xFF,
+            0xFF,
+        ];
+        assert_eq!(warnings_for(program), vec![TaintWarning {
+            offset: 0x0B,
+            opcode: SOLTRANSFER,
+            operand: "destination",
+            source: 0x00,
+        }]);
+
+        // 0x00: LOAD 0x80, DUP 0, PUSH1 4, GT, JUMPIF 0x0A
+        // 0x09: HALT
+        // 0x0A: PUSH1 100, SOLTRANSFER, HALT
+        // The transfer runs when x > 4, while the fallthrough would be bounded
+        let program = vec![
+            0x40, 0x80, 0x05, 0, 0x01, 4, 0x25, 0x31, 0x0A,
+            0xFF,
+            0x01, 100, 0x50, 0xFF,
+        ];
+        assert_eq!(warnings_for(program).len(), 1);
+
+        // Moving the transfer to the fallthrough makes the GT check cover it
+        let program = vec![
+            0x40, 0x80, 0x05, 0, 0x01, 4, 0x25, 0x31, 0x0D,
+            0x01, 100, 0x50, 0xFF,
+            0xFF,
+        ];
+        assert!(warnings_for(program).is_empty());
+
+        // With the constant on the left, 4 < x is the unbounded branch
+        // 0x00: LOAD 0x80, PUSH1 4, DUP 1, LT, JUMPIF 0x0A
+        let program = vec![
+            0x40, 0x80, 0x01, 4, 0x05, 1, 0x24, 0x31, 0x0A,
+            0xFF,
+            0x01, 100, 0x50, 0xFF,
+        ];
+        assert_eq!(warnings_for(program).len(), 1);
+    }
+}
@@ -0,0 +1,52 @@
+use std::fs;
+use std::process::Command;
+
+/// Write `bytecode` to a scratch file and run it through `ideless run --headless`
+fn run_headless(name: &str, bytecode: &[u8], extra_args: &[&str]) -> std::process::Output {
+    let path = std::env::temp_dir().join(format!("ideless-headless-{}-{}.bin", name, std::process::id()));
+    fs::write(&path, bytecode).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ideless"))
+        .arg("run")
+        .arg(&path)
+        .arg("--headless")
+        .args(extra_args)
+        .output()
+        .unwrap();
+
+    fs::remove_file(&path).unwrap();
+    output
+}
+
+#[test]
+fn headless_run_prints_final_state() {
+    // PUSH1 2, PUSH1 3, ADD, HALT
+    let output = run_headless("text", &[0x01, 2, 0x01, 3, 0x10, 0xFF], &[]);
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("State: Halted"));
+    assert!(stdout.contains("Stack: [5]"));
+}
+
+#[test]
+fn headless_run_prints_json() {
+    let output = run_headless("json", &[0x01, 2, 0x01, 3, 0x10, 0xFF], &["--format", "json"]);
+    assert!(output.status.success());
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(report["state"], "Halted");
+    assert_eq!(report["stack"], serde_json::json!([5]));
+    assert_eq!(report["cycles"], 4);
+    assert!(report["error"].is_null());
+}
+
+#[test]
+fn headless_run_fails_on_vm_error() {
+    // ADD on an empty stack
+    let output = run_headless("error", &[0x10, 0xFF], &[]);
+    assert!(!output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("State: Error"));
+}
@@ -17,7 +17,8 @@ pub use solana_program::{
     sysvar::Sysvar,
 };
 
-use vm::VM;
+use solana_program::program::set_return_data;
+use vm::{container, core::DEFAULT_GAS_LIMIT, VM};
 pub use vm::data_structures::*;
 use solana::{
     account::AccountManager,
@@ -112,9 +113,25 @@ fn process_execute<'a>(
         return Err(ProgramError::UninitializedAccount);
     }
 
-    // Create and execute VM
-    let mut vm = VM::new(program_id, accounts, bytecode);
-    let _result = vm.execute(bytecode)?;
+    // Programs may come in a versioned container or as raw bytecode
+    let (meta, code) = container::split(bytecode)?;
+
+    // Reject malformed bytecode before running any of it, including an
+    // entrypoint that isn't on an instruction
+    let entrypoint = meta.and_then(|meta| meta.entrypoint).map_or(0, |entry| entry as usize);
+    VM::validate(code, entrypoint)?;
+
+    // Create and execute VM. The container's gas limit comes from the
+    // caller, so it can only lower the default
+    let gas_limit = meta
+        .and_then(|meta| meta.gas_limit)
+        .map_or(DEFAULT_GAS_LIMIT, |limit| limit.min(DEFAULT_GAS_LIMIT));
+    let mut vm = VM::with_gas_limit(program_id, accounts, code, gas_limit);
+    vm.set_pc(entrypoint);
+    vm.execute(code)?;
+    if let Some(data) = vm.return_data() {
+        set_return_data(&data.to_bytes());
+    }
 
     // Update program state
     state.total_executions += 1;
@@ -161,24 +178,22 @@ mod tests {
     use super::*;
     use solana_program::clock::Epoch;
 
-    fn create_test_account(
+    fn create_test_account<'a>(
         key: Pubkey,
         lamports: u64,
         space: usize,
         owner: Pubkey,
         is_signer: bool,
         is_writable: bool,
-    ) -> AccountInfo<'static> {
-        let mut lamports = lamports;
-        let mut data = vec![0; space];
-        
+    ) -> AccountInfo<'a> {
+        // AccountInfo only borrows what it describes, so leak it to outlive the call
         AccountInfo::new(
-            &key,
+            Box::leak(Box::new(key)),
             is_signer,
             is_writable,
-            &mut lamports,
-            &mut data,
-            &owner,
+            Box::leak(Box::new(lamports)),
+            Box::leak(vec![0; space].into_boxed_slice()),
+            Box::leak(Box::new(owner)),
             false,
             Epoch::default(),
         )
@@ -234,6 +249,14 @@ mod tests {
         let state = ProgramState::unpack(&accounts[0].try_borrow_data().unwrap()).unwrap();
         assert_eq!(state.total_executions, 1);
         assert!(state.total_gas_used > 0);
+
+        // Truncated bytecode is rejected without being executed
+        let bytecode = vec![0x01, 0x05, 0x02, 0x01]; // push1 5, push8 with one byte
+        let exec_data = Instruction::Execute { bytecode }.try_to_vec().unwrap();
+        assert!(process_instruction(&program_id, &accounts, &exec_data).is_err());
+
+        let state = ProgramState::unpack(&accounts[0].try_borrow_data().unwrap()).unwrap();
+        assert_eq!(state.total_executions, 1);
     }
 
     #[test]
@@ -146,24 +146,22 @@ mod tests {
     use super::*;
     use solana_program::clock::Epoch;
 
-    fn create_test_account(
+    fn create_test_account<'a>(
         key: Pubkey,
         lamports: u64,
         space: usize,
         owner: Pubkey,
         is_signer: bool,
         is_writable: bool,
-    ) -> AccountInfo<'static> {
-        let mut lamports = lamports;
-        let mut data = vec![0; space];
-        
+    ) -> AccountInfo<'a> {
+        // AccountInfo only borrows what it describes, so leak it to outlive the call
         AccountInfo::new(
-            &key,
+            Box::leak(Box::new(key)),
             is_signer,
             is_writable,
-            &mut lamports,
-            &mut data,
-            &owner,
+            Box::leak(Box::new(lamports)),
+            Box::leak(vec![0; space].into_boxed_slice()),
+            Box::leak(Box::new(owner)),
             false,
             Epoch::default(),
         )
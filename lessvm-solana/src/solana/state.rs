@@ -138,14 +138,14 @@ mod tests {
         
         // Test packing into too small buffer
         let mut small_buf = vec![0u8; ProgramState::LEN - 1];
-        assert!(std::panic::catch_unwind(|| state.pack_into_slice(&mut small_buf)).is_err());
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| state.pack_into_slice(&mut small_buf))).is_err());
 
         // Test unpacking from too small buffer
         assert!(ProgramState::unpack_from_slice(&small_buf).is_err());
 
         // Test packing into too large buffer
         let mut large_buf = vec![0u8; ProgramState::LEN + 1];
-        assert!(std::panic::catch_unwind(|| state.pack_into_slice(&mut large_buf)).is_err());
+        assert!(std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| state.pack_into_slice(&mut large_buf))).is_err());
 
         // Test unpacking from too large buffer
         assert!(ProgramState::unpack_from_slice(&large_buf).is_err());
@@ -64,36 +64,21 @@ mod tests {
         clock::Epoch,
     };
 
-    fn setup_test_accounts() -> (Pubkey, Vec<AccountInfo>) {
-        let program_id = Pubkey::new_unique();
-        let mut lamports1 = 1000000;
-        let mut lamports2 = 1000000;
-        let mut data1 = vec![0; 32];
-        let mut data2 = vec![0; 32];
-
-        let account1 = AccountInfo::new(
-            &Pubkey::new_unique(),
-            true,
-            true,
-            &mut lamports1,
-            &mut data1,
-            &program_id,
-            false,
-            Epoch::default(),
-        );
-
-        let account2 = AccountInfo::new(
-            &Pubkey::new_unique(),
+    fn setup_test_accounts<'a>() -> (Pubkey, Vec<AccountInfo<'a>>) {
+        // AccountInfo only borrows what it describes, so leak it to outlive the call
+        let program_id: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+        let account = || AccountInfo::new(
+            Box::leak(Box::new(Pubkey::new_unique())),
             true,
             true,
-            &mut lamports2,
-            &mut data2,
-            &program_id,
+            Box::leak(Box::new(1000000)),
+            Box::leak(vec![0; 32].into_boxed_slice()),
+            program_id,
             false,
             Epoch::default(),
         );
 
-        (program_id, vec![account1, account2])
+        (*program_id, vec![account(), account()])
     }
 
     #[test]
@@ -0,0 +1,127 @@
+//! Versioned container for LessVM programs
+//!
+//! Layout, with integers little-endian:
+//!
+//! | Field      | Size | Notes                                   |
+//! |------------|------|-----------------------------------------|
+//! | magic      | 4    | `LSVM`                                  |
+//! | version    | 8    | `VERSION` of the VM the program targets |
+//! | flags      | 1    | bit 0: entrypoint, bit 1: gas limit     |
+//! | entrypoint | 4    | only if flag bit 0 is set               |
+//! | gas limit  | 8    | only if flag bit 1 is set               |
+//! | code size  | 4    |                                         |
+//! | code       | size |                                         |
+//!
+//! Raw bytecode never starts with the magic bytes, since `L` (0x4C) is
+//! `AccountDataLoad`, which can't run first with nothing on the stack, so
+//! loaders can accept either.
+
+use super::core::VERSION;
+use super::{VMError, VMResult};
+
+/// Bytes every container starts with
+pub const MAGIC: &[u8; 4] = b"LSVM";
+
+const FLAG_ENTRYPOINT: u8 = 1 << 0;
+const FLAG_GAS_LIMIT: u8 = 1 << 1;
+
+/// Metadata stored with the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Meta {
+    /// VM version the program was built for
+    pub version: u64,
+    /// Offset execution starts at, when not 0
+    pub entrypoint: Option<u32>,
+    /// Gas the program is expected to need, capped at `DEFAULT_GAS_LIMIT`
+    /// when it runs
+    pub gas_limit: Option<u64>,
+}
+
+impl Default for Meta {
+    fn default() -> Self {
+        Self {
+            version: VERSION,
+            entrypoint: None,
+            gas_limit: None,
+        }
+    }
+}
+
+/// Wrap `code` in a container
+pub fn pack(code: &[u8], meta: &Meta) -> Vec<u8> {
+    let size = u32::try_from(code.len()).expect("program too large for a container");
+    let mut flags = 0;
+    if meta.entrypoint.is_some() {
+        flags |= FLAG_ENTRYPOINT;
+    }
+    if meta.gas_limit.is_some() {
+        flags |= FLAG_GAS_LIMIT;
+    }
+
+    let mut bytes = Vec::with_capacity(29 + code.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&meta.version.to_le_bytes());
+    bytes.push(flags);
+    if let Some(entrypoint) = meta.entrypoint {
+        bytes.extend_from_slice(&entrypoint.to_le_bytes());
+    }
+    if let Some(gas_limit) = meta.gas_limit {
+        bytes.extend_from_slice(&gas_limit.to_le_bytes());
+    }
+    bytes.extend_from_slice(&size.to_le_bytes());
+    bytes.extend_from_slice(code);
+    bytes
+}
+
+/// Take a container apart into its metadata and code
+pub fn unpack(bytes: &[u8]) -> VMResult<(Meta, Vec<u8>)> {
+    match split(bytes)? {
+        (Some(meta), code) => Ok((meta, code.to_vec())),
+        (None, _) => Err(VMError::InvalidContainer),
+    }
+}
+
+/// Metadata and code of a program that may or may not be in a container.
+/// Raw bytecode is returned as-is with no metadata.
+pub fn split(bytes: &[u8]) -> VMResult<(Option<Meta>, &[u8])> {
+    let Some(mut rest) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        return Ok((None, bytes));
+    };
+
+    let version = u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap());
+    if version != VERSION {
+        return Err(VMError::UnsupportedVersion);
+    }
+    let flags = take(&mut rest, 1)?[0];
+    if flags & !(FLAG_ENTRYPOINT | FLAG_GAS_LIMIT) != 0 {
+        return Err(VMError::InvalidContainer);
+    }
+    let entrypoint = match flags & FLAG_ENTRYPOINT {
+        0 => None,
+        _ => Some(u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap())),
+    };
+    let gas_limit = match flags & FLAG_GAS_LIMIT {
+        0 => None,
+        _ => Some(u64::from_le_bytes(take(&mut rest, 8)?.try_into().unwrap())),
+    };
+    let size = u32::from_le_bytes(take(&mut rest, 4)?.try_into().unwrap()) as usize;
+    let code = take(&mut rest, size)?;
+    if !rest.is_empty() {
+        return Err(VMError::InvalidContainer);
+    }
+    if entrypoint.is_some_and(|entry| entry as usize >= code.len()) {
+        return Err(VMError::InvalidContainer);
+    }
+
+    Ok((Some(Meta { version, entrypoint, gas_limit }), code))
+}
+
+/// Split `n` bytes off the front of `rest`
+fn take<'b>(rest: &mut &'b [u8], n: usize) -> VMResult<&'b [u8]> {
+    if rest.len() < n {
+        return Err(VMError::InvalidContainer);
+    }
+    let (field, tail) = rest.split_at(n);
+    *rest = tail;
+    Ok(field)
+}
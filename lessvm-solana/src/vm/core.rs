@@ -1,11 +1,19 @@
 use solana_program::{
     account_info::AccountInfo,
+    clock::Clock,
+    instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+    keccak,
+    sysvar::{
+        instructions::{load_current_index_checked, load_instruction_at_checked},
+        Sysvar,
+    },
+    program::invoke,
     program_error::ProgramError,
     pubkey::Pubkey,
     msg,
 };
 use super::{
-    OpCode, Stack, Memory, Gas, Value, VMError,
+    OpCode, Stack, Memory, Gas, GasSchedule, Value, VMError, fixed,
     data_structures::{
         BTreeMapDS,
         TrieDS,
@@ -13,14 +21,19 @@ use super::{
         OHLCVDS,
         HypergraphDS,
         DataStructureType,
-        OHLCVEntry
+        OHLCVEntry,
+        RangeCursor,
     },
     debug::{Tracer, DefaultTracer, ExecutionTrace},
+    return_data::ReturnData,
 };
 
 /// Current version of the VM runtime
 pub const VERSION: u64 = 2;
 
+/// Gas available to a VM created with `VM::new`
+pub const DEFAULT_GAS_LIMIT: u64 = 200_000;
+
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
@@ -54,13 +67,16 @@ impl ReentrancyGuard {
 /// Maximum number of data structures that can be created
 const MAX_DATA_STRUCTURES: usize = 16;
 
+/// Index `BSearchMem` pushes when the key isn't in the region
+pub const BSEARCH_NOT_FOUND: u64 = u64::MAX;
+
 #[repr(C, align(64))]
-struct DataStructureStore {
-    btrees: Vec<Option<BTreeMapDS>>,
-    tries: Vec<Option<TrieDS>>,
-    graphs: Vec<Option<GraphDS>>,
-    ohlcvs: Vec<Option<OHLCVDS>>,
-    hypergraphs: Vec<Option<HypergraphDS>>,
+pub(crate) struct DataStructureStore {
+    pub(crate) btrees: Vec<Option<BTreeMapDS>>,
+    pub(crate) tries: Vec<Option<TrieDS>>,
+    pub(crate) graphs: Vec<Option<GraphDS>>,
+    pub(crate) ohlcvs: Vec<Option<OHLCVDS>>,
+    pub(crate) hypergraphs: Vec<Option<HypergraphDS>>,
 }
 
 impl DataStructureStore {
@@ -74,7 +90,7 @@ impl DataStructureStore {
         }
     }
 
-    fn ensure_capacity(&mut self, ds_type: DataStructureType, id: usize) {
+    pub(crate) fn ensure_capacity(&mut self, ds_type: DataStructureType, id: usize) {
         match ds_type {
             DataStructureType::BTreeMap => {
                 if id >= self.btrees.len() {
@@ -109,13 +125,16 @@ impl DataStructureStore {
 pub struct VM<'a> {
     pc: usize,
     gas: Gas,
-    stack: Stack,
-    memory: Memory,
+    gas_limit: u64,
+    gas_schedule: GasSchedule,
+    pub(crate) stack: Stack,
+    pub(crate) memory: Memory,
     accounts: AccountsView<'a>,
     program_id: &'a Pubkey,
-    data_structures: DataStructureStore,
+    pub(crate) data_structures: DataStructureStore,
     reentrancy_guard: ReentrancyGuard,
     tracer: Box<dyn Tracer>,
+    return_data: Option<ReturnData>,
 }
 
 impl<'a> VM<'a> {
@@ -125,13 +144,36 @@ impl<'a> VM<'a> {
     }
 
     pub fn new(
+        program_id: &'a Pubkey,
+        accounts: &'a [AccountInfo<'a>],
+        instruction_data: &'a [u8],
+    ) -> Self {
+        Self::with_gas_limit(program_id, accounts, instruction_data, DEFAULT_GAS_LIMIT)
+    }
+
+    /// Create a VM with a custom gas limit
+    pub fn with_gas_limit(
+        program_id: &'a Pubkey,
+        accounts: &'a [AccountInfo<'a>],
+        instruction_data: &'a [u8],
+        gas_limit: u64,
+    ) -> Self {
+        Self::with_gas_schedule(program_id, accounts, instruction_data, gas_limit, GasSchedule::default())
+    }
+
+    /// Create a VM that charges opcodes according to `gas_schedule`
+    pub fn with_gas_schedule(
         program_id: &'a Pubkey,
         accounts: &'a [AccountInfo<'a>],
         _instruction_data: &'a [u8],
+        gas_limit: u64,
+        gas_schedule: GasSchedule,
     ) -> Self {
         Self {
             pc: 0,
-            gas: Gas::new(200_000),
+            gas: Gas::new(gas_limit),
+            gas_limit,
+            gas_schedule,
             stack: Stack::new(),
             memory: Memory::new(),
             accounts: AccountsView { accounts, current: 0 },
@@ -139,6 +181,7 @@ impl<'a> VM<'a> {
             data_structures: DataStructureStore::new(),
             reentrancy_guard: ReentrancyGuard::new(),
             tracer: Box::new(DefaultTracer),
+            return_data: None,
         }
     }
 
@@ -152,9 +195,96 @@ impl<'a> VM<'a> {
         self.stack.pop_frame()
     }
 
+    pub fn gas_limit(&self) -> u64 {
+        self.gas_limit
+    }
+
     pub fn gas_used(&self) -> u64 {
-        let initial_gas = 200_000; // Same as in new()
-        initial_gas - self.gas.remaining()
+        self.gas_limit.saturating_sub(self.gas.remaining())
+    }
+
+    pub fn gas_schedule(&self) -> &GasSchedule {
+        &self.gas_schedule
+    }
+
+    /// Start execution at `pc` rather than 0, e.g. a container's entrypoint
+    pub fn set_pc(&mut self, pc: usize) {
+        self.pc = pc;
+    }
+
+    /// The operand stack, e.g. to read results after a Halt
+    pub fn stack(&self) -> &Stack {
+        &self.stack
+    }
+
+    /// The operand stack, e.g. to push inputs before `execute`
+    pub fn stack_mut(&mut self) -> &mut Stack {
+        &mut self.stack
+    }
+
+    /// What the last `execute` returned, if the program returned anything
+    pub fn return_data(&self) -> Option<&ReturnData> {
+        self.return_data.as_ref()
+    }
+
+    /// The BTreeMap with the given ID, if it has been created
+    pub fn btree(&self, id: usize) -> Option<&BTreeMapDS> {
+        self.data_structures.btrees.get(id)?.as_ref()
+    }
+
+    /// The Trie with the given ID, if it has been created
+    pub fn trie(&self, id: usize) -> Option<&TrieDS> {
+        self.data_structures.tries.get(id)?.as_ref()
+    }
+
+    /// The Graph with the given ID, if it has been created
+    pub fn graph(&self, id: usize) -> Option<&GraphDS> {
+        self.data_structures.graphs.get(id)?.as_ref()
+    }
+
+    /// Check a program before running it from `entry`: every opcode must be
+    /// known, its operands must fit in the program, and `entry` as well as a
+    /// jump or call whose target was pushed by the previous instruction must
+    /// land on an instruction
+    pub fn validate(code: &[u8], entry: usize) -> Result<(), VMError> {
+        let mut boundaries = vec![false; code.len()];
+        let mut targets = Vec::new();
+        let mut pushed = None;
+        let mut pc = 0;
+
+        while pc < code.len() {
+            let opcode = OpCode::from_byte(code[pc]).ok_or(VMError::InvalidInstruction)?;
+            boundaries[pc] = true;
+
+            let end = pc + 1 + opcode.operand_size();
+            let operand = code.get(pc + 1..end).ok_or(VMError::InvalidInstruction)?;
+
+            if matches!(opcode, OpCode::Jump | OpCode::JumpI | OpCode::Call) {
+                targets.extend(pushed);
+            }
+            pushed = match opcode {
+                OpCode::Push1 => Some(operand[0] as u64),
+                OpCode::Push8 => Some(u64::from_le_bytes(operand.try_into().unwrap())),
+                _ => None,
+            };
+            pc = end;
+        }
+
+        // Nothing runs from offset 0 of an empty program, so that's fine too
+        if entry != 0 && !boundaries.get(entry).copied().unwrap_or(false) {
+            return Err(VMError::InvalidInstruction);
+        }
+        for target in targets {
+            let on_boundary = usize::try_from(target)
+                .ok()
+                .and_then(|target| boundaries.get(target).copied())
+                .unwrap_or(false);
+            if !on_boundary {
+                return Err(VMError::InvalidInstruction);
+            }
+        }
+
+        Ok(())
     }
 
     #[inline(always)]
@@ -177,39 +307,115 @@ impl<'a> VM<'a> {
         Ok(u64::from_le_bytes(bytes))
     }
 
-    #[cfg(target_arch = "x86_64")]
+    /// Pops two 4-lane vectors, returning them in push order.
+    ///
+    /// With `a0 a1 a2 a3 b0 b1 b2 b3` on the stack (top last) this returns
+    /// `([a0, a1, a2, a3], [b0, b1, b2, b3])`.
     #[inline(always)]
-    unsafe fn vector_add(&mut self) -> Result<(), VMError> {
+    fn pop_vector_pair(&mut self) -> Result<([u64; 4], [u64; 4]), VMError> {
         if self.stack.depth() < 8 {
             return Err(VMError::StackUnderflow);
         }
 
-        // Get the second vector from the stack (4 values)
-        let values2 = _mm256_loadu_si256(self.stack.as_simd_ptr()? as *const __m256i);
-        
-        // Pop the first 4 values to get to the first vector
-        self.stack.pop()?;
-        self.stack.pop()?;
-        self.stack.pop()?;
-        self.stack.pop()?;
-        
-        // Get the first vector from the stack (4 values)
-        let values1 = _mm256_loadu_si256(self.stack.as_simd_ptr()? as *const __m256i);
-        
-        // Add the two vectors
-        let result = _mm256_add_epi64(values1, values2);
-        
-        // Store the result back to the stack
-        _mm256_storeu_si256(self.stack.as_simd_mut_ptr()? as *mut __m256i, result);
+        let mut a = [0u64; 4];
+        let mut b = [0u64; 4];
+        for lane in b.iter_mut().rev() {
+            *lane = self.stack.pop()?.0;
+        }
+        for lane in a.iter_mut().rev() {
+            *lane = self.stack.pop()?.0;
+        }
+        Ok((a, b))
+    }
+
+    #[inline(always)]
+    fn push_vector(&mut self, lanes: [u64; 4]) -> Result<(), VMError> {
+        for lane in lanes {
+            self.stack.push(Value(lane))?;
+        }
+        Ok(())
+    }
+
+    /// Pops a Q64.64 number stored as integer part then fraction (top)
+    #[inline(always)]
+    fn pop_fixed(&mut self) -> Result<u128, VMError> {
+        let frac = self.stack.pop()?.0;
+        let int = self.stack.pop()?.0;
+        Ok(fixed::from_parts(int, frac))
+    }
+
+    #[inline(always)]
+    fn push_fixed(&mut self, value: u128) -> Result<(), VMError> {
+        let (int, frac) = fixed::to_parts(value);
+        self.stack.push(Value(int))?;
+        self.stack.push(Value(frac))
+    }
+
+    /// Pushes the next batch of a BTreeMap range scan like BTreeRange does,
+    /// and saves the advanced cursor at `ptr`. Each entry returned costs two
+    /// extra gas.
+    fn push_range_batch(&mut self, id: usize, ptr: usize, mut cursor: RangeCursor) -> Result<(), VMError> {
+        let entries = match self.data_structures.btrees.get(id) {
+            Some(Some(btree)) => btree.range_batch(&mut cursor),
+            _ => return Err(VMError::InvalidDataStructureOperation),
+        };
+        self.gas.consume(entries.len() as u64 * 2)?;
+        self.memory.store(ptr, &cursor.to_bytes())?;
+
+        self.stack.push(Value(entries.len() as u64))?;
+        for (key, value) in entries.iter().rev() {
+            self.stack.push(Value(*value))?;
+            self.stack.push(Value(*key))?;
+        }
         Ok(())
     }
 
+    /// Pushes the first 8 bytes of the Keccak-256 hash of a data structure's
+    /// canonical serialization, charging six gas per 32-byte word like
+    /// Keccak256
+    fn push_contents_hash(&mut self, contents: &[u8]) -> Result<(), VMError> {
+        self.gas.consume(contents.len().div_ceil(32) as u64 * 6)?;
+        let digest = keccak::hash(contents).to_bytes();
+        self.stack.push(Value(u64::from_le_bytes(digest[..8].try_into().unwrap())))
+    }
+
+    /// Replaces the top two 4-lane vectors with their lane-wise (wrapping)
+    /// sum, using AVX2 when the CPU has it
+    #[inline(always)]
+    pub(crate) fn vector_add(&mut self) -> Result<(), VMError> {
+        #[cfg(target_arch = "x86_64")]
+        #[target_feature(enable = "avx2")]
+        unsafe fn add_lanes_avx2(a: &[u64; 4], b: &[u64; 4]) -> [u64; 4] {
+            let lhs = _mm256_loadu_si256(a.as_ptr() as *const __m256i);
+            let rhs = _mm256_loadu_si256(b.as_ptr() as *const __m256i);
+
+            let mut sum = [0u64; 4];
+            _mm256_storeu_si256(sum.as_mut_ptr() as *mut __m256i, _mm256_add_epi64(lhs, rhs));
+            sum
+        }
+
+        let (a, b) = self.pop_vector_pair()?;
+
+        #[cfg(target_arch = "x86_64")]
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: AVX2 support was checked just above
+            return self.push_vector(unsafe { add_lanes_avx2(&a, &b) });
+        }
+
+        let mut sum = [0u64; 4];
+        for (out, (x, y)) in sum.iter_mut().zip(a.iter().zip(b.iter())) {
+            *out = x.wrapping_add(*y);
+        }
+        self.push_vector(sum)
+    }
+
     pub fn execute(&mut self, code: &[u8]) -> Result<Option<Value>, ProgramError> {
         self.reentrancy_guard.enter()?;
+        self.return_data = None;
 
         while self.pc < code.len() {
             let opcode = self.fetch_opcode(code)?;
-            let gas_cost = opcode.gas_cost();
+            let gas_cost = self.gas_schedule.cost(opcode);
             self.gas.consume(gas_cost)?;
 
             self.tracer.trace_execution(ExecutionTrace {
@@ -297,6 +503,74 @@ impl<'a> VM<'a> {
                     let result = Value(a.0 % b.0);
                     self.stack.push(result)?;
                 },
+                OpCode::DivMod => {
+                    // Pushes the quotient, then the remainder
+                    let b = self.stack.pop()?;
+                    let a = self.stack.pop()?;
+                    if b.0 == 0 {
+                        return Err(VMError::DivisionByZero.into());
+                    }
+                    self.stack.push(Value(a.0 / b.0))?;
+                    self.stack.push(Value(a.0 % b.0))?;
+                },
+                OpCode::MulWide => {
+                    // Pushes the high 64 bits of the 128-bit product, then the low 64 bits
+                    let b = self.stack.pop()?;
+                    let a = self.stack.pop()?;
+                    let product = (a.0 as u128) * (b.0 as u128);
+                    self.stack.push(Value((product >> 64) as u64))?;
+                    self.stack.push(Value(product as u64))?;
+                },
+                // Q64.64 fixed-point operations, see `vm::fixed`
+                OpCode::FpFromInt => {
+                    let value = self.stack.pop()?.0;
+                    self.push_fixed(fixed::from_int(value))?;
+                },
+                OpCode::FpToInt => {
+                    let value = self.pop_fixed()?;
+                    self.stack.push(Value(fixed::to_int(value)))?;
+                },
+                OpCode::FpMul => {
+                    let b = self.pop_fixed()?;
+                    let a = self.pop_fixed()?;
+                    let result = fixed::mul(a, b).ok_or(VMError::ArithmeticOverflow)?;
+                    self.push_fixed(result)?;
+                },
+                OpCode::FpDiv => {
+                    let b = self.pop_fixed()?;
+                    let a = self.pop_fixed()?;
+                    let result = fixed::div(a, b).ok_or(VMError::ArithmeticOverflow)?;
+                    self.push_fixed(result)?;
+                },
+                OpCode::Lt => {
+                    let b = self.stack.pop()?.0;
+                    let a = self.stack.pop()?.0;
+                    self.stack.push(Value((a < b) as u64))?;
+                },
+                OpCode::Gt => {
+                    let b = self.stack.pop()?.0;
+                    let a = self.stack.pop()?.0;
+                    self.stack.push(Value((a > b) as u64))?;
+                },
+                OpCode::Eq => {
+                    let b = self.stack.pop()?.0;
+                    let a = self.stack.pop()?.0;
+                    self.stack.push(Value((a == b) as u64))?;
+                },
+                OpCode::IsZero => {
+                    let a = self.stack.pop()?.0;
+                    self.stack.push(Value((a == 0) as u64))?;
+                },
+                OpCode::NarrowU32 => {
+                    // Pushes the low 32 bits, then 1 if any high bit was lost
+                    let value = self.stack.pop()?.0;
+                    self.stack.push(Value(value as u32 as u64))?;
+                    self.stack.push(Value((value > u32::MAX as u64) as u64))?;
+                },
+                OpCode::ExtendU32 => {
+                    let value = self.stack.pop()?.0;
+                    self.stack.push(Value(value as u32 as u64))?;
+                },
                 OpCode::Exp => {
                     let exponent = self.stack.pop()?.0;
                     let base = self.stack.pop()?.0;
@@ -330,7 +604,7 @@ impl<'a> VM<'a> {
                     if byte_num >= 8 {
                         // No change if byte_num is out of range
                         self.stack.push(Value(value))?;
-                        return Ok(());
+                        continue;
                     }
                     
                     let bit_position = (byte_num + 1) * 8 - 1;
@@ -390,6 +664,79 @@ impl<'a> VM<'a> {
                     self.memory.store8(offset, value)?;
                 },
 
+                OpCode::StackToMem => {
+                    let count = self.stack.pop()?.0 as usize;
+                    let offset = self.stack.pop()?.0 as usize;
+                    if count > self.stack.depth() {
+                        return Err(VMError::StackUnderflow.into());
+                    }
+                    // One extra gas per value moved
+                    self.gas.consume(count as u64)?;
+
+                    // The deepest of the values lands at `offset` and the top
+                    // of the stack last, so MemToStack restores the order
+                    let mut bytes = vec![0u8; count * 8];
+                    for chunk in bytes.chunks_exact_mut(8).rev() {
+                        chunk.copy_from_slice(&self.stack.pop()?.0.to_le_bytes());
+                    }
+                    self.memory.store(offset, &bytes)?;
+                },
+                OpCode::MemToStack => {
+                    let offset = self.stack.pop()?.0 as usize;
+                    let count = self.stack.pop()?.0 as usize;
+                    let len = count.checked_mul(8).ok_or(VMError::InvalidMemoryAccess)?;
+                    self.gas.consume(count as u64)?;
+
+                    let words: Vec<u64> = self.memory.load(offset, len)?
+                        .chunks_exact(8)
+                        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+                        .collect();
+                    for word in words {
+                        self.stack.push(Value(word))?;
+                    }
+                },
+                OpCode::MCopy => {
+                    let dst = self.stack.pop()?.0 as usize;
+                    let src = self.stack.pop()?.0 as usize;
+                    let len = self.stack.pop()?.0 as usize;
+                    // Three extra gas per 32-byte word copied
+                    self.gas.consume((len.div_ceil(32) as u64).saturating_mul(3))?;
+
+                    self.memory.copy_within(dst, src, len)?;
+                },
+                OpCode::SortMem => {
+                    let offset = self.stack.pop()?.0 as usize;
+                    let count = self.stack.pop()?.0 as usize;
+                    let width = self.stack.pop()?.0 as usize;
+                    // n·log2(n) extra gas for the comparisons, rounding the log up
+                    let rounds = usize::BITS - count.saturating_sub(1).leading_zeros();
+                    self.gas.consume((count as u64).saturating_mul(rounds as u64))?;
+
+                    self.memory.sort(offset, count, width)?;
+                },
+                OpCode::BSearchMem => {
+                    let offset = self.stack.pop()?.0 as usize;
+                    let count = self.stack.pop()?.0 as usize;
+                    let width = self.stack.pop()?.0 as usize;
+                    let key = self.stack.pop()?.0;
+                    // One extra gas per probe
+                    self.gas.consume((usize::BITS - count.leading_zeros()) as u64)?;
+
+                    let index = self.memory.binary_search(offset, count, width, key)?;
+                    self.stack.push(Value(index.map_or(BSEARCH_NOT_FOUND, |index| index as u64)))?;
+                },
+                // Crypto Operations
+                OpCode::Keccak256 => {
+                    let dest = self.stack.pop()?.0 as usize;
+                    let len = self.stack.pop()?.0 as usize;
+                    let offset = self.stack.pop()?.0 as usize;
+                    // Six extra gas per 32-byte word hashed, as on the EVM
+                    self.gas.consume((len.div_ceil(32) as u64).saturating_mul(6))?;
+
+                    let digest = keccak::hash(self.memory.load(offset, len)?);
+                    self.memory.store(dest, &digest.to_bytes())?;
+                },
+
                 // Control Flow
                 OpCode::Jump => {
                     let target = self.stack.pop()?.0 as usize;
@@ -420,8 +767,34 @@ impl<'a> VM<'a> {
                 OpCode::Return => {
                     let return_value = self.stack.pop()?;
                     self.exit_function()?;
+                    self.return_data = Some(ReturnData::Value(return_value));
                     return Ok(Some(return_value));
                 },
+                OpCode::ReturnBytes => {
+                    let len = self.stack.pop()?.0 as usize;
+                    let offset = self.stack.pop()?.0 as usize;
+                    // Three extra gas per 32-byte word returned
+                    self.gas.consume((len.div_ceil(32) as u64).saturating_mul(3))?;
+
+                    let bytes = self.memory.load(offset, len)?.to_vec();
+                    self.return_data = Some(ReturnData::bytes(bytes)?);
+                    break;
+                },
+                OpCode::ReturnValues => {
+                    let count = self.stack.pop()?.0 as usize;
+                    if count > self.stack.depth() {
+                        return Err(VMError::StackUnderflow.into());
+                    }
+                    // One extra gas per value returned
+                    self.gas.consume(count as u64)?;
+
+                    let mut values = vec![Value(0); count];
+                    for value in values.iter_mut().rev() {
+                        *value = self.stack.pop()?;
+                    }
+                    self.return_data = Some(ReturnData::values(values)?);
+                    break;
+                },
 
                 // Solana Operations
                 OpCode::Transfer => {
@@ -447,8 +820,38 @@ impl<'a> VM<'a> {
                         .ok_or(VMError::ArithmeticOverflow)?;
                 },
                 OpCode::SPLTransfer => {
-                    // Implement SPL token transfer
-                    return Err(ProgramError::InvalidInstructionData);
+                    // Stack: [source_idx, dest_idx, authority_idx, amount]
+                    let amount = self.stack.pop()?.0;
+                    let authority_idx = self.stack.pop()?.0 as usize;
+                    let dest_idx = self.stack.pop()?.0 as usize;
+                    let src_idx = self.stack.pop()?.0 as usize;
+
+                    let num_accounts = self.accounts.accounts.len();
+                    if src_idx >= num_accounts || dest_idx >= num_accounts || authority_idx >= num_accounts {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+
+                    let source = &self.accounts.accounts[src_idx];
+                    let destination = &self.accounts.accounts[dest_idx];
+                    let authority = &self.accounts.accounts[authority_idx];
+
+                    let ix = spl_token::instruction::transfer(
+                        &spl_token::id(),
+                        source.key,
+                        destination.key,
+                        authority.key,
+                        &[],
+                        amount,
+                    )?;
+
+                    let mut account_infos = vec![source.clone(), destination.clone(), authority.clone()];
+                    if let Some(token_program) = self.accounts.accounts.iter().find(|a| *a.key == spl_token::id()) {
+                        account_infos.push(token_program.clone());
+                    }
+
+                    // The reentrancy guard stays entered for the duration of the CPI,
+                    // so a callback into this VM through the token program is rejected
+                    invoke(&ix, &account_infos)?;
                 },
                 OpCode::CPI => {
                     // Implement CPI
@@ -462,9 +865,7 @@ impl<'a> VM<'a> {
                 // Data Structure Operations - BTreeMap
                 OpCode::BTreeCreate => {
                     let id = self.stack.pop()?.0 as usize;
-                    if id >= self.data_structures.btrees.len() {
-                        return Err(VMError::InvalidDataStructureOperation.into());
-                    }
+                    self.data_structures.ensure_capacity(DataStructureType::BTreeMap, id);
                     self.data_structures.btrees[id] = Some(BTreeMapDS::new());
                 },
                 OpCode::BTreeInsert => {
@@ -574,6 +975,60 @@ impl<'a> VM<'a> {
                         return Err(VMError::InvalidDataStructureOperation.into());
                     }
                 },
+                OpCode::BTreeRange => {
+                    let high = self.stack.pop()?.0;
+                    let low = self.stack.pop()?.0;
+                    let id = self.stack.pop()?.0 as usize;
+
+                    if id >= self.data_structures.btrees.len() {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+
+                    if let Some(btree) = &self.data_structures.btrees[id] {
+                        let entries = btree.range(low, high);
+
+                        // Push the count, then each pair in reverse order so
+                        // the lowest key pops first, followed by its value
+                        self.stack.push(Value(entries.len() as u64))?;
+                        for (key, value) in entries.iter().rev() {
+                            self.stack.push(Value(*value))?;
+                            self.stack.push(Value(*key))?;
+                        }
+                    } else {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+                },
+                OpCode::BTreeRangeCursor => {
+                    let ptr = self.stack.pop()?.0 as usize;
+                    let limit = self.stack.pop()?.0;
+                    let high = self.stack.pop()?.0;
+                    let low = self.stack.pop()?.0;
+                    let id = self.stack.pop()?.0 as usize;
+
+                    if limit == 0 {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+                    self.push_range_batch(id, ptr, RangeCursor::new(low, high, limit))?;
+                },
+                OpCode::BTreeRangeNext => {
+                    let ptr = self.stack.pop()?.0 as usize;
+                    let id = self.stack.pop()?.0 as usize;
+
+                    let cursor = RangeCursor::from_bytes(self.memory.load(ptr, RangeCursor::SIZE)?)?;
+                    if cursor.limit == 0 {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+                    self.push_range_batch(id, ptr, cursor)?;
+                },
+                OpCode::BTreeHash => {
+                    let id = self.stack.pop()?.0 as usize;
+
+                    let contents = match self.data_structures.btrees.get(id) {
+                        Some(Some(btree)) => btree.canonical_bytes(),
+                        _ => return Err(VMError::InvalidDataStructureOperation.into()),
+                    };
+                    self.push_contents_hash(&contents)?;
+                },
                 OpCode::BTreeClear => {
                     let id = self.stack.pop()?.0 as usize;
                     
@@ -675,13 +1130,56 @@ impl<'a> VM<'a> {
                         return Err(VMError::InvalidDataStructureOperation.into());
                     }
                 },
+                OpCode::TrieRemove => {
+                    // Stack: [id, key_ptr, key_len]
+                    let key_len = self.stack.pop()?.0 as usize;
+                    let key_ptr = self.stack.pop()?.0 as usize;
+                    let id = self.stack.pop()?.0 as usize;
+                    
+                    // Validate key length
+                    if key_len == 0 {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+                    
+                    self.data_structures.ensure_capacity(DataStructureType::Trie, id);
+                    
+                    // Read key from memory with bounds check
+                    let key = match self.memory.load(key_ptr, key_len) {
+                        Ok(k) => k,
+                        Err(_) => return Err(VMError::InvalidDataStructureOperation.into()),
+                    };
+                    
+                    if let Some(trie) = &mut self.data_structures.tries[id] {
+                        self.stack.push(Value(if trie.remove(key) { 1 } else { 0 }))?;
+                    } else {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+                },
+                OpCode::TrieLen => {
+                    let id = self.stack.pop()?.0 as usize;
+                    
+                    self.data_structures.ensure_capacity(DataStructureType::Trie, id);
+                    
+                    if let Some(trie) = &self.data_structures.tries[id] {
+                        self.stack.push(Value(trie.len() as u64))?;
+                    } else {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+                },
+                OpCode::TrieHash => {
+                    let id = self.stack.pop()?.0 as usize;
+
+                    let contents = match self.data_structures.tries.get(id) {
+                        Some(Some(trie)) => trie.canonical_bytes(),
+                        _ => return Err(VMError::InvalidDataStructureOperation.into()),
+                    };
+                    self.push_contents_hash(&contents)?;
+                },
                 
                 // Graph operations - implementing basic ones
                 OpCode::GraphCreate => {
                     let id = self.stack.pop()?.0 as usize;
-                    if id >= self.data_structures.graphs.len() {
-                        return Err(VMError::InvalidDataStructureOperation.into());
-                    }
+                    self.data_structures.ensure_capacity(DataStructureType::Graph, id);
                     self.data_structures.graphs[id] = Some(GraphDS::new());
                 },
                 OpCode::GraphAddNode => {
@@ -827,7 +1325,7 @@ impl<'a> VM<'a> {
                         return Err(VMError::InvalidAccount.into());
                     }
                     let account = &self.accounts.accounts[account_idx];
-                    self.stack.push(Value(if account.is_writable() { 1 } else { 0 }))?;
+                    self.stack.push(Value(if account.is_writable { 1 } else { 0 }))?;
                 },
                 OpCode::IsSigner => {
                     let account_idx = self.stack.pop()?.0 as usize;
@@ -837,6 +1335,122 @@ impl<'a> VM<'a> {
                     let account = &self.accounts.accounts[account_idx];
                     self.stack.push(Value(if account.is_signer { 1 } else { 0 }))?;
                 },
+                OpCode::CallerProgramId => {
+                    // The instructions sysvar only records transaction-level
+                    // instructions, so it names our caller only when that
+                    // instruction invoked us directly
+                    let dest = self.stack.pop()?.0 as usize;
+                    let sysvar_idx = self.stack.pop()?.0 as usize;
+                    if sysvar_idx >= self.accounts.accounts.len() {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+
+                    let height = get_stack_height();
+                    if height <= TRANSACTION_LEVEL_STACK_HEIGHT {
+                        // Invoked directly by the transaction, there is no caller
+                        self.stack.push(Value(0))?;
+                    } else if height > TRANSACTION_LEVEL_STACK_HEIGHT + 1 {
+                        // Some other program sits between us and the
+                        // transaction-level one
+                        return Err(VMError::UnknownCaller.into());
+                    } else {
+                        let sysvar = &self.accounts.accounts[sysvar_idx];
+                        let current = load_current_index_checked(sysvar)
+                            .map_err(|_| VMError::InvalidAccount)?;
+                        let caller = load_instruction_at_checked(current as usize, sysvar)
+                            .map_err(|_| VMError::InvalidAccount)?
+                            .program_id;
+                        self.memory.store(dest, caller.as_ref())?;
+                        self.stack.push(Value(1))?;
+                    }
+                },
+                OpCode::LoadInstructionAt => {
+                    // Copies another instruction of the transaction into memory
+                    // as its 32-byte program id followed by its data
+                    let dest = self.stack.pop()?.0 as usize;
+                    let index = self.stack.pop()?.0 as usize;
+                    let sysvar_idx = self.stack.pop()?.0 as usize;
+                    if sysvar_idx >= self.accounts.accounts.len() {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+
+                    let instruction = load_instruction_at_checked(
+                        index,
+                        &self.accounts.accounts[sysvar_idx],
+                    )
+                    .map_err(|_| VMError::InvalidAccount)?;
+                    let data_start = dest.checked_add(32)
+                        .ok_or(VMError::InvalidMemoryAccess)?;
+                    self.memory.store(dest, instruction.program_id.as_ref())?;
+                    self.memory.store(data_start, &instruction.data)?;
+                    self.stack.push(Value(instruction.data.len() as u64))?;
+                },
+                OpCode::GetClock => {
+                    // Read from the sysvar rather than the host clock so every
+                    // validator sees the same values
+                    let clock = Clock::get()?;
+                    self.stack.push(Value(clock.unix_timestamp as u64))?;
+                    self.stack.push(Value(clock.slot))?;
+                    self.stack.push(Value(clock.epoch))?;
+                },
+                OpCode::AccountDataLen => {
+                    let account_idx = self.stack.pop()?.0 as usize;
+                    if account_idx >= self.accounts.accounts.len() {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+                    let account = &self.accounts.accounts[account_idx];
+                    self.stack.push(Value(account.data_len() as u64))?;
+                },
+                OpCode::AccountDataLoad => {
+                    let offset = self.stack.pop()?.0 as usize;
+                    let account_idx = self.stack.pop()?.0 as usize;
+                    if account_idx >= self.accounts.accounts.len() {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+                    let data = self.accounts.accounts[account_idx].try_borrow_data()?;
+                    let word = offset.checked_add(8)
+                        .and_then(|end| data.get(offset..end))
+                        .ok_or(VMError::InvalidMemoryAccess)?;
+                    self.stack.push(Value(u64::from_le_bytes(word.try_into().unwrap())))?;
+                },
+                OpCode::AccountDataStore => {
+                    let value = self.stack.pop()?.0;
+                    let offset = self.stack.pop()?.0 as usize;
+                    let account_idx = self.stack.pop()?.0 as usize;
+                    if account_idx >= self.accounts.accounts.len() {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+                    let account = &self.accounts.accounts[account_idx];
+                    if !account.is_writable {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+                    let mut data = account.try_borrow_mut_data()?;
+                    let word = offset.checked_add(8)
+                        .and_then(|end| data.get_mut(offset..end))
+                        .ok_or(VMError::InvalidMemoryAccess)?;
+                    word.copy_from_slice(&value.to_le_bytes());
+                },
+                OpCode::HashAccountData => {
+                    let account_idx = self.stack.pop()?.0 as usize;
+                    if account_idx >= self.accounts.accounts.len() {
+                        return Err(VMError::InvalidAccount.into());
+                    }
+                    let data = self.accounts.accounts[account_idx].try_borrow_data()?;
+                    // Six extra gas per 32-byte word hashed, as for Keccak256
+                    self.gas.consume(data.len().div_ceil(32) as u64 * 6)?;
+
+                    let digest = keccak::hash(&data).to_bytes();
+                    self.stack.push(Value(u64::from_le_bytes(digest[..8].try_into().unwrap())))?;
+                },
+                OpCode::LogGas => {
+                    let len = self.stack.pop()?.0 as usize;
+                    let offset = self.stack.pop()?.0 as usize;
+                    // One extra gas per 32-byte word of label
+                    self.gas.consume(len.div_ceil(32) as u64)?;
+
+                    let label = String::from_utf8_lossy(self.memory.load(offset, len)?);
+                    msg!("VM Gas: {}: {} remaining", label, self.gas.remaining());
+                },
                 // Control Flow
                 OpCode::Revert => {
                     let error_code = self.stack.pop()?.0;
@@ -937,6 +1551,39 @@ impl<'a> VM<'a> {
                         return Err(VMError::InvalidDataStructureOperation.into());
                     }
                 },
+                OpCode::GraphShortestPath => {
+                    let end_node = self.stack.pop()?.0;
+                    let start_node = self.stack.pop()?.0;
+                    let id = self.stack.pop()?.0 as usize;
+
+                    if id >= self.data_structures.graphs.len() {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+
+                    if let Some(graph) = &self.data_structures.graphs[id] {
+                        let (cost, path) = graph.shortest_path(start_node, end_node)
+                            .unwrap_or_default();
+
+                        // Push the cost and path length, then the path in reverse
+                        // order so the start node is popped first
+                        self.stack.push(Value(cost))?;
+                        self.stack.push(Value(path.len() as u64))?;
+                        for node in path.iter().rev() {
+                            self.stack.push(Value(*node))?;
+                        }
+                    } else {
+                        return Err(VMError::InvalidDataStructureOperation.into());
+                    }
+                },
+                OpCode::GraphHash => {
+                    let id = self.stack.pop()?.0 as usize;
+
+                    let contents = match self.data_structures.graphs.get(id) {
+                        Some(Some(graph)) => graph.canonical_bytes(),
+                        _ => return Err(VMError::InvalidDataStructureOperation.into()),
+                    };
+                    self.push_contents_hash(&contents)?;
+                },
                 OpCode::GraphClear => {
                     let id = self.stack.pop()?.0 as usize;
                     
@@ -1032,6 +1679,46 @@ impl<'a> VM<'a> {
                         return Err(VMError::InvalidDataStructureOperation.into());
                     }
                 },
+                OpCode::OhlcvEma | OpCode::OhlcvRsi => {
+                    let period = self.stack.pop()?.0 as usize;
+                    let id = self.stack.pop()?.0 as usize;
+
+                    let ohlcv = match self.data_structures.ohlcvs.get(id) {
+                        Some(Some(ohlcv)) => ohlcv,
+                        _ => return Err(VMError::InvalidDataStructureOperation.into()),
+                    };
+                    let values = if opcode == OpCode::OhlcvEma {
+                        ohlcv.calculate_ema(period)
+                    } else {
+                        ohlcv.calculate_rsi(period)
+                    };
+
+                    // Same layout as OhlcvSma: count, then value/timestamp pairs
+                    self.stack.push(Value(values.len() as u64))?;
+                    for (timestamp, value) in values.iter().rev() {
+                        self.stack.push(Value(*value))?;
+                        self.stack.push(Value(*timestamp))?;
+                    }
+                },
+                OpCode::OhlcvBollinger => {
+                    let period = self.stack.pop()?.0 as usize;
+                    let id = self.stack.pop()?.0 as usize;
+
+                    let bands = match self.data_structures.ohlcvs.get(id) {
+                        Some(Some(ohlcv)) => ohlcv.calculate_bollinger(period),
+                        _ => return Err(VMError::InvalidDataStructureOperation.into()),
+                    };
+
+                    // Push the count, then lower, middle, upper and timestamp
+                    // for each band in reverse order
+                    self.stack.push(Value(bands.len() as u64))?;
+                    for (timestamp, lower, middle, upper) in bands.iter().rev() {
+                        self.stack.push(Value(*lower))?;
+                        self.stack.push(Value(*middle))?;
+                        self.stack.push(Value(*upper))?;
+                        self.stack.push(Value(*timestamp))?;
+                    }
+                },
                 // Hypergraph operations
                 OpCode::HyperAddNode => {
                     let value = self.stack.pop()?.0;
@@ -1092,21 +1779,23 @@ mod tests {
     use super::*;
     use solana_program::clock::Epoch;
 
-    fn create_test_account(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'static>) {
-        let key = Pubkey::new_unique();
-        let mut lamports = lamports;
-        let mut data = vec![0; 32];
-        
-        AccountInfo::new(
-            &key,
+    fn create_test_account<'a>(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'a>) {
+        // AccountInfo only borrows what it describes, so leak it to outlive the call
+        let key: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+        let owner: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+        let data = vec![0; 32];
+    
+        let account = AccountInfo::new(
+            key,
             true,
             true,
-            &mut lamports,
-            &mut data,
-            &Pubkey::new_unique(),
+            Box::leak(Box::new(lamports)),
+            Box::leak(data.clone().into_boxed_slice()),
+            owner,
             false,
             Epoch::default(),
-        )
+        );
+        (*key, data, account)
     }
 
     #[test]
@@ -1141,4 +1830,454 @@ mod tests {
         assert_eq!(accounts[0].lamports(), 500000);
         assert_eq!(accounts[1].lamports(), 500000);
     }
+
+    /// Routes CPIs straight into the SPL Token processor so token transfers
+    /// can be exercised without a running validator
+    struct TokenProgramStubs;
+
+    impl solana_program::program_stubs::SyscallStubs for TokenProgramStubs {
+        fn sol_invoke_signed(
+            &self,
+            instruction: &solana_program::instruction::Instruction,
+            account_infos: &[AccountInfo],
+            _signers_seeds: &[&[&[u8]]],
+        ) -> solana_program::entrypoint::ProgramResult {
+            spl_token::processor::Processor::process(
+                &instruction.program_id,
+                account_infos,
+                &instruction.data,
+            )
+        }
+    }
+
+    /// Pretends every call happens this many levels deep inside a CPI
+    struct NestedCpiStubs(u64);
+
+    impl solana_program::program_stubs::SyscallStubs for NestedCpiStubs {
+        fn sol_get_stack_height(&self) -> u64 {
+            TRANSACTION_LEVEL_STACK_HEIGHT as u64 + self.0
+        }
+    }
+
+    /// Serves a fixed Clock sysvar
+    struct ClockStubs(Clock);
+
+    impl solana_program::program_stubs::SyscallStubs for ClockStubs {
+        fn sol_get_clock_sysvar(&self, var_addr: *mut u8) -> u64 {
+            unsafe { *(var_addr as *mut Clock) = self.0.clone() };
+            solana_program::entrypoint::SUCCESS
+        }
+    }
+
+    /// Records every message a program logs
+    struct LogStubs(std::sync::Arc<std::sync::Mutex<Vec<String>>>);
+
+    impl solana_program::program_stubs::SyscallStubs for LogStubs {
+        fn sol_log(&self, message: &str) {
+            self.0.lock().unwrap().push(message.to_string());
+        }
+    }
+
+    /// Syscall stubs are process-wide, so tests installing them run one at a time
+    static SYSCALL_STUBS_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    fn pack_token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Vec<u8> {
+        use solana_program::program_pack::Pack;
+
+        let account = spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        spl_token::state::Account::pack(account, &mut data).unwrap();
+        data
+    }
+
+    fn token_amount(account: &AccountInfo) -> u64 {
+        use solana_program::program_pack::Pack;
+
+        spl_token::state::Account::unpack(&account.data.borrow()).unwrap().amount
+    }
+
+    #[test]
+    fn test_spl_transfer() {
+        let _guard = SYSCALL_STUBS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        solana_program::program_stubs::set_syscall_stubs(Box::new(TokenProgramStubs));
+
+        let program_id = Pubkey::new_unique();
+        let token_program = spl_token::id();
+        let mint = Pubkey::new_unique();
+        let system_program = Pubkey::default();
+
+        let source_key = Pubkey::new_unique();
+        let dest_key = Pubkey::new_unique();
+        let authority_key = Pubkey::new_unique();
+
+        let (mut source_lamports, mut dest_lamports, mut authority_lamports) = (1_000_000, 1_000_000, 1_000_000);
+        let mut source_data = pack_token_account(&mint, &authority_key, 1_000);
+        let mut dest_data = pack_token_account(&mint, &Pubkey::new_unique(), 0);
+        let mut authority_data = vec![];
+
+        let accounts = vec![
+            AccountInfo::new(&source_key, false, true, &mut source_lamports, &mut source_data, &token_program, false, Epoch::default()),
+            AccountInfo::new(&dest_key, false, true, &mut dest_lamports, &mut dest_data, &token_program, false, Epoch::default()),
+            AccountInfo::new(&authority_key, true, false, &mut authority_lamports, &mut authority_data, &system_program, false, Epoch::default()),
+        ];
+
+        // push1 0 (source), push1 1 (destination), push1 2 (authority), push1 250 (amount), spltransfer, halt
+        let bytecode = vec![0x01, 0x00, 0x01, 0x01, 0x01, 0x02, 0x01, 0xFA, OpCode::SPLTransfer as u8, 0xFF];
+
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+
+        assert_eq!(token_amount(&accounts[0]), 750);
+        assert_eq!(token_amount(&accounts[1]), 250);
+
+        // An out of range authority index is rejected before any CPI happens
+        let bytecode = vec![0x01, 0x00, 0x01, 0x01, 0x01, 0x07, 0x01, 0x01, OpCode::SPLTransfer as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert_eq!(
+            vm.execute(&bytecode).unwrap_err(),
+            ProgramError::from(VMError::InvalidAccount)
+        );
+        assert_eq!(token_amount(&accounts[0]), 750);
+    }
+
+    #[test]
+    fn test_gas_used_never_wraps() {
+        let program_id = Pubkey::new_unique();
+
+        for limit in [0, 1, DEFAULT_GAS_LIMIT, u64::MAX - 1, u64::MAX] {
+            let mut vm = VM::with_gas_limit(&program_id, &[], &[], limit);
+            assert_eq!(vm.gas_limit(), limit);
+            assert_eq!(vm.gas_used(), 0);
+
+            // Consuming gas is reflected exactly
+            let spent = limit.min(7);
+            vm.gas.consume(spent).unwrap();
+            assert_eq!(vm.gas_used(), spent);
+
+            // More gas remaining than the limit clamps to zero instead of wrapping
+            vm.gas = Gas::new(limit.saturating_add(10));
+            assert_eq!(vm.gas_used(), 0);
+        }
+    }
+
+    #[test]
+    fn test_validate() {
+        // PUSH1 5, PUSH8 7, ADD, PUSH1 0, JUMP, HALT
+        let mut code = vec![OpCode::Push1 as u8, 5, OpCode::Push8 as u8];
+        code.extend_from_slice(&7u64.to_le_bytes());
+        code.extend_from_slice(&[OpCode::Add as u8, OpCode::Push1 as u8, 0, OpCode::Jump as u8, 0xFF]);
+        assert!(VM::validate(&code, 0).is_ok());
+        assert!(VM::validate(&[], 0).is_ok());
+
+        // PUSH8 with only three operand bytes
+        assert!(matches!(
+            VM::validate(&[OpCode::Push8 as u8, 1, 2, 3], 0),
+            Err(VMError::InvalidInstruction)
+        ));
+        assert!(VM::validate(&[OpCode::Dup as u8], 0).is_err());
+
+        // Unknown opcode after valid instructions
+        assert!(OpCode::from_byte(0xEE).is_none());
+        assert!(VM::validate(&[OpCode::Push1 as u8, 1, 0xEE, 0xFF], 0).is_err());
+
+        // Jump into the operand of PUSH1, or past the end
+        let push1 = OpCode::Push1 as u8;
+        assert!(VM::validate(&[push1, 1, push1, 3, OpCode::Jump as u8], 0).is_err());
+        assert!(VM::validate(&[push1, 1, push1, 9, OpCode::JumpI as u8], 0).is_err());
+        assert!(VM::validate(&[push1, 1, push1, 0, OpCode::JumpI as u8], 0).is_ok());
+
+        // Starting anywhere but on an instruction, e.g. inside the PUSH8
+        // operand, runs bytes that were never decoded as instructions
+        assert!(VM::validate(&code, 2).is_ok());
+        assert!(VM::validate(&code, 11).is_ok());
+        for entry in [1, 3, 10, code.len()] {
+            assert!(matches!(VM::validate(&code, entry), Err(VMError::InvalidInstruction)), "entry {}", entry);
+        }
+    }
+
+    #[test]
+    fn test_gas_schedule() {
+        let program_id = Pubkey::new_unique();
+        // PUSH1 6, PUSH1 7, MUL, HALT
+        let bytecode = [0x01, 0x06, 0x01, 0x07, OpCode::Mul as u8, 0xFF];
+
+        let mut vm = VM::new(&program_id, &[], &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+        assert_eq!(vm.gas_used(), 2 + 2 + 5 + 1);
+
+        let schedule = GasSchedule::default()
+            .with_cost(OpCode::Push1, 1)
+            .with_cost(OpCode::Mul, 50);
+        let mut vm = VM::with_gas_schedule(&program_id, &[], &bytecode, DEFAULT_GAS_LIMIT, schedule);
+        assert!(vm.execute(&bytecode).is_ok());
+        assert_eq!(vm.gas_used(), 1 + 1 + 50 + 1);
+        assert_eq!(vm.gas_schedule().cost(OpCode::Add), OpCode::Add.gas_cost());
+    }
+
+    #[test]
+    fn test_vector_add_distinct_vectors() {
+        let program_id = Pubkey::new_unique();
+        let mut vm = VM::new(&program_id, &[], &[]);
+
+        for v in [1, 2, 3, 4, 5, 6, 7, 8] {
+            vm.stack.push(Value(v)).unwrap();
+        }
+        vm.vector_add().unwrap();
+
+        assert_eq!(vm.stack.depth(), 4);
+        let mut result = [0u64; 4];
+        for lane in result.iter_mut().rev() {
+            *lane = vm.stack.pop().unwrap().0;
+        }
+        assert_eq!(result, [6, 8, 10, 12]);
+
+        // Not enough values leaves the stack untouched
+        for v in 0..7 {
+            vm.stack.push(Value(v)).unwrap();
+        }
+        assert!(matches!(vm.vector_add(), Err(VMError::StackUnderflow)));
+        assert_eq!(vm.stack.depth(), 7);
+    }
+
+    #[test]
+    fn test_vector_add_wraps() {
+        let program_id = Pubkey::new_unique();
+        let mut vm = VM::new(&program_id, &[], &[]);
+
+        for v in [u64::MAX, 1, u64::MAX - 1, 0, 1, 2, 3, u64::MAX] {
+            vm.stack.push(Value(v)).unwrap();
+        }
+        vm.vector_add().unwrap();
+
+        let mut result = [0u64; 4];
+        for lane in result.iter_mut().rev() {
+            *lane = vm.stack.pop().unwrap().0;
+        }
+        assert_eq!(result, [0, 3, 1, u64::MAX]);
+    }
+
+    #[test]
+    fn test_caller_program_id() {
+        use solana_program::sysvar::instructions::{
+            construct_instructions_data, store_current_index, BorrowedInstruction,
+        };
+
+        let _guard = SYSCALL_STUBS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+        let program_id = Pubkey::new_unique();
+        let caller_id = Pubkey::new_unique();
+        let sysvar_id = solana_program::sysvar::instructions::id();
+        let sysvar_owner = solana_program::sysvar::id();
+
+        // The transaction's only instruction calls `caller_id`, which CPIs into us
+        let mut sysvar_data = construct_instructions_data(&[BorrowedInstruction {
+            program_id: &caller_id,
+            accounts: vec![],
+            data: &[],
+        }]);
+        store_current_index(&mut sysvar_data, 0);
+        let mut sysvar_lamports = 0;
+        let accounts = vec![AccountInfo::new(
+            &sysvar_id, false, false, &mut sysvar_lamports, &mut sysvar_data,
+            &sysvar_owner, false, Epoch::default(),
+        )];
+
+        // push1 0 (sysvar account), push1 64 (destination), callerprogramid, halt
+        let bytecode = vec![0x01, 0x00, 0x01, 0x40, OpCode::CallerProgramId as u8, 0xFF];
+
+        solana_program::program_stubs::set_syscall_stubs(Box::new(NestedCpiStubs(1)));
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+        assert_eq!(vm.stack.pop().unwrap().0, 1);
+        assert_eq!(vm.memory.load(64, 32).unwrap(), caller_id.as_ref());
+
+        // Two levels down, `caller_id` only called whoever called us
+        solana_program::program_stubs::set_syscall_stubs(Box::new(NestedCpiStubs(2)));
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert_eq!(vm.execute(&bytecode).unwrap_err(), ProgramError::from(VMError::UnknownCaller));
+
+        // Outside of a CPI there is no caller to report
+        solana_program::program_stubs::set_syscall_stubs(Box::new(TokenProgramStubs));
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+        assert_eq!(vm.stack.pop().unwrap().0, 0);
+    }
+
+    #[test]
+    fn test_load_instruction_at() {
+        use solana_program::sysvar::instructions::{
+            construct_instructions_data, store_current_index, BorrowedInstruction,
+        };
+
+        let program_id = Pubkey::new_unique();
+        let sibling_id = Pubkey::new_unique();
+        let sysvar_id = solana_program::sysvar::instructions::id();
+        let sysvar_owner = solana_program::sysvar::id();
+
+        // A sibling instruction runs right before ours in the same transaction
+        let mut sysvar_data = construct_instructions_data(&[
+            BorrowedInstruction {
+                program_id: &sibling_id,
+                accounts: vec![],
+                data: &[7, 8, 9],
+            },
+            BorrowedInstruction {
+                program_id: &program_id,
+                accounts: vec![],
+                data: &[],
+            },
+        ]);
+        store_current_index(&mut sysvar_data, 1);
+        let mut sysvar_lamports = 0;
+        let accounts = vec![AccountInfo::new(
+            &sysvar_id, false, false, &mut sysvar_lamports, &mut sysvar_data,
+            &sysvar_owner, false, Epoch::default(),
+        )];
+
+        // push1 0 (sysvar account), push1 0 (instruction), push1 64 (destination),
+        // loadinstructionat, halt
+        let bytecode = vec![0x01, 0x00, 0x01, 0x00, 0x01, 0x40, OpCode::LoadInstructionAt as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+        assert_eq!(vm.stack.pop().unwrap().0, 3);
+        assert_eq!(vm.memory.load(64, 32).unwrap(), sibling_id.as_ref());
+        assert_eq!(vm.memory.load(96, 3).unwrap(), &[7, 8, 9]);
+
+        // Indexes past the end of the transaction are rejected
+        let bytecode = vec![0x01, 0x00, 0x01, 0x02, 0x01, 0x40, OpCode::LoadInstructionAt as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert_eq!(vm.execute(&bytecode).unwrap_err(), ProgramError::from(VMError::InvalidAccount));
+    }
+
+    #[test]
+    fn test_get_clock() {
+        let _guard = SYSCALL_STUBS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        solana_program::program_stubs::set_syscall_stubs(Box::new(ClockStubs(Clock {
+            slot: 250_000_000,
+            epoch: 578,
+            unix_timestamp: 1_700_000_000,
+            ..Clock::default()
+        })));
+
+        let program_id = Pubkey::new_unique();
+        let accounts = vec![];
+        // getclock, halt
+        let bytecode = vec![OpCode::GetClock as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        let result = vm.execute(&bytecode);
+        solana_program::program_stubs::set_syscall_stubs(Box::new(TokenProgramStubs));
+        assert!(result.is_ok());
+        assert_eq!(vm.stack.pop().unwrap().0, 578);
+        assert_eq!(vm.stack.pop().unwrap().0, 250_000_000);
+        assert_eq!(vm.stack.pop().unwrap().0, 1_700_000_000);
+    }
+
+    #[test]
+    fn test_account_data() {
+        let program_id = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let (writable_key, readonly_key) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mut writable_lamports, mut readonly_lamports) = (0, 0);
+        let mut writable_data = vec![0u8; 16];
+        let mut readonly_data = 0x1122_3344_5566_7788u64.to_le_bytes().to_vec();
+        let accounts = vec![
+            AccountInfo::new(
+                &writable_key, false, true, &mut writable_lamports, &mut writable_data,
+                &owner, false, Epoch::default(),
+            ),
+            AccountInfo::new(
+                &readonly_key, false, false, &mut readonly_lamports, &mut readonly_data,
+                &owner, false, Epoch::default(),
+            ),
+        ];
+
+        // push1 1, accountdatalen, push1 1, push1 0, accountdataload,
+        // push1 0, push1 8, push1 42, accountdatastore, halt
+        let bytecode = vec![
+            0x01, 0x01, OpCode::AccountDataLen as u8,
+            0x01, 0x01, 0x01, 0x00, OpCode::AccountDataLoad as u8,
+            0x01, 0x00, 0x01, 0x08, 0x01, 42, OpCode::AccountDataStore as u8,
+            0xFF,
+        ];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+        assert_eq!(vm.stack.pop().unwrap().0, 0x1122_3344_5566_7788);
+        assert_eq!(vm.stack.pop().unwrap().0, 8);
+        assert_eq!(&accounts[0].try_borrow_mut_data().unwrap()[8..], &42u64.to_le_bytes());
+
+        // Read-only accounts can't be written
+        let bytecode = vec![0x01, 0x01, 0x01, 0x00, 0x01, 42, OpCode::AccountDataStore as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert_eq!(vm.execute(&bytecode).unwrap_err(), ProgramError::from(VMError::InvalidAccount));
+
+        // Words must lie within the data
+        let bytecode = vec![0x01, 0x00, 0x01, 0x09, 0x01, 42, OpCode::AccountDataStore as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert_eq!(vm.execute(&bytecode).unwrap_err(), ProgramError::from(VMError::InvalidMemoryAccess));
+        let bytecode = vec![0x01, 0x01, 0x01, 0x01, OpCode::AccountDataLoad as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert_eq!(vm.execute(&bytecode).unwrap_err(), ProgramError::from(VMError::InvalidMemoryAccess));
+    }
+
+    #[test]
+    fn test_log_gas() {
+        let _guard = SYSCALL_STUBS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let logs = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        solana_program::program_stubs::set_syscall_stubs(Box::new(LogStubs(logs.clone())));
+
+        let program_id = Pubkey::new_unique();
+        let accounts = vec![];
+        // push1 0, push1 9, loggas, halt
+        let bytecode = vec![0x01, 0x00, 0x01, 0x09, OpCode::LogGas as u8, 0xFF];
+        let mut vm = VM::with_gas_limit(&program_id, &accounts, &bytecode, 1_000);
+        vm.memory.store(0, b"after add").unwrap();
+        let result = vm.execute(&bytecode);
+        solana_program::program_stubs::set_syscall_stubs(Box::new(TokenProgramStubs));
+        assert!(result.is_ok());
+
+        // Two pushes, LogGas and one word of label have been charged by then
+        let remaining = 1_000 - 2 * OpCode::Push1.gas_cost() - OpCode::LogGas.gas_cost() - 1;
+        assert_eq!(logs.lock().unwrap().as_slice(), [format!("VM Gas: after add: {} remaining", remaining)]);
+        assert_eq!(vm.gas_used(), 1_000 - remaining + OpCode::Halt.gas_cost());
+    }
+
+    #[test]
+    fn test_hash_account_data() {
+        let program_id = Pubkey::new_unique();
+        let (key, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut lamports = 0;
+        // Not a whole number of 32-byte words, so the last one is partial
+        let mut data: Vec<u8> = (0..100).map(|i| i as u8).collect();
+        let words = data.len().div_ceil(32) as u64;
+        let expected = u64::from_le_bytes(keccak::hash(&data).to_bytes()[..8].try_into().unwrap());
+        let accounts = vec![AccountInfo::new(
+            &key, false, true, &mut lamports, &mut data, &owner, false, Epoch::default(),
+        )];
+
+        // push1 0, hashaccountdata, halt
+        let bytecode = vec![0x01, 0x00, OpCode::HashAccountData as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+        let before = vm.stack.pop().unwrap().0;
+        assert_eq!(before, expected);
+        let charged = OpCode::Push1.gas_cost() + OpCode::HashAccountData.gas_cost() + OpCode::Halt.gas_cost();
+        assert_eq!(vm.gas_used(), charged + words * 6);
+
+        // Any write to the account changes the hash, even in the partial last word
+        let last = accounts[0].data_len() - 1;
+        accounts[0].try_borrow_mut_data().unwrap()[last] ^= 1;
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert!(vm.execute(&bytecode).is_ok());
+        assert_ne!(vm.stack.pop().unwrap().0, before);
+
+        let bytecode = vec![0x01, 0x01, OpCode::HashAccountData as u8, 0xFF];
+        let mut vm = VM::new(&program_id, &accounts, &bytecode);
+        assert_eq!(vm.execute(&bytecode).unwrap_err(), ProgramError::from(VMError::InvalidAccount));
+    }
 } 
\ No newline at end of file
@@ -9,7 +9,8 @@
 
 use super::{VMError, Value};
 use std::collections::{BTreeMap as StdBTreeMap, HashMap, HashSet};
-use std::collections::VecDeque;
+use std::collections::{BinaryHeap, VecDeque};
+use std::cmp::Reverse;
 use solana_program::msg;
 
 // Constants for data structure IDs
@@ -58,6 +59,52 @@ pub struct TrieNode {
     pub value: Option<Value>,
 }
 
+/// Position of a paginated BTreeMap range scan. It is kept in VM memory
+/// between batches, so programs can save it to account data and resume the
+/// scan in a later transaction.
+///
+/// Serialized as `next`, `high` and `limit` as little-endian u64s followed
+/// by a `done` byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RangeCursor {
+    /// Smallest key the next batch can contain
+    pub next: u64,
+    /// Largest key in the range, inclusive
+    pub high: u64,
+    /// Maximum number of entries per batch
+    pub limit: u64,
+    /// Whether the whole range has been returned
+    pub done: bool,
+}
+
+impl RangeCursor {
+    /// Size of a serialized cursor in bytes
+    pub const SIZE: usize = 25;
+
+    /// Cursor over keys in `low..=high`
+    pub fn new(low: u64, high: u64, limit: u64) -> Self {
+        Self { next: low, high, limit, done: low > high }
+    }
+
+    pub fn to_bytes(&self) -> [u8; Self::SIZE] {
+        let mut bytes = [0; Self::SIZE];
+        bytes[0..8].copy_from_slice(&self.next.to_le_bytes());
+        bytes[8..16].copy_from_slice(&self.high.to_le_bytes());
+        bytes[16..24].copy_from_slice(&self.limit.to_le_bytes());
+        bytes[24] = self.done as u8;
+        bytes
+    }
+
+    /// Parse a cursor written by `to_bytes`
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, VMError> {
+        if bytes.len() != Self::SIZE || bytes[24] > 1 {
+            return Err(VMError::InvalidDataStructureOperation);
+        }
+        let word = |i: usize| u64::from_le_bytes(bytes[i..i + 8].try_into().unwrap());
+        Ok(Self { next: word(0), high: word(8), limit: word(16), done: bytes[24] == 1 })
+    }
+}
+
 /// BTreeMap implementation
 #[derive(Debug, Clone)]
 pub struct BTreeMapDS {
@@ -103,6 +150,51 @@ impl BTreeMapDS {
         self.data.keys().next_back().copied()
     }
 
+    /// Entries with keys in `low..=high`, in ascending key order
+    pub fn range(&self, low: u64, high: u64) -> Vec<(u64, u64)> {
+        if low > high {
+            return Vec::new();
+        }
+        self.data.range(low..=high).map(|(&k, &v)| (k, v)).collect()
+    }
+
+    /// Next batch of at most `cursor.limit` entries in the cursor's range,
+    /// in ascending key order, moving the cursor past them
+    pub fn range_batch(&self, cursor: &mut RangeCursor) -> Vec<(u64, u64)> {
+        if cursor.done {
+            return Vec::new();
+        }
+        let batch: Vec<_> = self.data.range(cursor.next..=cursor.high)
+            .take(cursor.limit as usize)
+            .map(|(&k, &v)| (k, v))
+            .collect();
+
+        // A short batch, or one ending at the top of the range, is the last
+        match batch.last() {
+            Some(&(key, _)) if batch.len() as u64 == cursor.limit && key < cursor.high => {
+                cursor.next = key + 1;
+            }
+            _ => cursor.done = true,
+        }
+        batch
+    }
+
+    /// All entries in ascending key order
+    pub fn iter(&self) -> impl Iterator<Item = (u64, u64)> + '_ {
+        self.data.iter().map(|(&k, &v)| (k, v))
+    }
+
+    /// Entries in ascending key order, each as a little-endian key followed
+    /// by its value. Equal maps always serialize to the same bytes.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(self.data.len() * 16);
+        for (key, value) in self.iter() {
+            bytes.extend_from_slice(&key.to_le_bytes());
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+
     pub fn clear(&mut self) {
         self.data.clear();
     }
@@ -113,6 +205,7 @@ impl BTreeMapDS {
 pub struct TrieDS {
     nodes: Vec<TrieNode>,
     root: usize,
+    len: usize,
 }
 
 impl TrieDS {
@@ -129,6 +222,7 @@ impl TrieDS {
         Self {
             nodes,
             root: 0,
+            len: 0,
         }
     }
 
@@ -156,12 +250,46 @@ impl TrieDS {
             current_node = next_node;
         }
 
+        if !self.nodes[current_node].is_end_of_word {
+            self.len += 1;
+        }
         self.nodes[current_node].is_end_of_word = true;
         self.nodes[current_node].value = Some(value);
 
         Ok(())
     }
 
+    /// Remove a key, returning whether it was present. Nodes on the key's
+    /// path stay allocated until the trie is cleared.
+    pub fn remove(&mut self, key: &[u8]) -> bool {
+        let mut current_node = self.root;
+
+        for &byte in key {
+            match self.nodes[current_node].children.get(&byte) {
+                Some(&next) => current_node = next,
+                None => return false,
+            }
+        }
+
+        let node = &mut self.nodes[current_node];
+        if !node.is_end_of_word {
+            return false;
+        }
+        node.is_end_of_word = false;
+        node.value = None;
+        self.len -= 1;
+        true
+    }
+
+    /// Number of keys stored
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn get(&self, key: &[u8]) -> Option<Value> {
         let mut current_node = self.root;
 
@@ -183,6 +311,41 @@ impl TrieDS {
         self.get(key).is_some()
     }
 
+    /// All keys and their values, in lexicographic key order
+    pub fn entries(&self) -> Vec<(Vec<u8>, Value)> {
+        let mut entries = Vec::with_capacity(self.len);
+        let mut pending = vec![(self.root, Vec::new())];
+
+        while let Some((node_idx, key)) = pending.pop() {
+            let node = &self.nodes[node_idx];
+            if let (true, Some(value)) = (node.is_end_of_word, node.value) {
+                entries.push((key.clone(), value));
+            }
+
+            // Push children in reverse so the smallest byte is visited next
+            let mut children: Vec<_> = node.children.iter().collect();
+            children.sort_unstable_by(|a, b| b.0.cmp(a.0));
+            for (&byte, &child) in children {
+                let mut child_key = key.clone();
+                child_key.push(byte);
+                pending.push((child, child_key));
+            }
+        }
+        entries
+    }
+
+    /// Entries in lexicographic key order, each as a little-endian u64 key
+    /// length, the key bytes and the little-endian value
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (key, value) in self.entries() {
+            bytes.extend_from_slice(&(key.len() as u64).to_le_bytes());
+            bytes.extend_from_slice(&key);
+            bytes.extend_from_slice(&value.0.to_le_bytes());
+        }
+        bytes
+    }
+
     pub fn clear(&mut self) {
         self.nodes.clear();
         let root_node = TrieNode {
@@ -192,6 +355,7 @@ impl TrieDS {
         };
         self.nodes.push(root_node);
         self.root = 0;
+        self.len = 0;
     }
 }
 
@@ -255,6 +419,45 @@ impl GraphDS {
         Ok(())
     }
 
+    /// All nodes and their values, ordered by node ID
+    pub fn nodes(&self) -> Vec<(u64, u64)> {
+        let mut nodes: Vec<_> = self.node_values.iter().map(|(&id, &v)| (id, v)).collect();
+        nodes.sort_unstable();
+        nodes
+    }
+
+    /// All edges as (from, to, weight), ordered by source node and then in
+    /// insertion order
+    pub fn edges(&self) -> Vec<(u64, u64, u64)> {
+        let mut sources: Vec<_> = self.edges.keys().copied().collect();
+        sources.sort_unstable();
+        sources.into_iter()
+            .flat_map(|from| self.edges[&from].iter().map(move |&(to, w)| (from, to, w)))
+            .collect()
+    }
+
+    /// Node count, the nodes as (id, value) sorted by ID, then the edges as
+    /// (from, to, weight) fully sorted, all as little-endian u64s. Insertion
+    /// order doesn't affect the result.
+    pub fn canonical_bytes(&self) -> Vec<u8> {
+        let nodes = self.nodes();
+        let mut edges = self.edges();
+        edges.sort_unstable();
+
+        let mut bytes = Vec::with_capacity(8 + nodes.len() * 16 + edges.len() * 24);
+        bytes.extend_from_slice(&(nodes.len() as u64).to_le_bytes());
+        for (id, value) in nodes {
+            bytes.extend_from_slice(&id.to_le_bytes());
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        for (from, to, weight) in edges {
+            bytes.extend_from_slice(&from.to_le_bytes());
+            bytes.extend_from_slice(&to.to_le_bytes());
+            bytes.extend_from_slice(&weight.to_le_bytes());
+        }
+        bytes
+    }
+
     pub fn get_neighbors(&self, node_id: u64) -> Vec<(u64, u64)> {
         self.edges.get(&node_id)
             .cloned()
@@ -303,6 +506,49 @@ impl GraphDS {
         result
     }
 
+    // Dijkstra's shortest path - returns the total weight and the nodes from
+    // start to end, or None if end isn't reachable
+    pub fn shortest_path(&self, start: u64, end: u64) -> Option<(u64, Vec<u64>)> {
+        if !self.node_values.contains_key(&start) || !self.node_values.contains_key(&end) {
+            return None;
+        }
+
+        let mut dist = HashMap::new();
+        let mut prev = HashMap::new();
+        let mut heap = BinaryHeap::new();
+
+        dist.insert(start, 0u64);
+        heap.push(Reverse((0u64, start)));
+
+        while let Some(Reverse((cost, node))) = heap.pop() {
+            if node == end {
+                // Walk back along the predecessors
+                let mut path = vec![end];
+                while let Some(&p) = prev.get(path.last().unwrap()) {
+                    path.push(p);
+                }
+                path.reverse();
+                return Some((cost, path));
+            }
+
+            // Skip stale heap entries
+            if cost > dist[&node] {
+                continue;
+            }
+
+            for &(neighbor, weight) in self.edges.get(&node).into_iter().flatten() {
+                let next = cost.saturating_add(weight);
+                if dist.get(&neighbor).map_or(true, |&d| next < d) {
+                    dist.insert(neighbor, next);
+                    prev.insert(neighbor, node);
+                    heap.push(Reverse((next, neighbor)));
+                }
+            }
+        }
+
+        None
+    }
+
     pub fn clear(&mut self) {
         self.edges.clear();
         self.node_values.clear();
@@ -379,6 +625,111 @@ impl OHLCVDS {
 
         result
     }
+
+    /// Calculate Exponential Moving Average (EMA) of close prices
+    ///
+    /// Seeded with the SMA of the first `period` closes, then smoothed with
+    /// k = 2 / (period + 1). Each step truncates to an integer.
+    pub fn calculate_ema(&self, period: usize) -> Vec<(u64, u64)> {
+        let sma = self.calculate_sma(period);
+        let Some(&(timestamp, seed)) = sma.first() else {
+            return Vec::new();
+        };
+
+        let period = period as u128;
+        let mut ema = seed as u128;
+        let mut result = vec![(timestamp, seed)];
+
+        for entry in &self.data[period as usize..] {
+            ema = (entry.close as u128 * 2 + ema * (period - 1)) / (period + 1);
+            result.push((entry.timestamp, ema as u64));
+        }
+
+        result
+    }
+
+    /// Calculate the Relative Strength Index (RSI) of close prices, 0 to 100
+    ///
+    /// Average gains and losses start as the mean of the first `period`
+    /// changes and then use Wilder's smoothing. The first value is at the
+    /// bar after those changes, so `period + 1` bars are needed.
+    pub fn calculate_rsi(&self, period: usize) -> Vec<(u64, u64)> {
+        // Fixed-point scale for the running averages
+        const SCALE: u128 = 1_000_000;
+
+        if period == 0 || self.data.len() <= period {
+            return Vec::new();
+        }
+
+        let change = |i: usize| {
+            let (prev, close) = (self.data[i - 1].close as u128, self.data[i].close as u128);
+            (close.saturating_sub(prev) * SCALE, prev.saturating_sub(close) * SCALE)
+        };
+        let rsi = |gain: u128, loss: u128| match gain + loss {
+            0 => 50,
+            total => (gain * 100 / total) as u64,
+        };
+
+        let period_u = period as u128;
+        let (mut avg_gain, mut avg_loss) = (1..=period)
+            .map(change)
+            .fold((0, 0), |(g, l), (gain, loss)| (g + gain, l + loss));
+        avg_gain /= period_u;
+        avg_loss /= period_u;
+
+        let mut result = vec![(self.data[period].timestamp, rsi(avg_gain, avg_loss))];
+
+        for i in period + 1..self.data.len() {
+            let (gain, loss) = change(i);
+            avg_gain = (avg_gain * (period_u - 1) + gain) / period_u;
+            avg_loss = (avg_loss * (period_u - 1) + loss) / period_u;
+            result.push((self.data[i].timestamp, rsi(avg_gain, avg_loss)));
+        }
+
+        result
+    }
+
+    /// Calculate Bollinger Bands of close prices as
+    /// (timestamp, lower, middle, upper)
+    ///
+    /// The middle band is the SMA and the outer bands sit two population
+    /// standard deviations (integer square root) either side of it.
+    pub fn calculate_bollinger(&self, period: usize) -> Vec<(u64, u64, u64, u64)> {
+        if period == 0 || self.data.len() < period {
+            return Vec::new();
+        }
+
+        let n = period as u128;
+        self.data
+            .windows(period)
+            .map(|window| {
+                let sum: u128 = window.iter().map(|e| e.close as u128).sum();
+                let sum_sq: u128 = window.iter().map(|e| (e.close as u128).pow(2)).sum();
+                let middle = (sum / n) as u64;
+                let deviation = isqrt((n * sum_sq - sum * sum) / (n * n)) as u64;
+
+                let timestamp = window[period - 1].timestamp;
+                let width = deviation.saturating_mul(2);
+                (timestamp, middle.saturating_sub(width), middle, middle.saturating_add(width))
+            })
+            .collect()
+    }
+}
+
+/// Integer square root, rounded down
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
+    }
+
+    // Newton's method from an overestimate converges from above
+    let mut x = n;
+    let mut y = (x + 1) / 2;
+    while y < x {
+        x = y;
+        y = (x + n / x) / 2;
+    }
+    x
 }
 
 /// Hypergraph implementation
@@ -1,4 +1,4 @@
-use super::{OpCode, Stack, Memory, Value};
+use super::{OpCode, Stack, Memory, Value, VM, BTreeMapDS, TrieDS, GraphDS};
 use std::fmt;
 
 pub trait Tracer {
@@ -25,6 +25,29 @@ pub struct ExecutionTrace {
 
 pub struct StackView<'a>(&'a Stack);
 pub struct MemoryView<'a>(&'a Memory);
+pub struct BTreeView<'a>(pub &'a BTreeMapDS);
+pub struct TrieView<'a>(pub &'a TrieDS);
+pub struct GraphView<'a>(pub &'a GraphDS);
+
+/// Handle the debugger's `ds <btree|trie|graph> <id>` command, given the
+/// words after `ds`, by dumping that data structure's contents
+pub fn ds_command(vm: &VM, args: &[&str]) -> String {
+    let (kind, id) = match args {
+        [kind, id] => match id.parse::<usize>() {
+            Ok(id) => (*kind, id),
+            Err(_) => return format!("Invalid id: {}", id),
+        },
+        _ => return "Usage: ds <btree|trie|graph> <id>".to_string(),
+    };
+
+    let dump = match kind {
+        "btree" => vm.btree(id).map(|ds| format!("{:?}", BTreeView(ds))),
+        "trie" => vm.trie(id).map(|ds| format!("{:?}", TrieView(ds))),
+        "graph" => vm.graph(id).map(|ds| format!("{:?}", GraphView(ds))),
+        _ => return format!("Unknown data structure: {}", kind),
+    };
+    dump.unwrap_or_else(|| format!("No {} with id {}", kind, id))
+}
 
 impl<'a> fmt::Debug for StackView<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -58,4 +81,49 @@ impl<'a> fmt::Debug for MemoryView<'a> {
         }
         write!(f, "]")
     }
-}
\ No newline at end of file
+}
+
+impl<'a> fmt::Debug for BTreeView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "BTreeMap ({} entries) {{", self.0.len())?;
+        for (key, value) in self.0.iter() {
+            writeln!(f, "  {} => {}", key, value)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl<'a> fmt::Debug for TrieView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Trie ({} keys) {{", self.0.len())?;
+        for (key, value) in self.0.entries() {
+            // Show printable keys as text and anything else as hex
+            match std::str::from_utf8(&key) {
+                Ok(text) if text.chars().all(|c| !c.is_control()) => write!(f, "  {:?}", text)?,
+                _ => {
+                    write!(f, "  0x")?;
+                    for byte in &key {
+                        write!(f, "{:02x}", byte)?;
+                    }
+                }
+            }
+            writeln!(f, " => {}", value.0)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+impl<'a> fmt::Debug for GraphView<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nodes = self.0.nodes();
+        let edges = self.0.edges();
+        writeln!(f, "Graph ({} nodes, {} edges) {{", nodes.len(), edges.len())?;
+        for (id, value) in nodes {
+            writeln!(f, "  node {} = {}", id, value)?;
+        }
+        for (from, to, weight) in edges {
+            writeln!(f, "  {} -> {} (weight {})", from, to, weight)?;
+        }
+        write!(f, "}}")
+    }
+}
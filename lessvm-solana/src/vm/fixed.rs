@@ -0,0 +1,114 @@
+//! Unsigned Q64.64 fixed-point arithmetic.
+//!
+//! A Q64.64 number is a `u128` holding the value multiplied by 2^64: the high
+//! 64 bits are the integer part and the low 64 bits are the fraction, so 1.5
+//! is `0x1_8000_0000_0000_0000`. On the VM stack it takes two slots, pushed
+//! in the same order as `MulWide` leaves its result: the integer part first,
+//! then the fraction on top.
+//!
+//! All operations are exact integer math, so results are identical on every
+//! validator. Fractions are truncated toward zero and anything that doesn't
+//! fit in 128 bits returns `None`.
+
+/// Number of fractional bits
+pub const FRAC_BITS: u32 = 64;
+
+/// 1.0 in Q64.64
+pub const ONE: u128 = 1 << FRAC_BITS;
+
+/// Convert an integer to Q64.64
+#[inline(always)]
+pub fn from_int(value: u64) -> u128 {
+    (value as u128) << FRAC_BITS
+}
+
+/// Integer part of a Q64.64 number, truncating the fraction
+#[inline(always)]
+pub fn to_int(value: u128) -> u64 {
+    (value >> FRAC_BITS) as u64
+}
+
+/// Join an integer part and a fraction into a Q64.64 number
+#[inline(always)]
+pub fn from_parts(int: u64, frac: u64) -> u128 {
+    from_int(int) | frac as u128
+}
+
+/// Split a Q64.64 number into its integer part and fraction
+#[inline(always)]
+pub fn to_parts(value: u128) -> (u64, u64) {
+    (to_int(value), value as u64)
+}
+
+/// Multiply two Q64.64 numbers.
+///
+/// The 256-bit product is built from four 64x64 partial products and shifted
+/// back down by 64 bits.
+pub fn mul(a: u128, b: u128) -> Option<u128> {
+    let (a_hi, a_lo) = to_parts(a);
+    let (b_hi, b_lo) = to_parts(b);
+
+    let hi_hi = (a_hi as u128) * (b_hi as u128);
+    if hi_hi >> FRAC_BITS != 0 {
+        return None;
+    }
+
+    (hi_hi << FRAC_BITS)
+        .checked_add((a_hi as u128) * (b_lo as u128))?
+        .checked_add((a_lo as u128) * (b_hi as u128))?
+        .checked_add(((a_lo as u128) * (b_lo as u128)) >> FRAC_BITS)
+}
+
+/// Divide two Q64.64 numbers, returning `None` on division by zero or overflow.
+///
+/// The integer part comes from a plain 128-bit division; the 64 fraction bits
+/// are then produced one at a time by long division of the remainder.
+pub fn div(a: u128, b: u128) -> Option<u128> {
+    if b == 0 {
+        return None;
+    }
+
+    let int = a / b;
+    if int >> FRAC_BITS != 0 {
+        return None;
+    }
+
+    let mut rem = a % b;
+    let mut frac = 0u128;
+    for _ in 0..FRAC_BITS {
+        // `rem < b`, so doubling it can carry out of 128 bits but the
+        // wrapped subtraction still yields the true remainder
+        let carry = rem >> 127 != 0;
+        rem <<= 1;
+        frac <<= 1;
+        if carry || rem >= b {
+            rem = rem.wrapping_sub(b);
+            frac |= 1;
+        }
+    }
+
+    Some((int << FRAC_BITS) | frac)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fixed_point_math() {
+        let one_and_half = ONE + ONE / 2;
+        assert_eq!(mul(one_and_half, from_int(2)), Some(from_int(3)));
+        assert_eq!(div(from_int(3), from_int(2)), Some(one_and_half));
+        assert_eq!(div(from_int(1), from_int(3)).map(to_parts), Some((0, u64::MAX / 3)));
+        assert_eq!(to_int(mul(from_int(7), ONE / 4).unwrap()), 1);
+
+        // Results that don't fit in 128 bits are rejected
+        assert_eq!(mul(from_int(u64::MAX), from_int(2)), None);
+        assert_eq!(div(from_int(u64::MAX), ONE / 4), None);
+        assert_eq!(div(ONE, 0), None);
+
+        // Dividing by a value above 2^127 exercises the carry in the long division
+        let big = u128::MAX - 1;
+        assert_eq!(div(big - 1, big), Some(u64::MAX as u128));
+    }
+}
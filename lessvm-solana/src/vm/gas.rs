@@ -1,6 +1,44 @@
-use super::VMError;
+use super::{OpCode, VMError};
 use smallvec::SmallVec;
 
+/// Per-opcode gas costs charged by the VM.
+///
+/// The default schedule matches `OpCode::gas_cost`; override individual
+/// opcodes to model a different fee market.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GasSchedule {
+    // Indexed by opcode byte; boxed to keep the table off the BPF stack
+    costs: Box<[u64]>,
+}
+
+impl GasSchedule {
+    /// Gas charged for executing `opcode`
+    #[inline(always)]
+    pub fn cost(&self, opcode: OpCode) -> u64 {
+        self.costs[opcode as usize]
+    }
+
+    /// Change the gas charged for `opcode`
+    pub fn set_cost(&mut self, opcode: OpCode, cost: u64) {
+        self.costs[opcode as usize] = cost;
+    }
+
+    /// Builder-style variant of `set_cost`
+    pub fn with_cost(mut self, opcode: OpCode, cost: u64) -> Self {
+        self.set_cost(opcode, cost);
+        self
+    }
+}
+
+impl Default for GasSchedule {
+    fn default() -> Self {
+        let costs = (0..=u8::MAX)
+            .map(|byte| OpCode::from_byte(byte).map_or(0, |op| op.gas_cost()))
+            .collect();
+        Self { costs }
+    }
+}
+
 #[repr(C, align(64))]
 pub struct Gas {
     remaining: u64,
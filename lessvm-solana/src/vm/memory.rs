@@ -6,6 +6,13 @@ pub struct Memory {
     size: usize,
 }
 
+// Little-endian unsigned integer of up to 8 bytes
+fn read_uint(bytes: &[u8]) -> u64 {
+    let mut word = [0u8; 8];
+    word[..bytes.len()].copy_from_slice(bytes);
+    u64::from_le_bytes(word)
+}
+
 impl Memory {
     pub fn new() -> Self {
         Self {
@@ -106,6 +113,63 @@ impl Memory {
         Ok(())
     }
 
+    // Copy `len` bytes from `src` to `dst`, correct for overlapping ranges like
+    // memmove. The source must already be addressable; the destination grows.
+    #[inline(always)]
+    pub fn copy_within(&mut self, dst: usize, src: usize, len: usize) -> Result<(), VMError> {
+        self.bounds_check(src, len)?;
+        let dst_end = dst.checked_add(len).ok_or(VMError::InvalidMemoryAccess)?;
+
+        self.ensure_capacity(dst_end);
+        self.data.copy_within(src..src + len, dst);
+        if len > 0 {
+            self.size = self.size.max(dst_end);
+        }
+        Ok(())
+    }
+
+    // Length of `count` integers of `width` bytes at `offset`, checking that
+    // the width is 1 to 8 bytes and the range is addressable
+    fn integer_region(&self, offset: usize, count: usize, width: usize) -> Result<usize, VMError> {
+        if !(1..=8).contains(&width) {
+            return Err(VMError::InvalidMemoryAccess);
+        }
+        let len = count.checked_mul(width).ok_or(VMError::InvalidMemoryAccess)?;
+        self.bounds_check(offset, len)?;
+        Ok(len)
+    }
+
+    // Sort `count` little-endian unsigned integers of `width` bytes at
+    // `offset` in ascending order. The range must already be addressable.
+    pub fn sort(&mut self, offset: usize, count: usize, width: usize) -> Result<(), VMError> {
+        let len = self.integer_region(offset, count, width)?;
+        let region = &mut self.data[offset..offset + len];
+        let mut values: Vec<u64> = region.chunks_exact(width).map(read_uint).collect();
+        values.sort_unstable();
+        for (chunk, value) in region.chunks_exact_mut(width).zip(values) {
+            chunk.copy_from_slice(&value.to_le_bytes()[..width]);
+        }
+        Ok(())
+    }
+
+    // Index of `key` among `count` ascending integers laid out as for `sort`,
+    // or `None` if it isn't there
+    pub fn binary_search(&self, offset: usize, count: usize, width: usize, key: u64) -> Result<Option<usize>, VMError> {
+        let len = self.integer_region(offset, count, width)?;
+        let region = &self.data[offset..offset + len];
+        let (mut low, mut high) = (0, count);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let value = read_uint(&region[mid * width..(mid + 1) * width]);
+            match value.cmp(&key) {
+                std::cmp::Ordering::Less => low = mid + 1,
+                std::cmp::Ordering::Greater => high = mid,
+                std::cmp::Ordering::Equal => return Ok(Some(mid)),
+            }
+        }
+        Ok(None)
+    }
+
     #[inline(always)]
     pub fn as_slice(&self) -> &[u8] {
         &self.data[..self.size]
@@ -5,14 +5,18 @@ pub mod stack;
 pub mod gas;
 pub mod debug;
 pub mod data_structures;
+pub mod fixed;
+pub mod container;
+pub mod return_data;
 pub mod tests;
 
-pub use core::VM;
-pub use opcodes::OpCode;
+pub use core::{VM, BSEARCH_NOT_FOUND};
+pub use opcodes::{OpCode, OpCategory};
 pub use memory::Memory;
 pub use stack::{Stack, Value};
-pub use gas::Gas;
-pub use data_structures::{BTreeMapDS, TrieDS, GraphDS, OHLCVDS, HypergraphDS};
+pub use gas::{Gas, GasSchedule};
+pub use return_data::ReturnData;
+pub use data_structures::{BTreeMapDS, TrieDS, GraphDS, OHLCVDS, HypergraphDS, RangeCursor};
 
 use thiserror::Error;
 use solana_program::program_error::ProgramError;
@@ -37,6 +41,16 @@ pub enum VMError {
     ReentrancyDetected,
     #[error("Invalid data structure operation")]
     InvalidDataStructureOperation,
+    #[error("Malformed program container")]
+    InvalidContainer,
+    #[error("Program built for a different VM version")]
+    UnsupportedVersion,
+    #[error("Return data too large")]
+    ReturnDataTooLarge,
+    #[error("Calling program can't be determined")]
+    UnknownCaller,
+    #[error("Division by zero")]
+    DivisionByZero,
 }
 
 impl From<VMError> for ProgramError {
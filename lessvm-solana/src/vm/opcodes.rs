@@ -1,3 +1,16 @@
+/// Functional grouping of opcodes, used for documentation and profiling reports
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum OpCategory {
+    Stack,
+    Math,
+    Bitwise,
+    Memory,
+    ControlFlow,
+    Solana,
+    DataStructure,
+    Crypto,
+}
+
 #[repr(u8)]
 #[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub enum OpCode {
@@ -37,6 +50,11 @@ pub enum OpCode {
     Msize = 0x24,
     Mload8 = 0x25,
     Mstore8 = 0x26,
+    StackToMem = 0x27,
+    MemToStack = 0x28,
+    MCopy = 0x29,
+    SortMem = 0x2A,
+    BSearchMem = 0x2B,
 
     // Control Flow (0x3*)
     Jump = 0x30,
@@ -44,6 +62,8 @@ pub enum OpCode {
     Call = 0x32,
     Return = 0x33,
     Revert = 0x34,
+    ReturnBytes = 0x35,
+    ReturnValues = 0x36,
 
     // Solana Operations (0x4*)
     Transfer = 0x40,
@@ -54,6 +74,14 @@ pub enum OpCode {
     GetOwner = 0x45,
     IsWritable = 0x46,
     IsSigner = 0x47,
+    CallerProgramId = 0x48,
+    LoadInstructionAt = 0x49,
+    GetClock = 0x4A,
+    AccountDataLen = 0x4B,
+    AccountDataLoad = 0x4C,
+    AccountDataStore = 0x4D,
+    LogGas = 0x4E,
+    HashAccountData = 0x4F,
 
     // Data Structure Operations (0x5*)
     // BTreeMap operations
@@ -73,6 +101,8 @@ pub enum OpCode {
     TrieGet = 0x5B,
     TrieContains = 0x5C,
     TrieClear = 0x5D,
+    TrieRemove = 0x5E,
+    TrieLen = 0x5F,
 
     // Graph operations
     GraphCreate = 0x60,
@@ -96,11 +126,53 @@ pub enum OpCode {
     HyperAddEdge = 0x6E,
     HyperAddNodeToEdge = 0x6F,
 
+    // Extended Math Operations (0x7*)
+    DivMod = 0x70,
+    MulWide = 0x71,
+    FpFromInt = 0x72,
+    FpToInt = 0x73,
+    FpMul = 0x74,
+    FpDiv = 0x75,
+
+    // Comparison Operations, unsigned, pushing 1 or 0
+    Lt = 0x76,
+    Gt = 0x77,
+    Eq = 0x78,
+    IsZero = 0x79,
+
+    // Width conversions between the 64-bit words here and 32-bit values
+    NarrowU32 = 0x7A,
+    ExtendU32 = 0x7B,
+
+    // Extended Data Structure Operations (0x8*)
+    OhlcvEma = 0x80,
+    OhlcvRsi = 0x81,
+    OhlcvBollinger = 0x82,
+    GraphShortestPath = 0x83,
+    BTreeRange = 0x84,
+    BTreeHash = 0x85,
+    TrieHash = 0x86,
+    GraphHash = 0x87,
+    BTreeRangeCursor = 0x88,
+    BTreeRangeNext = 0x89,
+
+    // Crypto Operations (0x9*)
+    Keccak256 = 0x90,
+
     // System Operations (0xF*)
     Halt = 0xFF,
 }
 
 impl OpCode {
+    /// Number of immediate operand bytes following the opcode
+    pub fn operand_size(&self) -> usize {
+        match self {
+            OpCode::Push1 | OpCode::Dup | OpCode::Swap => 1,
+            OpCode::Push8 => 8,
+            _ => 0,
+        }
+    }
+
     pub fn gas_cost(&self) -> u64 {
         match self {
             // Stack operations
@@ -114,6 +186,13 @@ impl OpCode {
             OpCode::Mul => 5,
             OpCode::Div | OpCode::Mod => 8,
             OpCode::MulDiv => 10,
+            OpCode::DivMod => 10,
+            OpCode::MulWide => 8,
+            OpCode::FpFromInt | OpCode::FpToInt => 3,
+            OpCode::FpMul => 15,
+            OpCode::FpDiv => 40,
+            OpCode::Lt | OpCode::Gt | OpCode::Eq | OpCode::IsZero => 3,
+            OpCode::NarrowU32 | OpCode::ExtendU32 => 3,
             OpCode::Exp => 50,
             OpCode::SignExtend => 5,
 
@@ -127,6 +206,12 @@ impl OpCode {
             OpCode::LoadN | OpCode::StoreN => 6,
             OpCode::Msize => 2,
             OpCode::Mload8 | OpCode::Mstore8 => 3,
+            OpCode::StackToMem | OpCode::MemToStack => 6,
+            OpCode::MCopy => 3,
+            // Plus n·log2(n) for the elements sorted at execution
+            OpCode::SortMem => 10,
+            // Plus log2(n) for the probes at execution
+            OpCode::BSearchMem => 5,
 
             // Control flow
             OpCode::Jump => 8,
@@ -134,43 +219,368 @@ impl OpCode {
             OpCode::Call => 40,
             OpCode::Return => 0,
             OpCode::Revert => 0,
+            // Plus a per-word charge on the returned data at execution
+            OpCode::ReturnBytes | OpCode::ReturnValues => 2,
 
             // Solana operations
-            OpCode::Transfer | OpCode::SPLTransfer => 100,
+            OpCode::Transfer => 100,
             OpCode::CPI => 200,
+            // Native transfer cost plus CPI overhead for invoking the token program
+            OpCode::SPLTransfer => 300,
             OpCode::Log => 8,
             OpCode::GetBalance | OpCode::GetOwner => 20,
             OpCode::IsWritable | OpCode::IsSigner => 5,
+            OpCode::CallerProgramId => 20,
+            OpCode::LoadInstructionAt => 30,
+            OpCode::GetClock => 20,
+            OpCode::AccountDataLen => 5,
+            OpCode::AccountDataLoad => 20,
+            OpCode::AccountDataStore => 30,
+            // Plus a per-word charge on the label at execution
+            OpCode::LogGas => 10,
+            // Plus a per-word charge on the data hashed at execution
+            OpCode::HashAccountData => 30,
+
+            // Crypto operations, plus a per-word charge at execution
+            OpCode::Keccak256 => 30,
 
             // Data Structure operations - gas costs reflect complexity
             OpCode::BTreeCreate | OpCode::TrieCreate | OpCode::GraphCreate | 
             OpCode::OhlcvCreate | OpCode::HyperCreate => 5,
             OpCode::BTreeInsert | OpCode::BTreeGet | OpCode::BTreeRemove | 
             OpCode::BTreeContains | OpCode::BTreeFirstKey | OpCode::BTreeLastKey => 15,
-            OpCode::TrieInsert | OpCode::TrieGet | OpCode::TrieContains => 20, 
+            OpCode::BTreeRange => 25,
+            // Plus a per-word charge on the serialized contents at execution
+            OpCode::BTreeHash | OpCode::TrieHash | OpCode::GraphHash => 30,
+            // Plus a per-entry charge on the batch at execution
+            OpCode::BTreeRangeCursor | OpCode::BTreeRangeNext => 20,
+            OpCode::TrieInsert | OpCode::TrieGet | OpCode::TrieContains |
+            OpCode::TrieRemove => 20,
             OpCode::GraphAddNode | OpCode::GraphGetNode | OpCode::GraphSetNode => 10,
             OpCode::GraphAddEdge | OpCode::GraphGetNeighbors => 15,
             OpCode::GraphBfs => 50,
+            OpCode::GraphShortestPath => 80,
             OpCode::OhlcvAddBar | OpCode::OhlcvGetBar => 8,
-            OpCode::OhlcvSma => 30,
+            OpCode::OhlcvSma | OpCode::OhlcvEma => 30,
+            OpCode::OhlcvRsi | OpCode::OhlcvBollinger => 40,
             OpCode::HyperAddNode | OpCode::HyperAddEdge | OpCode::HyperAddNodeToEdge => 20,
-            OpCode::BTreeLen | OpCode::BTreeClear | OpCode::TrieClear | OpCode::GraphClear => 5,
+            OpCode::BTreeLen | OpCode::BTreeClear | OpCode::TrieClear | OpCode::TrieLen |
+            OpCode::GraphClear => 5,
 
-            // System operations
-            OpCode::Halt => 0,
+            // System operations, charged the same as HALT in the ideless VM
+            OpCode::Halt => 1,
         }
     }
 
+    pub fn category(&self) -> OpCategory {
+        match self {
+            OpCode::Nop | OpCode::Push1 | OpCode::Push8 | OpCode::Pop |
+            OpCode::Dup | OpCode::Swap => OpCategory::Stack,
+
+            OpCode::Add | OpCode::Sub | OpCode::Mul | OpCode::Div | OpCode::MulDiv |
+            OpCode::Mod | OpCode::Exp | OpCode::SignExtend | OpCode::DivMod |
+            OpCode::MulWide | OpCode::FpFromInt | OpCode::FpToInt |
+            OpCode::FpMul | OpCode::FpDiv | OpCode::Lt | OpCode::Gt |
+            OpCode::Eq | OpCode::IsZero | OpCode::NarrowU32 |
+            OpCode::ExtendU32 => OpCategory::Math,
+
+            OpCode::And | OpCode::Or | OpCode::Xor | OpCode::Not | OpCode::Byte |
+            OpCode::Shl | OpCode::Shr | OpCode::Sar => OpCategory::Bitwise,
+
+            OpCode::Load | OpCode::Store | OpCode::LoadN | OpCode::StoreN |
+            OpCode::Msize | OpCode::Mload8 | OpCode::Mstore8 | OpCode::StackToMem |
+            OpCode::MemToStack | OpCode::MCopy | OpCode::SortMem |
+            OpCode::BSearchMem => OpCategory::Memory,
+
+            OpCode::Jump | OpCode::JumpI | OpCode::Call | OpCode::Return |
+            OpCode::Revert | OpCode::ReturnBytes | OpCode::ReturnValues |
+            OpCode::Halt => OpCategory::ControlFlow,
+
+            OpCode::Transfer | OpCode::SPLTransfer | OpCode::CPI | OpCode::Log |
+            OpCode::GetBalance | OpCode::GetOwner | OpCode::IsWritable |
+            OpCode::IsSigner | OpCode::CallerProgramId |
+            OpCode::LoadInstructionAt | OpCode::GetClock | OpCode::AccountDataLen |
+            OpCode::AccountDataLoad | OpCode::AccountDataStore | OpCode::LogGas |
+            OpCode::HashAccountData => OpCategory::Solana,
+
+            OpCode::Keccak256 => OpCategory::Crypto,
+
+            OpCode::BTreeCreate | OpCode::BTreeInsert | OpCode::BTreeGet |
+            OpCode::BTreeRemove | OpCode::BTreeContains | OpCode::BTreeLen |
+            OpCode::BTreeFirstKey | OpCode::BTreeLastKey | OpCode::BTreeClear |
+            OpCode::BTreeRange | OpCode::BTreeHash |
+            OpCode::BTreeRangeCursor | OpCode::BTreeRangeNext |
+            OpCode::TrieCreate | OpCode::TrieInsert | OpCode::TrieGet |
+            OpCode::TrieContains | OpCode::TrieClear | OpCode::TrieRemove |
+            OpCode::TrieLen | OpCode::TrieHash |
+            OpCode::GraphCreate | OpCode::GraphAddNode | OpCode::GraphAddEdge |
+            OpCode::GraphGetNode | OpCode::GraphSetNode | OpCode::GraphGetNeighbors |
+            OpCode::GraphBfs | OpCode::GraphClear | OpCode::GraphShortestPath |
+            OpCode::GraphHash |
+            OpCode::OhlcvCreate | OpCode::OhlcvAddBar | OpCode::OhlcvGetBar |
+            OpCode::OhlcvSma | OpCode::OhlcvEma | OpCode::OhlcvRsi | OpCode::OhlcvBollinger |
+            OpCode::HyperCreate | OpCode::HyperAddNode | OpCode::HyperAddEdge |
+            OpCode::HyperAddNodeToEdge => OpCategory::DataStructure,
+        }
+    }
+
+    /// Stack effect in `( before -- after )` notation, top of stack rightmost.
+    /// Q64.64 fixed-point values take two words, `int frac`.
+    pub fn stack_effect(&self) -> &'static str {
+        match self {
+            OpCode::Nop => "( -- )",
+            OpCode::Push1 | OpCode::Push8 => "( -- value )",
+            OpCode::Pop => "( value -- )",
+            OpCode::Dup => "( ... value -- ... value value )",
+            OpCode::Swap => "( value ... top -- top ... value )",
+
+            OpCode::Add => "( a b -- a+b )",
+            OpCode::Sub => "( a b -- a-b )",
+            OpCode::Mul => "( a b -- a*b )",
+            OpCode::Div => "( a b -- a/b )",
+            OpCode::MulDiv => "( a b c -- a*b/c )",
+            OpCode::Mod => "( a b -- a%b )",
+            OpCode::Exp => "( base exponent -- result )",
+            OpCode::SignExtend => "( value bytes -- result )",
+            OpCode::DivMod => "( a b -- a/b a%b )",
+            OpCode::MulWide => "( a b -- hi lo )",
+            OpCode::FpFromInt => "( n -- int frac )",
+            OpCode::FpToInt => "( int frac -- n )",
+            OpCode::FpMul => "( int frac int frac -- int frac )",
+            OpCode::FpDiv => "( int frac int frac -- int frac )",
+            OpCode::Lt => "( a b -- a<b )",
+            OpCode::Gt => "( a b -- a>b )",
+            OpCode::Eq => "( a b -- a==b )",
+            OpCode::IsZero => "( a -- a==0 )",
+            OpCode::NarrowU32 => "( value -- low overflow )",
+            OpCode::ExtendU32 => "( value -- low )",
+
+            OpCode::And => "( a b -- a&b )",
+            OpCode::Or => "( a b -- a|b )",
+            OpCode::Xor => "( a b -- a^b )",
+            OpCode::Not => "( a -- ~a )",
+            OpCode::Byte => "( value index -- byte )",
+            OpCode::Shl => "( value shift -- value<<shift )",
+            OpCode::Shr | OpCode::Sar => "( value shift -- value>>shift )",
+
+            OpCode::Load => "( offset -- value )",
+            OpCode::Store => "( value offset -- )",
+            OpCode::LoadN => "( offset len -- )",
+            OpCode::StoreN => "( value offset len -- )",
+            OpCode::Msize => "( -- size )",
+            OpCode::Mload8 => "( offset -- byte )",
+            OpCode::Mstore8 => "( byte offset -- )",
+            OpCode::StackToMem => "( values... offset count -- )",
+            OpCode::MemToStack => "( count offset -- values... )",
+            OpCode::MCopy => "( len src dst -- )",
+            OpCode::SortMem => "( width count offset -- )",
+            OpCode::BSearchMem => "( key width count offset -- index )",
+
+            OpCode::Jump => "( target -- )",
+            OpCode::JumpI => "( condition target -- )",
+            OpCode::Call => "( target -- )",
+            OpCode::Return => "( value -- )",
+            OpCode::Revert => "( code -- )",
+            OpCode::ReturnBytes => "( offset len -- )",
+            OpCode::ReturnValues => "( value ... count -- )",
+            OpCode::Halt => "( -- )",
+
+            OpCode::Transfer => "( from to amount -- )",
+            OpCode::SPLTransfer => "( from to authority amount -- )",
+            OpCode::CPI => "( -- )",
+            OpCode::Log => "( value -- )",
+            OpCode::GetBalance => "( account -- lamports )",
+            OpCode::GetOwner => "( account -- owner )",
+            OpCode::IsWritable => "( account -- writable )",
+            OpCode::IsSigner => "( account -- signer )",
+            OpCode::CallerProgramId => "( sysvar dest -- found )",
+            OpCode::LoadInstructionAt => "( sysvar index dest -- len )",
+            OpCode::GetClock => "( -- timestamp slot epoch )",
+            OpCode::AccountDataLen => "( account -- len )",
+            OpCode::AccountDataLoad => "( account offset -- value )",
+            OpCode::AccountDataStore => "( account offset value -- )",
+            OpCode::LogGas => "( offset len -- )",
+            OpCode::HashAccountData => "( account -- hash )",
+
+            OpCode::Keccak256 => "( offset len dest -- )",
+
+            OpCode::BTreeCreate | OpCode::TrieCreate | OpCode::GraphCreate |
+            OpCode::OhlcvCreate | OpCode::HyperCreate => "( id -- )",
+            OpCode::BTreeClear | OpCode::TrieClear | OpCode::GraphClear => "( id -- )",
+            OpCode::BTreeInsert => "( id key value -- old )",
+            OpCode::BTreeGet => "( id key -- value )",
+            OpCode::BTreeRemove => "( id key -- old )",
+            OpCode::BTreeContains => "( id key -- found )",
+            OpCode::BTreeLen | OpCode::TrieLen => "( id -- len )",
+            OpCode::BTreeFirstKey | OpCode::BTreeLastKey => "( id -- key )",
+            OpCode::BTreeRange => "( id low high -- count value key ... )",
+            OpCode::BTreeHash | OpCode::TrieHash | OpCode::GraphHash => "( id -- hash )",
+            OpCode::BTreeRangeCursor => "( id low high limit ptr -- count value key ... )",
+            OpCode::BTreeRangeNext => "( id ptr -- count value key ... )",
+            OpCode::TrieInsert => "( id ptr len value -- )",
+            OpCode::TrieGet => "( id ptr len -- value )",
+            OpCode::TrieContains | OpCode::TrieRemove => "( id ptr len -- found )",
+            OpCode::GraphAddNode | OpCode::GraphSetNode |
+            OpCode::HyperAddNode => "( id node value -- )",
+            OpCode::GraphAddEdge => "( id from to weight -- )",
+            OpCode::GraphGetNode => "( id node -- value )",
+            OpCode::GraphGetNeighbors => "( id node -- count weight neighbor ... )",
+            OpCode::GraphBfs => "( id start -- count node ... )",
+            OpCode::GraphShortestPath => "( id start end -- cost len node ... )",
+            OpCode::OhlcvAddBar => "( id timestamp open high low close volume -- )",
+            OpCode::OhlcvGetBar => "( id index -- volume close low high open timestamp )",
+            OpCode::OhlcvSma | OpCode::OhlcvEma |
+            OpCode::OhlcvRsi => "( id period -- count value timestamp ... )",
+            OpCode::OhlcvBollinger => "( id period -- count lower middle upper timestamp ... )",
+            OpCode::HyperAddEdge => "( id edge weight -- )",
+            OpCode::HyperAddNodeToEdge => "( id edge node -- )",
+        }
+    }
+
+    /// One-line description for generated documentation
+    pub fn description(&self) -> &'static str {
+        match self {
+            OpCode::Nop => "Do nothing",
+            OpCode::Push1 => "Push a 1-byte immediate",
+            OpCode::Push8 => "Push an 8-byte little-endian immediate",
+            OpCode::Pop => "Discard the top of the stack",
+            OpCode::Dup => "Duplicate the value n below the top (immediate n)",
+            OpCode::Swap => "Swap the top with the value n below it (immediate n)",
+
+            OpCode::Add => "Addition, failing on overflow",
+            OpCode::Sub => "Subtraction, failing on underflow",
+            OpCode::Mul => "Multiplication, failing on overflow",
+            OpCode::Div => "Unsigned division, failing on division by zero",
+            OpCode::MulDiv => "Multiply then divide with a 128-bit intermediate",
+            OpCode::Mod => "Unsigned remainder, failing on division by zero",
+            OpCode::Exp => "Exponentiation, failing on overflow",
+            OpCode::SignExtend => "Sign-extend from the given number of bytes",
+            OpCode::DivMod => "Quotient and remainder",
+            OpCode::MulWide => "Full 128-bit product as two words",
+            OpCode::FpFromInt => "Convert an integer to Q64.64 fixed point",
+            OpCode::FpToInt => "Truncate a Q64.64 fixed-point value to an integer",
+            OpCode::FpMul => "Q64.64 fixed-point multiplication",
+            OpCode::FpDiv => "Q64.64 fixed-point division",
+            OpCode::Lt => "Unsigned less than",
+            OpCode::Gt => "Unsigned greater than",
+            OpCode::Eq => "Equality",
+            OpCode::IsZero => "Test for zero",
+            OpCode::NarrowU32 => "Low 32 bits, flagging whether the high bits were set",
+            OpCode::ExtendU32 => "Zero-extend the low 32 bits to a word",
+
+            OpCode::And => "Bitwise AND",
+            OpCode::Or => "Bitwise OR",
+            OpCode::Xor => "Bitwise XOR",
+            OpCode::Not => "Bitwise NOT",
+            OpCode::Byte => "Extract a single byte",
+            OpCode::Shl => "Shift left",
+            OpCode::Shr => "Logical shift right",
+            OpCode::Sar => "Arithmetic shift right",
+
+            OpCode::Load => "Load a word from memory",
+            OpCode::Store => "Store a word to memory",
+            OpCode::LoadN => "Check that a memory range is readable",
+            OpCode::StoreN => "Store the low bytes of a word to memory",
+            OpCode::Msize => "Current memory size in bytes",
+            OpCode::Mload8 => "Load a byte from memory",
+            OpCode::Mstore8 => "Store a byte to memory",
+            OpCode::StackToMem => "Move words from the stack to memory",
+            OpCode::MemToStack => "Push words from memory",
+            OpCode::MCopy => "Copy a memory range, allowing overlap",
+            OpCode::SortMem => "Sort little-endian unsigned integers in memory",
+            OpCode::BSearchMem => "Find a key in memory sorted by SortMem",
+
+            OpCode::Jump => "Unconditional jump",
+            OpCode::JumpI => "Jump if the condition is non-zero",
+            OpCode::Call => "Call a subroutine",
+            OpCode::Return => "Return from a subroutine with a value",
+            OpCode::Revert => "Abort with a custom error code",
+            OpCode::ReturnBytes => "Stop, returning a memory range to the caller",
+            OpCode::ReturnValues => "Stop, returning several values to the caller",
+            OpCode::Halt => "Stop execution",
+
+            OpCode::Transfer => "Transfer lamports between accounts",
+            OpCode::SPLTransfer => "Transfer SPL tokens through the token program",
+            OpCode::CPI => "Cross-program invocation (not yet supported)",
+            OpCode::Log => "Log a value",
+            OpCode::GetBalance => "Lamport balance of an account",
+            OpCode::GetOwner => "First 8 bytes of an account's owner",
+            OpCode::IsWritable => "Whether an account is writable",
+            OpCode::IsSigner => "Whether an account signed the transaction",
+            OpCode::CallerProgramId => "Write the directly calling program's id to memory",
+            OpCode::LoadInstructionAt => "Copy a transaction instruction into memory",
+            OpCode::GetClock => "Unix timestamp, slot and epoch from the Clock sysvar",
+            OpCode::AccountDataLen => "Length of an account's data",
+            OpCode::AccountDataLoad => "Load a word from an account's data",
+            OpCode::AccountDataStore => "Store a word to a writable account's data",
+            OpCode::LogGas => "Log the remaining gas with a label from memory",
+            OpCode::HashAccountData => "Keccak-256 of an account's data, truncated to a word",
+
+            OpCode::Keccak256 => "Keccak-256 hash of a memory range",
+
+            OpCode::BTreeCreate => "Create a BTreeMap",
+            OpCode::BTreeInsert => "Insert into a BTreeMap",
+            OpCode::BTreeGet => "Look up a BTreeMap key, 0 if missing",
+            OpCode::BTreeRemove => "Remove a BTreeMap key",
+            OpCode::BTreeContains => "Whether a BTreeMap has a key",
+            OpCode::BTreeLen => "Number of BTreeMap entries",
+            OpCode::BTreeFirstKey => "Smallest BTreeMap key",
+            OpCode::BTreeLastKey => "Largest BTreeMap key",
+            OpCode::BTreeClear => "Remove all BTreeMap entries",
+            OpCode::BTreeRange => "BTreeMap entries with keys in [low, high]",
+            OpCode::BTreeHash => "Keccak-256 commitment to a BTreeMap's contents",
+            OpCode::BTreeRangeCursor => "First batch of a BTreeMap range, saving a cursor to memory",
+            OpCode::BTreeRangeNext => "Next batch of a BTreeMap range from a cursor in memory",
+            OpCode::TrieCreate => "Create a trie",
+            OpCode::TrieInsert => "Insert a key from memory into a trie",
+            OpCode::TrieGet => "Look up a trie key, 0 if missing",
+            OpCode::TrieContains => "Whether a trie has a key",
+            OpCode::TrieClear => "Remove all trie entries",
+            OpCode::TrieRemove => "Remove a trie key",
+            OpCode::TrieLen => "Number of trie entries",
+            OpCode::TrieHash => "Keccak-256 commitment to a trie's contents",
+            OpCode::GraphCreate => "Create a graph",
+            OpCode::GraphAddNode => "Add a graph node",
+            OpCode::GraphAddEdge => "Add a weighted graph edge",
+            OpCode::GraphGetNode => "Value of a graph node, 0 if missing",
+            OpCode::GraphSetNode => "Set the value of a graph node",
+            OpCode::GraphGetNeighbors => "Neighbors of a graph node with edge weights",
+            OpCode::GraphBfs => "Breadth-first traversal from a node",
+            OpCode::GraphClear => "Remove all graph nodes and edges",
+            OpCode::GraphShortestPath => "Cheapest path between two nodes",
+            OpCode::GraphHash => "Keccak-256 commitment to a graph's nodes and edges",
+            OpCode::OhlcvCreate => "Create an OHLCV series",
+            OpCode::OhlcvAddBar => "Append an OHLCV bar",
+            OpCode::OhlcvGetBar => "Read an OHLCV bar, zeros if missing",
+            OpCode::OhlcvSma => "Simple moving average of closes",
+            OpCode::OhlcvEma => "Exponential moving average of closes",
+            OpCode::OhlcvRsi => "Relative strength index of closes",
+            OpCode::OhlcvBollinger => "Bollinger bands of closes",
+            OpCode::HyperCreate => "Create a hypergraph",
+            OpCode::HyperAddNode => "Add a hypergraph node",
+            OpCode::HyperAddEdge => "Add a weighted hyperedge",
+            OpCode::HyperAddNodeToEdge => "Add a node to a hyperedge",
+        }
+    }
+
+    /// Every opcode, in byte order
+    pub fn all() -> impl Iterator<Item = OpCode> {
+        (0..=u8::MAX).filter_map(OpCode::from_byte)
+    }
+
     pub fn from_byte(byte: u8) -> Option<Self> {
         use std::mem::transmute;
         match byte {
             0x00..=0x05 | // Stack ops
             0x10..=0x1F | // Math and bitwise ops
-            0x20..=0x26 | // Memory ops
-            0x30..=0x34 | // Control flow
-            0x40..=0x47 | // Solana ops
-            0x50..=0x5D | // BTreeMap and Trie ops
+            0x20..=0x2B | // Memory ops
+            0x30..=0x36 | // Control flow
+            0x40..=0x4F | // Solana ops
+            0x50..=0x5F | // BTreeMap and Trie ops
             0x60..=0x6F | // Graph, OHLCV, and Hypergraph ops
+            0x70..=0x7B | // Extended math, Q64.64 fixed-point, comparison and width ops
+            0x80..=0x89 | // OHLCV indicators, graph shortest path, BTree ranges and hashes
+            0x90 | // Crypto ops
             0xFF => unsafe { Some(transmute(byte)) }, // Safe because we check valid ranges
             _ => None
         }
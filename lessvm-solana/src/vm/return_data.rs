@@ -0,0 +1,53 @@
+//! Data a program hands back to its caller
+//!
+//! `Return` gives back a single value, while `ReturnValues` and `ReturnBytes`
+//! stop the program with several values or a buffer copied out of memory,
+//! e.g. a struct. Whatever was returned is encoded with `to_bytes` for the
+//! runtime's `set_return_data`, with values as little-endian u64s.
+
+use solana_program::program::MAX_RETURN_DATA;
+
+use super::{VMError, VMResult, Value};
+
+/// What a program returned
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReturnData {
+    /// A single value from `Return`
+    Value(Value),
+    /// Values from `ReturnValues`, deepest first
+    Values(Vec<Value>),
+    /// A memory buffer from `ReturnBytes`
+    Bytes(Vec<u8>),
+}
+
+impl ReturnData {
+    /// Returned values, or `VMError::ReturnDataTooLarge` if they wouldn't fit
+    /// in the runtime's return data
+    pub fn values(values: Vec<Value>) -> VMResult<Self> {
+        Self::check_len(values.len().saturating_mul(8))?;
+        Ok(ReturnData::Values(values))
+    }
+
+    /// Returned buffer, or `VMError::ReturnDataTooLarge` if it wouldn't fit
+    /// in the runtime's return data
+    pub fn bytes(bytes: Vec<u8>) -> VMResult<Self> {
+        Self::check_len(bytes.len())?;
+        Ok(ReturnData::Bytes(bytes))
+    }
+
+    fn check_len(len: usize) -> VMResult<()> {
+        if len > MAX_RETURN_DATA {
+            return Err(VMError::ReturnDataTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Encoding passed to `set_return_data`
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            ReturnData::Value(value) => value.0.to_le_bytes().to_vec(),
+            ReturnData::Values(values) => values.iter().flat_map(|v| v.0.to_le_bytes()).collect(),
+            ReturnData::Bytes(bytes) => bytes.clone(),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use bytemuck::{Pod, Zeroable};
 use super::VMError;
-use smallvec::{SmallVec, smallvec};
+use smallvec::SmallVec;
 
 #[repr(transparent)]
 #[derive(Copy, Clone, Debug, PartialEq, Eq)]
@@ -45,10 +45,13 @@ impl Default for Value {
     }
 }
 
+/// Maximum number of values on the stack, sized to the inline buffer so
+/// pushes never allocate
+pub const STACK_SIZE: usize = 64;
 
 #[repr(C, align(64))]
 pub struct Stack {
-    data: SmallVec<[Value; 64]>,
+    data: SmallVec<[Value; STACK_SIZE]>,
     frames: SmallVec<[usize; 8]>,
     top: usize,
 }
@@ -56,7 +59,7 @@ pub struct Stack {
 impl Stack {
     pub fn new() -> Self {
         Self {
-            data: smallvec![Value(0); 64],
+            data: SmallVec::new(),
             frames: SmallVec::new(),
             top: 0,
         }
@@ -64,7 +67,7 @@ impl Stack {
 
     #[inline(always)]
     pub fn push(&mut self, value: Value) -> Result<(), VMError> {
-        if self.top >= self.data.len() {
+        if self.top >= STACK_SIZE {
             return Err(VMError::StackOverflow);
         }
         self.data.push(value);
@@ -128,7 +131,7 @@ impl Stack {
 
     #[inline(always)]
     pub fn is_full(&self) -> bool {
-        self.top >= self.data.len()
+        self.top >= STACK_SIZE
     }
 
     #[inline(always)]
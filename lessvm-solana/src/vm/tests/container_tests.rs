@@ -0,0 +1,90 @@
+use super::super::*;
+use super::super::container::{pack, split, unpack, Meta, MAGIC};
+use super::super::core::VERSION;
+use solana_program::pubkey::Pubkey;
+
+#[test]
+fn test_container_roundtrip() {
+    let code = vec![OpCode::Push1 as u8, 7, OpCode::Halt as u8];
+
+    let meta = Meta::default();
+    let packed = pack(&code, &meta);
+    assert!(packed.starts_with(MAGIC));
+    assert_eq!(packed.len(), 17 + code.len());
+    assert_eq!(unpack(&packed).unwrap(), (meta, code.clone()));
+
+    let meta = Meta {
+        version: VERSION,
+        entrypoint: Some(2),
+        gas_limit: Some(5_000),
+    };
+    let packed = pack(&code, &meta);
+    assert_eq!(packed.len(), 29 + code.len());
+    assert_eq!(unpack(&packed).unwrap(), (meta, code.clone()));
+
+    // Raw bytecode passes through untouched
+    assert_eq!(split(&code).unwrap(), (None, code.as_slice()));
+    assert!(matches!(unpack(&code), Err(VMError::InvalidContainer)));
+}
+
+#[test]
+fn test_container_rejects_other_versions() {
+    let code = [OpCode::Halt as u8];
+    for version in [0, VERSION - 1, VERSION + 1] {
+        let packed = pack(&code, &Meta { version, ..Meta::default() });
+        assert!(matches!(unpack(&packed), Err(VMError::UnsupportedVersion)));
+        assert!(matches!(split(&packed), Err(VMError::UnsupportedVersion)));
+    }
+}
+
+#[test]
+fn test_container_rejects_malformed() {
+    let code = [OpCode::Push1 as u8, 1, OpCode::Halt as u8];
+    let packed = pack(&code, &Meta { gas_limit: Some(10), ..Meta::default() });
+
+    // Truncated anywhere, or with bytes after the code
+    for len in MAGIC.len()..packed.len() {
+        assert!(matches!(split(&packed[..len]), Err(VMError::InvalidContainer)), "length {}", len);
+    }
+    let mut trailing = packed.clone();
+    trailing.push(0);
+    assert!(matches!(split(&trailing), Err(VMError::InvalidContainer)));
+
+    // Unknown flags and entrypoints past the end of the code
+    let mut flags = packed.clone();
+    flags[12] |= 0x80;
+    assert!(matches!(split(&flags), Err(VMError::InvalidContainer)));
+    let outside = pack(&code, &Meta { entrypoint: Some(3), ..Meta::default() });
+    assert!(matches!(split(&outside), Err(VMError::InvalidContainer)));
+}
+
+#[test]
+fn test_container_entrypoint() {
+    // 0x00: Push1 1, Halt
+    // 0x03: Push1 2, Halt
+    let push1 = OpCode::Push1 as u8;
+    let halt = OpCode::Halt as u8;
+    let packed = pack(&[push1, 1, halt, push1, 2, halt], &Meta { entrypoint: Some(3), ..Meta::default() });
+
+    let (meta, code) = split(&packed).unwrap();
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.set_pc(meta.unwrap().entrypoint.unwrap() as usize);
+    vm.execute(code).unwrap();
+    assert_eq!(vm.stack().peek().unwrap().0, 2);
+}
+
+#[test]
+fn test_container_entrypoint_inside_operand() {
+    // 0x00: Push8 0x01FF, Halt
+    let mut code = vec![OpCode::Push8 as u8];
+    code.extend_from_slice(&0x01FFu64.to_le_bytes());
+    code.push(OpCode::Halt as u8);
+
+    // Byte 1 decodes as Halt on its own, but it's part of the operand
+    let packed = pack(&code, &Meta { entrypoint: Some(1), ..Meta::default() });
+    let (meta, code) = split(&packed).unwrap();
+    let entrypoint = meta.unwrap().entrypoint.unwrap() as usize;
+    assert!(matches!(VM::validate(code, entrypoint), Err(VMError::InvalidInstruction)));
+    assert!(VM::validate(code, 9).is_ok());
+}
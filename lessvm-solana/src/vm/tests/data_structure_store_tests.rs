@@ -4,21 +4,23 @@ use solana_program::clock::Epoch;
 use solana_program::pubkey::Pubkey;
 use solana_program::account_info::AccountInfo;
 
-fn create_test_account(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'static>) {
-    let key = Pubkey::new_unique();
-    let mut lamports = lamports;
-    let mut data = vec![0; 32];
-    
-    AccountInfo::new(
-        &key,
+fn create_test_account<'a>(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'a>) {
+    // AccountInfo only borrows what it describes, so leak it to outlive the call
+    let key: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let owner: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let data = vec![0; 32];
+
+    let account = AccountInfo::new(
+        key,
         true,
         true,
-        &mut lamports,
-        &mut data,
-        &Pubkey::new_unique(),
+        Box::leak(Box::new(lamports)),
+        Box::leak(data.clone().into_boxed_slice()),
+        owner,
         false,
         Epoch::default(),
-    )
+    );
+    (*key, data, account)
 }
 
 #[test]
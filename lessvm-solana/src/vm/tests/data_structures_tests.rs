@@ -60,6 +60,53 @@ fn test_btreemap_basic_operations() {
     assert_eq!(btree.get(10), None);
 }
 
+#[test]
+fn test_btreemap_range() {
+    let mut btree = BTreeMapDS::new();
+    for key in [50, 10, 40, 20, 30] {
+        btree.insert(key, key * 10);
+    }
+
+    // Both bounds are inclusive
+    assert_eq!(btree.range(20, 40), vec![(20, 200), (30, 300), (40, 400)]);
+    assert_eq!(btree.range(15, 35), vec![(20, 200), (30, 300)]);
+    assert_eq!(btree.range(50, 50), vec![(50, 500)]);
+    assert_eq!(btree.range(0, u64::MAX).len(), 5);
+
+    // Empty and inverted ranges
+    assert!(btree.range(41, 49).is_empty());
+    assert!(btree.range(40, 20).is_empty());
+}
+
+#[test]
+fn test_btreemap_range_batch() {
+    let mut btree = BTreeMapDS::new();
+    for key in [50, 10, 40, 20, 30] {
+        btree.insert(key, key * 10);
+    }
+
+    let mut cursor = RangeCursor::new(15, 50, 2);
+    assert_eq!(btree.range_batch(&mut cursor), vec![(20, 200), (30, 300)]);
+    assert_eq!(cursor.next, 31);
+    assert!(!cursor.done);
+    assert_eq!(RangeCursor::from_bytes(&cursor.to_bytes()).unwrap(), cursor);
+
+    // A full batch ending at the top of the range is the last one
+    assert_eq!(btree.range_batch(&mut cursor), vec![(40, 400), (50, 500)]);
+    assert!(cursor.done);
+    assert!(btree.range_batch(&mut cursor).is_empty());
+
+    // Keys up to u64::MAX don't overflow the cursor
+    btree.insert(u64::MAX, 1);
+    let mut cursor = RangeCursor::new(50, u64::MAX, 1);
+    assert_eq!(btree.range_batch(&mut cursor), vec![(50, 500)]);
+    assert_eq!(btree.range_batch(&mut cursor), vec![(u64::MAX, 1)]);
+    assert!(cursor.done);
+
+    assert!(RangeCursor::new(2, 1, 10).done);
+    assert!(RangeCursor::from_bytes(&[0; RangeCursor::SIZE - 1]).is_err());
+}
+
 // Tests for TrieDS
 #[test]
 fn test_trie_basic_operations() {
@@ -90,6 +137,18 @@ fn test_trie_basic_operations() {
     assert_eq!(trie.get(b"world"), None);
 }
 
+#[test]
+fn test_trie_entries() {
+    let mut trie = TrieDS::new();
+    for (key, value) in [(&b"to"[..], 2), (b"tea", 3), (b"a", 1), (b"ten", 4)] {
+        trie.insert(key, Value(value)).unwrap();
+    }
+    trie.remove(b"tea");
+
+    let entries: Vec<(Vec<u8>, u64)> = trie.entries().into_iter().map(|(k, v)| (k, v.0)).collect();
+    assert_eq!(entries, vec![(b"a".to_vec(), 1), (b"ten".to_vec(), 4), (b"to".to_vec(), 2)]);
+}
+
 // Tests for GraphDS
 #[test]
 fn test_graph_basic_operations() {
@@ -146,6 +205,40 @@ fn test_graph_basic_operations() {
     assert_eq!(graph.get_neighbors(1).len(), 0);
 }
 
+#[test]
+fn test_graph_shortest_path() {
+    let mut graph = GraphDS::new();
+    graph.add_edge(1, 2, 4).unwrap();
+    graph.add_edge(1, 3, 1).unwrap();
+    graph.add_edge(3, 2, 2).unwrap();
+    graph.add_edge(2, 4, 1).unwrap();
+    graph.add_edge(3, 4, 5).unwrap();
+    graph.add_edge(4, 5, 3).unwrap();
+
+    // 1 -> 3 -> 2 -> 4 beats both 1 -> 2 -> 4 (5) and 1 -> 3 -> 4 (6)
+    assert_eq!(graph.shortest_path(1, 4), Some((4, vec![1, 3, 2, 4])));
+    assert_eq!(graph.shortest_path(1, 5), Some((7, vec![1, 3, 2, 4, 5])));
+    assert_eq!(graph.shortest_path(1, 1), Some((0, vec![1])));
+
+    // Edges are directed, and unknown nodes have no path
+    assert_eq!(graph.shortest_path(5, 1), None);
+    assert_eq!(graph.shortest_path(1, 9), None);
+}
+
+#[test]
+fn test_graph_nodes_and_edges() {
+    let mut graph = GraphDS::new();
+    for node in [3, 1, 2] {
+        graph.add_node(node, node * 10).unwrap();
+    }
+    graph.add_edge(2, 3, 7).unwrap();
+    graph.add_edge(1, 3, 5).unwrap();
+    graph.add_edge(1, 2, 1).unwrap();
+
+    assert_eq!(graph.nodes(), vec![(1, 10), (2, 20), (3, 30)]);
+    assert_eq!(graph.edges(), vec![(1, 3, 5), (1, 2, 1), (2, 3, 7)]);
+}
+
 // Tests for OHLCVDS
 #[test]
 fn test_ohlcv_basic_operations() {
@@ -217,6 +310,40 @@ fn test_ohlcv_basic_operations() {
     assert_eq!(ohlcv.len(), 0);
 }
 
+#[test]
+fn test_ohlcv_indicators() {
+    let mut ohlcv = OHLCVDS::new();
+    for (i, close) in [100, 120, 110, 130, 150].into_iter().enumerate() {
+        ohlcv.add_entry(OHLCVEntry {
+            timestamp: i as u64 + 1,
+            open: close,
+            high: close,
+            low: close,
+            close,
+            volume: 1,
+        }).unwrap();
+    }
+
+    // EMA(3): seeded with SMA 110, then (close * 2 + ema * 2) / 4
+    assert_eq!(ohlcv.calculate_ema(3), vec![(3, 110), (4, 120), (5, 135)]);
+
+    // RSI(3): changes +20 -10 +20 give 40/3 gain vs 10/3 loss = 80, then
+    // Wilder smoothing of +20 gives 14/9 vs 2/9 = 87.5
+    assert_eq!(ohlcv.calculate_rsi(3), vec![(4, 80), (5, 87)]);
+
+    // Bollinger(3): variances 66, 66 and 266 give deviations 8, 8 and 16
+    assert_eq!(ohlcv.calculate_bollinger(3), vec![
+        (3, 94, 110, 126),
+        (4, 104, 120, 136),
+        (5, 98, 130, 162),
+    ]);
+
+    // Not enough bars, or a zero period
+    assert!(ohlcv.calculate_ema(6).is_empty());
+    assert!(ohlcv.calculate_rsi(5).is_empty());
+    assert!(ohlcv.calculate_bollinger(0).is_empty());
+}
+
 // Tests for HypergraphDS
 #[test]
 fn test_hypergraph_basic_operations() {
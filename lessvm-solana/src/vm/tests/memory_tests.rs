@@ -3,21 +3,23 @@ use solana_program::clock::Epoch;
 use solana_program::pubkey::Pubkey;
 use solana_program::account_info::AccountInfo;
 
-fn create_test_account(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'static>) {
-    let key = Pubkey::new_unique();
-    let mut lamports = lamports;
-    let mut data = vec![0; 32];
-    
-    AccountInfo::new(
-        &key,
+fn create_test_account<'a>(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'a>) {
+    // AccountInfo only borrows what it describes, so leak it to outlive the call
+    let key: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let owner: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let data = vec![0; 32];
+
+    let account = AccountInfo::new(
+        key,
         true,
         true,
-        &mut lamports,
-        &mut data,
-        &Pubkey::new_unique(),
+        Box::leak(Box::new(lamports)),
+        Box::leak(data.clone().into_boxed_slice()),
+        owner,
         false,
         Epoch::default(),
-    )
+    );
+    (*key, data, account)
 }
 
 #[test]
@@ -94,6 +96,27 @@ fn test_memory_operations_with_growth() {
     }
 }
 
+#[test]
+fn test_memory_copy_within_overlap() {
+    let mut memory = Memory::new();
+    memory.store(0, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+
+    // Forward copy onto an overlapping, higher range
+    memory.copy_within(2, 0, 6).unwrap();
+    assert_eq!(memory.load(0, 8).unwrap(), &[1, 2, 1, 2, 3, 4, 5, 6]);
+
+    // Backward copy onto an overlapping, lower range
+    memory.store(0, &[1, 2, 3, 4, 5, 6, 7, 8]).unwrap();
+    memory.copy_within(0, 2, 6).unwrap();
+    assert_eq!(memory.load(0, 8).unwrap(), &[3, 4, 5, 6, 7, 8, 7, 8]);
+
+    // The destination grows, but the source must be in bounds
+    memory.copy_within(2000, 0, 4).unwrap();
+    assert_eq!(memory.load(2000, 4).unwrap(), &[3, 4, 5, 6]);
+    assert!(matches!(memory.copy_within(0, 10_000, 4), Err(VMError::InvalidMemoryAccess)));
+    assert!(matches!(memory.copy_within(usize::MAX, 0, 4), Err(VMError::InvalidMemoryAccess)));
+}
+
 #[test]
 fn test_memory_with_vm_operations() {
     let program_id = Pubkey::new_unique();
@@ -11,4 +11,9 @@ pub mod data_structure_store_tests;
 pub mod opcode_tests;
 
 #[cfg(test)]
-pub mod memory_tests;
\ No newline at end of file
+pub mod memory_tests;
+
+#[cfg(test)]
+pub mod opcode_vector_tests;
+#[cfg(test)]
+pub mod container_tests;
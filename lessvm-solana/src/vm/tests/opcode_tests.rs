@@ -3,22 +3,25 @@ use super::super::data_structures::*;
 use solana_program::clock::Epoch;
 use solana_program::pubkey::Pubkey;
 use solana_program::account_info::AccountInfo;
+use solana_program::program_error::ProgramError;
 
-fn create_test_account(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'static>) {
-    let key = Pubkey::new_unique();
-    let mut lamports = lamports;
-    let mut data = vec![0; 32];
-    
-    AccountInfo::new(
-        &key,
+fn create_test_account<'a>(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'a>) {
+    // AccountInfo only borrows what it describes, so leak it to outlive the call
+    let key: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let owner: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let data = vec![0; 32];
+
+    let account = AccountInfo::new(
+        key,
         true,
         true,
-        &mut lamports,
-        &mut data,
-        &Pubkey::new_unique(),
+        Box::leak(Box::new(lamports)),
+        Box::leak(data.clone().into_boxed_slice()),
+        owner,
         false,
         Epoch::default(),
-    )
+    );
+    (*key, data, account)
 }
 
 // Test for Solana account operations
@@ -182,6 +185,221 @@ fn test_graph_operations() {
     assert_eq!(vm.stack.pop().unwrap().0, 0); // Should return 0 for non-existent node
 }
 
+// Test for Trie remove and length operations
+#[test]
+fn test_btree_range() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+
+    let push1 = OpCode::Push1 as u8;
+    let mut code = vec![push1, 0, OpCode::BTreeCreate as u8];
+    for key in [50, 10, 40, 20, 30] {
+        code.extend_from_slice(&[push1, 0, push1, key, push1, key + 1, OpCode::BTreeInsert as u8, OpCode::Pop as u8]);
+    }
+    code.extend_from_slice(&[push1, 0, push1, 20, push1, 40, OpCode::BTreeRange as u8]);
+
+    assert!(vm.execute(&code).is_ok());
+    let entries: Vec<u64> = (0..6).map(|_| vm.stack.pop().unwrap().0).collect();
+    assert_eq!(entries, vec![20, 21, 30, 31, 40, 41]);
+    assert_eq!(vm.stack.pop().unwrap().0, 3);
+
+    // Inverted bounds match nothing
+    let mut vm = VM::new(&program_id, &[], &[]);
+    code.extend_from_slice(&[push1, 0, push1, 40, push1, 20, OpCode::BTreeRange as u8]);
+    assert!(vm.execute(&code).is_ok());
+    assert_eq!(vm.stack.pop().unwrap().0, 0);
+}
+
+#[test]
+fn test_btree_hash() {
+    let program_id = Pubkey::new_unique();
+    let push1 = OpCode::Push1 as u8;
+
+    // Fill BTree `id` with (key, key + 1) in the given order, then hash it
+    let hash_of = |id: u8, keys: &[u8]| {
+        let mut code = vec![push1, id, OpCode::BTreeCreate as u8];
+        for &key in keys {
+            code.extend_from_slice(&[push1, id, push1, key, push1, key + 1, OpCode::BTreeInsert as u8, OpCode::Pop as u8]);
+        }
+        code.extend_from_slice(&[push1, id, OpCode::BTreeHash as u8]);
+
+        let mut vm = VM::new(&program_id, &[], &[]);
+        assert!(vm.execute(&code).is_ok());
+        vm.stack.pop().unwrap().0
+    };
+
+    // Insertion order and the structure's ID don't matter
+    let hash = hash_of(0, &[10, 20, 30]);
+    assert_eq!(hash_of(0, &[10, 20, 30]), hash);
+    assert_eq!(hash_of(3, &[30, 10, 20]), hash);
+
+    assert_ne!(hash_of(0, &[10, 20, 31]), hash);
+    assert_ne!(hash_of(0, &[10, 20]), hash);
+    assert_ne!(hash_of(0, &[]), hash);
+
+    // Hashing a BTree that was never created is an error
+    let mut vm = VM::new(&program_id, &[], &[]);
+    assert!(vm.execute(&[push1, 0, OpCode::BTreeHash as u8]).is_err());
+}
+
+#[test]
+fn test_btree_range_cursor() {
+    let program_id = Pubkey::new_unique();
+    let push1 = OpCode::Push1 as u8;
+    let cursor_ptr = 64;
+
+    // Each batch runs in a fresh VM, as it would in a separate transaction,
+    // with the cursor carried over in memory
+    let mut populate = vec![push1, 0, OpCode::BTreeCreate as u8];
+    for key in 0..100 {
+        populate.extend_from_slice(&[push1, 0, push1, key, push1, key, push1, 2, OpCode::Mul as u8, OpCode::BTreeInsert as u8, OpCode::Pop as u8]);
+    }
+
+    let mut saved: Option<[u8; RangeCursor::SIZE]> = None;
+    let mut seen = Vec::new();
+    for batch in 0..11 {
+        let mut vm = VM::new(&program_id, &[], &[]);
+        let mut code = populate.clone();
+        match saved {
+            None => code.extend_from_slice(&[push1, 0, push1, 0, push1, 255, push1, 10, push1, cursor_ptr, OpCode::BTreeRangeCursor as u8]),
+            Some(cursor) => {
+                vm.memory.store(cursor_ptr as usize, &cursor).unwrap();
+                code.extend_from_slice(&[push1, 0, push1, cursor_ptr, OpCode::BTreeRangeNext as u8]);
+            }
+        }
+        assert!(vm.execute(&code).is_ok(), "batch {}", batch);
+
+        let mut entries: Vec<u64> = (0..vm.stack.depth()).map(|_| vm.stack.pop().unwrap().0).collect();
+        let count = entries.pop().unwrap();
+        assert_eq!(count, if batch < 10 { 10 } else { 0 });
+        for pair in entries.chunks(2) {
+            assert_eq!(pair[1], pair[0] * 2);
+            seen.push(pair[0]);
+        }
+
+        let cursor = RangeCursor::from_bytes(vm.memory.load(cursor_ptr as usize, RangeCursor::SIZE).unwrap()).unwrap();
+        // The tenth batch is full, so only the empty eleventh one finishes
+        assert_eq!(cursor.done, batch == 10);
+        saved = Some(cursor.to_bytes());
+    }
+    assert_eq!(seen, (0..100).collect::<Vec<u64>>());
+
+    // A corrupt cursor is rejected
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.memory.store(0, &[0xFF; RangeCursor::SIZE]).unwrap();
+    let mut code = vec![push1, 0, OpCode::BTreeCreate as u8];
+    code.extend_from_slice(&[push1, 0, push1, 0, OpCode::BTreeRangeNext as u8]);
+    assert!(vm.execute(&code).is_err());
+}
+
+#[test]
+fn test_trie_and_graph_hash() {
+    let program_id = Pubkey::new_unique();
+    let push1 = OpCode::Push1 as u8;
+
+    let trie_hash = |inserts: &[(u8, u8, u8)]| {
+        let mut vm = VM::new(&program_id, &[], &[]);
+        vm.memory.store(0, b"abcd").unwrap();
+        let mut code = vec![push1, 0, OpCode::TrieCreate as u8];
+        for &(ptr, len, value) in inserts {
+            code.extend_from_slice(&[push1, 0, push1, ptr, push1, len, push1, value, OpCode::TrieInsert as u8]);
+        }
+        code.extend_from_slice(&[push1, 0, OpCode::TrieHash as u8]);
+        assert!(vm.execute(&code).is_ok());
+        vm.stack.pop().unwrap().0
+    };
+    // "ab" then "abc", in either order
+    assert_eq!(trie_hash(&[(0, 2, 1), (0, 3, 2)]), trie_hash(&[(0, 3, 2), (0, 2, 1)]));
+    // "ab" -> 1, "c" -> 2 isn't "a" -> 1, "bc" -> 2
+    assert_ne!(trie_hash(&[(0, 2, 1), (2, 1, 2)]), trie_hash(&[(0, 1, 1), (1, 2, 2)]));
+
+    let graph_hash = |edges: &[(u8, u8, u8)]| {
+        let mut vm = VM::new(&program_id, &[], &[]);
+        let mut code = vec![push1, 0, OpCode::GraphCreate as u8];
+        for &(from, to, weight) in edges {
+            code.extend_from_slice(&[push1, 0, push1, from, push1, to, push1, weight, OpCode::GraphAddEdge as u8]);
+        }
+        code.extend_from_slice(&[push1, 0, OpCode::GraphHash as u8]);
+        assert!(vm.execute(&code).is_ok());
+        vm.stack.pop().unwrap().0
+    };
+    assert_eq!(graph_hash(&[(1, 2, 5), (1, 3, 7)]), graph_hash(&[(1, 3, 7), (1, 2, 5)]));
+    assert_ne!(graph_hash(&[(1, 2, 5), (1, 3, 7)]), graph_hash(&[(1, 2, 5), (1, 3, 8)]));
+}
+
+#[test]
+fn test_trie_remove_and_len() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.memory.store(0, b"abcabdxyz").unwrap();
+
+    let push1 = OpCode::Push1 as u8;
+    let mut code = vec![push1, 0, OpCode::TrieCreate as u8];
+    // Insert "abc", "abd" and "xyz", then overwrite "abc"
+    for (key_ptr, value) in [(0, 1), (3, 2), (6, 3), (0, 4)] {
+        code.extend_from_slice(&[push1, 0, push1, key_ptr, push1, 3, push1, value, OpCode::TrieInsert as u8]);
+    }
+    // Remove "abd" twice; only the first finds it
+    for _ in 0..2 {
+        code.extend_from_slice(&[push1, 0, push1, 3, push1, 3, OpCode::TrieRemove as u8]);
+    }
+    code.extend_from_slice(&[push1, 0, OpCode::TrieLen as u8]);
+    code.extend_from_slice(&[push1, 0, push1, 3, push1, 3, OpCode::TrieContains as u8]);
+    // A prefix of a stored key isn't a key itself
+    code.extend_from_slice(&[push1, 0, push1, 0, push1, 2, OpCode::TrieRemove as u8]);
+    code.extend_from_slice(&[push1, 0, OpCode::TrieLen as u8]);
+
+    assert!(vm.execute(&code).is_ok());
+    assert_eq!(vm.stack.pop().unwrap().0, 2); // length unchanged
+    assert_eq!(vm.stack.pop().unwrap().0, 0); // "ab" not found
+    assert_eq!(vm.stack.pop().unwrap().0, 0); // "abd" gone
+    assert_eq!(vm.stack.pop().unwrap().0, 2); // "abc" and "xyz" remain
+    assert_eq!(vm.stack.pop().unwrap().0, 0);
+    assert_eq!(vm.stack.pop().unwrap().0, 1);
+
+    // Length of a trie that was never created is an error
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.stack.push(Value(1)).unwrap();
+    assert!(vm.execute(&[OpCode::TrieLen as u8]).is_err());
+}
+
+// Test for graph shortest path
+#[test]
+fn test_graph_shortest_path() {
+    let program_id = Pubkey::new_unique();
+    let (_, _, account) = create_test_account(1000000);
+    let accounts = vec![account];
+
+    let mut vm = VM::new(&program_id, &accounts, &[]);
+
+    let mut code = vec![OpCode::Push1 as u8, 0, OpCode::GraphCreate as u8];
+    for (from, to, weight) in [(1, 2, 4), (1, 3, 1), (3, 2, 2), (2, 4, 1), (3, 4, 5)] {
+        code.extend_from_slice(&[
+            OpCode::Push1 as u8, 0,
+            OpCode::Push1 as u8, from,
+            OpCode::Push1 as u8, to,
+            OpCode::Push1 as u8, weight,
+            OpCode::GraphAddEdge as u8,
+        ]);
+    }
+    // Path from 1 to 4, then from 4 back to 1
+    code.extend_from_slice(&[OpCode::Push1 as u8, 0, OpCode::Push1 as u8, 1, OpCode::Push1 as u8, 4]);
+    code.push(OpCode::GraphShortestPath as u8);
+    code.extend_from_slice(&[OpCode::Push1 as u8, 0, OpCode::Push1 as u8, 4, OpCode::Push1 as u8, 1]);
+    code.push(OpCode::GraphShortestPath as u8);
+    assert!(vm.execute(&code).is_ok());
+
+    // No path: length 0 and cost 0
+    assert_eq!(vm.stack.pop().unwrap().0, 0);
+    assert_eq!(vm.stack.pop().unwrap().0, 0);
+
+    // Nodes in order, then the length and total cost
+    let path: Vec<u64> = (0..4).map(|_| vm.stack.pop().unwrap().0).collect();
+    assert_eq!(path, vec![1, 3, 2, 4]);
+    assert_eq!(vm.stack.pop().unwrap().0, 4);
+    assert_eq!(vm.stack.pop().unwrap().0, 4);
+}
+
 // Test for OHLCV operations
 #[test]
 fn test_ohlcv_operations() {
@@ -273,6 +491,50 @@ fn test_ohlcv_operations() {
     assert_eq!(value2, (115 + 125) / 2); // (bar2.close + bar3.close) / 2
 }
 
+// Test for OHLCV indicator operations
+#[test]
+fn test_ohlcv_indicator_operations() {
+    let program_id = Pubkey::new_unique();
+    let (_, _, account) = create_test_account(1000000);
+    let accounts = vec![account];
+
+    let mut vm = VM::new(&program_id, &accounts, &[]);
+
+    let mut code = vec![OpCode::OhlcvCreate as u8];
+    vm.stack.push(Value(0)).unwrap(); // ohlcv id
+    for (i, close) in [100u64, 120, 110, 130, 150].into_iter().enumerate() {
+        for value in [0, i as u64 + 1, close, close, close, close, 1] {
+            code.extend_from_slice(&[OpCode::Push8 as u8]);
+            code.extend_from_slice(&value.to_le_bytes());
+        }
+        code.push(OpCode::OhlcvAddBar as u8);
+    }
+    for opcode in [OpCode::OhlcvEma, OpCode::OhlcvRsi, OpCode::OhlcvBollinger] {
+        code.extend_from_slice(&[OpCode::Push1 as u8, 0, OpCode::Push1 as u8, 3, opcode as u8]);
+    }
+    assert!(vm.execute(&code).is_ok());
+
+    let mut pop = || vm.stack.pop().unwrap().0;
+
+    // Bollinger: first band on top as timestamp, upper, middle, lower
+    assert_eq!([pop(), pop(), pop(), pop()], [3, 126, 110, 94]);
+    assert_eq!([pop(), pop(), pop(), pop()], [4, 136, 120, 104]);
+    assert_eq!([pop(), pop(), pop(), pop()], [5, 162, 130, 98]);
+    assert_eq!(pop(), 3);
+
+    // RSI
+    assert_eq!([pop(), pop(), pop(), pop(), pop()], [4, 80, 5, 87, 2]);
+
+    // EMA
+    assert_eq!([pop(), pop(), pop(), pop(), pop(), pop(), pop()], [3, 110, 4, 120, 5, 135, 3]);
+
+    // Unknown OHLCV id
+    let mut vm = VM::new(&program_id, &accounts, &[]);
+    vm.stack.push(Value(7)).unwrap();
+    vm.stack.push(Value(3)).unwrap();
+    assert!(vm.execute(&[OpCode::OhlcvEma as u8]).is_err());
+}
+
 // Test for Hypergraph operations
 #[test]
 fn test_hypergraph_operations() {
@@ -352,6 +614,323 @@ fn test_hypergraph_operations() {
     assert!(result.is_ok());
 }
 
+// Test for DivMod and MulWide opcodes
+#[test]
+fn test_stack_to_mem_round_trip() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+
+    let push1 = OpCode::Push1 as u8;
+    let mut code = vec![];
+    for value in [1u64, 2, u64::MAX, 0x0102_0304_0506_0708] {
+        code.push(OpCode::Push8 as u8);
+        code.extend_from_slice(&value.to_le_bytes());
+    }
+    // Move all four values to memory offset 16
+    code.extend_from_slice(&[push1, 16, push1, 4, OpCode::StackToMem as u8]);
+    assert!(vm.execute(&code).is_ok());
+
+    assert!(vm.stack.is_empty());
+    let memory = vm.memory.load(16, 32).unwrap();
+    assert_eq!(&memory[..8], &1u64.to_le_bytes());
+    assert_eq!(&memory[24..], &0x0102_0304_0506_0708u64.to_le_bytes());
+
+    // And back again, in the original order
+    let mut vm2 = VM::new(&program_id, &[], &[]);
+    vm2.memory.store(16, memory).unwrap();
+    assert!(vm2.execute(&[push1, 4, push1, 16, OpCode::MemToStack as u8]).is_ok());
+    assert_eq!(vm2.stack.pop().unwrap().0, 0x0102_0304_0506_0708);
+    assert_eq!(vm2.stack.pop().unwrap().0, u64::MAX);
+    assert_eq!(vm2.stack.pop().unwrap().0, 2);
+    assert_eq!(vm2.stack.pop().unwrap().0, 1);
+    assert!(vm2.stack.is_empty());
+
+    // Moving more values than the stack holds fails
+    let mut vm3 = VM::new(&program_id, &[], &[]);
+    assert!(vm3.execute(&[push1, 7, push1, 0, push1, 2, OpCode::StackToMem as u8]).is_err());
+}
+
+#[test]
+fn test_mcopy() {
+    let program_id = Pubkey::new_unique();
+    let push1 = OpCode::Push1 as u8;
+
+    // Copy 6 bytes from offset 0 to the overlapping offset 2: len, src, dst
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.memory.store(0, b"abcdefgh").unwrap();
+    assert!(vm.execute(&[push1, 6, push1, 0, push1, 2, OpCode::MCopy as u8]).is_ok());
+    assert_eq!(vm.memory.load(0, 8).unwrap(), b"ababcdef");
+
+    // And from offset 2 back down to offset 0
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.memory.store(0, b"abcdefgh").unwrap();
+    assert!(vm.execute(&[push1, 6, push1, 2, push1, 0, OpCode::MCopy as u8]).is_ok());
+    assert_eq!(vm.memory.load(0, 8).unwrap(), b"cdefghgh");
+
+    // Reading past the end of memory fails
+    let mut vm = VM::new(&program_id, &[], &[]);
+    let mut code = vec![push1, 8, OpCode::Push8 as u8];
+    code.extend_from_slice(&1_000_000u64.to_le_bytes());
+    code.extend_from_slice(&[push1, 0, OpCode::MCopy as u8]);
+    assert!(vm.execute(&code).is_err());
+}
+
+#[test]
+fn test_sort_mem() {
+    let program_id = Pubkey::new_unique();
+    let push1 = OpCode::Push1 as u8;
+    let values = [42u64, u64::MAX, 7, 1 << 40, 0];
+    let store = |vm: &mut VM| {
+        for (i, value) in values.iter().enumerate() {
+            vm.memory.store(8 + i * 8, &value.to_le_bytes()).unwrap();
+        }
+    };
+
+    // Sort five 8-byte values at offset 8: width, count, offset
+    let mut vm = VM::new(&program_id, &[], &[]);
+    store(&mut vm);
+    assert!(vm.execute(&[push1, 8, push1, 5, push1, 8, OpCode::SortMem as u8, OpCode::Halt as u8]).is_ok());
+    let sorted: Vec<u64> = vm.memory.load(8, 40).unwrap()
+        .chunks_exact(8)
+        .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    assert_eq!(sorted, vec![0, 7, 42, 1 << 40, u64::MAX]);
+    // Three comparison rounds for five elements
+    let expected_gas = 3 * OpCode::Push1.gas_cost() + OpCode::SortMem.gas_cost() + 5 * 3 + OpCode::Halt.gas_cost();
+    assert_eq!(vm.gas_used(), expected_gas);
+
+    // Narrower widths compare only their own bytes
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.memory.store(0, &[0x02, 0x01, 0x01, 0x02, 0xFF, 0x00]).unwrap();
+    assert!(vm.execute(&[push1, 2, push1, 3, push1, 0, OpCode::SortMem as u8]).is_ok());
+    assert_eq!(vm.memory.load(0, 6).unwrap(), &[0xFF, 0x00, 0x02, 0x01, 0x01, 0x02]);
+
+    // Widths over a word and ranges past the end of memory fail
+    let mut vm = VM::new(&program_id, &[], &[]);
+    store(&mut vm);
+    assert!(vm.execute(&[push1, 9, push1, 1, push1, 0, OpCode::SortMem as u8]).is_err());
+    let mut vm = VM::new(&program_id, &[], &[]);
+    assert!(vm.execute(&[push1, 8, push1, 200, push1, 0, OpCode::SortMem as u8]).is_err());
+}
+
+#[test]
+fn test_bsearch_mem() {
+    let program_id = Pubkey::new_unique();
+    let push1 = OpCode::Push1 as u8;
+    let mut sorted = Vec::new();
+    for value in [3u64, 9, 27, 81, 243] {
+        sorted.extend_from_slice(&value.to_le_bytes());
+    }
+    // key, width, count, offset
+    let search = |key: u8, width: u8, count: u8| {
+        let mut vm = VM::new(&program_id, &[], &[]);
+        vm.memory.store(16, &sorted).unwrap();
+        let code = [push1, key, push1, width, push1, count, push1, 16, OpCode::BSearchMem as u8];
+        vm.execute(&code).map(|_| vm.stack.pop().unwrap().0)
+    };
+
+    assert_eq!(search(81, 8, 5), Ok(3));
+    assert_eq!(search(3, 8, 5), Ok(0));
+    assert_eq!(search(243, 8, 5), Ok(4));
+    assert_eq!(search(10, 8, 5), Ok(BSEARCH_NOT_FOUND));
+    assert_eq!(search(250, 8, 5), Ok(BSEARCH_NOT_FOUND));
+    assert_eq!(search(3, 8, 0), Ok(BSEARCH_NOT_FOUND));
+    assert!(search(3, 0, 5).is_err());
+}
+
+#[test]
+fn test_ds_command_dumps_btree() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+
+    // Create BTreeMap 2 and insert 30 => 300, 10 => 100
+    let push1 = OpCode::Push1 as u8;
+    let code = [
+        push1, 2, OpCode::BTreeCreate as u8,
+        push1, 2, push1, 30, OpCode::Push8 as u8, 0x2C, 0x01, 0, 0, 0, 0, 0, 0,
+        OpCode::BTreeInsert as u8, OpCode::Pop as u8,
+        push1, 2, push1, 10, push1, 100, OpCode::BTreeInsert as u8, OpCode::Pop as u8,
+    ];
+    assert!(vm.execute(&code).is_ok());
+
+    assert_eq!(
+        crate::vm::debug::ds_command(&vm, &["btree", "2"]),
+        "BTreeMap (2 entries) {\n  10 => 100\n  30 => 300\n}"
+    );
+    assert_eq!(crate::vm::debug::ds_command(&vm, &["btree", "0"]), "No btree with id 0");
+    assert_eq!(crate::vm::debug::ds_command(&vm, &["graph", "2"]), "No graph with id 2");
+    assert!(crate::vm::debug::ds_command(&vm, &["btree"]).starts_with("Usage"));
+}
+
+#[test]
+fn test_keccak256() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+
+    // Hash the empty input at offset 0 into offset 32
+    let push1 = OpCode::Push1 as u8;
+    assert!(vm.execute(&[push1, 0, push1, 0, push1, 32, OpCode::Keccak256 as u8]).is_ok());
+    assert_eq!(
+        vm.memory.load(32, 32).unwrap(),
+        &[
+            0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+            0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+        ]
+    );
+
+    // "abc" hashes to the published test vector
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.memory.store(0, b"abc").unwrap();
+    assert!(vm.execute(&[push1, 0, push1, 3, push1, 8, OpCode::Keccak256 as u8]).is_ok());
+    assert_eq!(vm.memory.load(8, 4).unwrap(), &[0x4e, 0x03, 0x65, 0x7a]);
+}
+
+#[test]
+fn test_divmod_and_mulwide() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+
+    // 17 / 5 leaves the remainder on top of the quotient
+    vm.stack.push(Value(17)).unwrap();
+    vm.stack.push(Value(5)).unwrap();
+    assert!(vm.execute(&[OpCode::DivMod as u8]).is_ok());
+    assert_eq!(vm.stack.pop().unwrap().0, 2);
+    assert_eq!(vm.stack.pop().unwrap().0, 3);
+
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.stack.push(Value(17)).unwrap();
+    vm.stack.push(Value(0)).unwrap();
+    assert_eq!(
+        vm.execute(&[OpCode::DivMod as u8]).unwrap_err(),
+        ProgramError::from(VMError::DivisionByZero)
+    );
+
+    // MOD reports a zero divisor the same way
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.stack.push(Value(17)).unwrap();
+    vm.stack.push(Value(0)).unwrap();
+    assert_eq!(
+        vm.execute(&[OpCode::Mod as u8]).unwrap_err(),
+        ProgramError::from(VMError::DivisionByZero)
+    );
+
+    // (2^64 - 1) * (2^64 - 1) = 2^128 - 2^65 + 1
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.stack.push(Value(u64::MAX)).unwrap();
+    vm.stack.push(Value(u64::MAX)).unwrap();
+    assert!(vm.execute(&[OpCode::MulWide as u8]).is_ok());
+    assert_eq!(vm.stack.pop().unwrap().0, 1);
+    assert_eq!(vm.stack.pop().unwrap().0, u64::MAX - 1);
+
+    // 2^40 * 3 * 2^30 = 3 * 2^70, whose low half is zero
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.stack.push(Value(1 << 40)).unwrap();
+    vm.stack.push(Value(3 << 30)).unwrap();
+    assert!(vm.execute(&[OpCode::MulWide as u8]).is_ok());
+    assert_eq!(vm.stack.pop().unwrap().0, 0);
+    assert_eq!(vm.stack.pop().unwrap().0, 3 << 6);
+}
+
+// Test for comparison opcodes
+#[test]
+fn test_comparison_opcodes() {
+    let program_id = Pubkey::new_unique();
+    let compare = |op: OpCode, operands: &[u64]| {
+        let mut vm = VM::new(&program_id, &[], &[]);
+        for &value in operands {
+            vm.stack.push(Value(value)).unwrap();
+        }
+        assert!(vm.execute(&[op as u8]).is_ok());
+        vm.stack.pop().unwrap().0
+    };
+
+    assert_eq!(compare(OpCode::Lt, &[3, 5]), 1);
+    assert_eq!(compare(OpCode::Lt, &[5, 3]), 0);
+    assert_eq!(compare(OpCode::Lt, &[5, 5]), 0);
+    assert_eq!(compare(OpCode::Gt, &[5, 3]), 1);
+    assert_eq!(compare(OpCode::Gt, &[3, 5]), 0);
+    assert_eq!(compare(OpCode::Eq, &[7, 7]), 1);
+    assert_eq!(compare(OpCode::Eq, &[7, 8]), 0);
+
+    // Comparisons are unsigned: -1 in two's complement is the largest value
+    let minus_one = -1i64 as u64;
+    assert_eq!(compare(OpCode::Lt, &[minus_one, 1]), 0);
+    assert_eq!(compare(OpCode::Gt, &[minus_one, 1]), 1);
+
+    assert_eq!(compare(OpCode::IsZero, &[0]), 1);
+    assert_eq!(compare(OpCode::IsZero, &[1]), 0);
+    assert_eq!(compare(OpCode::IsZero, &[minus_one]), 0);
+
+    let mut vm = VM::new(&program_id, &[], &[]);
+    vm.stack.push(Value(1)).unwrap();
+    assert!(vm.execute(&[OpCode::Lt as u8]).is_err());
+}
+
+// Test for 32-bit width conversion opcodes
+#[test]
+fn test_width_conversion_opcodes() {
+    let program_id = Pubkey::new_unique();
+    let convert = |op: OpCode, value: u64| {
+        let mut vm = VM::new(&program_id, &[], &[]);
+        vm.stack.push(Value(value)).unwrap();
+        assert!(vm.execute(&[op as u8]).is_ok());
+        (0..vm.stack.depth()).filter_map(|i| vm.stack.get_value(i)).map(|v| v.0).collect::<Vec<_>>()
+    };
+
+    // A value that fits narrows unchanged, with the overflow flag clear
+    assert_eq!(convert(OpCode::NarrowU32, 0xDEAD_BEEF), vec![0xDEAD_BEEF, 0]);
+    assert_eq!(convert(OpCode::NarrowU32, u32::MAX as u64), vec![u32::MAX as u64, 0]);
+
+    // One that doesn't keeps its low bits and sets the flag
+    assert_eq!(convert(OpCode::NarrowU32, 0x1_0000_0002), vec![2, 1]);
+    assert_eq!(convert(OpCode::NarrowU32, u64::MAX), vec![u32::MAX as u64, 1]);
+
+    assert_eq!(convert(OpCode::ExtendU32, 0xFFFF_FFFF_8000_0000), vec![0x8000_0000]);
+    assert_eq!(convert(OpCode::ExtendU32, 7), vec![7]);
+
+    let mut vm = VM::new(&program_id, &[], &[]);
+    assert!(vm.execute(&[OpCode::NarrowU32 as u8]).is_err());
+}
+
+// Test for Q64.64 fixed-point opcodes
+#[test]
+fn test_fixed_point_opcodes() {
+    let program_id = Pubkey::new_unique();
+
+    // 1.5 as Q64.64: integer part 1, fraction 0.5 * 2^64
+    let mut code = vec![OpCode::Push1 as u8, 1, OpCode::Push8 as u8];
+    code.extend_from_slice(&(1u64 << 63).to_le_bytes());
+    // times 2.0 converted from an integer, then back to an integer
+    code.extend_from_slice(&[
+        OpCode::Push1 as u8, 2,
+        OpCode::FpFromInt as u8,
+        OpCode::FpMul as u8,
+        OpCode::FpToInt as u8,
+        OpCode::Halt as u8,
+    ]);
+
+    let mut vm = VM::new(&program_id, &[], &code);
+    assert!(vm.execute(&code).is_ok());
+    assert_eq!(vm.stack.pop().unwrap().0, 3);
+    assert_eq!(vm.stack.depth(), 0);
+
+    // 3.0 / 2.0 = 1.5
+    let mut vm = VM::new(&program_id, &[], &[]);
+    for value in [3, 0, 2, 0] {
+        vm.stack.push(Value(value)).unwrap();
+    }
+    assert!(vm.execute(&[OpCode::FpDiv as u8]).is_ok());
+    assert_eq!(vm.stack.pop().unwrap().0, 1 << 63);
+    assert_eq!(vm.stack.pop().unwrap().0, 1);
+
+    // Dividing by zero fails
+    let mut vm = VM::new(&program_id, &[], &[]);
+    for value in [3, 0, 0, 0] {
+        vm.stack.push(Value(value)).unwrap();
+    }
+    assert!(vm.execute(&[OpCode::FpDiv as u8]).is_err());
+}
+
 // Test for Revert opcode
 #[test]
 fn test_revert_opcode() {
@@ -376,4 +955,85 @@ fn test_revert_opcode() {
             panic!("Expected ProgramError::Custom, got {:?}", err);
         }
     }
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_return_bytes() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+
+    // A 16-byte struct of two u64 fields, stored at 32 and returned
+    let push1 = OpCode::Push1 as u8;
+    let mut code = Vec::new();
+    for (field, offset) in [(0x0102_0304_0506_0708u64, 32), (u64::MAX - 1, 40)] {
+        code.push(OpCode::Push8 as u8);
+        code.extend_from_slice(&field.to_le_bytes());
+        code.extend_from_slice(&[push1, offset, OpCode::Store as u8]);
+    }
+    code.extend_from_slice(&[push1, 32, push1, 16, OpCode::ReturnBytes as u8]);
+    // Never reached
+    code.extend_from_slice(&[push1, 1, push1, 0, OpCode::Revert as u8]);
+
+    assert!(vm.execute(&code).is_ok());
+    let mut expected = 0x0102_0304_0506_0708u64.to_le_bytes().to_vec();
+    expected.extend_from_slice(&(u64::MAX - 1).to_le_bytes());
+    assert_eq!(vm.return_data(), Some(&ReturnData::Bytes(expected.clone())));
+    assert_eq!(vm.return_data().unwrap().to_bytes(), expected);
+
+    // More than the runtime accepts
+    let mut vm = VM::new(&program_id, &[], &[]);
+    let mut code = vec![push1, 0, OpCode::Push8 as u8];
+    code.extend_from_slice(&2048u64.to_le_bytes());
+    code.push(OpCode::ReturnBytes as u8);
+    assert!(vm.execute(&code).is_err());
+    assert!(vm.return_data().is_none());
+}
+
+#[test]
+fn test_return_values() {
+    let program_id = Pubkey::new_unique();
+    let mut vm = VM::new(&program_id, &[], &[]);
+
+    let push1 = OpCode::Push1 as u8;
+    let code = [push1, 9, push1, 1, push1, 2, push1, 3, push1, 3, OpCode::ReturnValues as u8];
+    assert!(vm.execute(&code).is_ok());
+    assert_eq!(vm.return_data(), Some(&ReturnData::Values(vec![Value(1), Value(2), Value(3)])));
+    assert_eq!(vm.return_data().unwrap().to_bytes().len(), 24);
+    assert_eq!(vm.stack.pop().unwrap().0, 9);
+
+    // Asking for more values than the stack holds
+    let mut vm = VM::new(&program_id, &[], &[]);
+    assert!(vm.execute(&[push1, 1, push1, 2, OpCode::ReturnValues as u8]).is_err());
+}
+
+#[test]
+fn test_opcode_categories() {
+    assert_eq!(OpCode::Push1.category(), OpCategory::Stack);
+    assert_eq!(OpCode::MulDiv.category(), OpCategory::Math);
+    assert_eq!(OpCode::Shl.category(), OpCategory::Bitwise);
+    assert_eq!(OpCode::Mstore8.category(), OpCategory::Memory);
+    assert_eq!(OpCode::JumpI.category(), OpCategory::ControlFlow);
+    assert_eq!(OpCode::Halt.category(), OpCategory::ControlFlow);
+    assert_eq!(OpCode::SPLTransfer.category(), OpCategory::Solana);
+    assert_eq!(OpCode::GraphBfs.category(), OpCategory::DataStructure);
+
+    // Categories follow the opcode byte layout
+    for byte in 0..=255u8 {
+        if let Some(op) = OpCode::from_byte(byte) {
+            let expected = match byte {
+                0x00..=0x0F => OpCategory::Stack,
+                0x10..=0x17 => OpCategory::Math,
+                0x18..=0x1F => OpCategory::Bitwise,
+                0x20..=0x2F => OpCategory::Memory,
+                0x30..=0x3F | 0xFF => OpCategory::ControlFlow,
+                0x40..=0x4F => OpCategory::Solana,
+                0x50..=0x6F => OpCategory::DataStructure,
+                0x70..=0x7F => OpCategory::Math,
+                0x80..=0x8F => OpCategory::DataStructure,
+                0x90..=0x9F => OpCategory::Crypto,
+                _ => panic!("opcode {:#04X} has no expected category", byte),
+            };
+            assert_eq!(op.category(), expected, "opcode {:?}", op);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use super::super::*;
+use solana_program::pubkey::Pubkey;
+use serde_json::Value as Json;
+
+/// Look up an opcode by its mnemonic, e.g. `MSTORE8` for `OpCode::Mstore8`
+fn opcode_from_name(name: &str) -> Option<OpCode> {
+    (0..=255u8)
+        .filter_map(OpCode::from_byte)
+        .find(|op| format!("{:?}", op).eq_ignore_ascii_case(name))
+}
+
+fn values(v: &Json) -> Vec<u64> {
+    v.as_array().map_or(Vec::new(), |a| a.iter().map(|x| x.as_u64().unwrap()).collect())
+}
+
+/// Runs the shared vectors in `test-vectors/opcodes.json` against this VM
+#[test]
+fn test_opcode_vectors() {
+    let vectors: Json =
+        serde_json::from_str(include_str!("../../../../test-vectors/opcodes.json")).unwrap();
+    let program_id = Pubkey::new_unique();
+
+    let mut failures = Vec::new();
+    let mut ran = 0;
+    for vector in vectors.as_array().unwrap() {
+        if vector.get("vm").is_some_and(|vm| vm != "lessvm-solana") {
+            continue;
+        }
+        let name = vector["name"].as_str().unwrap();
+        let op = vector["op"].as_str().unwrap();
+        let opcode = opcode_from_name(op)
+            .unwrap_or_else(|| panic!("{}: unknown opcode {}", name, op));
+
+        let mut code = vec![opcode as u8];
+        code.extend(values(&vector["operands"]).iter().map(|&b| b as u8));
+        code.push(OpCode::Halt as u8);
+
+        let mut vm = VM::new(&program_id, &[], &code);
+        for value in values(&vector["stack"]) {
+            vm.stack.push(Value(value)).unwrap();
+        }
+        for region in vector["memory"].as_array().into_iter().flatten() {
+            let offset = region["offset"].as_u64().unwrap() as usize;
+            let bytes: Vec<u8> = values(&region["bytes"]).iter().map(|&b| b as u8).collect();
+            vm.memory.store(offset, &bytes).unwrap();
+        }
+
+        let result = vm.execute(&code);
+        ran += 1;
+        if vector["error"].as_bool().unwrap_or(false) {
+            if result.is_ok() {
+                failures.push(format!("{}: expected an error", name));
+            }
+            continue;
+        }
+        if let Err(e) = result {
+            failures.push(format!("{}: unexpected error {:?}", name, e));
+            continue;
+        }
+
+        let mut stack = Vec::new();
+        while vm.stack.depth() > 0 {
+            stack.push(vm.stack.pop().unwrap().0);
+        }
+        stack.reverse();
+        let expected = values(&vector["expected_stack"]);
+        if stack != expected {
+            failures.push(format!("{}: stack {:?}, expected {:?}", name, stack, expected));
+        }
+        for region in vector["expected_memory"].as_array().into_iter().flatten() {
+            let offset = region["offset"].as_u64().unwrap() as usize;
+            let bytes: Vec<u8> = values(&region["bytes"]).iter().map(|&b| b as u8).collect();
+            if vm.memory.load(offset, bytes.len()).ok() != Some(&bytes[..]) {
+                failures.push(format!("{}: memory at {:#X} differs", name, offset));
+            }
+        }
+        if let Some(gas) = vector["expected_gas"].as_u64() {
+            if vm.gas_used() != gas {
+                failures.push(format!("{}: used {} gas, expected {}", name, vm.gas_used(), gas));
+            }
+        }
+    }
+
+    assert!(ran >= 30, "only {} vectors ran", ran);
+    assert!(failures.is_empty(), "failing vectors:\n{}", failures.join("\n"));
+}
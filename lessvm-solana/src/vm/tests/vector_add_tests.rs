@@ -6,21 +6,23 @@ use solana_program::account_info::AccountInfo;
 #[cfg(target_arch = "x86_64")]
 use std::arch::x86_64::*;
 
-fn create_test_account(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'static>) {
-    let key = Pubkey::new_unique();
-    let mut lamports = lamports;
-    let mut data = vec![0; 32];
-    
-    AccountInfo::new(
-        &key,
+fn create_test_account<'a>(lamports: u64) -> (Pubkey, Vec<u8>, AccountInfo<'a>) {
+    // AccountInfo only borrows what it describes, so leak it to outlive the call
+    let key: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let owner: &'static Pubkey = Box::leak(Box::new(Pubkey::new_unique()));
+    let data = vec![0; 32];
+
+    let account = AccountInfo::new(
+        key,
         true,
         true,
-        &mut lamports,
-        &mut data,
-        &Pubkey::new_unique(),
+        Box::leak(Box::new(lamports)),
+        Box::leak(data.clone().into_boxed_slice()),
+        owner,
         false,
         Epoch::default(),
-    )
+    );
+    (*key, data, account)
 }
 
 #[test]
@@ -38,9 +40,7 @@ fn test_vector_add() {
     }
     
     // Execute vector_add operation
-    unsafe {
-        vm.vector_add().unwrap();
-    }
+    vm.vector_add().unwrap();
     
     // The stack should now have 4 values: [6, 8, 10, 12]
     // These are the sums of [1+5, 2+6, 3+7, 4+8]
@@ -71,9 +71,7 @@ fn test_vector_add_with_different_values() {
     vm.stack.push(Value(35)).unwrap();
     
     // Execute vector_add operation
-    unsafe {
-        vm.vector_add().unwrap();
-    }
+    vm.vector_add().unwrap();
     
     // The stack should now have 4 values: [15, 35, 55, 75]
     // These are the sums of [10+5, 20+15, 30+25, 40+35]
@@ -99,9 +97,6 @@ fn test_vector_add_stack_underflow() {
     }
     
     // Execute vector_add operation - should fail with StackUnderflow
-    unsafe {
-        let result = vm.vector_add();
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), VMError::StackUnderflow);
-    }
+    let result = vm.vector_add();
+    assert!(matches!(result, Err(VMError::StackUnderflow)));
 }
\ No newline at end of file